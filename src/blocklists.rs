@@ -0,0 +1,236 @@
+//! Lists of sites to hide from results that are downloaded from somewhere else,
+//! like the community SEO spam lists for uBlacklist. They're downloaded at
+//! startup and then every `refresh_hours`, and results from a listed site are
+//! removed along with the ones that have a url weight of 0.
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use parking_lot::{Mutex, RwLock};
+use regex::{Regex, RegexSet};
+use tracing::{info, warn};
+use url::Url;
+
+use crate::{config::Config, engines::CLIENT};
+
+/// Every subscription, compiled into one list.
+static BLOCKLIST: LazyLock<RwLock<Arc<Blocklist>>> = LazyLock::new(Default::default);
+
+/// The last list that was downloaded from each subscription, so a list that
+/// fails to download once isn't forgotten.
+static DOWNLOADED: LazyLock<Mutex<HashMap<String, String>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Default)]
+pub struct Blocklist {
+    /// Blocked along with their subdomains.
+    domains: HashSet<String>,
+    /// Only blocked if the host is exactly the same.
+    hosts: HashSet<String>,
+    /// Rules with a path and regex rules, which are checked against the whole
+    /// url.
+    patterns: Option<RegexSet>,
+}
+
+impl Blocklist {
+    /// Parse lists that are either in uBlacklist's format, with rules like
+    /// `*://*.example.com/*` and `/regex/`, or in the hosts file format, with
+    /// lines like `0.0.0.0 example.com`. Lines that are neither are skipped.
+    pub fn parse<'a>(lists: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut blocklist = Blocklist::default();
+        let mut patterns = Vec::new();
+        for line in lists.into_iter().flat_map(str::lines) {
+            let line = line.trim();
+            // rules starting with @ unblock sites, which isn't supported
+            if line.is_empty() || line.starts_with(['#', '!', '@']) {
+                continue;
+            }
+            if let Some(regex) = line.strip_prefix('/').and_then(|l| l.strip_suffix('/')) {
+                if Regex::new(regex).is_ok() {
+                    patterns.push(regex.to_string());
+                }
+            } else if line.contains("://") {
+                blocklist.add_match_pattern(line, &mut patterns);
+            } else {
+                blocklist.add_hosts_line(line);
+            }
+        }
+        if !patterns.is_empty() {
+            // every pattern was checked already, so this only fails if there
+            // are too many of them
+            match RegexSet::new(&patterns) {
+                Ok(set) => blocklist.patterns = Some(set),
+                Err(err) => warn!("Couldn't compile the blocklist patterns: {err}"),
+            }
+        }
+        blocklist
+    }
+
+    /// A rule like `*://*.example.com/*`, in the format of browser extension
+    /// match patterns.
+    fn add_match_pattern(&mut self, line: &str, patterns: &mut Vec<String>) {
+        let Some((_scheme, rest)) = line.split_once("://") else {
+            return;
+        };
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let host = host.to_lowercase();
+        // a rule for every host would hide everything
+        if host.is_empty() || host == "*" {
+            return;
+        }
+        if path.is_empty() || path == "*" {
+            match host.strip_prefix("*.") {
+                Some(domain) => self.domains.insert(domain.to_string()),
+                None => self.hosts.insert(host),
+            };
+            return;
+        }
+
+        let host_pattern = match host.strip_prefix("*.") {
+            Some(domain) => format!(r"([^/]+\.)?{}", regex::escape(domain)),
+            None => regex::escape(&host),
+        };
+        let path_pattern = path
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join(".*");
+        patterns.push(format!("^https?://{host_pattern}/{path_pattern}$"));
+    }
+
+    /// A line like `0.0.0.0 example.com`, or just `example.com`.
+    fn add_hosts_line(&mut self, line: &str) {
+        // comments can be at the end of a line too
+        let line = line.split('#').next().unwrap_or_default();
+        let mut parts = line.split_whitespace().peekable();
+        if parts
+            .peek()
+            .is_some_and(|first| first.parse::<IpAddr>().is_ok())
+        {
+            parts.next();
+        }
+        for host in parts {
+            let host = host.to_lowercase();
+            if host.contains('.') && !host.contains('/') && host.parse::<IpAddr>().is_err() {
+                self.domains.insert(host);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty() && self.hosts.is_empty() && self.patterns.is_none()
+    }
+
+    pub fn len(&self) -> usize {
+        self.domains.len() + self.hosts.len() + self.patterns.as_ref().map_or(0, RegexSet::len)
+    }
+
+    pub fn contains(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default();
+        if self.hosts.contains(host) || self.domains.contains(host) {
+            return true;
+        }
+        let mut rest = host;
+        while let Some((_, parent)) = rest.split_once('.') {
+            if self.domains.contains(parent) {
+                return true;
+            }
+            rest = parent;
+        }
+        self.patterns
+            .as_ref()
+            .is_some_and(|patterns| patterns.is_match(url.as_str()))
+    }
+}
+
+/// Whether the url is on one of the subscribed blocklists.
+pub fn is_blocked(url: &str) -> bool {
+    let blocklist = BLOCKLIST.read().clone();
+    if blocklist.is_empty() {
+        return false;
+    }
+    Url::parse(url).is_ok_and(|url| blocklist.contains(&url))
+}
+
+/// Download the subscriptions now and every `refresh_hours`, if there are any.
+pub fn spawn(config: Arc<Config>) {
+    if config.blocklists.subscriptions.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(config.blocklists.refresh_hours.max(1) * 60 * 60);
+        loop {
+            refresh(&config).await;
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+async fn refresh(config: &Config) {
+    for url in &config.blocklists.subscriptions {
+        match download(url).await {
+            Ok(list) => {
+                DOWNLOADED.lock().insert(url.clone(), list);
+            }
+            Err(err) => warn!("Couldn't download the blocklist at {url}: {err}"),
+        }
+    }
+    let blocklist = {
+        let downloaded = DOWNLOADED.lock();
+        Blocklist::parse(downloaded.values().map(String::as_str))
+    };
+    info!("Loaded {} blocklist rules", blocklist.len());
+    *BLOCKLIST.write() = Arc::new(blocklist);
+}
+
+async fn download(url: &str) -> eyre::Result<String> {
+    Ok(CLIENT
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked(blocklist: &Blocklist, url: &str) -> bool {
+        blocklist.contains(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_ublacklist() {
+        let blocklist = Blocklist::parse(["# a comment\n\
+            *://*.spam.com/*\n\
+            *://exact.net/*\n\
+            *://example.org/blog/*\n\
+            /copycat\\.[a-z]+/\n\
+            *://*/*\n"]);
+        assert!(blocked(&blocklist, "https://spam.com/"));
+        assert!(blocked(&blocklist, "https://www.spam.com/page"));
+        assert!(blocked(&blocklist, "https://exact.net/"));
+        assert!(!blocked(&blocklist, "https://sub.exact.net/"));
+        assert!(blocked(&blocklist, "https://example.org/blog/post"));
+        assert!(!blocked(&blocklist, "https://example.org/docs"));
+        assert!(blocked(&blocklist, "https://copycat.io/rust"));
+        assert!(!blocked(&blocklist, "https://rust-lang.org/"));
+    }
+
+    #[test]
+    fn test_hosts() {
+        let blocklist = Blocklist::parse(["127.0.0.1 localhost\n\
+            0.0.0.0 spam.com www.spam.net # two at once\n\
+            plain.org\n"]);
+        assert_eq!(blocklist.len(), 3);
+        assert!(blocked(&blocklist, "https://spam.com/"));
+        assert!(blocked(&blocklist, "https://www.spam.net/"));
+        assert!(blocked(&blocklist, "https://docs.plain.org/"));
+        assert!(!blocked(&blocklist, "https://localhost/"));
+    }
+}
@@ -1,39 +1,212 @@
 use std::{
+    cmp::Reverse,
     collections::HashMap,
     fs,
-    net::SocketAddr,
+    net::{IpAddr, SocketAddr},
     path::Path,
+    str::FromStr,
     sync::{Arc, LazyLock},
 };
 
-use serde::Deserialize;
-use tracing::info;
+use serde::{Deserialize, Deserializer};
+use tracing::warn;
+use wreq_util::{Emulation, EmulationOS};
 
-use crate::engines::Engine;
+use crate::engines::{
+    preprocess::{PreprocessStep, DEFAULT_STEPS},
+    Engine, SafeSearch, DEFAULT_REQUESTED_COUNT,
+};
 
 impl Default for Config {
     fn default() -> Self {
         Config {
             bind: "0.0.0.0:28019".parse().unwrap(),
             api: false,
+            explain: false,
+            cookie_secret: "".to_string(),
+            language: "en-US".to_string(),
+            safesearch: SafeSearch::Moderate,
+            log_format: LogFormat::Text,
+            enrich_descriptions: false,
+            requested_count: DEFAULT_REQUESTED_COUNT,
+            max_results_per_engine: None,
+            max_pages: 1,
+            assets_dir: None,
+            auth: AuthConfig {
+                basic: None,
+                token: None,
+                allowed_ips: vec![],
+                trust_x_forwarded_for: false,
+                admin_token: None,
+            },
             ui: UiConfig {
                 show_engine_list_separator: false,
                 show_version_info: false,
                 site_name: "metasearch".to_string(),
                 show_settings_link: true,
+                theme: "dark".to_string(),
                 stylesheet_url: "".to_string(),
                 stylesheet_str: "".to_string(),
                 favicon_url: "".to_string(),
                 show_autocomplete: true,
+                results_per_page: None,
+                open_in_new_tab: false,
+                skip_recipe_stories: false,
+                redirect_links: false,
+                explain_results: true,
             },
             image_search: ImageSearchConfig {
                 enabled: false,
                 show_engines: true,
+                prefetch: false,
+                probe: false,
+                blur_nsfw: true,
                 proxy: ImageProxyConfig {
-                    enabled: true,
+                    enabled: cfg!(feature = "proxies"),
                     max_download_size: 10_000_000,
                 },
             },
+            proxy_cache: ProxyCacheConfig {
+                enabled: false,
+                directory: "proxy-cache".to_string(),
+                max_size: 500_000_000,
+            },
+            plugins: PluginsConfig { directory: None },
+            fallback: FallbackConfig {
+                engines: vec![Engine::Google, Engine::Bing],
+                instances: vec![],
+            },
+            autocomplete: AutocompleteConfig {
+                peers: vec![],
+                use_engines: true,
+            },
+            answers: AnswersConfig {
+                precedence: vec![
+                    Engine::Timezone,
+                    Engine::ColorPicker,
+                    Engine::Ip,
+                    Engine::Dns,
+                    Engine::Useragent,
+                    Engine::Ticker,
+                    Engine::Encoding,
+                    Engine::Packages,
+                    Engine::Cheatsheet,
+                    Engine::Numbat,
+                    Engine::Fend,
+                    Engine::Lyrics,
+                    Engine::Dictionary,
+                    Engine::Thesaurus,
+                    Engine::Wikipedia,
+                    Engine::Notepad,
+                ],
+                show_secondary: true,
+            },
+            ranking: RankingConfig {
+                algorithm: RankingAlgorithm::RankSum,
+                rrf_k: 60.,
+                max_description_length: 300,
+            },
+            canary: CanaryConfig {
+                enabled: false,
+                query: "wikipedia".to_string(),
+                interval_minutes: 60,
+                min_results: 3,
+                disable_broken: false,
+            },
+            drift: DriftConfig {
+                enabled: false,
+                min_samples: 20,
+                drop_ratio: 0.3,
+                show_banner: true,
+            },
+            geo: GeoConfig { location: None },
+            history: HistoryConfig {
+                enabled: false,
+                database: "history.sqlite".to_string(),
+                session_minutes: 30,
+            },
+            visits: VisitsConfig {
+                enabled: false,
+                database: "visits.sqlite".to_string(),
+            },
+            saved_searches: SavedSearchesConfig {
+                enabled: false,
+                database: "saved-searches.sqlite".to_string(),
+                interval_minutes: 60,
+                webhook: None,
+            },
+            snapshots: SnapshotsConfig {
+                enabled: false,
+                database: "snapshots.sqlite".to_string(),
+                expiry_days: 30,
+            },
+            http: HttpConfig {
+                async_dns: true,
+                pool_max_idle_per_host: None,
+                http2: true,
+                tcp_keepalive_secs: 15,
+                hosts: HashMap::new(),
+                emulation: Emulation::Firefox139,
+                emulation_os: EmulationOS::MacOS,
+                cookies: false,
+                cookie_directory: "cookies".to_string(),
+            },
+            scheduler: SchedulerConfig {
+                default: HostLimits::default(),
+                // small engines that rate limit bursts of searches
+                hosts: HashMap::from([
+                    (
+                        "old-search.marginalia.nu".to_string(),
+                        HostLimits {
+                            min_delay_ms: 250,
+                            max_concurrent: 2,
+                        },
+                    ),
+                    (
+                        "stract.com".to_string(),
+                        HostLimits {
+                            min_delay_ms: 250,
+                            max_concurrent: 4,
+                        },
+                    ),
+                    // for [wayback]
+                    (
+                        "archive.org".to_string(),
+                        HostLimits {
+                            min_delay_ms: 0,
+                            max_concurrent: 4,
+                        },
+                    ),
+                ]),
+            },
+            adult_filter: AdultFilterConfig {
+                enabled: false,
+                domains: vec![],
+                keywords: vec![],
+                min_keyword_matches: 2,
+            },
+            language_filter: LanguageFilterConfig {
+                mode: LanguageFilterMode::Off,
+                languages: vec![],
+                downrank_factor: 0.5,
+            },
+            blocklists: BlocklistsConfig {
+                subscriptions: vec![],
+                refresh_hours: 24,
+            },
+            wayback: WaybackConfig {
+                enabled: false,
+                engines: vec![],
+                max_lookups: 10,
+            },
+            dead_links: DeadLinksConfig {
+                enabled: false,
+                max_checks: 10,
+                timeout_ms: 2000,
+            },
+            preprocess: PreprocessConfig {
+                steps: DEFAULT_STEPS.to_vec(),
+            },
             engines: Arc::new(EnginesConfig::default()),
             urls: UrlsConfig {
                 replace: vec![(
@@ -41,6 +214,7 @@ impl Default for Config {
                     HostAndPath::new("minecraft.wiki/w/"),
                 )],
                 weight: vec![],
+                annotations: vec![],
             },
         }
     }
@@ -83,6 +257,15 @@ impl Default for EnginesConfig {
             Engine::GoogleScholar,
             EngineConfig::new().with_weight(0.50).disabled(),
         );
+        map.insert(
+            Engine::OpenAlex,
+            EngineConfig::new().with_weight(0.50).disabled(),
+        );
+        // the index is small, so it doesn't have anything for a lot of queries
+        map.insert(
+            Engine::Mwmbl,
+            EngineConfig::new().with_weight(0.15).disabled(),
+        );
         map.insert(
             Engine::RightDao,
             EngineConfig::new().with_weight(0.10).disabled(),
@@ -91,10 +274,74 @@ impl Default for EnginesConfig {
             Engine::Stract,
             EngineConfig::new().with_weight(0.15).disabled(),
         );
+        map.insert(
+            Engine::Wiby,
+            EngineConfig::new().with_weight(0.10).disabled(),
+        );
         map.insert(
             Engine::Yep,
             EngineConfig::new().with_weight(0.10).disabled(),
         );
+        // these scrape the big engines from somewhere else, for when those block us
+        map.insert(
+            Engine::FourGet,
+            EngineConfig::new().with_weight(0.50).disabled(),
+        );
+        map.insert(
+            Engine::LibreY,
+            EngineConfig::new().with_weight(0.50).disabled(),
+        );
+        // there's no default instance, so this needs endpoints to be set
+        map.insert(
+            Engine::SearXng,
+            EngineConfig::new().with_weight(0.50).disabled(),
+        );
+        // needs a directory to be set, and shows the server's files to anyone who
+        // can search
+        map.insert(Engine::Local, EngineConfig::new().opt_in());
+        // only does anything if [visits] is enabled, and it's high so the pages
+        // are shown first
+        map.insert(Engine::Visited, EngineConfig::new().with_weight(5.0));
+        // mostly useful for non-english (especially chinese) queries
+        map.insert(
+            Engine::Petal,
+            EngineConfig::new().with_weight(0.30).disabled(),
+        );
+        map.insert(
+            Engine::Sogou,
+            EngineConfig::new().with_weight(0.30).disabled(),
+        );
+        // every search is a request per site, and the api has a low daily limit
+        // without a key
+        map.insert(
+            Engine::StackExchangeSearch,
+            EngineConfig::new().with_weight(0.50).disabled(),
+        );
+        // code tab
+        map.insert(Engine::Codeberg, EngineConfig::new().with_weight(1.0));
+        map.insert(Engine::SourceHut, EngineConfig::new().with_weight(0.50));
+        // media tab, needs an api key
+        map.insert(
+            Engine::PodcastIndex,
+            EngineConfig::new().with_weight(1.0).disabled(),
+        );
+        // recipes tab, which also searches the normal engines
+        map.insert(
+            Engine::TheMealDb,
+            EngineConfig::new().with_weight(0.50).disabled(),
+        );
+        // jobs tab
+        map.insert(Engine::Remotive, EngineConfig::new().with_weight(1.0));
+        // lyrics are a legal gray area, so the instance picks a backend first
+        map.insert(Engine::Lyrics, EngineConfig::new().disabled());
+        // paid apis, so users can't turn them on unless the instance has a key
+        map.insert(Engine::Kagi, EngineConfig::new().with_weight(1.25).opt_in());
+        // shadow libraries aren't legal everywhere, so this has to be enabled in the
+        // config
+        map.insert(
+            Engine::AnnasArchive,
+            EngineConfig::new().with_weight(0.30).opt_in(),
+        );
 
         // calculators (give them a high weight so they're always the first thing in
         // autocomplete)
@@ -104,6 +351,9 @@ impl Default for EnginesConfig {
             EngineConfig::new().with_weight(10.0).disabled(),
         );
 
+        // higher than wikipedia (1.0) so its infobox wins when it has facts to show
+        map.insert(Engine::Wikidata, EngineConfig::new().with_weight(1.1));
+
         // other engines
         map.insert(
             Engine::Mdn,
@@ -123,6 +373,7 @@ impl Default for EngineConfig {
         Self {
             enabled: true,
             weight: 1.0,
+            opt_in: false,
             extra: Default::default(),
         }
     }
@@ -144,6 +395,14 @@ impl EngineConfig {
     pub fn with_extra(self, extra: toml::Table) -> Self {
         Self { extra, ..self }
     }
+    /// Disabled, and users can't enable it unless it's enabled in the config.
+    pub fn opt_in(self) -> Self {
+        Self {
+            enabled: false,
+            opt_in: true,
+            ..self
+        }
+    }
 }
 
 //
@@ -153,8 +412,58 @@ pub struct Config {
     pub bind: SocketAddr,
     /// Whether the JSON API should be accessible.
     pub api: bool,
+    /// Whether /api/explain is accessible. It also needs `auth.admin_token`
+    /// to be set, since it shows how engines are requested.
+    pub explain: bool,
+    /// Used for signing the settings cookie. If this is empty or too short, a
+    /// random one is generated and saved to `cookie-secret` in the directory
+    /// that metasearch is run from.
+    pub cookie_secret: String,
+    /// A BCP 47 language tag like `en-US`, used by engines that support
+    /// localized results. If this is `auto`, it's detected from each query.
+    pub language: String,
+    pub safesearch: SafeSearch,
+    pub log_format: LogFormat,
+    /// Whether to fetch the pages of results that the engines didn't give a
+    /// description for, and use the description from their meta tags.
+    pub enrich_descriptions: bool,
+    /// How many results to ask each engine for, for the engines that let us
+    /// choose.
+    pub requested_count: usize,
+    /// Only keep this many results from each engine, even if it returned more.
+    pub max_results_per_engine: Option<usize>,
+    /// How many pages engines like Google and Bing can be asked for at once,
+    /// when `requested_count` is more than one page. The pages are requested
+    /// at the same time, and 1 turns this off.
+    pub max_pages: usize,
+    /// A directory with files that override the css, js, and other assets
+    /// that are built into metasearch, like `style.css`. It can also have new
+    /// files, like a theme for `ui.stylesheet_url`.
+    pub assets_dir: Option<String>,
+    pub auth: AuthConfig,
     pub ui: UiConfig,
     pub image_search: ImageSearchConfig,
+    pub proxy_cache: ProxyCacheConfig,
+    pub plugins: PluginsConfig,
+    pub fallback: FallbackConfig,
+    pub autocomplete: AutocompleteConfig,
+    pub answers: AnswersConfig,
+    pub ranking: RankingConfig,
+    pub canary: CanaryConfig,
+    pub drift: DriftConfig,
+    pub geo: GeoConfig,
+    pub history: HistoryConfig,
+    pub visits: VisitsConfig,
+    pub saved_searches: SavedSearchesConfig,
+    pub snapshots: SnapshotsConfig,
+    pub http: HttpConfig,
+    pub scheduler: SchedulerConfig,
+    pub adult_filter: AdultFilterConfig,
+    pub language_filter: LanguageFilterConfig,
+    pub blocklists: BlocklistsConfig,
+    pub wayback: WaybackConfig,
+    pub dead_links: DeadLinksConfig,
+    pub preprocess: PreprocessConfig,
     // wrapped in an arc to make Config cheaper to clone
     pub engines: Arc<EnginesConfig>,
     pub urls: UrlsConfig,
@@ -164,8 +473,40 @@ pub struct Config {
 pub struct PartialConfig {
     pub bind: Option<SocketAddr>,
     pub api: Option<bool>,
+    pub explain: Option<bool>,
+    pub cookie_secret: Option<String>,
+    pub language: Option<String>,
+    pub safesearch: Option<SafeSearch>,
+    pub log_format: Option<LogFormat>,
+    pub enrich_descriptions: Option<bool>,
+    pub requested_count: Option<usize>,
+    pub max_results_per_engine: Option<usize>,
+    pub max_pages: Option<usize>,
+    pub assets_dir: Option<String>,
+    pub auth: Option<PartialAuthConfig>,
     pub ui: Option<PartialUiConfig>,
     pub image_search: Option<PartialImageSearchConfig>,
+    pub proxy_cache: Option<PartialProxyCacheConfig>,
+    pub plugins: Option<PartialPluginsConfig>,
+    pub fallback: Option<PartialFallbackConfig>,
+    pub autocomplete: Option<PartialAutocompleteConfig>,
+    pub answers: Option<PartialAnswersConfig>,
+    pub ranking: Option<PartialRankingConfig>,
+    pub canary: Option<PartialCanaryConfig>,
+    pub drift: Option<PartialDriftConfig>,
+    pub geo: Option<PartialGeoConfig>,
+    pub history: Option<PartialHistoryConfig>,
+    pub visits: Option<PartialVisitsConfig>,
+    pub saved_searches: Option<PartialSavedSearchesConfig>,
+    pub snapshots: Option<PartialSnapshotsConfig>,
+    pub http: Option<PartialHttpConfig>,
+    pub scheduler: Option<PartialSchedulerConfig>,
+    pub adult_filter: Option<PartialAdultFilterConfig>,
+    pub language_filter: Option<PartialLanguageFilterConfig>,
+    pub blocklists: Option<PartialBlocklistsConfig>,
+    pub wayback: Option<PartialWaybackConfig>,
+    pub dead_links: Option<PartialDeadLinksConfig>,
+    pub preprocess: Option<PartialPreprocessConfig>,
     pub engines: Option<PartialEnginesConfig>,
     pub urls: Option<PartialUrlsConfig>,
 }
@@ -174,15 +515,172 @@ impl Config {
     pub fn overlay(&mut self, partial: PartialConfig) {
         self.bind = partial.bind.unwrap_or(self.bind);
         self.api = partial.api.unwrap_or(self.api);
+        self.explain = partial.explain.unwrap_or(self.explain);
+        self.cookie_secret = partial.cookie_secret.unwrap_or(self.cookie_secret.clone());
+        self.language = partial.language.unwrap_or(self.language.clone());
+        self.safesearch = partial.safesearch.unwrap_or(self.safesearch);
+        self.log_format = partial.log_format.unwrap_or(self.log_format);
+        self.enrich_descriptions = partial
+            .enrich_descriptions
+            .unwrap_or(self.enrich_descriptions);
+        self.requested_count = partial.requested_count.unwrap_or(self.requested_count);
+        self.max_results_per_engine = partial
+            .max_results_per_engine
+            .or(self.max_results_per_engine);
+        self.max_pages = partial.max_pages.unwrap_or(self.max_pages);
+        self.assets_dir = partial.assets_dir.or(self.assets_dir.clone());
+        self.auth.overlay(partial.auth.unwrap_or_default());
         self.ui.overlay(partial.ui.unwrap_or_default());
         self.image_search
             .overlay(partial.image_search.unwrap_or_default());
+        self.proxy_cache
+            .overlay(partial.proxy_cache.unwrap_or_default());
+        self.plugins.overlay(partial.plugins.unwrap_or_default());
+        self.fallback.overlay(partial.fallback.unwrap_or_default());
+        self.autocomplete
+            .overlay(partial.autocomplete.unwrap_or_default());
+        self.answers.overlay(partial.answers.unwrap_or_default());
+        self.ranking.overlay(partial.ranking.unwrap_or_default());
+        self.canary.overlay(partial.canary.unwrap_or_default());
+        self.drift.overlay(partial.drift.unwrap_or_default());
+        self.geo.overlay(partial.geo.unwrap_or_default());
+        self.history.overlay(partial.history.unwrap_or_default());
+        self.visits.overlay(partial.visits.unwrap_or_default());
+        self.saved_searches
+            .overlay(partial.saved_searches.unwrap_or_default());
+        self.snapshots
+            .overlay(partial.snapshots.unwrap_or_default());
+        self.http.overlay(partial.http.unwrap_or_default());
+        self.scheduler
+            .overlay(partial.scheduler.unwrap_or_default());
+        self.adult_filter
+            .overlay(partial.adult_filter.unwrap_or_default());
+        self.language_filter
+            .overlay(partial.language_filter.unwrap_or_default());
+        self.blocklists
+            .overlay(partial.blocklists.unwrap_or_default());
+        self.wayback.overlay(partial.wayback.unwrap_or_default());
+        self.dead_links
+            .overlay(partial.dead_links.unwrap_or_default());
+        self.preprocess
+            .overlay(partial.preprocess.unwrap_or_default());
         if let Some(partial_engines) = partial.engines {
             let mut engines = self.engines.as_ref().clone();
             engines.overlay(partial_engines);
             self.engines = Arc::new(engines);
         }
         self.urls.overlay(partial.urls.unwrap_or_default());
+
+        // everyone who uses the instance shares the same history, saved searches,
+        // and visits, so they're only allowed on private instances
+        if self.history.enabled && !self.auth.is_enabled() {
+            warn!("history.enabled is set, but it needs auth since the history is shared by everyone who uses the instance");
+            self.history.enabled = false;
+        }
+        if self.saved_searches.enabled && !self.auth.is_enabled() {
+            warn!("saved_searches.enabled is set, but it needs auth since saved searches are shared by everyone who uses the instance");
+            self.saved_searches.enabled = false;
+        }
+        if self.visits.enabled && !self.auth.is_enabled() {
+            warn!("visits.enabled is set, but it needs auth since visits are shared by everyone who uses the instance");
+            self.visits.enabled = false;
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    /// One json object per line, for log collectors. Every line from a search
+    /// has its `request_id`.
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    /// Require HTTP basic auth with these credentials.
+    pub basic: Option<BasicAuthConfig>,
+    /// A shared secret that can be entered on the /login page (which sets a
+    /// cookie) or sent as a bearer token.
+    pub token: Option<String>,
+    /// Clients from these IP ranges are always let in.
+    pub allowed_ips: Vec<IpRange>,
+    /// Use the last address in the X-Forwarded-For header as the client IP.
+    /// Only enable this if you're behind a reverse proxy that sets it.
+    pub trust_x_forwarded_for: bool,
+    /// A secret for the endpoints that only whoever runs the instance should
+    /// use, like /api/explain. It's sent in the `X-Admin-Token` header, and
+    /// is separate from the other auth so users of a private instance can't
+    /// use them.
+    pub admin_token: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BasicAuthConfig {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialAuthConfig {
+    pub basic: Option<BasicAuthConfig>,
+    pub token: Option<String>,
+    pub allowed_ips: Option<Vec<IpRange>>,
+    pub trust_x_forwarded_for: Option<bool>,
+    pub admin_token: Option<String>,
+}
+
+impl AuthConfig {
+    pub fn overlay(&mut self, partial: PartialAuthConfig) {
+        self.basic = partial.basic.or(self.basic.clone());
+        self.token = partial.token.or(self.token.clone());
+        self.allowed_ips = partial.allowed_ips.unwrap_or(self.allowed_ips.clone());
+        self.trust_x_forwarded_for = partial
+            .trust_x_forwarded_for
+            .unwrap_or(self.trust_x_forwarded_for);
+        self.admin_token = partial.admin_token.or(self.admin_token.clone());
+    }
+
+    /// Whether any kind of access control is configured. If this is false then
+    /// the instance is public.
+    pub fn is_enabled(&self) -> bool {
+        self.basic.is_some() || self.token.is_some() || !self.allowed_ips.is_empty()
+    }
+}
+
+/// An IP address with a prefix length, like `192.168.0.0/16`. A plain address
+/// is treated as a range containing only itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpRange {
+    pub addr: IpAddr,
+    pub prefix_len: u8,
+}
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = s.split_once('/').unwrap_or((s, ""));
+        let addr = IpAddr::from_str(addr).map_err(|_| format!("invalid ip address '{addr}'"))?;
+        let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = if prefix_len.is_empty() {
+            max_prefix_len
+        } else {
+            match u8::from_str(prefix_len) {
+                Ok(n) if n <= max_prefix_len => n,
+                _ => return Err(format!("invalid prefix length '{prefix_len}'")),
+            }
+        };
+        Ok(Self { addr, prefix_len })
+    }
+}
+impl<'de> Deserialize<'de> for IpRange {
+    fn deserialize<D>(deserializer: D) -> Result<IpRange, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        IpRange::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
 
@@ -195,9 +693,25 @@ pub struct UiConfig {
     pub show_settings_link: bool,
     pub site_name: String,
     pub show_autocomplete: bool,
+    /// The id of one of the built-in themes, like `light` or `auto`.
+    pub theme: String,
+    /// A stylesheet that's loaded after the theme.
     pub stylesheet_url: String,
     pub stylesheet_str: String,
     pub favicon_url: String,
+    /// The maximum number of results shown on the page. All of them are shown
+    /// if this is None.
+    pub results_per_page: Option<usize>,
+    pub open_in_new_tab: bool,
+    /// Link recipes straight to their recipe card, skipping the story before
+    /// it.
+    pub skip_recipe_stories: bool,
+    /// Send result links through `/r`, so sites don't see the search page as
+    /// the referrer even if the browser ignores `rel="noreferrer"`.
+    pub redirect_links: bool,
+    /// Show a "why is this here" popover on results, with the engines that
+    /// returned them and how they were ranked.
+    pub explain_results: bool,
 }
 
 #[derive(Deserialize, Debug, Default)]
@@ -206,11 +720,17 @@ pub struct PartialUiConfig {
     pub show_version_info: Option<bool>,
     pub show_settings_link: Option<bool>,
     pub show_autocomplete: Option<bool>,
+    pub open_in_new_tab: Option<bool>,
+    pub skip_recipe_stories: Option<bool>,
+    pub redirect_links: Option<bool>,
+    pub explain_results: Option<bool>,
 
     pub site_name: Option<String>,
+    pub theme: Option<String>,
     pub stylesheet_url: Option<String>,
     pub stylesheet_str: Option<String>,
     pub favicon_url: Option<String>,
+    pub results_per_page: Option<usize>,
 }
 
 impl UiConfig {
@@ -224,6 +744,7 @@ impl UiConfig {
             .unwrap_or(self.show_settings_link);
         self.show_autocomplete = partial.show_autocomplete.unwrap_or(self.show_autocomplete);
         self.site_name = partial.site_name.unwrap_or(self.site_name.clone());
+        self.theme = partial.theme.unwrap_or(self.theme.clone());
         self.stylesheet_url = partial
             .stylesheet_url
             .unwrap_or(self.stylesheet_url.clone());
@@ -231,6 +752,13 @@ impl UiConfig {
             .stylesheet_str
             .unwrap_or(self.stylesheet_str.clone());
         self.favicon_url = partial.favicon_url.unwrap_or(self.favicon_url.clone());
+        self.results_per_page = partial.results_per_page.or(self.results_per_page);
+        self.open_in_new_tab = partial.open_in_new_tab.unwrap_or(self.open_in_new_tab);
+        self.skip_recipe_stories = partial
+            .skip_recipe_stories
+            .unwrap_or(self.skip_recipe_stories);
+        self.redirect_links = partial.redirect_links.unwrap_or(self.redirect_links);
+        self.explain_results = partial.explain_results.unwrap_or(self.explain_results);
     }
 }
 
@@ -238,6 +766,17 @@ impl UiConfig {
 pub struct ImageSearchConfig {
     pub enabled: bool,
     pub show_engines: bool,
+    /// Fetch image results in the background when someone searches on the
+    /// "all" tab. This makes switching tabs instant, but it means every search
+    /// also makes image requests.
+    pub prefetch: bool,
+    /// Check that each image result loads before showing it, and get its real
+    /// size from the file. This hides dead images, but makes the images tab
+    /// slower.
+    pub probe: bool,
+    /// Blur images that are probably not safe for work until they're clicked,
+    /// unless safe search is off.
+    pub blur_nsfw: bool,
     pub proxy: ImageProxyConfig,
 }
 
@@ -245,6 +784,9 @@ pub struct ImageSearchConfig {
 pub struct PartialImageSearchConfig {
     pub enabled: Option<bool>,
     pub show_engines: Option<bool>,
+    pub prefetch: Option<bool>,
+    pub probe: Option<bool>,
+    pub blur_nsfw: Option<bool>,
     pub proxy: Option<PartialImageProxyConfig>,
 }
 
@@ -252,7 +794,14 @@ impl ImageSearchConfig {
     pub fn overlay(&mut self, partial: PartialImageSearchConfig) {
         self.enabled = partial.enabled.unwrap_or(self.enabled);
         self.show_engines = partial.show_engines.unwrap_or(self.show_engines);
+        self.prefetch = partial.prefetch.unwrap_or(self.prefetch);
+        self.probe = partial.probe.unwrap_or(self.probe);
+        self.blur_nsfw = partial.blur_nsfw.unwrap_or(self.blur_nsfw);
         self.proxy.overlay(partial.proxy.unwrap_or_default());
+        if self.enabled && !cfg!(feature = "images") {
+            warn!("image_search.enabled is set, but metasearch was built without the `images` feature");
+            self.enabled = false;
+        }
     }
 }
 
@@ -275,6 +824,645 @@ impl ImageProxyConfig {
     pub fn overlay(&mut self, partial: PartialImageProxyConfig) {
         self.enabled = partial.enabled.unwrap_or(self.enabled);
         self.max_download_size = partial.max_download_size.unwrap_or(self.max_download_size);
+        if self.enabled && !cfg!(feature = "proxies") {
+            // images are loaded straight from their sites instead
+            self.enabled = false;
+        }
+    }
+}
+
+/// Proxied files are kept on disk, so they're only downloaded once and the
+/// cache survives restarts.
+#[derive(Debug, Clone)]
+pub struct ProxyCacheConfig {
+    pub enabled: bool,
+    /// The directory the files are kept in, relative to where metasearch is
+    /// run.
+    pub directory: String,
+    /// The most the cache can take up, in bytes. The files that were used least
+    /// recently are deleted when it's full.
+    pub max_size: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialProxyCacheConfig {
+    pub enabled: Option<bool>,
+    pub directory: Option<String>,
+    pub max_size: Option<u64>,
+}
+
+impl ProxyCacheConfig {
+    pub fn overlay(&mut self, partial: PartialProxyCacheConfig) {
+        self.enabled = partial.enabled.unwrap_or(self.enabled);
+        self.directory = partial.directory.unwrap_or(self.directory.clone());
+        self.max_size = partial.max_size.unwrap_or(self.max_size);
+    }
+}
+
+/// Engines that are defined in files, see [`crate::engines::plugins`].
+#[derive(Debug, Clone)]
+pub struct PluginsConfig {
+    /// The directory with the engine definitions, relative to where metasearch
+    /// is run. No plugins are loaded if it's not set.
+    pub directory: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialPluginsConfig {
+    pub directory: Option<String>,
+}
+
+impl PluginsConfig {
+    pub fn overlay(&mut self, partial: PartialPluginsConfig) {
+        self.directory = partial.directory.or(self.directory.clone());
+    }
+}
+
+/// Other frontends that searches are retried through when an engine sends a
+/// captcha.
+#[derive(Debug, Clone)]
+pub struct FallbackConfig {
+    /// The engines that are retried, since they're the ones that block us.
+    pub engines: Vec<Engine>,
+    /// Tried in order, but the ones that failed recently are tried last.
+    pub instances: Vec<FallbackInstance>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialFallbackConfig {
+    pub engines: Option<Vec<Engine>>,
+    pub instances: Option<Vec<FallbackInstance>>,
+}
+
+impl FallbackConfig {
+    pub fn overlay(&mut self, partial: PartialFallbackConfig) {
+        self.engines = partial.engines.unwrap_or(self.engines.clone());
+        self.instances = partial.instances.unwrap_or(self.instances.clone());
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FallbackInstance {
+    /// The base url of the instance, like `https://4get.ca`.
+    pub url: String,
+    pub kind: FallbackKind,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FallbackKind {
+    #[serde(rename = "4get")]
+    FourGet,
+    /// LibreX has the same api.
+    #[serde(rename = "librey", alias = "librex")]
+    LibreY,
+}
+
+#[derive(Debug, Clone)]
+pub struct AutocompleteConfig {
+    /// Other metasearch or SearXNG instances to get autocomplete suggestions
+    /// from, so instances can share the load instead of all using Google.
+    pub peers: Vec<AutocompletePeer>,
+    /// Whether our own engines (like Google) should be used for autocomplete.
+    /// You can turn this off if you'd rather only use peers.
+    pub use_engines: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialAutocompleteConfig {
+    pub peers: Option<Vec<AutocompletePeer>>,
+    pub use_engines: Option<bool>,
+}
+
+impl AutocompleteConfig {
+    pub fn overlay(&mut self, partial: PartialAutocompleteConfig) {
+        self.peers = partial.peers.unwrap_or(self.peers.clone());
+        self.use_engines = partial.use_engines.unwrap_or(self.use_engines);
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AutocompletePeer {
+    /// The base url of the instance, like `https://search.example.com`.
+    pub url: String,
+    #[serde(default)]
+    pub kind: PeerKind,
+    #[serde(default = "default_peer_weight")]
+    pub weight: f64,
+    /// Sent as a bearer token, for peers that have `auth.token` set.
+    pub token: Option<String>,
+}
+
+fn default_peer_weight() -> f64 {
+    1.
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PeerKind {
+    #[default]
+    Metasearch,
+    Searxng,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnswersConfig {
+    /// When multiple answer engines match a query, the one that's earliest in
+    /// this list is shown. Engines that aren't in the list go after the ones
+    /// that are, ordered by weight.
+    pub precedence: Vec<Engine>,
+    /// Whether the answer that came second should be shown (collapsed) under
+    /// the main one.
+    pub show_secondary: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialAnswersConfig {
+    pub precedence: Option<Vec<Engine>>,
+    pub show_secondary: Option<bool>,
+}
+
+impl AnswersConfig {
+    pub fn overlay(&mut self, partial: PartialAnswersConfig) {
+        self.precedence = partial.precedence.unwrap_or(self.precedence.clone());
+        self.show_secondary = partial.show_secondary.unwrap_or(self.show_secondary);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RankingConfig {
+    pub algorithm: RankingAlgorithm,
+    /// The `k` constant for reciprocal rank fusion. Higher values make the top
+    /// positions matter less compared to being returned by more engines.
+    pub rrf_k: f64,
+    /// Longer descriptions are cut at the end of a sentence, since some
+    /// engines return whole abstracts. 0 means they're never cut.
+    pub max_description_length: usize,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RankingAlgorithm {
+    /// Each engine adds `weight / position` to a result's score.
+    RankSum,
+    /// Each engine adds `weight / (k + position)`, which favors results that
+    /// many engines agree on over ones that a single engine put first.
+    ReciprocalRankFusion,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialRankingConfig {
+    pub algorithm: Option<RankingAlgorithm>,
+    pub rrf_k: Option<f64>,
+    pub max_description_length: Option<usize>,
+}
+
+impl RankingConfig {
+    pub fn overlay(&mut self, partial: PartialRankingConfig) {
+        self.algorithm = partial.algorithm.unwrap_or(self.algorithm);
+        self.rrf_k = partial.rrf_k.unwrap_or(self.rrf_k);
+        self.max_description_length = partial
+            .max_description_length
+            .unwrap_or(self.max_description_length);
+    }
+}
+
+/// A background check that searches for something on every engine now and
+/// then, to notice when an engine changes its markup and our parser breaks.
+#[derive(Debug, Clone)]
+pub struct CanaryConfig {
+    pub enabled: bool,
+    /// Should be something that every engine has plenty of results for.
+    pub query: String,
+    pub interval_minutes: u64,
+    /// Engines that return fewer results than this are considered broken.
+    pub min_results: usize,
+    /// Whether broken engines should be skipped in searches until they pass
+    /// the check again.
+    pub disable_broken: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialCanaryConfig {
+    pub enabled: Option<bool>,
+    pub query: Option<String>,
+    pub interval_minutes: Option<u64>,
+    pub min_results: Option<usize>,
+    pub disable_broken: Option<bool>,
+}
+
+impl CanaryConfig {
+    pub fn overlay(&mut self, partial: PartialCanaryConfig) {
+        self.enabled = partial.enabled.unwrap_or(self.enabled);
+        self.query = partial.query.unwrap_or(self.query.clone());
+        self.interval_minutes = partial.interval_minutes.unwrap_or(self.interval_minutes);
+        self.min_results = partial.min_results.unwrap_or(self.min_results);
+        self.disable_broken = partial.disable_broken.unwrap_or(self.disable_broken);
+    }
+}
+
+/// Keeping track of how many results each engine usually gives, to warn when
+/// an engine still responds but suddenly gives much fewer results.
+#[derive(Debug, Clone)]
+pub struct DriftConfig {
+    pub enabled: bool,
+    /// How many responses an engine needs before it can be considered
+    /// drifting.
+    pub min_samples: u64,
+    /// An engine is drifting if its recent average is below this fraction of
+    /// its long-term average.
+    pub drop_ratio: f64,
+    /// Whether to show a warning above the results when an engine is drifting.
+    pub show_banner: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialDriftConfig {
+    pub enabled: Option<bool>,
+    pub min_samples: Option<u64>,
+    pub drop_ratio: Option<f64>,
+    pub show_banner: Option<bool>,
+}
+
+impl DriftConfig {
+    pub fn overlay(&mut self, partial: PartialDriftConfig) {
+        self.enabled = partial.enabled.unwrap_or(self.enabled);
+        self.min_samples = partial.min_samples.unwrap_or(self.min_samples);
+        self.drop_ratio = partial.drop_ratio.unwrap_or(self.drop_ratio);
+        self.show_banner = partial.show_banner.unwrap_or(self.show_banner);
+    }
+}
+
+/// Where the instance's users are, for results that depend on a location.
+#[derive(Debug, Clone)]
+pub struct GeoConfig {
+    /// A place like "Berlin, Germany". Results for other places are filtered
+    /// out, like jobs on the jobs tab.
+    pub location: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialGeoConfig {
+    pub location: Option<String>,
+}
+
+impl GeoConfig {
+    pub fn overlay(&mut self, partial: PartialGeoConfig) {
+        self.location = partial.location.or(self.location.clone());
+    }
+}
+
+/// Remembering searches, for the /history page. Everyone who can search on the
+/// instance shares the same history, so this is meant for personal instances.
+#[derive(Debug, Clone)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// The path of the sqlite database, relative to where metasearch is run.
+    pub database: String,
+    /// Related searches are in the same session if they're at most this far
+    /// apart.
+    pub session_minutes: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialHistoryConfig {
+    pub enabled: Option<bool>,
+    pub database: Option<String>,
+    pub session_minutes: Option<u64>,
+}
+
+impl HistoryConfig {
+    pub fn overlay(&mut self, partial: PartialHistoryConfig) {
+        self.enabled = partial.enabled.unwrap_or(self.enabled);
+        self.database = partial.database.unwrap_or(self.database.clone());
+        self.session_minutes = partial.session_minutes.unwrap_or(self.session_minutes);
+    }
+}
+
+/// Remembering the results that are clicked, so the `visited` engine can show
+/// them first when they match a search again. Like the history, it's shared by
+/// everyone who can search on the instance.
+#[derive(Debug, Clone)]
+pub struct VisitsConfig {
+    pub enabled: bool,
+    /// The path of the sqlite database, relative to where metasearch is run.
+    pub database: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialVisitsConfig {
+    pub enabled: Option<bool>,
+    pub database: Option<String>,
+}
+
+impl VisitsConfig {
+    pub fn overlay(&mut self, partial: PartialVisitsConfig) {
+        self.enabled = partial.enabled.unwrap_or(self.enabled);
+        self.database = partial.database.unwrap_or(self.database.clone());
+    }
+}
+
+/// Queries that are searched again now and then, with a feed of the new
+/// results.
+#[derive(Debug, Clone)]
+pub struct SavedSearchesConfig {
+    pub enabled: bool,
+    /// The path of the sqlite database, relative to where metasearch is run.
+    pub database: String,
+    pub interval_minutes: u64,
+    /// A url that new results are POSTed to as json.
+    pub webhook: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialSavedSearchesConfig {
+    pub enabled: Option<bool>,
+    pub database: Option<String>,
+    pub interval_minutes: Option<u64>,
+    pub webhook: Option<String>,
+}
+
+impl SavedSearchesConfig {
+    pub fn overlay(&mut self, partial: PartialSavedSearchesConfig) {
+        self.enabled = partial.enabled.unwrap_or(self.enabled);
+        self.database = partial.database.unwrap_or(self.database.clone());
+        self.interval_minutes = partial.interval_minutes.unwrap_or(self.interval_minutes);
+        self.webhook = partial.webhook.or(self.webhook.clone());
+    }
+}
+
+/// Frozen copies of result pages that can be shared at /s/<id>.
+#[derive(Debug, Clone)]
+pub struct SnapshotsConfig {
+    pub enabled: bool,
+    /// The path of the sqlite database, relative to where metasearch is run.
+    pub database: String,
+    /// How long a snapshot can be viewed for after it's shared.
+    pub expiry_days: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialSnapshotsConfig {
+    pub enabled: Option<bool>,
+    pub database: Option<String>,
+    pub expiry_days: Option<u64>,
+}
+
+impl SnapshotsConfig {
+    pub fn overlay(&mut self, partial: PartialSnapshotsConfig) {
+        self.enabled = partial.enabled.unwrap_or(self.enabled);
+        self.database = partial.database.unwrap_or(self.database.clone());
+        self.expiry_days = partial.expiry_days.unwrap_or(self.expiry_days);
+    }
+}
+
+/// How the http client that engines use connects. Engines can override these
+/// with `http` in their config, like `google = { http = { http2 = false } }`,
+/// and then get their own client.
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// Resolve hostnames with an async resolver that caches lookups, instead
+    /// of the system's resolver on a thread pool.
+    pub async_dns: bool,
+    /// How many idle connections to each host are kept open. None is no limit.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Whether HTTP/2 can be used. If it's off, only HTTP/1.1 is used.
+    pub http2: bool,
+    /// 0 turns off TCP keepalive.
+    pub tcp_keepalive_secs: u64,
+    /// Addresses to use for these hostnames instead of looking them up.
+    pub hosts: HashMap<String, Vec<IpAddr>>,
+    /// The browser that requests look like, down to the TLS and HTTP/2
+    /// fingerprints, so sites that block unusual fingerprints let us in.
+    pub emulation: Emulation,
+    /// The operating system that the browser says it's running on.
+    pub emulation_os: EmulationOS,
+    /// Keep the cookies that sites set, in a jar for each engine that's saved
+    /// to disk. This is usually only turned on for the engines that need it.
+    pub cookies: bool,
+    /// Where the cookie jars are saved, as `<engine>.json`.
+    pub cookie_directory: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialHttpConfig {
+    pub async_dns: Option<bool>,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub http2: Option<bool>,
+    pub tcp_keepalive_secs: Option<u64>,
+    pub hosts: Option<HashMap<String, Vec<IpAddr>>>,
+    pub emulation: Option<Emulation>,
+    pub emulation_os: Option<EmulationOS>,
+    pub cookies: Option<bool>,
+    pub cookie_directory: Option<String>,
+}
+
+impl HttpConfig {
+    pub fn overlay(&mut self, partial: PartialHttpConfig) {
+        self.async_dns = partial.async_dns.unwrap_or(self.async_dns);
+        self.pool_max_idle_per_host = partial
+            .pool_max_idle_per_host
+            .or(self.pool_max_idle_per_host);
+        self.http2 = partial.http2.unwrap_or(self.http2);
+        self.tcp_keepalive_secs = partial
+            .tcp_keepalive_secs
+            .unwrap_or(self.tcp_keepalive_secs);
+        self.hosts.extend(partial.hosts.unwrap_or_default());
+        self.emulation = partial.emulation.unwrap_or(self.emulation);
+        self.emulation_os = partial.emulation_os.unwrap_or(self.emulation_os);
+        self.cookies = partial.cookies.unwrap_or(self.cookies);
+        self.cookie_directory = partial
+            .cookie_directory
+            .unwrap_or(self.cookie_directory.clone());
+    }
+}
+
+/// How often requests can be sent to each host, shared by every search.
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// The limits for hosts that aren't in `hosts`.
+    pub default: HostLimits,
+    pub hosts: HashMap<String, HostLimits>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub struct HostLimits {
+    /// The least time between the starts of two requests to the host.
+    pub min_delay_ms: u64,
+    /// How many requests can be sent to the host at once. 0 is no limit.
+    pub max_concurrent: usize,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialSchedulerConfig {
+    pub default: Option<HostLimits>,
+    pub hosts: Option<HashMap<String, HostLimits>>,
+}
+
+impl SchedulerConfig {
+    pub fn overlay(&mut self, partial: PartialSchedulerConfig) {
+        self.default = partial.default.unwrap_or(self.default);
+        self.hosts.extend(partial.hosts.unwrap_or_default());
+    }
+}
+
+/// Removing adult results ourselves with lists of sites and keywords, for
+/// engines that ignore safe search.
+#[derive(Debug, Clone)]
+pub struct AdultFilterConfig {
+    pub enabled: bool,
+    /// Sites to filter on top of the built-in ones, along with their
+    /// subdomains.
+    pub domains: Vec<String>,
+    /// Words to look for on top of the built-in ones.
+    pub keywords: Vec<String>,
+    /// How many different keywords a result needs to have to be filtered.
+    pub min_keyword_matches: usize,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialAdultFilterConfig {
+    pub enabled: Option<bool>,
+    pub domains: Option<Vec<String>>,
+    pub keywords: Option<Vec<String>>,
+    pub min_keyword_matches: Option<usize>,
+}
+
+impl AdultFilterConfig {
+    pub fn overlay(&mut self, partial: PartialAdultFilterConfig) {
+        self.enabled = partial.enabled.unwrap_or(self.enabled);
+        self.domains = partial.domains.unwrap_or(self.domains.clone());
+        self.keywords = partial.keywords.unwrap_or(self.keywords.clone());
+        self.min_keyword_matches = partial
+            .min_keyword_matches
+            .unwrap_or(self.min_keyword_matches);
+    }
+}
+
+/// Results that aren't in the user's language, for engines that ignore the
+/// language we ask for.
+#[derive(Debug, Clone)]
+pub struct LanguageFilterConfig {
+    pub mode: LanguageFilterMode,
+    /// Tags like `en-US` for the languages that results can be in. If it's
+    /// empty, only the `language` from the config or the user's settings is
+    /// allowed.
+    pub languages: Vec<String>,
+    /// What the scores of results in other languages are multiplied by when
+    /// `mode` is `downrank`.
+    pub downrank_factor: f64,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LanguageFilterMode {
+    Off,
+    Downrank,
+    Drop,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialLanguageFilterConfig {
+    pub mode: Option<LanguageFilterMode>,
+    pub languages: Option<Vec<String>>,
+    pub downrank_factor: Option<f64>,
+}
+
+impl LanguageFilterConfig {
+    pub fn overlay(&mut self, partial: PartialLanguageFilterConfig) {
+        self.mode = partial.mode.unwrap_or(self.mode);
+        self.languages = partial.languages.unwrap_or(self.languages.clone());
+        self.downrank_factor = partial.downrank_factor.unwrap_or(self.downrank_factor);
+    }
+}
+
+/// Lists of sites to hide from results, see [`crate::blocklists`].
+#[derive(Debug, Clone)]
+pub struct BlocklistsConfig {
+    /// The urls of the lists, in uBlacklist or hosts file format.
+    pub subscriptions: Vec<String>,
+    pub refresh_hours: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialBlocklistsConfig {
+    pub subscriptions: Option<Vec<String>>,
+    pub refresh_hours: Option<u64>,
+}
+
+impl BlocklistsConfig {
+    pub fn overlay(&mut self, partial: PartialBlocklistsConfig) {
+        self.subscriptions = partial.subscriptions.unwrap_or(self.subscriptions.clone());
+        self.refresh_hours = partial.refresh_hours.unwrap_or(self.refresh_hours);
+    }
+}
+
+/// Showing whether the Wayback Machine has a copy of each result.
+#[derive(Debug, Clone)]
+pub struct WaybackConfig {
+    pub enabled: bool,
+    /// Only results from these engines are looked up. If it's empty, every
+    /// result is.
+    pub engines: Vec<Engine>,
+    /// How many results are looked up for each search, not counting the ones
+    /// that are cached.
+    pub max_lookups: usize,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialWaybackConfig {
+    pub enabled: Option<bool>,
+    pub engines: Option<Vec<Engine>>,
+    pub max_lookups: Option<usize>,
+}
+
+impl WaybackConfig {
+    pub fn overlay(&mut self, partial: PartialWaybackConfig) {
+        self.enabled = partial.enabled.unwrap_or(self.enabled);
+        self.engines = partial.engines.unwrap_or(self.engines.clone());
+        self.max_lookups = partial.max_lookups.unwrap_or(self.max_lookups);
+    }
+}
+
+/// Checking whether the top results still exist after they're shown, and
+/// flagging the dead ones.
+#[derive(Debug, Clone)]
+pub struct DeadLinksConfig {
+    pub enabled: bool,
+    /// How many of the top results are checked for each search.
+    pub max_checks: usize,
+    pub timeout_ms: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialDeadLinksConfig {
+    pub enabled: Option<bool>,
+    pub max_checks: Option<usize>,
+    pub timeout_ms: Option<u64>,
+}
+
+impl DeadLinksConfig {
+    pub fn overlay(&mut self, partial: PartialDeadLinksConfig) {
+        self.enabled = partial.enabled.unwrap_or(self.enabled);
+        self.max_checks = partial.max_checks.unwrap_or(self.max_checks);
+        self.timeout_ms = partial.timeout_ms.unwrap_or(self.timeout_ms);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PreprocessConfig {
+    /// The steps that every query goes through before it's searched, in order.
+    pub steps: Vec<PreprocessStep>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PartialPreprocessConfig {
+    pub steps: Option<Vec<PreprocessStep>>,
+}
+
+impl PreprocessConfig {
+    pub fn overlay(&mut self, partial: PartialPreprocessConfig) {
+        self.steps = partial.steps.unwrap_or(self.steps.clone());
     }
 }
 
@@ -285,6 +1473,10 @@ pub struct EnginesConfig {
 
 #[derive(Deserialize, Debug, Default)]
 pub struct PartialEnginesConfig {
+    /// Engines that scrape a site with css selectors, see
+    /// [`crate::engines::plugins`].
+    #[serde(default)]
+    pub custom: HashMap<String, PartialEngineConfig>,
     #[serde(flatten)]
     pub map: HashMap<Engine, PartialDefaultableEngineConfig>,
 }
@@ -298,7 +1490,14 @@ pub enum PartialDefaultableEngineConfig {
 
 impl EnginesConfig {
     pub fn overlay(&mut self, partial: PartialEnginesConfig) {
-        for (key, value) in partial.map {
+        // the custom engines were registered when the config was read
+        let custom = partial.custom.into_iter().filter_map(|(id, full)| {
+            Some((
+                Engine::from_str(&id).ok()?,
+                PartialDefaultableEngineConfig::Full(full),
+            ))
+        });
+        for (key, value) in partial.map.into_iter().chain(custom) {
             let full = match value {
                 PartialDefaultableEngineConfig::Boolean(enabled) => PartialEngineConfig {
                     enabled: Some(enabled),
@@ -319,6 +1518,15 @@ impl EnginesConfig {
     pub fn get(&self, engine: Engine) -> &EngineConfig {
         self.map.get(&engine).unwrap_or(&DEFAULT_ENGINE_CONFIG_REF)
     }
+
+    pub fn set_enabled(&mut self, engine: Engine, enabled: bool) {
+        let mut engine_config = self.get(engine).clone();
+        if enabled && engine_config.opt_in && !engine_config.enabled {
+            return;
+        }
+        engine_config.enabled = enabled;
+        self.map.insert(engine, engine_config);
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -326,6 +1534,8 @@ pub struct EngineConfig {
     pub enabled: bool,
     /// The priority of this engine relative to the other engines.
     pub weight: f64,
+    /// Whether the engine is hidden from users unless it's enabled in the config.
+    pub opt_in: bool,
     /// Per-engine configs. These are parsed at request time.
     pub extra: toml::Table,
 }
@@ -340,6 +1550,11 @@ pub struct PartialEngineConfig {
 
 impl EngineConfig {
     pub fn overlay(&mut self, partial: PartialEngineConfig) {
+        // engines that are turned off in the config can't be turned back on by
+        // users
+        if partial.enabled == Some(false) {
+            self.opt_in = true;
+        }
         self.enabled = partial.enabled.unwrap_or(self.enabled);
         self.weight = partial.weight.unwrap_or(self.weight);
         self.extra.extend(partial.extra);
@@ -351,7 +1566,6 @@ impl Config {
         let mut config = Config::default();
 
         if !config_path.exists() {
-            info!("No config found, creating one at {config_path:?}");
             let default_config_str = include_str!("../config-default.toml");
             if let Some(parent_path) = config_path.parent() {
                 let _ = fs::create_dir_all(parent_path);
@@ -359,7 +1573,31 @@ impl Config {
             fs::write(config_path, default_config_str)?;
         }
 
-        let given_config = toml::from_str::<PartialConfig>(&fs::read_to_string(config_path)?)?;
+        let config_str = fs::read_to_string(config_path)?;
+
+        // engines that aren't built in have to be registered before the rest of
+        // the config is parsed, so they can be used in [engines]
+        #[derive(Deserialize)]
+        struct EarlyConfig {
+            plugins: Option<PartialPluginsConfig>,
+            engines: Option<EarlyEnginesConfig>,
+        }
+        #[derive(Deserialize)]
+        struct EarlyEnginesConfig {
+            #[serde(default)]
+            custom: HashMap<String, PartialEngineConfig>,
+        }
+        let early = toml::from_str::<EarlyConfig>(&config_str)?;
+        config.plugins.overlay(early.plugins.unwrap_or_default());
+        if let Some(directory) = &config.plugins.directory {
+            crate::engines::plugins::load(Path::new(directory));
+        }
+        for (id, engine_config) in early.engines.map(|e| e.custom).unwrap_or_default() {
+            crate::engines::plugins::register_custom(&id, engine_config.extra)
+                .map_err(|err| eyre::eyre!("Invalid custom engine {id:?}: {err}"))?;
+        }
+
+        let given_config = toml::from_str::<PartialConfig>(&config_str)?;
         config.overlay(given_config);
         Ok(config)
     }
@@ -384,6 +1622,8 @@ impl HostAndPath {
 pub struct UrlsConfig {
     pub replace: Vec<(HostAndPath, HostAndPath)>,
     pub weight: Vec<(HostAndPath, f64)>,
+    /// Notes that are shown under matching results, like "official mirror".
+    pub annotations: Vec<(HostAndPath, String)>,
 }
 #[derive(Deserialize, Debug, Default)]
 pub struct PartialUrlsConfig {
@@ -391,6 +1631,11 @@ pub struct PartialUrlsConfig {
     pub replace: HashMap<String, String>,
     #[serde(default)]
     pub weight: HashMap<String, f64>,
+    #[serde(default)]
+    pub annotations: HashMap<String, String>,
+    /// A file with more annotations, in the format from
+    /// [`crate::urls::parse_annotations`].
+    pub annotations_file: Option<String>,
 }
 impl UrlsConfig {
     pub fn overlay(&mut self, partial: PartialUrlsConfig) {
@@ -412,11 +1657,21 @@ impl UrlsConfig {
             self.weight.push((url, weight));
         }
 
+        for (url, note) in partial.annotations {
+            self.annotations.push((HostAndPath::new(&url), note));
+        }
+        if let Some(path) = partial.annotations_file {
+            match fs::read_to_string(&path) {
+                Ok(file) => self
+                    .annotations
+                    .extend(crate::urls::parse_annotations(&file)),
+                Err(err) => warn!("Couldn't read annotations file {path}: {err}"),
+            }
+        }
+
         // sort by length so that more specific checks are done first
-        self.weight.sort_by(|(a, _), (b, _)| {
-            let a_len = a.path.len() + a.host.len();
-            let b_len = b.path.len() + b.host.len();
-            b_len.cmp(&a_len)
-        });
+        let specificity = |url: &HostAndPath| Reverse(url.path.len() + url.host.len());
+        self.weight.sort_by_key(|(url, _)| specificity(url));
+        self.annotations.sort_by_key(|(url, _)| specificity(url));
     }
 }
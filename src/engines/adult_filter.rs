@@ -0,0 +1,97 @@
+//! A filter for adult results that doesn't rely on the engines, since some of
+//! them don't have a safe search option at all. It's meant for places like
+//! libraries and schools, so it's on for every user when it's enabled.
+
+use crate::config::AdultFilterConfig;
+
+/// Sites that are always filtered, along with their subdomains.
+const DOMAINS: &[&str] = &[
+    "pornhub.com",
+    "xvideos.com",
+    "xnxx.com",
+    "xhamster.com",
+    "redtube.com",
+    "youporn.com",
+    "tube8.com",
+    "spankbang.com",
+    "eporner.com",
+    "brazzers.com",
+    "onlyfans.com",
+    "chaturbate.com",
+    "stripchat.com",
+    "livejasmin.com",
+    "rule34.xxx",
+];
+
+/// Top-level domains that are only for adult sites.
+const TLDS: &[&str] = &["xxx", "porn", "sex", "adult"];
+
+/// Words that a result is filtered for if it has enough of them.
+const KEYWORDS: &[&str] = &[
+    "porn", "porno", "xxx", "nsfw", "hentai", "nude", "nudes", "naked", "camgirl", "camgirls",
+    "milf", "escort", "escorts", "erotic", "blowjob", "hardcore", "onlyfans", "sexcam",
+];
+
+/// Whether the result should be removed, because the filter is enabled and it
+/// [`looks_adult`].
+pub fn is_adult(url: &str, text: &str, config: &AdultFilterConfig) -> bool {
+    config.enabled && looks_adult(url, text, config)
+}
+
+/// Whether the result looks like it's adult content, from its url and text.
+/// This is also used for blurring images when the filter isn't enabled.
+pub fn looks_adult(url: &str, text: &str, config: &AdultFilterConfig) -> bool {
+    let Ok(url) = url::Url::parse(url) else {
+        return false;
+    };
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+    if TLDS.iter().any(|tld| host.ends_with(&format!(".{tld}"))) {
+        return true;
+    }
+    let is_listed = |domain: &str| host == domain || host.ends_with(&format!(".{domain}"));
+    if DOMAINS.iter().any(|d| is_listed(d)) || config.domains.iter().any(|d| is_listed(d)) {
+        return true;
+    }
+
+    // the url counts too, since the title is often vague
+    let text = format!("{} {text}", url.as_str()).to_lowercase();
+    let mut matches = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| {
+            KEYWORDS.contains(word) || config.keywords.iter().any(|k| k.eq_ignore_ascii_case(word))
+        })
+        .collect::<Vec<_>>();
+    matches.sort_unstable();
+    matches.dedup();
+    matches.len() >= config.min_keyword_matches.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_adult() {
+        let config = AdultFilterConfig {
+            enabled: true,
+            domains: vec!["example.net".to_string()],
+            keywords: vec![],
+            min_keyword_matches: 2,
+        };
+        assert!(is_adult("https://www.pornhub.com/", "", &config));
+        assert!(is_adult("https://anything.xxx/", "", &config));
+        assert!(is_adult("https://cdn.example.net/a", "", &config));
+        assert!(is_adult(
+            "https://example.com/videos",
+            "Free porn videos, nsfw",
+            &config
+        ));
+        // one word isn't enough, since it might be about the word
+        assert!(!is_adult(
+            "https://en.wikipedia.org/wiki/Pornography",
+            "Porn is a film genre",
+            &config
+        ));
+        assert!(!is_adult("https://www.rust-lang.org/", "Rust", &config));
+    }
+}
@@ -1,12 +1,36 @@
+#[cfg(feature = "answers")]
+pub mod cheatsheet;
+#[cfg(feature = "answers")]
 pub mod colorpicker;
+#[cfg(feature = "answers")]
 pub mod dictionary;
+#[cfg(feature = "answers")]
+pub mod dns;
+#[cfg(feature = "answers")]
+pub mod encoding;
+#[cfg(feature = "answers")]
 pub mod fend;
+#[cfg(feature = "answers")]
 pub mod ip;
+#[cfg(feature = "answers")]
+pub mod lyrics;
+#[cfg(feature = "answers")]
 pub mod notepad;
+#[cfg(feature = "answers")]
 pub mod numbat;
+#[cfg(feature = "answers")]
+pub mod packages;
+#[cfg(feature = "answers")]
 pub mod thesaurus;
+#[cfg(feature = "answers")]
+pub mod ticker;
+#[cfg(feature = "answers")]
 pub mod timezone;
+#[cfg(feature = "answers")]
 pub mod useragent;
+#[cfg(feature = "answers")]
+pub mod wikidata;
+#[cfg(feature = "answers")]
 pub mod wikipedia;
 
 macro_rules! regex {
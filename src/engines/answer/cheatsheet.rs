@@ -0,0 +1,132 @@
+//! Cheat sheets from cheat.sh, for queries like "cheat grep" or "tldr tar". A
+//! self-hosted cheat.sh can be used with `endpoints`. Sheets rarely change, so
+//! they're cached for a day.
+
+use std::{
+    collections::HashMap,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use maud::html;
+use parking_lot::Mutex;
+
+use crate::engines::{
+    endpoints, Engine, EngineResponse, HttpResponse, RequestResponse, SearchQuery, CLIENT,
+};
+
+use super::regex;
+
+const TTL: Duration = Duration::from_secs(60 * 60 * 24);
+const MAX_ENTRIES: usize = 500;
+
+/// Sheets by their name on cheat.sh, like `tldr:tar`.
+static CACHE: LazyLock<Mutex<HashMap<String, (Instant, String)>>> = LazyLock::new(Default::default);
+
+/// The name of the sheet on cheat.sh. tldr pages are under `tldr:`.
+fn parse_query(query: &str) -> Option<String> {
+    let captures = regex!(r"(?i)^(tldr|cheat|cheat\.sh|cheatsheet|cheat sheet) ([\w.+\-]+)$")
+        .captures(query.trim())?;
+    let command = captures[2].to_lowercase();
+    Some(if captures[1].eq_ignore_ascii_case("tldr") {
+        format!("tldr:{command}")
+    } else {
+        command
+    })
+}
+
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    let Some(sheet) = parse_query(&query.query) else {
+        return RequestResponse::None;
+    };
+
+    if let Some(text) = cached(&sheet) {
+        return RequestResponse::Instant(Box::new(render(&sheet, &text)));
+    }
+
+    endpoints::requests(
+        Engine::Cheatsheet,
+        query,
+        &["https://cheat.sh"],
+        |endpoint| {
+            // ?T turns off the terminal colors
+            CLIENT.get(format!(
+                "{endpoint}/{}?T",
+                urlencoding::encode(&sheet).replace("%3A", ":")
+            ))
+        },
+    )
+}
+
+pub fn parse_response(res: &HttpResponse) -> eyre::Result<EngineResponse> {
+    // the last part of the path, in case a mirror isn't at the root of its domain
+    let path = res.res.url().path();
+    let sheet = urlencoding::decode(path.rsplit('/').next().unwrap_or(path))?.into_owned();
+    let Some(text) = clean_sheet(&res.body) else {
+        return Ok(EngineResponse::new());
+    };
+
+    let mut cache = CACHE.lock();
+    cache.retain(|_, (time, _)| time.elapsed() < TTL);
+    if cache.len() < MAX_ENTRIES {
+        cache.insert(sheet.clone(), (Instant::now(), text.clone()));
+    }
+    drop(cache);
+
+    Ok(render(&sheet, &text))
+}
+
+fn cached(sheet: &str) -> Option<String> {
+    let cache = CACHE.lock();
+    let (time, text) = cache.get(sheet)?;
+    (time.elapsed() < TTL).then(|| text.clone())
+}
+
+/// Remove any leftover terminal escapes, and return None if cheat.sh doesn't
+/// have the sheet.
+fn clean_sheet(body: &str) -> Option<String> {
+    let text = regex!(r"\x1b\[[0-9;]*m").replace_all(body, "");
+    let text = text.trim();
+    if text.is_empty() || text.starts_with("Unknown topic") || text.contains("Unknown cheat sheet")
+    {
+        return None;
+    }
+    Some(text.to_string())
+}
+
+fn render(sheet: &str, text: &str) -> EngineResponse {
+    let (source, command) = match sheet.strip_prefix("tldr:") {
+        Some(command) => ("tldr", command),
+        None => ("cheat.sh", sheet),
+    };
+    EngineResponse::answer_html(html! {
+        p.answer-query { (source) " · " code { (command) } }
+        pre.answer-cheatsheet { (text) }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(parse_query("tldr tar").as_deref(), Some("tldr:tar"));
+        assert_eq!(parse_query("cheat Grep").as_deref(), Some("grep"));
+        assert_eq!(parse_query("cheat sheet git").as_deref(), Some("git"));
+        assert_eq!(parse_query("tldr"), None);
+        assert_eq!(parse_query("cheat on a test"), None);
+    }
+
+    #[test]
+    fn test_clean_sheet() {
+        assert_eq!(
+            clean_sheet("\x1b[38;5;246m# tar\x1b[0m\ntar xf archive.tar\n").as_deref(),
+            Some("# tar\ntar xf archive.tar")
+        );
+        assert_eq!(
+            clean_sheet("Unknown topic.\nDo you mean one of these topics maybe?"),
+            None
+        );
+    }
+}
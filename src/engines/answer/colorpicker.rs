@@ -41,18 +41,8 @@ pub async fn request(query: &SearchQuery) -> EngineResponse {
     let (hsv_h, hsv_s, hsv_v) = hsv;
     let (hsl_h, hsl_s, hsl_l) = hsl;
 
-    let hex_str = format!(
-        "#{:02x}{:02x}{:02x}",
-        (r * 255.) as u8,
-        (g * 255.) as u8,
-        (b * 255.) as u8
-    );
-    let rgb_str = format!(
-        "{}, {}, {}",
-        (r * 255.) as u8,
-        (g * 255.) as u8,
-        (b * 255.) as u8
-    );
+    let hex_str = hex_string(rgb);
+    let rgb_str = format!("{}, {}, {}", to_byte(r), to_byte(g), to_byte(b));
     let cmyk_str = format!(
         "{:.0}%, {:.0}%, {:.0}%, {:.0}%",
         c * 100.,
@@ -160,6 +150,159 @@ pub async fn request(query: &SearchQuery) -> EngineResponse {
     })
 }
 
+/// The CSS named colors. These only match when the query has "color" in it, so
+/// searching for "coral" doesn't show a color picker.
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
 pub struct MatchedColorModel {
     pub rgb: Option<(f64, f64, f64)>,
     pub cmyk: Option<(f64, f64, f64, f64)>,
@@ -174,12 +317,34 @@ impl MatchedColorModel {
         let mut hsl = None;
 
         let query = query.trim().to_lowercase();
+        // "color #ff7f50", "coral colour"
+        let (query, has_color_word) =
+            match regex!("^(?:colou?r (.+)|(.+) colou?r)$").captures(&query) {
+                Some(caps) if !regex!("^colou?r ?picker$").is_match(&query) => (
+                    caps.get(1).or(caps.get(2)).unwrap().as_str().to_string(),
+                    true,
+                ),
+                _ => (query, false),
+            };
+
+        let named_color = NAMED_COLORS
+            .iter()
+            .find(|(name, _)| has_color_word && *name == query.replace(' ', ""));
 
         if regex!("^color ?picker$").is_match(&query) {
             // default to red
             rgb = Some((1., 0., 0.));
-        } else if let Some(caps) = regex!("^#?([0-9a-f]{6})$").captures(&query) {
-            let hex_str = caps.get(1).unwrap().as_str();
+        } else if let Some((_, hex_num)) = named_color {
+            rgb = Some((
+                ((hex_num >> 16) & 0xff) as f64 / 255.,
+                ((hex_num >> 8) & 0xff) as f64 / 255.,
+                (hex_num & 0xff) as f64 / 255.,
+            ));
+        } else if let Some(caps) = regex!("^(?:#?([0-9a-f]{6})|#([0-9a-f]{6})[0-9a-f]{2})$")
+            .captures(&query)
+        {
+            // the alpha channel of #rrggbbaa is ignored
+            let hex_str = caps.get(1).or(caps.get(2)).unwrap().as_str();
             let hex_num = u32::from_str_radix(hex_str, 16).unwrap();
             let r = ((hex_num >> 16) & 0xff) as f64 / 255.;
             let g = ((hex_num >> 8) & 0xff) as f64 / 255.;
@@ -190,8 +355,10 @@ impl MatchedColorModel {
             let b = b.clamp(0., 1.);
 
             rgb = Some((r, g, b));
-        } else if let Some(caps) = regex!("^#?([0-9a-f]{3})$").captures(&query) {
-            let hex_str = caps.get(1).unwrap().as_str();
+        } else if let Some(caps) =
+            regex!("^(?:#?([0-9a-f]{3})|#([0-9a-f]{3})[0-9a-f])$").captures(&query)
+        {
+            let hex_str = caps.get(1).or(caps.get(2)).unwrap().as_str();
             let hex_num = u32::from_str_radix(hex_str, 16).unwrap();
             let r = ((hex_num >> 8) & 0xf) as f64 / 15.;
             let g = ((hex_num >> 4) & 0xf) as f64 / 15.;
@@ -203,7 +370,8 @@ impl MatchedColorModel {
 
             rgb = Some((r, g, b));
         } else if let Some(caps) =
-            regex!("^rgb\\((\\d{1,3}), ?(\\d{1,3}), ?(\\d{1,3})\\)$").captures(&query)
+            regex!(r"^rgba?\( ?(\d{1,3})(?:, ?| )(\d{1,3})(?:, ?| )(\d{1,3})(?:(?:, ?| ?/ ?)[\d.]+%?)? ?\)$")
+                .captures(&query)
         {
             let r = caps
                 .get(1)
@@ -282,7 +450,8 @@ impl MatchedColorModel {
 
             hsv = Some((h, s, v));
         } else if let Some(caps) =
-            regex!("^hsl\\((\\d{1,3})(?:°|deg|), ?(\\d{1,3})%, ?(\\d{1,3})%\\)$").captures(&query)
+            regex!(r"^hsla?\( ?(\d{1,3})(?:°|deg|)(?:, ?| )(\d{1,3})%(?:, ?| )(\d{1,3})%(?:(?:, ?| ?/ ?)[\d.]+%?)? ?\)$")
+                .captures(&query)
         {
             let h = caps
                 .get(1)
@@ -320,6 +489,14 @@ impl MatchedColorModel {
     }
 }
 
+fn to_byte(channel: f64) -> u8 {
+    (channel * 255.).round() as u8
+}
+
+fn hex_string((r, g, b): (f64, f64, f64)) -> String {
+    format!("#{:02x}{:02x}{:02x}", to_byte(r), to_byte(g), to_byte(b))
+}
+
 // this is the code from colorpicker.js ported to rust
 // see that file for credits
 fn hsv_to_hsl((h, s, v): (f64, f64, f64)) -> (f64, f64, f64) {
@@ -395,3 +572,32 @@ fn cmyk_to_rgb((c, m, y, k): (f64, f64, f64, f64)) -> (f64, f64, f64) {
     let b = (1. - y) * (1. - k);
     (r, g, b)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(query: &str) -> Option<String> {
+        let matched = MatchedColorModel::new(query);
+        matched
+            .rgb
+            .or(matched.cmyk.map(cmyk_to_rgb))
+            .or(matched.hsv.map(hsv_to_rgb))
+            .or(matched.hsl.map(|hsl| hsv_to_rgb(hsl_to_hsv(hsl))))
+            .map(hex_string)
+    }
+
+    #[test]
+    fn test_matched_color_model() {
+        assert_eq!(hex("#FF7F50").as_deref(), Some("#ff7f50"));
+        assert_eq!(hex("#ff7f5080").as_deref(), Some("#ff7f50"));
+        assert_eq!(hex("rgb(10,20,30)").as_deref(), Some("#0a141e"));
+        assert_eq!(hex("rgba(10 20 30 / 50%)").as_deref(), Some("#0a141e"));
+        assert_eq!(hex("hsl(0, 100%, 50%)").as_deref(), Some("#ff0000"));
+        assert_eq!(hex("color coral").as_deref(), Some("#ff7f50"));
+        assert_eq!(hex("light blue colour").as_deref(), Some("#add8e6"));
+        assert_eq!(hex("color #fff").as_deref(), Some("#ffffff"));
+        assert_eq!(hex("coral"), None);
+        assert_eq!(hex("color theory"), None);
+    }
+}
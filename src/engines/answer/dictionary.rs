@@ -5,7 +5,7 @@ use maud::{html, PreEscaped};
 use serde::Deserialize;
 use url::Url;
 
-use crate::engines::{EngineResponse, HttpResponse, RequestResponse, CLIENT};
+use crate::engines::{sanitize, EngineResponse, HttpResponse, RequestResponse, CLIENT};
 
 use super::regex;
 
@@ -72,12 +72,7 @@ pub fn parse_response(
         return Ok(EngineResponse::new());
     };
 
-    let mut cleaner = ammonia::Builder::default();
-    cleaner
-        .link_rel(None)
-        .url_relative(ammonia::UrlRelative::RewriteWithBase(
-            Url::parse("https://en.wiktionary.org").unwrap(),
-        ));
+    let cleaner = sanitize::builder(Url::parse("https://en.wiktionary.org").unwrap());
 
     let mut html = String::new();
 
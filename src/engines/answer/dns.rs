@@ -0,0 +1,102 @@
+//! Live DNS lookups, for queries like "dns example.com".
+
+use std::{sync::LazyLock, time::Duration};
+
+use hickory_resolver::{name_server::TokioConnectionProvider, Resolver, TokioResolver};
+use maud::html;
+use tracing::warn;
+
+use crate::engines::{EngineResponse, SearchQuery};
+
+use super::regex;
+
+static RESOLVER: LazyLock<TokioResolver> = LazyLock::new(|| {
+    let mut builder = Resolver::builder_tokio().unwrap_or_else(|err| {
+        warn!("Couldn't read the system's dns config, using the default: {err}");
+        Resolver::builder_with_config(Default::default(), TokioConnectionProvider::default())
+    });
+    // this is answering a search, so don't wait around for slow servers
+    builder.options_mut().timeout = Duration::from_secs(2);
+    builder.options_mut().attempts = 1;
+    builder.build()
+});
+
+fn parse_query(query: &str) -> Option<String> {
+    let captures = regex!(r"^(?:dns|dig|nslookup|dns lookup|dns records for) ([a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,})\.?$")
+        .captures(query.trim())?;
+    Some(captures[1].to_string())
+}
+
+pub async fn request(query: &SearchQuery) -> EngineResponse {
+    let Some(domain) = parse_query(&query.query.to_lowercase()) else {
+        return EngineResponse::new();
+    };
+
+    // a missing record type is an error, but it's not worth showing
+    let (a, aaaa, mx, txt) = tokio::join!(
+        RESOLVER.ipv4_lookup(domain.as_str()),
+        RESOLVER.ipv6_lookup(domain.as_str()),
+        RESOLVER.mx_lookup(domain.as_str()),
+        RESOLVER.txt_lookup(domain.as_str()),
+    );
+    let a = a.map(|r| r.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+    let aaaa = aaaa.map(|r| r.iter().map(|aaaa| aaaa.to_string()).collect::<Vec<_>>());
+    let mut mx = mx
+        .map(|r| {
+            r.iter()
+                .map(|mx| (mx.preference(), mx.exchange().to_utf8()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    mx.sort();
+    let mx = mx
+        .into_iter()
+        .map(|(preference, exchange)| format!("{preference} {exchange}"))
+        .collect::<Vec<_>>();
+    let txt = txt.map(|r| r.iter().map(|txt| txt.to_string()).collect::<Vec<_>>());
+
+    let records = [
+        ("A", a.unwrap_or_default()),
+        ("AAAA", aaaa.unwrap_or_default()),
+        ("MX", mx),
+        ("TXT", txt.unwrap_or_default()),
+    ];
+
+    EngineResponse::answer_html(html! {
+        p.answer-query { "DNS records for " b { (domain) } }
+        @if records.iter().all(|(_, values)| values.is_empty()) {
+            p.answer-comment { "No records found" }
+        } @else {
+            table.answer-dns-records {
+                @for (record_type, values) in &records {
+                    @for value in values {
+                        tr {
+                            th { (record_type) }
+                            td { code { (value) } }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query("dns example.com"),
+            Some("example.com".to_string())
+        );
+        assert_eq!(
+            parse_query("dig mail.example.co.uk."),
+            Some("mail.example.co.uk".to_string())
+        );
+        assert_eq!(parse_query("dns"), None);
+        assert_eq!(parse_query("dns localhost"), None);
+        assert_eq!(parse_query("what is dns"), None);
+    }
+}
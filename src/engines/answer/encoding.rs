@@ -0,0 +1,177 @@
+//! Hashes, encodings, and uuids, for queries like "sha256 foo" or "base64
+//! decode Zm9v". These are computed here so the input doesn't have to be
+//! pasted into some random website.
+
+use base64::{engine::general_purpose, Engine as _};
+use maud::html;
+use md5::Md5;
+use rand::Rng;
+use sha1::Sha1;
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
+
+use crate::engines::{EngineResponse, SearchQuery};
+
+use super::regex;
+
+#[derive(Debug, PartialEq)]
+struct Evaluation {
+    /// What was done, like "SHA-256".
+    operation: &'static str,
+    input: Option<String>,
+    output: Result<String, &'static str>,
+}
+
+pub async fn request(query: &SearchQuery) -> EngineResponse {
+    let Some(Evaluation {
+        operation,
+        input,
+        output,
+    }) = evaluate(&query.query)
+    else {
+        return EngineResponse::new();
+    };
+
+    EngineResponse::answer_html(html! {
+        p.answer-query {
+            (operation)
+            @if let Some(input) = input {
+                " of " code { (input) }
+            }
+        }
+        @match output {
+            Ok(output) => h3.answer-encoding-output { code { (output) } },
+            Err(err) => p.answer-comment { (err) },
+        }
+    })
+}
+
+fn evaluate(query: &str) -> Option<Evaluation> {
+    let query = query.trim();
+
+    if regex!(r"(?i)^(uuid|uuid ?v?4|guid|(generate|random|new) (uuid|guid))$").is_match(query) {
+        return Some(Evaluation {
+            operation: "Random UUID",
+            input: None,
+            output: Ok(uuid_v4()),
+        });
+    }
+
+    if let Some(captures) =
+        regex!(r"(?i)^(md5|sha-?1|sha-?224|sha-?256|sha-?384|sha-?512)(?: hash(?: of)?)? (.+)$")
+            .captures(query)
+    {
+        let input = captures[2].to_string();
+        let bytes = input.as_bytes();
+        let (operation, output) = match captures[1].to_lowercase().replace('-', "").as_str() {
+            "md5" => ("MD5", hex(&Md5::digest(bytes))),
+            "sha1" => ("SHA-1", hex(&Sha1::digest(bytes))),
+            "sha224" => ("SHA-224", hex(&Sha224::digest(bytes))),
+            "sha256" => ("SHA-256", hex(&Sha256::digest(bytes))),
+            "sha384" => ("SHA-384", hex(&Sha384::digest(bytes))),
+            "sha512" => ("SHA-512", hex(&Sha512::digest(bytes))),
+            _ => return None,
+        };
+        return Some(Evaluation {
+            operation,
+            input: Some(input),
+            output: Ok(output),
+        });
+    }
+
+    // "base64 encode foo", "base64 decode Zm9v", "url encode a b"
+    if let Some(captures) = regex!(r"(?i)^(base ?64|url) ?(encode|decode) (.+)$").captures(query) {
+        let kind = captures[1].to_lowercase();
+        let direction = captures[2].to_lowercase();
+        let input = captures[3].to_string();
+        let (operation, output) = match (kind.as_str(), direction.as_str()) {
+            ("url", "encode") => ("URL encoding", Ok(urlencoding::encode(&input).into_owned())),
+            ("url", "decode") => (
+                "URL decoding",
+                urlencoding::decode(&input)
+                    .map(|s| s.into_owned())
+                    .map_err(|_| "That doesn't decode to valid UTF-8."),
+            ),
+            (_, "encode") => (
+                "Base64 encoding",
+                Ok(general_purpose::STANDARD.encode(input.as_bytes())),
+            ),
+            _ => ("Base64 decoding", base64_decode(&input)),
+        };
+        return Some(Evaluation {
+            operation,
+            input: Some(input),
+            output,
+        });
+    }
+
+    None
+}
+
+fn base64_decode(input: &str) -> Result<String, &'static str> {
+    // padding is optional, and the url-safe alphabet is common too
+    let input = input.trim().trim_end_matches('=');
+    let bytes = general_purpose::STANDARD_NO_PAD
+        .decode(input)
+        .or_else(|_| general_purpose::URL_SAFE_NO_PAD.decode(input))
+        .map_err(|_| "That isn't valid Base64.")?;
+    String::from_utf8(bytes).map_err(|_| "That doesn't decode to valid UTF-8.")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn uuid_v4() -> String {
+    let mut bytes = [0u8; 16];
+    rand::rng().fill(&mut bytes);
+    // version 4, variant 1
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex(&bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(query: &str) -> Result<String, &'static str> {
+        evaluate(query).unwrap().output
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(
+            output("md5 foo").unwrap(),
+            "acbd18db4cc2f85cedef654fccc4a4d8"
+        );
+        assert_eq!(
+            output("SHA256 hello world").unwrap(),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+        assert_eq!(
+            output("sha-1 abc").unwrap(),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(output("base64 encode foo bar").unwrap(), "Zm9vIGJhcg==");
+        assert_eq!(output("base64 decode Zm9vIGJhcg").unwrap(), "foo bar");
+        assert!(output("base64 decode !!!").is_err());
+        assert_eq!(output("url encode a b&c").unwrap(), "a%20b%26c");
+        assert_eq!(output("urldecode a%20b").unwrap(), "a b");
+        assert!(evaluate("md5").is_none());
+        assert!(evaluate("what is sha256").is_none());
+
+        let uuid = output("uuid").unwrap();
+        assert!(
+            regex!("^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$")
+                .is_match(&uuid)
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! Song lyrics, for queries like "bohemian rhapsody lyrics". Where they come
+//! from is picked with `backend` in the engine config, since instances have
+//! different ideas about what's okay to show:
+//!
+//! - `genius` (the default) uses Genius's official api, which needs an
+//!   `api_key` and only links to the lyrics.
+//! - `lyrics_ovh` uses the lyrics.ovh api, which shows the lyrics.
+//! - `genius_scrape` finds the song on Genius and scrapes the lyrics from its
+//!   page.
+
+use maud::html;
+use scraper::{Html, Node, Selector};
+use serde::Deserialize;
+use tracing::error;
+use url::Url;
+
+use crate::engines::{Engine, EngineResponse, RequestResponse, SearchQuery, CLIENT};
+
+use super::regex;
+
+#[derive(Deserialize, Default)]
+struct LyricsConfig {
+    #[serde(default)]
+    backend: Backend,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum Backend {
+    #[default]
+    Genius,
+    LyricsOvh,
+    GeniusScrape,
+}
+
+#[derive(Debug, PartialEq)]
+struct Song {
+    title: String,
+    artist: String,
+    /// Where the lyrics can be read.
+    url: Option<String>,
+    lyrics: Option<String>,
+}
+
+fn parse_query(query: &str) -> Option<&str> {
+    let captures = regex!(r"(?i)^(?:lyrics (?:to |for |of )?(.+)|(.+) lyrics)$").captures(query)?;
+    Some(captures.get(1).or(captures.get(2))?.as_str().trim())
+}
+
+pub async fn request(query: &SearchQuery) -> eyre::Result<RequestResponse> {
+    let Some(song_query) = parse_query(query.query.trim()) else {
+        return Ok(RequestResponse::None);
+    };
+    let config: LyricsConfig =
+        toml::Value::Table(query.config.engines.get(Engine::Lyrics).extra.clone()).try_into()?;
+
+    let song = match config.backend {
+        Backend::Genius => {
+            let Some(api_key) = &config.api_key else {
+                error!("The lyrics engine needs an api_key to use Genius");
+                return Ok(RequestResponse::None);
+            };
+            genius(song_query, api_key).await?
+        }
+        Backend::LyricsOvh => lyrics_ovh(song_query).await?,
+        Backend::GeniusScrape => genius_scrape(song_query).await?,
+    };
+    let Some(song) = song else {
+        return Ok(RequestResponse::None);
+    };
+
+    Ok(RequestResponse::Instant(Box::new(
+        EngineResponse::answer_html(html! {
+            h3 { b { (song.title) } " · " (song.artist) }
+            @if let Some(lyrics) = &song.lyrics {
+                div.answer-lyrics { (lyrics) }
+            }
+            @if let Some(url) = &song.url {
+                a href=(url) rel="noreferrer" {
+                    @if song.lyrics.is_some() { "Source" } @else { "Read the lyrics" }
+                }
+            }
+        }),
+    )))
+}
+
+#[derive(Deserialize)]
+struct GeniusResponse {
+    response: GeniusHits,
+}
+
+#[derive(Deserialize)]
+struct GeniusHits {
+    #[serde(default)]
+    hits: Vec<GeniusHit>,
+    /// The unofficial api has its hits in sections.
+    #[serde(default)]
+    sections: Vec<GeniusHits>,
+}
+
+#[derive(Deserialize)]
+struct GeniusHit {
+    result: GeniusSong,
+}
+
+#[derive(Deserialize)]
+struct GeniusSong {
+    title: String,
+    url: String,
+    primary_artist: GeniusArtist,
+}
+
+#[derive(Deserialize)]
+struct GeniusArtist {
+    name: String,
+}
+
+async fn genius(query: &str, api_key: &str) -> eyre::Result<Option<Song>> {
+    let body = CLIENT
+        .get(Url::parse_with_params("https://api.genius.com/search", &[("q", query)]).unwrap())
+        .header("Authorization", format!("Bearer {api_key}"))
+        .send()
+        .await?
+        .text()
+        .await?;
+    let res: GeniusResponse = serde_json::from_str(&body)?;
+    Ok(res.response.hits.into_iter().next().map(|hit| Song {
+        title: hit.result.title,
+        artist: hit.result.primary_artist.name,
+        url: Some(hit.result.url),
+        lyrics: None,
+    }))
+}
+
+async fn genius_scrape(query: &str) -> eyre::Result<Option<Song>> {
+    let body = CLIENT
+        .get(Url::parse_with_params("https://genius.com/api/search/song", &[("q", query)]).unwrap())
+        .send()
+        .await?
+        .text()
+        .await?;
+    let res: GeniusResponse = serde_json::from_str(&body)?;
+    let Some(hit) = res
+        .response
+        .sections
+        .into_iter()
+        .flat_map(|section| section.hits)
+        .next()
+    else {
+        return Ok(None);
+    };
+
+    let page = CLIENT.get(&hit.result.url).send().await?.text().await?;
+    Ok(Some(Song {
+        title: hit.result.title,
+        artist: hit.result.primary_artist.name,
+        lyrics: parse_genius_lyrics(&page),
+        url: Some(hit.result.url),
+    }))
+}
+
+/// The lyrics on a Genius page are split into several containers, with `<br>`
+/// for line breaks.
+fn parse_genius_lyrics(html: &str) -> Option<String> {
+    let dom = Html::parse_document(html);
+    let mut lyrics = String::new();
+    for container in dom.select(&Selector::parse("[data-lyrics-container='true']").unwrap()) {
+        for node in container.descendants() {
+            match node.value() {
+                Node::Text(text) => lyrics.push_str(text),
+                Node::Element(el) if el.name() == "br" => lyrics.push('\n'),
+                _ => {}
+            }
+        }
+        lyrics.push('\n');
+    }
+    let lyrics = lyrics.trim().to_string();
+    (!lyrics.is_empty()).then_some(lyrics)
+}
+
+#[derive(Deserialize)]
+struct LyricsOvhSuggestions {
+    #[serde(default)]
+    data: Vec<LyricsOvhSuggestion>,
+}
+
+#[derive(Deserialize)]
+struct LyricsOvhSuggestion {
+    title: String,
+    artist: GeniusArtist,
+}
+
+#[derive(Deserialize)]
+struct LyricsOvhLyrics {
+    lyrics: Option<String>,
+}
+
+async fn lyrics_ovh(query: &str) -> eyre::Result<Option<Song>> {
+    let suggestions: LyricsOvhSuggestions = CLIENT
+        .get(format!(
+            "https://api.lyrics.ovh/suggest/{}",
+            urlencoding::encode(query)
+        ))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let Some(suggestion) = suggestions.data.into_iter().next() else {
+        return Ok(None);
+    };
+
+    let url = format!(
+        "https://api.lyrics.ovh/v1/{}/{}",
+        urlencoding::encode(&suggestion.artist.name),
+        urlencoding::encode(&suggestion.title)
+    );
+    let res: LyricsOvhLyrics = CLIENT.get(&url).send().await?.json().await?;
+    let Some(lyrics) = res.lyrics.map(|l| l.trim().replace("\r\n", "\n")) else {
+        return Ok(None);
+    };
+    Ok(Some(Song {
+        title: suggestion.title,
+        artist: suggestion.artist.name,
+        url: None,
+        lyrics: Some(lyrics),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query("bohemian rhapsody lyrics"),
+            Some("bohemian rhapsody")
+        );
+        assert_eq!(parse_query("Lyrics to Hey Jude"), Some("Hey Jude"));
+        assert_eq!(parse_query("lyrics"), None);
+        assert_eq!(
+            parse_query("lyrics generator online"),
+            Some("generator online")
+        );
+    }
+
+    #[test]
+    fn test_parse_genius_lyrics() {
+        let html = r#"<div data-lyrics-container="true">[Verse 1]<br/>Is this the <i>real</i> life?<br/>Is this just fantasy?</div>
+            <div data-lyrics-container="true">Caught in a landslide</div>"#;
+        assert_eq!(
+            parse_genius_lyrics(html).as_deref(),
+            Some("[Verse 1]\nIs this the real life?\nIs this just fantasy?\nCaught in a landslide")
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Packages from crates.io, npm, and PyPI, for queries like "crate serde",
+//! "npm react", or "pypi requests".
+
+use maud::html;
+use serde::Deserialize;
+use wreq::StatusCode;
+
+use crate::engines::{EngineResponse, RequestResponse, SearchQuery, CLIENT};
+
+use super::{regex, wikidata::format_thousands};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Registry {
+    Crates,
+    Npm,
+    PyPi,
+}
+
+impl Registry {
+    fn name(self) -> &'static str {
+        match self {
+            Registry::Crates => "crates.io",
+            Registry::Npm => "npm",
+            Registry::PyPi => "PyPI",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Package {
+    name: String,
+    version: String,
+    description: String,
+    /// Downloads over the whole lifetime of the package for crates.io, and over
+    /// the last week for npm and PyPI.
+    downloads: Option<u64>,
+    url: String,
+    docs_url: Option<String>,
+}
+
+fn parse_query(query: &str) -> Option<(Registry, &str)> {
+    let captures =
+        regex!(r"(?i)^(crates?|cargo|crates\.io|npm|pypi|pip) (@?[\w.\-]+(?:/[\w.\-]+)?)$")
+            .captures(query.trim())?;
+    let registry = match captures[1].to_lowercase().as_str() {
+        "npm" => Registry::Npm,
+        "pypi" | "pip" => Registry::PyPi,
+        _ => Registry::Crates,
+    };
+    // only npm has scoped packages
+    let name = captures.get(2)?.as_str();
+    if name.contains('/') && registry != Registry::Npm {
+        return None;
+    }
+    Some((registry, name))
+}
+
+pub async fn request(query: &SearchQuery) -> eyre::Result<RequestResponse> {
+    let Some((registry, name)) = parse_query(&query.query) else {
+        return Ok(RequestResponse::None);
+    };
+    let package = match registry {
+        Registry::Crates => crates(name).await?,
+        Registry::Npm => npm(name).await?,
+        Registry::PyPi => pypi(name).await?,
+    };
+    let Some(package) = package else {
+        return Ok(RequestResponse::None);
+    };
+
+    Ok(RequestResponse::Instant(Box::new(
+        EngineResponse::answer_html(html! {
+            p.answer-query { (registry.name()) }
+            h3 {
+                a href=(package.url) { (package.name) }
+                " " span.answer-package-version { (package.version) }
+            }
+            @if !package.description.is_empty() {
+                p { (package.description) }
+            }
+            p.answer-comment {
+                @if let Some(downloads) = package.downloads {
+                    (format_thousands(downloads))
+                    @if registry == Registry::Crates { " downloads" } @else { " downloads last week" }
+                }
+                @if let Some(docs_url) = &package.docs_url {
+                    @if package.downloads.is_some() { " · " }
+                    a href=(docs_url) { "Documentation" }
+                }
+            }
+        }),
+    )))
+}
+
+/// Fetches json from a registry, treating a 404 as the package not existing.
+async fn get_json<T: for<'de> Deserialize<'de>>(url: String) -> eyre::Result<Option<T>> {
+    let res = CLIENT.get(&url).send().await?;
+    if res.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !res.status().is_success() {
+        eyre::bail!("{url} returned {}", res.status());
+    }
+    Ok(Some(serde_json::from_str(&res.text().await?)?))
+}
+
+#[derive(Deserialize)]
+struct CratesResponse {
+    #[serde(rename = "crate")]
+    krate: Crate,
+}
+
+#[derive(Deserialize)]
+struct Crate {
+    name: String,
+    max_stable_version: Option<String>,
+    newest_version: String,
+    description: Option<String>,
+    downloads: u64,
+    documentation: Option<String>,
+}
+
+async fn crates(name: &str) -> eyre::Result<Option<Package>> {
+    let url = format!(
+        "https://crates.io/api/v1/crates/{}",
+        urlencoding::encode(name)
+    );
+    let Some(res) = get_json::<CratesResponse>(url).await? else {
+        return Ok(None);
+    };
+    Ok(Some(parse_crate(res.krate)))
+}
+
+fn parse_crate(krate: Crate) -> Package {
+    Package {
+        url: format!("https://crates.io/crates/{}", krate.name),
+        docs_url: Some(
+            krate
+                .documentation
+                .unwrap_or_else(|| format!("https://docs.rs/{}", krate.name)),
+        ),
+        version: krate.max_stable_version.unwrap_or(krate.newest_version),
+        description: krate.description.unwrap_or_default().trim().to_string(),
+        downloads: Some(krate.downloads),
+        name: krate.name,
+    }
+}
+
+#[derive(Deserialize)]
+struct NpmPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    description: String,
+    homepage: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NpmDownloads {
+    downloads: u64,
+}
+
+async fn npm(name: &str) -> eyre::Result<Option<Package>> {
+    // scoped packages keep their @ but need the slash encoded
+    let name = name.replace('/', "%2F");
+    let (package, downloads) = tokio::join!(
+        get_json::<NpmPackage>(format!("https://registry.npmjs.org/{name}/latest")),
+        get_json::<NpmDownloads>(format!(
+            "https://api.npmjs.org/downloads/point/last-week/{name}"
+        )),
+    );
+    let Some(package) = package? else {
+        return Ok(None);
+    };
+    Ok(Some(Package {
+        url: format!("https://www.npmjs.com/package/{}", package.name),
+        docs_url: package.homepage,
+        version: package.version,
+        description: package.description,
+        // the download counts are nice to have but not worth failing over
+        downloads: downloads.ok().flatten().map(|d| d.downloads),
+        name: package.name,
+    }))
+}
+
+#[derive(Deserialize)]
+struct PyPiResponse {
+    info: PyPiInfo,
+}
+
+#[derive(Deserialize)]
+struct PyPiInfo {
+    name: String,
+    version: String,
+    #[serde(default)]
+    summary: Option<String>,
+    package_url: String,
+    docs_url: Option<String>,
+    #[serde(default)]
+    project_urls: Option<std::collections::HashMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+struct PyPiStats {
+    data: PyPiStatsData,
+}
+
+#[derive(Deserialize)]
+struct PyPiStatsData {
+    last_week: u64,
+}
+
+async fn pypi(name: &str) -> eyre::Result<Option<Package>> {
+    let name = urlencoding::encode(name);
+    let (package, stats) = tokio::join!(
+        get_json::<PyPiResponse>(format!("https://pypi.org/pypi/{name}/json")),
+        get_json::<PyPiStats>(format!(
+            "https://pypistats.org/api/packages/{}/recent",
+            name.to_lowercase()
+        )),
+    );
+    let Some(package) = package? else {
+        return Ok(None);
+    };
+    Ok(Some(parse_pypi(
+        package.info,
+        stats.ok().flatten().map(|s| s.data.last_week),
+    )))
+}
+
+fn parse_pypi(info: PyPiInfo, downloads: Option<u64>) -> Package {
+    let docs_url = info.docs_url.or_else(|| {
+        info.project_urls.and_then(|urls| {
+            urls.into_iter()
+                .find(|(label, _)| label.eq_ignore_ascii_case("documentation"))
+                .map(|(_, url)| url)
+        })
+    });
+    Package {
+        name: info.name,
+        version: info.version,
+        description: info.summary.unwrap_or_default().trim().to_string(),
+        downloads,
+        url: info.package_url,
+        docs_url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query("crate serde"),
+            Some((Registry::Crates, "serde"))
+        );
+        assert_eq!(
+            parse_query("npm @types/node"),
+            Some((Registry::Npm, "@types/node"))
+        );
+        assert_eq!(
+            parse_query("PyPI requests"),
+            Some((Registry::PyPi, "requests"))
+        );
+        assert_eq!(parse_query("pypi a/b"), None);
+        assert_eq!(parse_query("npm install react"), None);
+    }
+
+    #[test]
+    fn test_parse_registries() {
+        let krate: CratesResponse = serde_json::from_str(
+            r#"{"crate":{"name":"serde","max_stable_version":"1.0.210","newest_version":"1.0.211-rc.1",
+                "description":"A generic serialization/deserialization framework\n",
+                "downloads":400000000,"documentation":null}}"#,
+        )
+        .unwrap();
+        let package = parse_crate(krate.krate);
+        assert_eq!(package.version, "1.0.210");
+        assert_eq!(
+            package.description,
+            "A generic serialization/deserialization framework"
+        );
+        assert_eq!(package.docs_url.as_deref(), Some("https://docs.rs/serde"));
+
+        let pypi: PyPiResponse = serde_json::from_str(
+            r#"{"info":{"name":"requests","version":"2.32.3","summary":"Python HTTP for Humans.",
+                "package_url":"https://pypi.org/project/requests/","docs_url":null,
+                "project_urls":{"Documentation":"https://requests.readthedocs.io"}}}"#,
+        )
+        .unwrap();
+        let package = parse_pypi(pypi.info, Some(1000));
+        assert_eq!(
+            package.docs_url.as_deref(),
+            Some("https://requests.readthedocs.io")
+        );
+        assert_eq!(package.url, "https://pypi.org/project/requests/");
+    }
+}
@@ -0,0 +1,400 @@
+//! Stock and cryptocurrency prices, for queries like "AAPL stock" or "btc
+//! price".
+
+use maud::{html, PreEscaped};
+use serde::Deserialize;
+use tracing::error;
+use url::Url;
+
+use crate::engines::{Engine, EngineResponse, HttpResponse, RequestResponse, SearchQuery, CLIENT};
+
+use super::regex;
+
+/// Cryptocurrencies that are recognized when followed by "price", since their
+/// symbols aren't stock tickers.
+const CRYPTOCURRENCIES: &[(&str, &str)] = &[
+    ("bitcoin", "BTC"),
+    ("btc", "BTC"),
+    ("ethereum", "ETH"),
+    ("eth", "ETH"),
+    ("solana", "SOL"),
+    ("sol", "SOL"),
+    ("dogecoin", "DOGE"),
+    ("doge", "DOGE"),
+    ("litecoin", "LTC"),
+    ("ltc", "LTC"),
+    ("monero", "XMR"),
+    ("xmr", "XMR"),
+    ("ripple", "XRP"),
+    ("xrp", "XRP"),
+    ("cardano", "ADA"),
+    ("ada", "ADA"),
+];
+
+#[derive(Deserialize, Default)]
+struct TickerConfig {
+    #[serde(default)]
+    provider: Provider,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum Provider {
+    /// Yahoo Finance's chart api, which doesn't need a key.
+    #[default]
+    Yahoo,
+    /// Twelve Data, which needs an `api_key`.
+    TwelveData,
+}
+
+#[derive(Debug, PartialEq)]
+enum Ticker {
+    Stock(String),
+    Crypto {
+        symbol: &'static str,
+        currency: String,
+    },
+}
+
+fn parse_query(query: &str) -> Option<Ticker> {
+    let query = query.trim().to_lowercase();
+
+    if let Some(captures) =
+        regex!(r"^(?:price of )?([a-z]+)(?: price| to usd| usd)?(?: (?:in|to) ([a-z]{3}))?$")
+            .captures(&query)
+    {
+        let has_price_word = query.contains("price") || query.contains("usd");
+        let crypto = CRYPTOCURRENCIES
+            .iter()
+            .find(|(name, _)| *name == &captures[1]);
+        if let (true, Some((_, symbol))) = (has_price_word, crypto) {
+            let currency = captures.get(2).map_or("usd", |c| c.as_str());
+            return Some(Ticker::Crypto {
+                symbol,
+                currency: currency.to_uppercase(),
+            });
+        }
+    }
+
+    let captures =
+        regex!(r"^\$?([a-z][a-z0-9.\-]{0,9}) (?:stock|stock price|shares?|share price|ticker)$")
+            .captures(&query)?;
+    Some(Ticker::Stock(captures[1].to_uppercase()))
+}
+
+fn config(query: &SearchQuery) -> TickerConfig {
+    let extra = query.config.engines.get(Engine::Ticker).extra.clone();
+    match toml::Value::Table(extra).try_into() {
+        Ok(config) => config,
+        Err(err) => {
+            error!("Failed to parse ticker config: {err}");
+            TickerConfig::default()
+        }
+    }
+}
+
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    let Some(ticker) = parse_query(query) else {
+        return RequestResponse::None;
+    };
+    let config = config(query);
+
+    let url = match config.provider {
+        Provider::Yahoo => {
+            let symbol = match &ticker {
+                Ticker::Stock(symbol) => symbol.clone(),
+                Ticker::Crypto { symbol, currency } => format!("{symbol}-{currency}"),
+            };
+            Url::parse_with_params(
+                &format!(
+                    "https://query1.finance.yahoo.com/v8/finance/chart/{}",
+                    urlencoding::encode(&symbol)
+                ),
+                &[("range", "1d"), ("interval", "5m")],
+            )
+        }
+        Provider::TwelveData => {
+            let Some(api_key) = &config.api_key else {
+                error!("The ticker engine needs an api_key to use Twelve Data");
+                return RequestResponse::None;
+            };
+            let symbol = match &ticker {
+                Ticker::Stock(symbol) => symbol.clone(),
+                Ticker::Crypto { symbol, currency } => format!("{symbol}/{currency}"),
+            };
+            Url::parse_with_params(
+                "https://api.twelvedata.com/time_series",
+                &[
+                    ("symbol", symbol.as_str()),
+                    ("interval", "5min"),
+                    // about a day of trading
+                    ("outputsize", "78"),
+                    ("apikey", api_key),
+                ],
+            )
+        }
+    };
+
+    CLIENT.get(url.unwrap()).into()
+}
+
+/// A quote, in the same shape for every provider.
+#[derive(Debug, PartialEq)]
+struct Quote {
+    symbol: String,
+    name: Option<String>,
+    currency: String,
+    price: f64,
+    /// What the change is relative to, usually the previous close.
+    reference_price: f64,
+    /// The prices over the last day, oldest first.
+    history: Vec<f64>,
+}
+
+#[derive(Deserialize)]
+struct YahooResponse {
+    chart: YahooChart,
+}
+
+#[derive(Deserialize)]
+struct YahooChart {
+    result: Option<Vec<YahooResult>>,
+}
+
+#[derive(Deserialize)]
+struct YahooResult {
+    meta: YahooMeta,
+    indicators: YahooIndicators,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct YahooMeta {
+    symbol: String,
+    currency: Option<String>,
+    long_name: Option<String>,
+    short_name: Option<String>,
+    regular_market_price: f64,
+    chart_previous_close: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct YahooIndicators {
+    quote: Vec<YahooQuote>,
+}
+
+#[derive(Deserialize)]
+struct YahooQuote {
+    #[serde(default)]
+    close: Vec<Option<f64>>,
+}
+
+#[derive(Deserialize)]
+struct TwelveDataResponse {
+    meta: TwelveDataMeta,
+    values: Vec<TwelveDataValue>,
+}
+
+#[derive(Deserialize)]
+struct TwelveDataMeta {
+    symbol: String,
+    currency: Option<String>,
+    /// Set instead of `currency` for crypto pairs.
+    currency_quote: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TwelveDataValue {
+    close: String,
+}
+
+fn parse_yahoo(body: &str) -> eyre::Result<Option<Quote>> {
+    let res: YahooResponse = serde_json::from_str(body)?;
+    let Some(result) = res.chart.result.and_then(|r| r.into_iter().next()) else {
+        return Ok(None);
+    };
+    let history = result
+        .indicators
+        .quote
+        .into_iter()
+        .next()
+        .map(|q| q.close.into_iter().flatten().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let meta = result.meta;
+    Ok(Some(Quote {
+        symbol: meta.symbol,
+        name: meta.long_name.or(meta.short_name),
+        currency: meta.currency.unwrap_or_default(),
+        price: meta.regular_market_price,
+        reference_price: meta
+            .chart_previous_close
+            .or(history.first().copied())
+            .unwrap_or(meta.regular_market_price),
+        history,
+    }))
+}
+
+fn parse_twelve_data(body: &str) -> eyre::Result<Option<Quote>> {
+    // errors (like unknown symbols) don't have the same shape
+    let Ok(res) = serde_json::from_str::<TwelveDataResponse>(body) else {
+        return Ok(None);
+    };
+    let mut history = res
+        .values
+        .iter()
+        .filter_map(|v| v.close.parse::<f64>().ok())
+        .collect::<Vec<_>>();
+    // twelve data has the newest value first
+    history.reverse();
+    let (Some(&reference_price), Some(&price)) = (history.first(), history.last()) else {
+        return Ok(None);
+    };
+    Ok(Some(Quote {
+        symbol: res.meta.symbol,
+        name: None,
+        currency: res
+            .meta
+            .currency
+            .or(res.meta.currency_quote)
+            .unwrap_or_default(),
+        price,
+        reference_price,
+        history,
+    }))
+}
+
+pub fn parse_response(
+    HttpResponse { body, config, .. }: &HttpResponse,
+) -> eyre::Result<EngineResponse> {
+    let provider = toml::Value::Table(config.engines.get(Engine::Ticker).extra.clone())
+        .try_into::<TickerConfig>()
+        .unwrap_or_default()
+        .provider;
+    let quote = match provider {
+        Provider::Yahoo => parse_yahoo(body)?,
+        Provider::TwelveData => parse_twelve_data(body)?,
+    };
+    let Some(quote) = quote else {
+        return Ok(EngineResponse::new());
+    };
+
+    let change = quote.price - quote.reference_price;
+    let change_percent = if quote.reference_price == 0. {
+        0.
+    } else {
+        change / quote.reference_price * 100.
+    };
+    let direction = if change >= 0. { "up" } else { "down" };
+
+    Ok(EngineResponse::answer_html(html! {
+        div.answer-ticker {
+            p.answer-ticker-name {
+                (quote.symbol)
+                @if let Some(name) = &quote.name {
+                    " · " (name)
+                }
+            }
+            h3.answer-ticker-price {
+                (format_price(quote.price)) " " (quote.currency)
+            }
+            p.{"answer-ticker-change answer-ticker-" (direction)} {
+                (format!("{change:+.2} ({change_percent:+.2}%)")) " today"
+            }
+            @if quote.history.len() > 1 {
+                (sparkline(&quote.history, direction))
+            }
+        }
+    }))
+}
+
+fn format_price(price: f64) -> String {
+    // small prices (like some cryptocurrencies) need more precision
+    if price.abs() < 1. {
+        format!("{price:.4}")
+    } else {
+        format!("{price:.2}")
+    }
+}
+
+const SPARKLINE_WIDTH: f64 = 120.;
+const SPARKLINE_HEIGHT: f64 = 30.;
+
+fn sparkline(history: &[f64], direction: &str) -> PreEscaped<String> {
+    let min = history.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = history.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = if max > min { max - min } else { 1. };
+    let step = SPARKLINE_WIDTH / (history.len() - 1) as f64;
+    let points = history
+        .iter()
+        .enumerate()
+        .map(|(i, price)| {
+            let x = i as f64 * step;
+            let y = SPARKLINE_HEIGHT - (price - min) / range * SPARKLINE_HEIGHT;
+            format!("{x:.1},{y:.1}")
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    html! {
+        svg.{"answer-ticker-sparkline answer-ticker-" (direction)}
+            width=(SPARKLINE_WIDTH) height=(SPARKLINE_HEIGHT)
+            viewBox={ "0 0 " (SPARKLINE_WIDTH) " " (SPARKLINE_HEIGHT) }
+            preserveAspectRatio="none" {
+            polyline points=(points) fill="none" stroke="currentColor" stroke-width="1.5" {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query("AAPL stock"),
+            Some(Ticker::Stock("AAPL".to_string()))
+        );
+        assert_eq!(
+            parse_query("$brk.b share price"),
+            Some(Ticker::Stock("BRK.B".to_string()))
+        );
+        assert_eq!(
+            parse_query("btc price"),
+            Some(Ticker::Crypto {
+                symbol: "BTC",
+                currency: "USD".to_string()
+            })
+        );
+        assert_eq!(
+            parse_query("price of ethereum in eur"),
+            Some(Ticker::Crypto {
+                symbol: "ETH",
+                currency: "EUR".to_string()
+            })
+        );
+        assert_eq!(parse_query("btc"), None);
+        assert_eq!(parse_query("gold price"), None);
+        assert_eq!(parse_query("how to buy stock"), None);
+    }
+
+    #[test]
+    fn test_parse_yahoo() {
+        let body = r#"{"chart":{"result":[{"meta":{"currency":"USD","symbol":"AAPL",
+            "longName":"Apple Inc.","regularMarketPrice":190.5,"chartPreviousClose":188.0},
+            "timestamp":[1,2,3],"indicators":{"quote":[{"close":[188.5,null,190.5]}]}}],
+            "error":null}}"#;
+        assert_eq!(
+            parse_yahoo(body).unwrap(),
+            Some(Quote {
+                symbol: "AAPL".to_string(),
+                name: Some("Apple Inc.".to_string()),
+                currency: "USD".to_string(),
+                price: 190.5,
+                reference_price: 188.0,
+                history: vec![188.5, 190.5],
+            })
+        );
+    }
+}
@@ -1,26 +1,116 @@
-use chrono::{DateTime, TimeZone};
-use chrono_tz::{OffsetComponents, Tz};
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, Tz, TZ_VARIANTS};
 use maud::html;
 
 use crate::engines::EngineResponse;
 
 use super::regex;
 
+/// Cities that aren't the name of a timezone in the tz database. Cities that
+/// are (like Tokyo or Paris) are found without being in here.
+const CITIES: &[(&str, Tz)] = &[
+    ("san francisco", Tz::America__Los_Angeles),
+    ("seattle", Tz::America__Los_Angeles),
+    ("portland", Tz::America__Los_Angeles),
+    ("las vegas", Tz::America__Los_Angeles),
+    ("san diego", Tz::America__Los_Angeles),
+    ("silicon valley", Tz::America__Los_Angeles),
+    ("salt lake city", Tz::America__Denver),
+    ("austin", Tz::America__Chicago),
+    ("dallas", Tz::America__Chicago),
+    ("houston", Tz::America__Chicago),
+    ("minneapolis", Tz::America__Chicago),
+    ("new orleans", Tz::America__Chicago),
+    ("atlanta", Tz::America__New_York),
+    ("boston", Tz::America__New_York),
+    ("miami", Tz::America__New_York),
+    ("philadelphia", Tz::America__New_York),
+    ("washington", Tz::America__New_York),
+    ("washington dc", Tz::America__New_York),
+    ("nyc", Tz::America__New_York),
+    ("ottawa", Tz::America__Toronto),
+    ("montreal", Tz::America__Toronto),
+    ("rio de janeiro", Tz::America__Sao_Paulo),
+    ("rio", Tz::America__Sao_Paulo),
+    ("manchester", Tz::Europe__London),
+    ("edinburgh", Tz::Europe__London),
+    ("barcelona", Tz::Europe__Madrid),
+    ("munich", Tz::Europe__Berlin),
+    ("hamburg", Tz::Europe__Berlin),
+    ("frankfurt", Tz::Europe__Berlin),
+    ("milan", Tz::Europe__Rome),
+    ("geneva", Tz::Europe__Zurich),
+    ("st petersburg", Tz::Europe__Moscow),
+    ("beijing", Tz::Asia__Shanghai),
+    ("shenzhen", Tz::Asia__Shanghai),
+    ("guangzhou", Tz::Asia__Shanghai),
+    ("mumbai", Tz::Asia__Kolkata),
+    ("delhi", Tz::Asia__Kolkata),
+    ("new delhi", Tz::Asia__Kolkata),
+    ("bangalore", Tz::Asia__Kolkata),
+    ("bengaluru", Tz::Asia__Kolkata),
+    ("india", Tz::Asia__Kolkata),
+    ("osaka", Tz::Asia__Tokyo),
+    ("japan", Tz::Asia__Tokyo),
+    ("kyoto", Tz::Asia__Tokyo),
+    ("abu dhabi", Tz::Asia__Dubai),
+    ("hanoi", Tz::Asia__Bangkok),
+    ("canberra", Tz::Australia__Sydney),
+    ("wellington", Tz::Pacific__Auckland),
+    ("cape town", Tz::Africa__Johannesburg),
+];
+
+/// Abbreviations that aren't in the tz database, or that are but would give us
+/// a fixed offset without daylight savings.
+const ABBREVIATIONS: &[(&str, Tz)] = &[
+    ("cst", Tz::CST6CDT),
+    ("cdt", Tz::CST6CDT),
+    ("est", Tz::EST5EDT),
+    ("edt", Tz::EST5EDT),
+    ("pst", Tz::PST8PDT),
+    ("pdt", Tz::PST8PDT),
+    ("mst", Tz::MST7MDT),
+    ("mdt", Tz::MST7MDT),
+    ("cest", Tz::Etc__GMTMinus2),
+    ("bst", Tz::Europe__London),
+    ("ist", Tz::Asia__Kolkata),
+    ("jst", Tz::Asia__Tokyo),
+    ("aest", Tz::Australia__Sydney),
+];
+
+/// The cities in the world clock.
+const WORLD_CLOCK: &[(&str, Tz)] = &[
+    ("Los Angeles", Tz::America__Los_Angeles),
+    ("New York", Tz::America__New_York),
+    ("São Paulo", Tz::America__Sao_Paulo),
+    ("London", Tz::Europe__London),
+    ("Paris", Tz::Europe__Paris),
+    ("Moscow", Tz::Europe__Moscow),
+    ("Dubai", Tz::Asia__Dubai),
+    ("Mumbai", Tz::Asia__Kolkata),
+    ("Shanghai", Tz::Asia__Shanghai),
+    ("Tokyo", Tz::Asia__Tokyo),
+    ("Sydney", Tz::Australia__Sydney),
+];
+
 pub async fn request(query: &str) -> EngineResponse {
     match evaluate(query) {
         None => EngineResponse::new(),
-        Some(TimeResponse::Current { time, timezone }) => EngineResponse::answer_html(html! {
-            p.answer-query { "Current time in " (timezone_to_string(timezone)) }
+        Some(TimeResponse::Current { time, place }) => EngineResponse::answer_html(html! {
+            p.answer-query { "Current time in " (place.name) }
             h3 {
                 b { (time.format("%-I:%M %P")) }
                 span.answer-comment {
                     " (" (time.format("%B %-d")) ")"
                 }
             }
+            @if place.name != timezone_to_string(place.timezone) {
+                p.answer-comment { (timezone_to_string(place.timezone)) }
+            }
         }),
         Some(TimeResponse::Conversion {
-            source_timezone,
-            target_timezone,
+            source,
+            target,
             source_time,
             target_time,
             source_offset,
@@ -37,53 +127,87 @@ pub async fn request(query: &str) -> EngineResponse {
                 p.answer-query {
                     (source_time.format("%-I:%M %P"))
                     " "
-                    (timezone_to_string(source_timezone))
+                    (source.name)
                     " to "
-                    (timezone_to_string(target_timezone))
+                    (target.name)
                 }
                 h3 {
                     b { (target_time.format("%-I:%M %P")) }
                     " "
                     span.answer-comment {
-                        (timezone_to_string(target_timezone)) " (" (delta) ")"
+                        (target.name) " (" (delta) ")"
                     }
                 }
             })
         }
+        Some(TimeResponse::WorldClock { time }) => EngineResponse::answer_html(html! {
+            p.answer-query { "World clock" }
+            table.answer-world-clock {
+                @for (city, timezone) in WORLD_CLOCK {
+                    @let local = time.with_timezone(timezone);
+                    tr {
+                        th { (city) }
+                        td { b { (local.format("%-I:%M %P")) } }
+                        td.answer-comment { (local.format("%a %B %-d")) }
+                    }
+                }
+            }
+        }),
     }
 }
 
+/// A timezone, and what to call it.
+#[derive(Debug)]
+struct Place {
+    name: String,
+    timezone: Tz,
+}
+
 #[derive(Debug)]
 enum TimeResponse {
     Current {
         time: DateTime<Tz>,
-        timezone: Tz,
+        place: Place,
     },
     Conversion {
-        source_timezone: Tz,
-        target_timezone: Tz,
+        source: Place,
+        target: Place,
         source_time: DateTime<Tz>,
         target_time: DateTime<Tz>,
         source_offset: chrono::Duration,
         target_offset: chrono::Duration,
     },
+    WorldClock {
+        time: DateTime<Utc>,
+    },
 }
 
 fn evaluate(query: &str) -> Option<TimeResponse> {
-    // "4pm utc to cst"
-    let re = regex!(r"(\d{1,2})(?:(\d{1,2}))?\s*(am|pm|) ([\w/+\-]+) (to|as|in) ([\w/+\-]+)");
+    let query = query.trim();
+
+    if regex!(r"(?i)^(world ?clock|(current )?time(s)? around the world)$").is_match(query) {
+        return Some(TimeResponse::WorldClock { time: Utc::now() });
+    }
+
+    // "4pm utc to cst", "3:30 pm new york in tokyo"
+    let re = regex!(
+        r"(?i)\b(\d{1,2})(?::(\d{2}))?\s*(am|pm)?\s+([a-z][\w/+\- ]*?) (?:to|as|in) ([a-z][\w/+\- ]*)$"
+    );
     if let Some(captures) = re.captures(query) {
         if let Some(hour) = captures.get(1).map(|m| m.as_str().parse::<u32>().unwrap()) {
             let minute = match captures.get(2) {
                 Some(m) => m.as_str().parse::<u32>().ok()?,
                 None => 0,
             };
-            let ampm = captures.get(3).unwrap().as_str();
-            let timezone1_name = captures.get(4).unwrap().as_str();
-            let timezone2_name = captures.get(6).unwrap().as_str();
+            let ampm = captures
+                .get(3)
+                .map(|m| m.as_str().to_lowercase())
+                .unwrap_or_default();
 
-            let source_timezone = parse_timezone(timezone1_name)?;
-            let target_timezone = parse_timezone(timezone2_name)?;
+            let source = parse_place(captures.get(4).unwrap().as_str())?;
+            let target = parse_place(captures.get(5).unwrap().as_str())?;
+            let source_timezone = source.timezone;
+            let target_timezone = target.timezone;
 
             let current_date = chrono::Utc::now().date_naive();
 
@@ -110,8 +234,8 @@ fn evaluate(query: &str) -> Option<TimeResponse> {
             let target_time = source_time_utc.with_timezone(&target_timezone);
 
             return Some(TimeResponse::Conversion {
-                source_timezone,
-                target_timezone,
+                source,
+                target,
                 source_time,
                 target_time,
                 source_offset: source_offset.base_utc_offset(),
@@ -120,39 +244,74 @@ fn evaluate(query: &str) -> Option<TimeResponse> {
         }
     }
 
-    // "utc time"
-    let re = regex!(r"([\w/+\-]+)(?: current)? time$");
-    // "time in utc"
-    let re2 = regex!(r"time (?:in|as) ([\w/+\-]+)$");
-    if let Some(timezone_name) = re
+    // "utc time", "what's the tokyo current time"
+    let re = regex!(r"(?i)^(.*?)(?: current)? time$");
+    // "time in utc", "what time is it in new york"
+    let re2 = regex!(r"(?i)time (?:is it )?(?:in|at|as) ([a-z][\w/+\- ]*)$");
+    if let Some(place_name) = re
         .captures(query)
         .and_then(|m| m.get(1))
         .or_else(|| re2.captures(query).and_then(|m| m.get(1)))
     {
-        if let Some(timezone) = parse_timezone(timezone_name.as_str()) {
-            let time = chrono::Utc::now().with_timezone(&timezone);
-            return Some(TimeResponse::Current { time, timezone });
+        if let Some(place) = parse_place_suffix(place_name.as_str()) {
+            let time = chrono::Utc::now().with_timezone(&place.timezone);
+            return Some(TimeResponse::Current { time, place });
         }
     }
 
     None
 }
 
+fn parse_place(name: &str) -> Option<Place> {
+    let lowercase_name = name.trim().to_lowercase();
+    if let Some((_, timezone)) = CITIES.iter().find(|(city, _)| *city == lowercase_name) {
+        return Some(Place {
+            name: title_case(&lowercase_name),
+            timezone: *timezone,
+        });
+    }
+    if let Some(timezone) = parse_timezone(name.trim()) {
+        return Some(Place {
+            name: timezone_to_string(timezone),
+            timezone,
+        });
+    }
+    // cities like "Tokyo" and "New York" are the last part of a timezone's name
+    let tz_city = lowercase_name.replace(' ', "_");
+    let timezone = TZ_VARIANTS.iter().find(|tz| {
+        tz.name()
+            .rsplit_once('/')
+            .is_some_and(|(_, city)| city.eq_ignore_ascii_case(&tz_city))
+    })?;
+    Some(Place {
+        name: title_case(&lowercase_name),
+        timezone: *timezone,
+    })
+}
+
+/// Parse the longest run of words at the end of `s` that's a place, so "what's
+/// the tokyo" is Tokyo.
+fn parse_place_suffix(s: &str) -> Option<Place> {
+    let words = s.split_whitespace().collect::<Vec<_>>();
+    (0..words.len()).find_map(|i| parse_place(&words[i..].join(" ")))
+}
+
 fn parse_timezone(timezone_name: &str) -> Option<Tz> {
-    match timezone_name.to_lowercase().as_str() {
-        "cst" | "cdt" => Some(Tz::CST6CDT),
-        "est" | "edt" => Some(Tz::EST5EDT),
-        "cest" => Some(Tz::Etc__GMTMinus2),
-        _ => Tz::from_str_insensitive(timezone_name)
-            .ok()
-            .or_else(|| Tz::from_str_insensitive(&format!("etc/{timezone_name}")).ok()),
+    let lowercase_name = timezone_name.to_lowercase();
+    if let Some((_, timezone)) = ABBREVIATIONS.iter().find(|(a, _)| *a == lowercase_name) {
+        return Some(*timezone);
     }
+    Tz::from_str_insensitive(timezone_name)
+        .ok()
+        .or_else(|| Tz::from_str_insensitive(&format!("etc/{timezone_name}")).ok())
 }
 
 fn timezone_to_string(tz: Tz) -> String {
     match tz {
         Tz::CST6CDT => "CST".to_string(),
         Tz::EST5EDT => "EST".to_string(),
+        Tz::PST8PDT => "PST".to_string(),
+        Tz::MST7MDT => "MST".to_string(),
         _ => {
             let tz_string = tz.name();
             if let Some(tz_string) = tz_string.strip_prefix("Etc/") {
@@ -164,6 +323,19 @@ fn timezone_to_string(tz: Tz) -> String {
     }
 }
 
+fn title_case(s: &str) -> String {
+    s.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -186,4 +358,42 @@ mod tests {
         assert_eq!(source_time.format("%-I:%M %P").to_string(), "9:00 pm");
         assert_eq!(target_time.format("%-I:%M %P").to_string(), "8:00 pm");
     }
+
+    #[test]
+    fn test_parse_place() {
+        let place = parse_place("tokyo").unwrap();
+        assert_eq!(
+            (place.name.as_str(), place.timezone),
+            ("Tokyo", Tz::Asia__Tokyo)
+        );
+        let place = parse_place("New York").unwrap();
+        assert_eq!(place.timezone, Tz::America__New_York);
+        let place = parse_place("san francisco").unwrap();
+        assert_eq!(
+            (place.name.as_str(), place.timezone),
+            ("San Francisco", Tz::America__Los_Angeles)
+        );
+        assert_eq!(parse_place("utc").unwrap().name, "UTC");
+        assert_eq!(parse_place("est").unwrap().timezone, Tz::EST5EDT);
+        assert!(parse_place("what").is_none());
+
+        let Some(TimeResponse::Current { place, .. }) = evaluate("what time is it in london")
+        else {
+            panic!("expected the current time");
+        };
+        assert_eq!(place.timezone, Tz::Europe__London);
+        let Some(TimeResponse::Conversion { source, target, .. }) =
+            evaluate("3:30pm berlin to new york")
+        else {
+            panic!("expected a conversion");
+        };
+        assert_eq!(
+            (source.timezone, target.timezone),
+            (Tz::Europe__Berlin, Tz::America__New_York)
+        );
+        assert!(matches!(
+            evaluate("world clock"),
+            Some(TimeResponse::WorldClock { .. })
+        ));
+    }
 }
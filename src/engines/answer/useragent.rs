@@ -4,10 +4,13 @@ use crate::engines::{EngineResponse, SearchQuery};
 
 use super::regex;
 
+/// Whether the answer would be shown for this query.
+pub fn matches(query: &str) -> bool {
+    regex!("^(what('s|s| is) my (user ?agent|ua)|ua|user ?agent)$").is_match(&query.to_lowercase())
+}
+
 pub async fn request(query: &SearchQuery) -> EngineResponse {
-    if !regex!("^(what('s|s| is) my (user ?agent|ua)|ua|user ?agent)$")
-        .is_match(&query.query.to_lowercase())
-    {
+    if !matches(&query.query) {
         return EngineResponse::new();
     }
 
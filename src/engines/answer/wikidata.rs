@@ -0,0 +1,266 @@
+//! An infobox with facts about the entity that matches the query. It has a
+//! higher weight than the Wikipedia summary, so it's shown instead of it when
+//! there's anything to show.
+
+use std::collections::HashMap;
+
+use maud::html;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    config::Config,
+    engines::{EngineResponse, HttpResponse, SearchQuery, CLIENT},
+};
+
+pub async fn request(query: &SearchQuery) -> wreq::RequestBuilder {
+    let language = language(&query.config);
+    CLIENT
+        .get(
+            Url::parse_with_params(
+                "https://query.wikidata.org/sparql",
+                &[
+                    ("query", sparql(&query.query, &language).as_str()),
+                    ("format", "json"),
+                ],
+            )
+            .unwrap(),
+        )
+        .header("Accept", "application/sparql-results+json")
+}
+
+fn language(config: &Config) -> String {
+    let language = config.language.split('-').next().unwrap_or_default();
+    if !language.is_empty() && language.chars().all(|c| c.is_ascii_alphabetic()) {
+        language.to_lowercase()
+    } else {
+        "en".to_string()
+    }
+}
+
+/// Finds the best match for the query with Wikidata's entity search, but only
+/// if its label or one of its aliases is the query, so we don't show random
+/// entities for normal searches.
+fn sparql(query: &str, language: &str) -> String {
+    let escape = |s: &str| {
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace(['\n', '\r'], " ")
+    };
+    let search = escape(query.trim());
+    let lowercase_search = escape(&query.trim().to_lowercase());
+    format!(
+        r#"SELECT ?item ?itemLabel ?itemDescription ?typeLabel ?birth ?death ?population ?website ?image WHERE {{
+  SERVICE wikibase:mwapi {{
+    bd:serviceParam wikibase:endpoint "www.wikidata.org";
+                    wikibase:api "EntitySearch";
+                    mwapi:search "{search}";
+                    mwapi:language "{language}".
+    ?item wikibase:apiOutputItem mwapi:item.
+    ?ordinal wikibase:apiOrdinal true.
+  }}
+  FILTER(?ordinal = 0)
+  FILTER EXISTS {{
+    ?item rdfs:label|skos:altLabel ?name.
+    FILTER(LCASE(STR(?name)) = "{lowercase_search}")
+  }}
+  OPTIONAL {{ ?item wdt:P31 ?type. }}
+  OPTIONAL {{ ?item wdt:P569 ?birth. }}
+  OPTIONAL {{ ?item wdt:P570 ?death. }}
+  OPTIONAL {{ ?item wdt:P1082 ?population. }}
+  OPTIONAL {{ ?item wdt:P856 ?website. }}
+  OPTIONAL {{ ?item wdt:P18 ?image. }}
+  SERVICE wikibase:label {{ bd:serviceParam wikibase:language "{language},en". }}
+}}
+LIMIT 50"#
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct SparqlResponse {
+    results: SparqlResults,
+}
+
+#[derive(Debug, Deserialize)]
+struct SparqlResults {
+    bindings: Vec<HashMap<String, SparqlValue>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SparqlValue {
+    value: String,
+}
+
+/// The facts about an entity. Every row of the SPARQL response is the same
+/// entity, there's just more than one row when a property has several values.
+#[derive(Debug, Default, PartialEq)]
+struct Entity {
+    url: String,
+    label: String,
+    description: Option<String>,
+    types: Vec<String>,
+    birth: Option<String>,
+    death: Option<String>,
+    population: Option<u64>,
+    website: Option<String>,
+    image: Option<String>,
+}
+
+impl Entity {
+    fn has_facts(&self) -> bool {
+        !self.types.is_empty()
+            || self.birth.is_some()
+            || self.population.is_some()
+            || self.website.is_some()
+            || self.image.is_some()
+    }
+}
+
+pub fn parse_response(res: &HttpResponse) -> eyre::Result<EngineResponse> {
+    let Some(entity) = parse_entity(&res.body)? else {
+        return Ok(EngineResponse::new());
+    };
+    // if there's only a label and description then the wikipedia summary is better
+    if !entity.has_facts() {
+        return Ok(EngineResponse::new());
+    }
+
+    let image_src = entity.image.as_ref().map(|image| {
+        // commons redirects this to a thumbnail of the right size
+        let image = format!("{image}?width=300");
+        // the proxy only works when image search is enabled
+        let image_search = &res.config.image_search;
+        if image_search.enabled && image_search.proxy.enabled {
+            let escaped =
+                url::form_urlencoded::byte_serialize(image.as_bytes()).collect::<String>();
+            format!("/image-proxy?url={escaped}")
+        } else {
+            image
+        }
+    });
+
+    Ok(EngineResponse::infobox_html(html! {
+        a href=(entity.url) {
+            h2 { (entity.label) }
+        }
+        @if let Some(description) = &entity.description {
+            p.infobox-wikidata-description { (description) }
+        }
+        @if let Some(image_src) = image_src {
+            img.infobox-wikidata-image src=(image_src) alt=(entity.label) loading="lazy";
+        }
+        table.infobox-wikidata-facts {
+            @if !entity.types.is_empty() {
+                tr { th { "Type" } td { (entity.types.join(", ")) } }
+            }
+            @if let Some(birth) = &entity.birth {
+                tr { th { "Born" } td { (birth) } }
+            }
+            @if let Some(death) = &entity.death {
+                tr { th { "Died" } td { (death) } }
+            }
+            @if let Some(population) = entity.population {
+                tr { th { "Population" } td { (format_thousands(population)) } }
+            }
+            @if let Some(website) = &entity.website {
+                tr { th { "Website" } td { a href=(website) rel="noreferrer" { (website) } } }
+            }
+        }
+    }))
+}
+
+fn parse_entity(body: &str) -> eyre::Result<Option<Entity>> {
+    let res: SparqlResponse = serde_json::from_str(body)?;
+
+    let mut entity = Entity::default();
+    for row in res.results.bindings {
+        let get = |key: &str| row.get(key).map(|v| v.value.clone());
+
+        if entity.url.is_empty() {
+            let Some(url) = get("item") else {
+                continue;
+            };
+            entity.url = url
+                .replacen("http://", "https://", 1)
+                .replacen("/entity/", "/wiki/", 1);
+            entity.label = get("itemLabel").unwrap_or_default();
+            entity.description = get("itemDescription");
+        }
+
+        if let Some(type_label) = get("typeLabel") {
+            if !entity.types.contains(&type_label) {
+                entity.types.push(type_label);
+            }
+        }
+        entity.birth = entity.birth.or(get("birth").map(|d| format_date(&d)));
+        entity.death = entity.death.or(get("death").map(|d| format_date(&d)));
+        entity.population = entity
+            .population
+            .or(get("population").and_then(|p| p.parse::<f64>().ok().map(|p| p as u64)));
+        entity.website = entity.website.or(get("website"));
+        entity.image = entity
+            .image
+            .or(get("image").map(|i| i.replacen("http://", "https://", 1)));
+    }
+
+    // labels that weren't found in any language are just the entity id
+    if entity.url.is_empty() || entity.url.ends_with(&format!("/{}", entity.label)) {
+        return Ok(None);
+    }
+    Ok(Some(entity))
+}
+
+/// Wikidata dates look like `1952-03-11T00:00:00Z`.
+fn format_date(date: &str) -> String {
+    date.split('T').next().unwrap_or(date).to_string()
+}
+
+pub(super) fn format_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut formatted = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(c);
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entity() {
+        let body = r#"{"head":{"vars":[]},"results":{"bindings":[
+            {"item":{"type":"uri","value":"http://www.wikidata.org/entity/Q64"},
+             "itemLabel":{"type":"literal","value":"Berlin"},
+             "itemDescription":{"type":"literal","value":"capital of Germany"},
+             "typeLabel":{"type":"literal","value":"city"},
+             "population":{"type":"literal","value":"3755251"},
+             "website":{"type":"uri","value":"https://www.berlin.de/"}},
+            {"item":{"type":"uri","value":"http://www.wikidata.org/entity/Q64"},
+             "itemLabel":{"type":"literal","value":"Berlin"},
+             "typeLabel":{"type":"literal","value":"capital"},
+             "population":{"type":"literal","value":"3755251"}}
+        ]}}"#;
+        let entity = parse_entity(body).unwrap().unwrap();
+        assert_eq!(
+            entity,
+            Entity {
+                url: "https://www.wikidata.org/wiki/Q64".to_string(),
+                label: "Berlin".to_string(),
+                description: Some("capital of Germany".to_string()),
+                types: vec!["city".to_string(), "capital".to_string()],
+                population: Some(3755251),
+                website: Some("https://www.berlin.de/".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(format_thousands(entity.population.unwrap()), "3,755,251");
+
+        let empty = r#"{"head":{"vars":[]},"results":{"bindings":[]}}"#;
+        assert_eq!(parse_entity(empty).unwrap(), None);
+    }
+}
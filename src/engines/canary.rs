@@ -0,0 +1,151 @@
+//! Searching for something on every engine in the background, to notice when
+//! an engine changes its markup and our parser stops finding results.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, LazyLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use super::{
+    endpoints, make_request, preprocess, Engine, EngineProgressUpdate, RequestResponse,
+    SearchQuery, SearchTab, DEFAULT_REQUESTED_COUNT,
+};
+use crate::config::Config;
+
+static STATUSES: LazyLock<Mutex<BTreeMap<Engine, CanaryStatus>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CanaryStatus {
+    pub engine: Engine,
+    pub broken: bool,
+    /// How many usable results the engine returned.
+    pub results: usize,
+    pub error: Option<String>,
+    /// Unix timestamp of when the engine was last checked.
+    pub checked_at: u64,
+}
+
+/// Start checking the engines periodically, if it's enabled in the config.
+pub fn spawn(config: Arc<Config>) {
+    if !config.canary.enabled {
+        return;
+    }
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(config.canary.interval_minutes.max(1) * 60);
+        loop {
+            check_all(&config).await;
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+pub fn is_broken(engine: Engine) -> bool {
+    STATUSES.lock().get(&engine).is_some_and(|s| s.broken)
+}
+
+pub fn statuses() -> Vec<CanaryStatus> {
+    STATUSES.lock().values().cloned().collect()
+}
+
+async fn check_all(config: &Arc<Config>) {
+    let mut config = config.as_ref().clone();
+    if config.language == "auto" {
+        config.language = preprocess::auto_language(&config.canary.query, None);
+    }
+    let query = SearchQuery {
+        query: config.canary.query.clone(),
+        tab: SearchTab::All,
+        verbatim: false,
+        only_engines: vec![],
+        label: None,
+        language: None,
+        image_filters: Default::default(),
+        requested_count: DEFAULT_REQUESTED_COUNT,
+        info: preprocess::preprocess(&config.canary.query, &config.preprocess.steps).info,
+        debug: false,
+        request_headers: Default::default(),
+        ip: "127.0.0.1".to_string(),
+        config: Arc::new(config),
+    };
+
+    // one at a time, since this isn't in a hurry and shouldn't look like a burst
+    // of traffic to the engines
+    for &engine in Engine::all() {
+        if !query.config.engines.get(engine).enabled {
+            continue;
+        }
+        let Some(result) = check(engine, &query).await else {
+            // answer engines and engines that skipped the query don't make requests,
+            // so there's nothing to check
+            continue;
+        };
+
+        let min_results = query.config.canary.min_results;
+        let (results, error) = match result {
+            Ok(results) => (results, None),
+            Err(err) => (0, Some(err.to_string())),
+        };
+        let broken = results < min_results;
+        if broken {
+            warn!(
+                "{engine} looks broken: got {results} results for {:?}, expected at least {min_results}{}",
+                query.query,
+                error.as_ref().map(|e| format!(" ({e})")).unwrap_or_default()
+            );
+        } else if is_broken(engine) {
+            info!("{engine} works again");
+        }
+
+        let checked_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        STATUSES.lock().insert(
+            engine,
+            CanaryStatus {
+                engine,
+                broken,
+                results,
+                error,
+                checked_at,
+            },
+        );
+    }
+}
+
+/// Returns the number of results with a title and url, or None if the engine
+/// doesn't make a request for the query.
+async fn check(engine: Engine, query: &SearchQuery) -> Option<eyre::Result<usize>> {
+    let send_engine_progress_update = |_: Engine, _: EngineProgressUpdate| {};
+    let res = match engine.request(query).await {
+        Ok(RequestResponse::Http(request)) => {
+            make_request(*request, engine, query, send_engine_progress_update).await
+        }
+        Ok(RequestResponse::Failover(requests)) => {
+            endpoints::make_failover_request(requests, engine, query, send_engine_progress_update)
+                .await
+        }
+        // only the first page is checked
+        Ok(RequestResponse::Pages(requests)) => match requests.into_iter().next() {
+            Some(request) => {
+                make_request(request, engine, query, send_engine_progress_update).await
+            }
+            None => return None,
+        },
+        Ok(_) => return None,
+        Err(err) => return Some(Err(err)),
+    };
+    Some(res.and_then(|res| {
+        let response = engine.parse_response(&res)?;
+        Ok(response
+            .search_results
+            .iter()
+            .filter(|r| !r.title.is_empty() && !r.url.is_empty())
+            .count())
+    }))
+}
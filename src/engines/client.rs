@@ -0,0 +1,189 @@
+//! The http clients that engines make requests with. There's one shared client,
+//! and engines that have their own `http` settings or keep cookies get a
+//! separate one so their connection pool, dns overrides, and cookies don't
+//! affect the others.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use tracing::error;
+use wreq_util::EmulationOption;
+
+use super::{cookies::Jar, scheduler, Engine};
+use crate::config::{Config, HttpConfig, PartialHttpConfig};
+
+/// The `[http]` config, for building the shared client.
+static HTTP_CONFIG: OnceLock<HttpConfig> = OnceLock::new();
+
+/// The clients for engines that have their own `http` settings or keep
+/// cookies.
+static ENGINE_CLIENTS: OnceLock<HashMap<Engine, EngineClient>> = OnceLock::new();
+
+struct EngineClient {
+    client: wreq::Client,
+    jar: Option<Arc<Jar>>,
+}
+
+#[derive(Deserialize)]
+struct EngineHttpConfig {
+    http: Option<PartialHttpConfig>,
+}
+
+/// Remember the http settings, and build a client for each engine that has
+/// its own. This has to be called before the shared client is first used.
+pub fn init(config: &Config) {
+    if ENGINE_CLIENTS.get().is_some() {
+        return;
+    }
+    let _ = HTTP_CONFIG.set(config.http.clone());
+
+    let mut clients = HashMap::new();
+    for &engine in Engine::all() {
+        let extra = &config.engines.get(engine).extra;
+        let partial = if extra.contains_key("http") {
+            match toml::Value::Table(extra.clone()).try_into::<EngineHttpConfig>() {
+                Ok(EngineHttpConfig { http }) => http,
+                Err(err) => {
+                    error!("Failed to parse http config for {engine}: {err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        if partial.is_none() && !config.http.cookies {
+            continue;
+        }
+        let mut http_config = config.http.clone();
+        http_config.overlay(partial.unwrap_or_default());
+        let jar = http_config
+            .cookies
+            .then(|| Jar::open(&http_config.cookie_directory, engine));
+        clients.insert(
+            engine,
+            EngineClient {
+                client: build(&http_config, jar.clone()),
+                jar,
+            },
+        );
+    }
+    let _ = ENGINE_CLIENTS.set(clients);
+}
+
+/// The settings for the shared client, or the defaults if [`init`] wasn't
+/// called.
+pub(super) fn http_config() -> HttpConfig {
+    HTTP_CONFIG
+        .get()
+        .cloned()
+        .unwrap_or_else(|| Config::default().http)
+}
+
+/// The engine's own client, if it has different settings from the shared one.
+fn for_engine(engine: Engine) -> Option<&'static wreq::Client> {
+    Some(&ENGINE_CLIENTS.get()?.get(&engine)?.client)
+}
+
+/// Put cookies in the engine's cookie jar like they were set by `url`, and
+/// return false if the engine doesn't keep cookies.
+pub fn add_cookies(engine: Engine, url: &url::Url, cookies: &[&str]) -> bool {
+    let Some(jar) = ENGINE_CLIENTS
+        .get()
+        .and_then(|clients| clients.get(&engine)?.jar.as_ref())
+    else {
+        return false;
+    };
+    jar.add(url, cookies);
+    true
+}
+
+/// Send a request that was built with [`super::CLIENT`], with the engine's own
+/// client if it has one. It waits for the [`scheduler`] first.
+pub(super) async fn send(
+    engine: Engine,
+    request: wreq::RequestBuilder,
+) -> wreq::Result<wreq::Response> {
+    let (client, request) = request.build_split();
+    let client = for_engine(engine).unwrap_or(&client);
+    execute(client, request?).await
+}
+
+/// Send a request that isn't for an engine, like for looking up something
+/// about the results. It waits for the [`scheduler`] too.
+pub(super) async fn send_without_engine(
+    request: wreq::RequestBuilder,
+) -> wreq::Result<wreq::Response> {
+    let (client, request) = request.build_split();
+    execute(&client, request?).await
+}
+
+/// Send a request to a page from the results, like for reading its meta tags.
+/// The urls come from search engines, so they get the same ssrf protection as
+/// the image proxy, and the connection goes to the address that was checked.
+/// It waits for the [`scheduler`] too.
+pub(super) async fn send_to_page(request: wreq::RequestBuilder) -> eyre::Result<wreq::Response> {
+    let (_, request) = request.build_split();
+    let request = request?;
+    let v = url_jail::validate(request.url().as_str(), url_jail::Policy::PublicOnly).await?;
+    let client = builder(&http_config())
+        .resolve(&v.host, v.to_socket_addr())
+        .build()?;
+    Ok(execute(&client, request).await?)
+}
+
+async fn execute(client: &wreq::Client, request: wreq::Request) -> wreq::Result<wreq::Response> {
+    let _permit = match request.url().host_str() {
+        Some(host) => scheduler::wait(host).await,
+        None => None,
+    };
+    client.execute(request).await
+}
+
+/// A client with the settings, that keeps its cookies in `jar` if there is one.
+pub(super) fn build(http_config: &HttpConfig, jar: Option<Arc<Jar>>) -> wreq::Client {
+    let mut builder = builder(http_config);
+    if let Some(jar) = jar {
+        builder = builder.cookie_provider(jar);
+    }
+    builder.build().unwrap()
+}
+
+fn builder(http_config: &HttpConfig) -> wreq::ClientBuilder {
+    let mut builder = wreq::ClientBuilder::new()
+        .local_address(IpAddr::from_str("0.0.0.0").unwrap())
+        // we pretend to be a normal browser so websites don't block us
+        .emulation(
+            EmulationOption::builder()
+                .emulation(http_config.emulation)
+                .emulation_os(http_config.emulation_os)
+                .build(),
+        )
+        .timeout(Duration::from_secs(10))
+        .tcp_keepalive(
+            Some(Duration::from_secs(http_config.tcp_keepalive_secs)).filter(|d| !d.is_zero()),
+        );
+    if !http_config.async_dns {
+        builder = builder.no_hickory_dns();
+    }
+    if let Some(max) = http_config.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max);
+    }
+    if !http_config.http2 {
+        builder = builder.http1_only();
+    }
+    for (host, ips) in &http_config.hosts {
+        // the port is replaced with the one from the url
+        let addrs = ips
+            .iter()
+            .map(|ip| SocketAddr::new(*ip, 0))
+            .collect::<Vec<_>>();
+        builder = builder.resolve_to_addrs(host, &addrs);
+    }
+    builder
+}
@@ -0,0 +1,318 @@
+//! Identical searches that happen at the same time (like when a link to the
+//! instance is shared in a chat) only make one set of requests to the engines.
+//! Everyone who's waiting gets the same progress updates and results. Searches
+//! that just finished are kept for a minute too, so reloading the page or
+//! going back to it doesn't search again.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use tokio::sync::{mpsc, watch};
+use tracing::debug;
+
+use super::{page_cache::PageCache, ProgressUpdate, SearchQuery};
+
+/// How long the updates from a finished search are replayed for identical
+/// ones. Results rarely change that quickly.
+const RECENT_TTL: Duration = Duration::from_secs(60);
+const MAX_RECENT: usize = 100;
+
+struct InFlight {
+    /// Every update so far, so searches that join late can catch up.
+    updates: Vec<ProgressUpdate>,
+    subscribers: Vec<mpsc::UnboundedSender<ProgressUpdate>>,
+    /// Set to the error (if any) when the search is done.
+    done: DoneReceiver,
+}
+
+type DoneReceiver = watch::Receiver<Option<Result<(), Arc<str>>>>;
+
+/// Removes the search from [`IN_FLIGHT`] when it's dropped.
+struct InFlightGuard<'a>(&'a str);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        IN_FLIGHT.lock().remove(self.0);
+    }
+}
+
+static IN_FLIGHT: LazyLock<Mutex<HashMap<String, InFlight>>> = LazyLock::new(Default::default);
+/// Every update from searches that finished without an error, by cache key.
+static RECENT: LazyLock<PageCache<Arc<[ProgressUpdate]>>> =
+    LazyLock::new(|| PageCache::new(MAX_RECENT, RECENT_TTL));
+
+/// Do the search with `search`, unless an identical one is already running, in
+/// which case its updates are sent to `progress_tx` too, or one just finished,
+/// in which case its updates are sent again. Searches with results that can't
+/// be shared are never coalesced.
+pub async fn search<F, Fut>(
+    query: &SearchQuery,
+    progress_tx: mpsc::UnboundedSender<ProgressUpdate>,
+    search: F,
+) -> eyre::Result<()>
+where
+    F: FnOnce(SearchQuery, mpsc::UnboundedSender<ProgressUpdate>) -> Fut,
+    Fut: Future<Output = eyre::Result<()>>,
+{
+    let Some(key) = query.cache_key() else {
+        return search(query.clone(), progress_tx).await;
+    };
+
+    let joined = {
+        let mut in_flight = IN_FLIGHT.lock();
+        if let Some(updates) = RECENT.get(&key) {
+            debug!("sending the updates from an identical search that just finished");
+            for update in updates.iter() {
+                let _ = progress_tx.send(update.clone());
+            }
+            return Ok(());
+        }
+        match in_flight.get_mut(&key) {
+            Some(running) => {
+                debug!("joining an identical search that's already running");
+                Err(running.join(progress_tx))
+            }
+            None => {
+                let (done_tx, done_rx) = watch::channel(None);
+                in_flight.insert(
+                    key.clone(),
+                    InFlight {
+                        updates: Vec::new(),
+                        subscribers: vec![progress_tx],
+                        done: done_rx,
+                    },
+                );
+                Ok(done_tx)
+            }
+        }
+    };
+    let done_tx = match joined {
+        Ok(done_tx) => done_tx,
+        Err(done) => return wait(done).await,
+    };
+    // if this future is dropped before the search is done, the searches that
+    // joined it get an error instead of waiting forever
+    let in_flight_guard = InFlightGuard(&key);
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let forward = async {
+        while let Some(update) = rx.recv().await {
+            let mut in_flight = IN_FLIGHT.lock();
+            let Some(running) = in_flight.get_mut(&key) else {
+                continue;
+            };
+            // the ones that were dropped, like a client that went away, are
+            // forgotten
+            running
+                .subscribers
+                .retain(|subscriber| subscriber.send(update.clone()).is_ok());
+            running.updates.push(update);
+        }
+    };
+    let (result, ()) = tokio::join!(search(query.clone(), tx), forward);
+
+    // searches that come in after this get the updates from the cache, or start
+    // over if it failed, so they don't get results that are already done
+    if result.is_ok() {
+        if let Some(running) = IN_FLIGHT.lock().get(&key) {
+            RECENT.insert(key.clone(), running.updates.clone().into());
+        }
+    }
+    drop(in_flight_guard);
+    let _ = done_tx.send(Some(
+        result
+            .as_ref()
+            .map(|_| ())
+            .map_err(|err| Arc::from(err.to_string())),
+    ));
+    result
+}
+
+/// Send the updates from an identical search that's already running to
+/// `progress_tx`, without starting a new one. The future finishes when that
+/// search does. Returns None if there isn't one running.
+pub fn follow(
+    query: &SearchQuery,
+    progress_tx: mpsc::UnboundedSender<ProgressUpdate>,
+) -> Option<impl Future<Output = eyre::Result<()>>> {
+    let key = query.cache_key()?;
+    let done = IN_FLIGHT.lock().get_mut(&key)?.join(progress_tx);
+    Some(wait(done))
+}
+
+impl InFlight {
+    /// Send the updates so far to `progress_tx`, and the rest as they come.
+    fn join(&mut self, progress_tx: mpsc::UnboundedSender<ProgressUpdate>) -> DoneReceiver {
+        for update in &self.updates {
+            let _ = progress_tx.send(update.clone());
+        }
+        self.subscribers.push(progress_tx);
+        self.done.clone()
+    }
+}
+
+async fn wait(mut done: DoneReceiver) -> eyre::Result<()> {
+    match done.wait_for(Option::is_some).await {
+        Ok(result) => match &*result {
+            Some(Err(err)) => Err(eyre::eyre!("{err}")),
+            _ => Ok(()),
+        },
+        Err(_) => Err(eyre::eyre!("the search that this joined was cancelled")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+    use crate::{
+        config::Config,
+        engines::{Engine, EngineProgressUpdate, ProgressUpdateData, SearchTab},
+    };
+
+    fn query(query: &str) -> SearchQuery {
+        SearchQuery {
+            query: query.to_string(),
+            tab: SearchTab::All,
+            verbatim: false,
+            only_engines: vec![],
+            label: None,
+            language: None,
+            image_filters: Default::default(),
+            requested_count: 10,
+            info: Default::default(),
+            debug: false,
+            request_headers: Default::default(),
+            ip: "127.0.0.1".to_string(),
+            config: Arc::new(Config::default()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_identical_searches_are_coalesced() {
+        static SEARCHES: AtomicUsize = AtomicUsize::new(0);
+        async fn fake_search(
+            _: SearchQuery,
+            progress_tx: mpsc::UnboundedSender<ProgressUpdate>,
+        ) -> eyre::Result<()> {
+            SEARCHES.fetch_add(1, Ordering::SeqCst);
+            let update = ProgressUpdateData::Engine {
+                engine: Engine::Google,
+                update: EngineProgressUpdate::Done(1),
+            };
+            progress_tx.send(ProgressUpdate::new(update.clone(), Instant::now()))?;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            progress_tx.send(ProgressUpdate::new(update, Instant::now()))?;
+            Ok(())
+        }
+
+        let query = query("coalesce me");
+        let (tx_a, mut rx_a) = mpsc::unbounded_channel();
+        let (tx_b, mut rx_b) = mpsc::unbounded_channel();
+        let (a, b) = tokio::join!(search(&query, tx_a, fake_search), async {
+            // join after the first update was sent
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            search(&query, tx_b, fake_search).await
+        });
+        a.unwrap();
+        b.unwrap();
+
+        assert_eq!(SEARCHES.load(Ordering::SeqCst), 1);
+        for rx in [&mut rx_a, &mut rx_b] {
+            let mut updates = 0;
+            while rx.try_recv().is_ok() {
+                updates += 1;
+            }
+            assert_eq!(updates, 2);
+        }
+        assert!(!IN_FLIGHT.lock().contains_key(&query.cache_key().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_finished_searches_are_replayed() {
+        static SEARCHES: AtomicUsize = AtomicUsize::new(0);
+        async fn fake_search(
+            _: SearchQuery,
+            progress_tx: mpsc::UnboundedSender<ProgressUpdate>,
+        ) -> eyre::Result<()> {
+            SEARCHES.fetch_add(1, Ordering::SeqCst);
+            let update = ProgressUpdateData::Engine {
+                engine: Engine::Google,
+                update: EngineProgressUpdate::Done(1),
+            };
+            progress_tx.send(ProgressUpdate::new(update, Instant::now()))?;
+            Ok(())
+        }
+
+        let query = query("replay me");
+        for _ in 0..2 {
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            search(&query, tx, fake_search).await.unwrap();
+            assert!(rx.try_recv().is_ok());
+        }
+        assert_eq!(SEARCHES.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_follow() {
+        async fn fake_search(
+            _: SearchQuery,
+            progress_tx: mpsc::UnboundedSender<ProgressUpdate>,
+        ) -> eyre::Result<()> {
+            let update = ProgressUpdateData::Engine {
+                engine: Engine::Google,
+                update: EngineProgressUpdate::Requesting,
+            };
+            progress_tx.send(ProgressUpdate::new(update.clone(), Instant::now()))?;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            progress_tx.send(ProgressUpdate::new(update, Instant::now()))?;
+            Ok(())
+        }
+
+        let query = query("follow me");
+        let (tx, _) = mpsc::unbounded_channel();
+        assert!(follow(&query, tx).is_none());
+
+        let (tx_a, _rx_a) = mpsc::unbounded_channel();
+        let (tx_b, mut rx_b) = mpsc::unbounded_channel();
+        let (a, b) = tokio::join!(search(&query, tx_a, fake_search), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            follow(&query, tx_b).unwrap().await
+        });
+        a.unwrap();
+        b.unwrap();
+
+        let mut updates = 0;
+        while rx_b.try_recv().is_ok() {
+            updates += 1;
+        }
+        assert_eq!(updates, 2);
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let a = query("rust");
+        let mut config = Config::default();
+        config
+            .urls
+            .weight
+            .push((crate::config::HostAndPath::new("example.com"), 2.));
+        let b = SearchQuery {
+            config: Arc::new(config),
+            ..query("rust")
+        };
+        assert_ne!(a.cache_key(), b.cache_key());
+
+        // the answer has the user's ip in it
+        assert_eq!(query("what's my ip").cache_key(), None);
+    }
+}
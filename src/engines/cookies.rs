@@ -0,0 +1,138 @@
+//! Cookie jars for engines that need to keep cookies, like consent cookies or
+//! a session. Each engine has its own jar, and the jars are saved to disk now
+//! and then so they're still there after a restart.
+
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use cookie_store::CookieStore;
+use parking_lot::{Mutex, RwLock};
+use tracing::{error, warn};
+use wreq::{cookie::Cookie, header::HeaderValue};
+
+use super::Engine;
+
+/// How often the jars that changed are saved.
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Every jar that was opened, so they can be saved.
+static JARS: Mutex<Vec<Arc<Jar>>> = Mutex::new(Vec::new());
+
+pub struct Jar {
+    path: PathBuf,
+    store: RwLock<CookieStore>,
+    /// Whether a cookie was set since the jar was last saved.
+    changed: AtomicBool,
+}
+
+impl Jar {
+    /// Open the engine's jar in the directory, or make an empty one if it
+    /// wasn't saved before.
+    pub fn open(directory: &str, engine: Engine) -> Arc<Self> {
+        let path = Path::new(directory).join(format!("{engine}.json"));
+        let store = match File::open(&path) {
+            Ok(file) => {
+                cookie_store::serde::json::load_all(BufReader::new(file)).unwrap_or_else(|err| {
+                    warn!("Couldn't read the cookies at {path:?}: {err}");
+                    CookieStore::default()
+                })
+            }
+            Err(_) => CookieStore::default(),
+        };
+        let jar = Arc::new(Self {
+            path,
+            store: RwLock::new(store),
+            changed: AtomicBool::new(false),
+        });
+        JARS.lock().push(jar.clone());
+        jar
+    }
+
+    /// Add cookies like they were set by `url`.
+    pub fn add(&self, url: &url::Url, cookies: &[&str]) {
+        let cookies = cookies
+            .iter()
+            .filter_map(|cookie| Cookie::parse(cookie).ok())
+            .map(|cookie| cookie.into_inner().into_owned());
+        self.store.write().store_response_cookies(cookies, url);
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    fn save(&self) -> eyre::Result<()> {
+        if !self.changed.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // written somewhere else first so a crash doesn't leave half a file
+        let tmp_path = self.path.with_extension("json.tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        // session cookies are kept too, since a restart shouldn't end the
+        // session
+        cookie_store::serde::json::save_incl_expired_and_nonpersistent(
+            &self.store.read(),
+            &mut writer,
+        )
+        .map_err(|err| eyre::eyre!("{err}"))?;
+        drop(writer);
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+impl wreq::cookie::CookieStore for Jar {
+    fn set_cookies(&self, url: &url::Url, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>) {
+        let cookies = cookie_headers
+            .filter_map(|value| Cookie::parse(value.as_bytes()).ok())
+            .map(|cookie| cookie.into_inner().into_owned())
+            .collect::<Vec<_>>();
+        if cookies.is_empty() {
+            return;
+        }
+        self.store
+            .write()
+            .store_response_cookies(cookies.into_iter(), url);
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    fn cookies(&self, url: &url::Url) -> Option<HeaderValue> {
+        let store = self.store.read();
+        let cookie = store
+            .get_request_values(url)
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if cookie.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&cookie).ok()
+    }
+}
+
+/// Save the jars that changed.
+pub fn save_all() {
+    for jar in JARS.lock().iter() {
+        if let Err(err) = jar.save() {
+            error!("Couldn't save the cookies at {:?}: {err}", jar.path);
+        }
+    }
+}
+
+/// Save the jars every [`SAVE_INTERVAL`].
+pub fn spawn() {
+    tokio::spawn(async {
+        loop {
+            tokio::time::sleep(SAVE_INTERVAL).await;
+            save_all();
+        }
+    });
+}
@@ -0,0 +1,145 @@
+//! Checking whether the top results still exist, since small engines return a
+//! lot of dead links. The results are sent before they're checked, and the
+//! ones that turn out to be dead are flagged and moved down afterwards.
+
+use std::{
+    collections::HashMap,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use futures::future::join_all;
+use parking_lot::Mutex;
+use tracing::debug;
+
+use super::{EngineSearchResult, SearchResult};
+use crate::config::DeadLinksConfig;
+
+const TTL: Duration = Duration::from_secs(60 * 60);
+const MAX_ENTRIES: usize = 5000;
+
+/// Whether each url was reachable when it was last checked.
+type ReachableCache = HashMap<String, (Instant, bool)>;
+static CACHE: LazyLock<Mutex<ReachableCache>> = LazyLock::new(Default::default);
+
+/// Flag the results that were already found to be dead and move them to the
+/// bottom, so they don't have to be checked again.
+pub fn flag_cached(results: &mut [SearchResult<EngineSearchResult>]) {
+    for result in results.iter_mut() {
+        if cached(&result.result.url) == Some(false) {
+            result.result.extras.unreachable = true;
+        }
+    }
+    // stable, so the ranking is otherwise kept
+    results.sort_by_key(|r| r.result.extras.unreachable);
+}
+
+/// Check the urls of the top results, and return the indexes (starting at 1,
+/// like the `#result-N` anchors) of the ones that are dead. The flagged
+/// results are at the bottom, so they're left out without changing the
+/// indexes.
+pub async fn check(urls: Vec<String>, config: DeadLinksConfig) -> Vec<usize> {
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let checks = urls
+        .into_iter()
+        .enumerate()
+        .take(config.max_checks)
+        .map(|(i, url)| async move {
+            let reachable = match cached(&url) {
+                Some(reachable) => reachable,
+                None => {
+                    let reachable = is_reachable(&url, timeout).await;
+                    let mut cache = CACHE.lock();
+                    cache.retain(|_, (time, _)| time.elapsed() < TTL);
+                    if cache.len() < MAX_ENTRIES {
+                        cache.insert(url, (Instant::now(), reachable));
+                    }
+                    reachable
+                }
+            };
+            (i + 1, reachable)
+        });
+    join_all(checks)
+        .await
+        .into_iter()
+        .filter(|(_, reachable)| !reachable)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn cached(url: &str) -> Option<bool> {
+    let cache = CACHE.lock();
+    let (time, reachable) = cache.get(url)?;
+    (time.elapsed() < TTL).then_some(*reachable)
+}
+
+/// Whether the page exists. Sites that block us or don't support HEAD
+/// requests count as reachable, since the page is probably there.
+async fn is_reachable(url: &str, timeout: Duration) -> bool {
+    match head_status(url, timeout).await {
+        Ok(status) => !matches!(status, 404 | 410),
+        Err(err) => {
+            debug!("{url} is unreachable: {err}");
+            false
+        }
+    }
+}
+
+async fn head_status(url: &str, timeout: Duration) -> eyre::Result<u16> {
+    // the urls come from search engines, so they get the same ssrf protection as
+    // the image proxy
+    let v = url_jail::validate(url, url_jail::Policy::PublicOnly).await?;
+    let client = wreq::Client::builder()
+        .resolve(&v.host, v.to_socket_addr())
+        .timeout(timeout)
+        .build()?;
+    let res = client.head(&v.url).send().await?;
+    Ok(res.status().as_u16())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(url: &str) -> SearchResult<EngineSearchResult> {
+        SearchResult {
+            result: EngineSearchResult {
+                url: url.to_string(),
+                title: url.to_string(),
+                description: String::new(),
+                description_from_page: false,
+                extras: Default::default(),
+            },
+            engines: Default::default(),
+            score: 1.,
+            explanation: None,
+        }
+    }
+
+    #[test]
+    fn test_flag_cached() {
+        {
+            let mut cache = CACHE.lock();
+            cache.insert("https://dead.example/".to_string(), (Instant::now(), false));
+            cache.insert("https://alive.example/".to_string(), (Instant::now(), true));
+        }
+        let mut results = vec![
+            result("https://dead.example/"),
+            result("https://alive.example/"),
+            result("https://unchecked.example/"),
+        ];
+        flag_cached(&mut results);
+        let urls = results
+            .iter()
+            .map(|r| (r.result.url.as_str(), r.result.extras.unreachable))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            [
+                ("https://alive.example/", false),
+                ("https://unchecked.example/", false),
+                ("https://dead.example/", true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Watching how many results each engine gives in normal searches, to notice
+//! when an engine still responds fine but our parser suddenly finds much less
+//! in its pages. That's usually what it looks like when an engine changes its
+//! markup.
+
+use std::{
+    collections::BTreeMap,
+    sync::LazyLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use super::Engine;
+use crate::config::DriftConfig;
+
+/// How much a single search moves the long-term average. Small enough that a
+/// few searches with few results (like for an obscure query) don't matter.
+const BASELINE_WEIGHT: f64 = 0.02;
+/// How much a single search moves the recent average.
+const RECENT_WEIGHT: f64 = 0.2;
+
+static STATUSES: LazyLock<Mutex<BTreeMap<Engine, DriftStatus>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftStatus {
+    pub engine: Engine,
+    /// The long-term average number of results.
+    pub baseline: f64,
+    /// The average number of results in the last few searches.
+    pub recent: f64,
+    /// How many successful responses were counted.
+    pub samples: u64,
+    pub drifting: bool,
+    /// Unix timestamp of when the engine started drifting, if it is.
+    pub drifting_since: Option<u64>,
+}
+
+impl DriftStatus {
+    fn new(engine: Engine, results: f64) -> Self {
+        Self {
+            engine,
+            baseline: results,
+            recent: results,
+            samples: 1,
+            drifting: false,
+            drifting_since: None,
+        }
+    }
+
+    /// Count a response, and return whether the engine is drifting now.
+    fn update(&mut self, results: f64, config: &DriftConfig) -> bool {
+        self.baseline += (results - self.baseline) * BASELINE_WEIGHT;
+        self.recent += (results - self.recent) * RECENT_WEIGHT;
+        self.samples += 1;
+        // engines that usually return almost nothing (like ones that only match
+        // some queries) can't drop sharply
+        self.samples >= config.min_samples
+            && self.baseline >= 1.
+            && self.recent < self.baseline * config.drop_ratio
+    }
+}
+
+/// Count how many results an engine parsed from a successful response, and
+/// warn if it's much fewer than usual.
+pub fn record(engine: Engine, results: usize, config: &DriftConfig) {
+    if !config.enabled {
+        return;
+    }
+    let results = results as f64;
+    let mut statuses = STATUSES.lock();
+    let Some(status) = statuses.get_mut(&engine) else {
+        statuses.insert(engine, DriftStatus::new(engine, results));
+        return;
+    };
+
+    let drifting = status.update(results, config);
+    if drifting && !status.drifting {
+        warn!(
+            "{engine} is returning fewer results than usual ({:.1} recently, {:.1} on average), its markup might have changed",
+            status.recent, status.baseline
+        );
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        status.drifting_since = Some(now);
+    } else if !drifting && status.drifting {
+        info!("{engine} is returning as many results as usual again");
+        status.drifting_since = None;
+    }
+    status.drifting = drifting;
+}
+
+pub fn statuses() -> Vec<DriftStatus> {
+    STATUSES.lock().values().cloned().collect()
+}
+
+/// The engines that are returning much fewer results than usual.
+pub fn drifting_engines() -> Vec<Engine> {
+    STATUSES
+        .lock()
+        .values()
+        .filter(|s| s.drifting)
+        .map(|s| s.engine)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> DriftConfig {
+        DriftConfig {
+            enabled: true,
+            min_samples: 20,
+            drop_ratio: 0.3,
+            show_banner: true,
+        }
+    }
+
+    #[test]
+    fn test_sharp_drop_is_drifting() {
+        let config = config();
+        let mut status = DriftStatus::new(Engine::Google, 10.);
+        for _ in 0..50 {
+            assert!(!status.update(10., &config));
+        }
+        // a couple of empty responses could just be unusual queries
+        assert!(!status.update(0., &config));
+        assert!(!status.update(0., &config));
+        for _ in 0..9 {
+            status.update(0., &config);
+        }
+        assert!(status.update(0., &config));
+
+        for _ in 0..9 {
+            status.update(10., &config);
+        }
+        assert!(!status.update(10., &config));
+    }
+
+    #[test]
+    fn test_needs_enough_samples() {
+        let config = config();
+        let mut status = DriftStatus::new(Engine::Google, 10.);
+        for _ in 0..10 {
+            assert!(!status.update(0., &config));
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Engines that can use one of several instances of a site (like mirrors or
+//! self-hosted instances) try them in order, skipping the ones that failed
+//! recently.
+
+use std::{
+    collections::HashMap,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tracing::{error, warn};
+
+use super::{
+    make_request, Engine, EngineProgressUpdate, HttpResponse, RequestResponse, SearchQuery,
+};
+
+/// How long an endpoint that failed is tried last.
+const COOLDOWN: Duration = Duration::from_secs(60 * 10);
+
+/// When each endpoint last failed, by engine and base url.
+static FAILURES: LazyLock<Mutex<HashMap<(Engine, String), Instant>>> =
+    LazyLock::new(Default::default);
+
+pub struct EndpointRequest {
+    /// The base url that the request is for.
+    pub endpoint: String,
+    pub request: wreq::RequestBuilder,
+}
+
+#[derive(Deserialize)]
+struct EndpointsConfig {
+    endpoints: Option<Vec<String>>,
+}
+
+/// Make a request for each of the engine's endpoints, with the healthy ones
+/// first. The endpoints are from `endpoints` in the engine's config, or
+/// `defaults` if that isn't set.
+pub fn requests(
+    engine: Engine,
+    query: &SearchQuery,
+    defaults: &[&str],
+    make_request: impl Fn(&str) -> wreq::RequestBuilder,
+) -> RequestResponse {
+    let extra = query.config.engines.get(engine).extra.clone();
+    let endpoints = match toml::Value::Table(extra).try_into::<EndpointsConfig>() {
+        Ok(EndpointsConfig {
+            endpoints: Some(endpoints),
+        }) if !endpoints.is_empty() => endpoints,
+        Ok(_) => defaults.iter().map(|e| e.to_string()).collect(),
+        Err(err) => {
+            error!("Failed to parse endpoints for {engine}: {err}");
+            return RequestResponse::None;
+        }
+    };
+
+    RequestResponse::Failover(
+        healthy_first(engine, endpoints)
+            .into_iter()
+            .map(|endpoint| EndpointRequest {
+                request: make_request(endpoint.trim_end_matches('/')),
+                endpoint,
+            })
+            .collect(),
+    )
+}
+
+/// Send the requests in order until one of them works.
+pub async fn make_failover_request(
+    requests: Vec<EndpointRequest>,
+    engine: Engine,
+    query: &SearchQuery,
+    send_engine_progress_update: impl Fn(Engine, EngineProgressUpdate),
+) -> eyre::Result<HttpResponse> {
+    let mut last_err = eyre::eyre!("{engine} has no endpoints");
+    for EndpointRequest { endpoint, request } in requests {
+        let err = match make_request(request, engine, query, &send_engine_progress_update).await {
+            Ok(res) if res.res.status().is_success() => {
+                record_success(engine, endpoint);
+                return Ok(res);
+            }
+            Ok(res) => eyre::eyre!("{endpoint} returned {}", res.res.status()),
+            Err(err) => err,
+        };
+        warn!("{engine} endpoint failed, trying the next one: {err}");
+        record_failure(engine, endpoint);
+        last_err = err;
+    }
+    Err(last_err)
+}
+
+/// The endpoints in the order they should be tried, see [`order`].
+fn healthy_first(engine: Engine, endpoints: Vec<String>) -> Vec<String> {
+    order(endpoints, |endpoint| last_failure(engine, endpoint))
+}
+
+/// When the endpoint last failed, if it was recently.
+pub(super) fn last_failure(engine: Engine, endpoint: &str) -> Option<Instant> {
+    FAILURES
+        .lock()
+        .get(&(engine, endpoint.to_string()))
+        .filter(|time| time.elapsed() < COOLDOWN)
+        .copied()
+}
+
+pub(super) fn record_failure(engine: Engine, endpoint: String) {
+    FAILURES.lock().insert((engine, endpoint), Instant::now());
+}
+
+pub(super) fn record_success(engine: Engine, endpoint: String) {
+    FAILURES.lock().remove(&(engine, endpoint));
+}
+
+/// Put the endpoints that haven't failed recently first, in their original
+/// order, then the ones that did, least recent failure first.
+pub(super) fn order<T>(endpoints: Vec<T>, last_failure: impl Fn(&T) -> Option<Instant>) -> Vec<T> {
+    let (mut failed, healthy): (Vec<_>, Vec<_>) = endpoints
+        .into_iter()
+        .map(|endpoint| (last_failure(&endpoint), endpoint))
+        .partition(|(failure, _)| failure.is_some());
+    failed.sort_by_key(|(failure, _)| *failure);
+    healthy
+        .into_iter()
+        .chain(failed)
+        .map(|(_, endpoint)| endpoint)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order() {
+        let now = Instant::now();
+        let endpoints = ["https://a", "https://b", "https://c", "https://d"]
+            .map(String::from)
+            .to_vec();
+        let ordered = order(endpoints, |endpoint| match endpoint.as_str() {
+            "https://a" => Some(now),
+            "https://c" => Some(now - Duration::from_secs(60)),
+            _ => None,
+        });
+        assert_eq!(
+            ordered,
+            ["https://b", "https://d", "https://c", "https://a"]
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! Searching through other frontends (like 4get) when an engine sends us a
+//! captcha, so Google and Bing results keep working when they block the server.
+//! Instances that failed recently are tried last, like engine endpoints.
+
+use eyre::eyre;
+use tracing::warn;
+
+use super::{
+    endpoints, make_request, search, Engine, EngineProgressUpdate, EngineResponse, SearchQuery,
+};
+use crate::{
+    config::{FallbackInstance, FallbackKind},
+    parse::BlockPage,
+};
+
+impl FallbackKind {
+    /// The engine for this kind of instance, whose endpoint failures are shared
+    /// with the fallback.
+    fn engine(self) -> Engine {
+        match self {
+            FallbackKind::FourGet => Engine::FourGet,
+            FallbackKind::LibreY => Engine::LibreY,
+        }
+    }
+}
+
+/// Whether the engine failed because it's blocking us, and the search should be
+/// retried through the fallback instances.
+pub fn should_retry(engine: Engine, err: &eyre::Report, query: &SearchQuery) -> bool {
+    let config = &query.config.fallback;
+    err.downcast_ref::<BlockPage>().is_some()
+        && config.engines.contains(&engine)
+        && !config.instances.is_empty()
+}
+
+/// Search for the engine through the fallback instances, until one of them
+/// works.
+pub async fn search(
+    engine: Engine,
+    query: &SearchQuery,
+    send_engine_progress_update: impl Fn(Engine, EngineProgressUpdate),
+) -> eyre::Result<EngineResponse> {
+    let instances = endpoints::order(query.config.fallback.instances.clone(), |instance| {
+        endpoints::last_failure(instance.kind.engine(), &instance.url)
+    });
+
+    let mut last_err = eyre!("there are no fallback instances");
+    for instance in instances {
+        match search_instance(&instance, engine, query, &send_engine_progress_update).await {
+            Ok(response) => {
+                endpoints::record_success(instance.kind.engine(), instance.url);
+                return Ok(response);
+            }
+            Err(err) => {
+                warn!("fallback instance {} failed: {err}", instance.url);
+                endpoints::record_failure(instance.kind.engine(), instance.url);
+                last_err = err;
+            }
+        }
+    }
+    Err(last_err)
+}
+
+async fn search_instance(
+    instance: &FallbackInstance,
+    engine: Engine,
+    query: &SearchQuery,
+    send_engine_progress_update: impl Fn(Engine, EngineProgressUpdate),
+) -> eyre::Result<EngineResponse> {
+    let url = instance.url.trim_end_matches('/');
+    let request = match instance.kind {
+        FallbackKind::FourGet => search::fourget::request_to(url, query),
+        FallbackKind::LibreY => search::librey::request_to(url, query),
+    };
+    // the progress updates are for the engine that was blocked, since that's
+    // what the results are shown as
+    let res = make_request(request, engine, query, send_engine_progress_update).await?;
+    if !res.res.status().is_success() {
+        eyre::bail!("{url} returned {}", res.res.status());
+    }
+    match instance.kind {
+        FallbackKind::FourGet => search::fourget::parse_response(&res.body),
+        FallbackKind::LibreY => search::librey::parse_response(&res.body),
+    }
+}
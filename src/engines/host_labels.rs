@@ -0,0 +1,268 @@
+//! Labels for what kind of site a result is from, like official docs or a
+//! forum, so it's easy to skip past SEO blogs. Well-known hosts are in
+//! [`HOSTS`], and everything else is guessed from the url.
+
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use serde::Serialize;
+
+use super::{answer::regex, EngineSearchResult, SearchResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostLabel {
+    Docs,
+    Forum,
+    Qa,
+    Blog,
+    Vendor,
+    News,
+}
+
+impl HostLabel {
+    pub const ALL: &[HostLabel] = &[
+        HostLabel::Docs,
+        HostLabel::Forum,
+        HostLabel::Qa,
+        HostLabel::Blog,
+        HostLabel::Vendor,
+        HostLabel::News,
+    ];
+
+    /// Forums and Q&A sites, for the discussions filter.
+    pub fn is_discussion(self) -> bool {
+        matches!(self, HostLabel::Forum | HostLabel::Qa)
+    }
+
+    /// The name that's shown on results.
+    pub fn name(self) -> &'static str {
+        match self {
+            HostLabel::Docs => "Docs",
+            HostLabel::Forum => "Forum",
+            HostLabel::Qa => "Q&A",
+            HostLabel::Blog => "Blog",
+            HostLabel::Vendor => "Vendor",
+            HostLabel::News => "News",
+        }
+    }
+}
+
+impl FromStr for HostLabel {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HostLabel::ALL
+            .iter()
+            .find(|l| l.to_string() == s)
+            .copied()
+            .ok_or(())
+    }
+}
+
+impl Display for HostLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostLabel::Docs => write!(f, "docs"),
+            HostLabel::Forum => write!(f, "forum"),
+            HostLabel::Qa => write!(f, "qa"),
+            HostLabel::Blog => write!(f, "blog"),
+            HostLabel::Vendor => write!(f, "vendor"),
+            HostLabel::News => write!(f, "news"),
+        }
+    }
+}
+
+/// Hosts that can't be guessed from their url. Subdomains match too.
+const HOSTS: &[(&str, HostLabel)] = &[
+    // docs
+    ("docs.rs", HostLabel::Docs),
+    ("doc.rust-lang.org", HostLabel::Docs),
+    ("developer.mozilla.org", HostLabel::Docs),
+    ("learn.microsoft.com", HostLabel::Docs),
+    ("docs.python.org", HostLabel::Docs),
+    ("pkg.go.dev", HostLabel::Docs),
+    ("en.cppreference.com", HostLabel::Docs),
+    ("man7.org", HostLabel::Docs),
+    ("wiki.archlinux.org", HostLabel::Docs),
+    ("readthedocs.io", HostLabel::Docs),
+    // forums
+    ("reddit.com", HostLabel::Forum),
+    ("news.ycombinator.com", HostLabel::Forum),
+    ("lobste.rs", HostLabel::Forum),
+    ("users.rust-lang.org", HostLabel::Forum),
+    ("internals.rust-lang.org", HostLabel::Forum),
+    ("discuss.python.org", HostLabel::Forum),
+    ("bbs.archlinux.org", HostLabel::Forum),
+    ("github.com/orgs/community/discussions", HostLabel::Forum),
+    ("lemmy.world", HostLabel::Forum),
+    ("lemmy.ml", HostLabel::Forum),
+    ("programming.dev", HostLabel::Forum),
+    ("beehaw.org", HostLabel::Forum),
+    ("sh.itjust.works", HostLabel::Forum),
+    ("kbin.social", HostLabel::Forum),
+    ("tildes.net", HostLabel::Forum),
+    // q&a
+    ("stackoverflow.com", HostLabel::Qa),
+    ("stackexchange.com", HostLabel::Qa),
+    ("superuser.com", HostLabel::Qa),
+    ("serverfault.com", HostLabel::Qa),
+    ("askubuntu.com", HostLabel::Qa),
+    ("mathoverflow.net", HostLabel::Qa),
+    ("quora.com", HostLabel::Qa),
+    // blogs
+    ("medium.com", HostLabel::Blog),
+    ("dev.to", HostLabel::Blog),
+    ("hashnode.dev", HostLabel::Blog),
+    ("substack.com", HostLabel::Blog),
+    ("blogspot.com", HostLabel::Blog),
+    ("wordpress.com", HostLabel::Blog),
+    ("geeksforgeeks.org", HostLabel::Blog),
+    ("tutorialspoint.com", HostLabel::Blog),
+    ("w3schools.com", HostLabel::Blog),
+    ("javatpoint.com", HostLabel::Blog),
+    ("freecodecamp.org", HostLabel::Blog),
+    // vendors
+    ("aws.amazon.com", HostLabel::Vendor),
+    ("cloud.google.com", HostLabel::Vendor),
+    ("azure.microsoft.com", HostLabel::Vendor),
+    ("oracle.com", HostLabel::Vendor),
+    ("ibm.com", HostLabel::Vendor),
+    ("redhat.com", HostLabel::Vendor),
+    ("digitalocean.com", HostLabel::Vendor),
+    ("cloudflare.com", HostLabel::Vendor),
+    ("jetbrains.com", HostLabel::Vendor),
+    // news
+    ("bbc.co.uk", HostLabel::News),
+    ("bbc.com", HostLabel::News),
+    ("nytimes.com", HostLabel::News),
+    ("theguardian.com", HostLabel::News),
+    ("reuters.com", HostLabel::News),
+    ("apnews.com", HostLabel::News),
+    ("theverge.com", HostLabel::News),
+    ("arstechnica.com", HostLabel::News),
+    ("techcrunch.com", HostLabel::News),
+    ("lwn.net", HostLabel::News),
+    ("theregister.com", HostLabel::News),
+];
+
+/// Prefixes of hosts and paths that usually mean a label.
+const HOST_PREFIXES: &[(&str, HostLabel)] = &[
+    ("docs.", HostLabel::Docs),
+    ("doc.", HostLabel::Docs),
+    ("developer.", HostLabel::Docs),
+    ("developers.", HostLabel::Docs),
+    ("forum.", HostLabel::Forum),
+    ("forums.", HostLabel::Forum),
+    ("community.", HostLabel::Forum),
+    ("discuss.", HostLabel::Forum),
+    ("discourse.", HostLabel::Forum),
+    ("answers.", HostLabel::Qa),
+    ("blog.", HostLabel::Blog),
+    ("news.", HostLabel::News),
+];
+const PATH_PREFIXES: &[(&str, HostLabel)] = &[
+    ("/docs/", HostLabel::Docs),
+    ("/documentation/", HostLabel::Docs),
+    ("/forum/", HostLabel::Forum),
+    ("/forums/", HostLabel::Forum),
+    ("/questions/", HostLabel::Qa),
+    ("/blog/", HostLabel::Blog),
+    ("/news/", HostLabel::News),
+];
+
+pub fn label_for_url(url: &str) -> Option<HostLabel> {
+    let url = url::Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let path = url.path();
+
+    // entries can have a path, which the url's path has to start with
+    let matches_host = |entry: &str| {
+        let (entry_host, entry_path) = entry.split_once('/').unwrap_or((entry, ""));
+        (host == entry_host || host.ends_with(&format!(".{entry_host}")))
+            && path.trim_start_matches('/').starts_with(entry_path)
+    };
+    if let Some((_, label)) = HOSTS.iter().find(|(entry, _)| matches_host(entry)) {
+        return Some(*label);
+    }
+
+    if let Some((_, label)) = HOST_PREFIXES
+        .iter()
+        .find(|(prefix, _)| host.starts_with(prefix))
+        .or_else(|| {
+            PATH_PREFIXES
+                .iter()
+                .find(|(prefix, _)| path.starts_with(prefix))
+        })
+    {
+        return Some(*label);
+    }
+
+    // discourse topics are at /t/<slug>/<id>, and lemmy posts are at /post/<id>
+    let is_forum_path = regex!(r"^/t/[^/]+/\d+|^/post/\d+$").is_match(path);
+    (is_forum_path || host.split('.').any(|part| part == "lemmy")).then_some(HostLabel::Forum)
+}
+
+/// Label the results, and remove the ones that don't have the label the user
+/// is filtering by.
+pub fn label_results(
+    results: &mut Vec<SearchResult<EngineSearchResult>>,
+    filter: Option<HostLabel>,
+) {
+    for result in results.iter_mut() {
+        result.result.extras.host_label = label_for_url(&result.result.url);
+    }
+    if let Some(filter) = filter {
+        results.retain(|r| r.result.extras.host_label == Some(filter));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_for_url() {
+        assert_eq!(
+            label_for_url("https://docs.rs/serde/latest/serde/"),
+            Some(HostLabel::Docs)
+        );
+        assert_eq!(
+            label_for_url("https://serde.readthedocs.io/en/latest/"),
+            Some(HostLabel::Docs)
+        );
+        assert_eq!(
+            label_for_url("https://www.reddit.com/r/rust/comments/abc"),
+            Some(HostLabel::Forum)
+        );
+        assert_eq!(
+            label_for_url("https://unix.stackexchange.com/questions/1"),
+            Some(HostLabel::Qa)
+        );
+        assert_eq!(
+            label_for_url("https://blog.rust-lang.org/2024/01/01/post.html"),
+            Some(HostLabel::Blog)
+        );
+        assert_eq!(
+            label_for_url("https://example.com/docs/getting-started"),
+            Some(HostLabel::Docs)
+        );
+        assert_eq!(
+            label_for_url("https://users.rust-lang.org/t/some-topic/12345"),
+            Some(HostLabel::Forum)
+        );
+        assert_eq!(
+            label_for_url("https://meta.discourse.org/t/what-is-discourse/123/4"),
+            Some(HostLabel::Forum)
+        );
+        assert_eq!(
+            label_for_url("https://lemmy.example.org/post/42"),
+            Some(HostLabel::Forum)
+        );
+        assert_eq!(label_for_url("https://medium.company/"), None);
+        assert_eq!(label_for_url("https://example.com/"), None);
+        assert_eq!(HostLabel::from_str("qa"), Ok(HostLabel::Qa));
+    }
+}
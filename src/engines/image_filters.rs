@@ -0,0 +1,227 @@
+//! Filters for the images tab, like only showing large images or photos. Each
+//! engine has its own way of asking for them, see Google's `tbs=` and Bing's
+//! `qft=` parameters.
+
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// One kind of filter, like the size.
+pub trait ImageFilter: Copy + PartialEq + 'static {
+    const ALL: &'static [Self];
+
+    /// The name that's shown on the filter chips.
+    fn name(self) -> &'static str;
+}
+
+macro_rules! image_filter {
+    ($name:ident { $($variant:ident = $id:literal, $label:literal),* $(,)? }) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($variant,)*
+        }
+
+        impl ImageFilter for $name {
+            const ALL: &'static [$name] = &[$($name::$variant,)*];
+
+            fn name(self) -> &'static str {
+                match self {
+                    $($name::$variant => $label,)*
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ();
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($id => Ok($name::$variant),)*
+                    _ => Err(()),
+                }
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $($name::$variant => write!(f, $id),)*
+                }
+            }
+        }
+    };
+}
+
+image_filter!(ImageSize {
+    Large = "large", "Large",
+    Medium = "medium", "Medium",
+    Icon = "icon", "Icon",
+});
+
+image_filter!(ImageAspect {
+    Tall = "tall", "Tall",
+    Square = "square", "Square",
+    Wide = "wide", "Wide",
+});
+
+image_filter!(ImageType {
+    Photo = "photo", "Photo",
+    Clipart = "clipart", "Clip art",
+    Gif = "gif", "GIF",
+});
+
+image_filter!(ImageLicense {
+    CreativeCommons = "cc", "Creative Commons",
+    Commercial = "commercial", "Commercial use",
+});
+
+/// The filters from the `size`, `aspect`, `type`, and `license` url
+/// parameters. Engines that don't support a filter ignore it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImageFilters {
+    pub size: Option<ImageSize>,
+    pub aspect: Option<ImageAspect>,
+    pub kind: Option<ImageType>,
+    pub license: Option<ImageLicense>,
+}
+
+impl ImageFilters {
+    pub fn from_params(get: impl Fn(&str) -> Option<String>) -> Self {
+        Self {
+            size: get("size").and_then(|s| s.parse().ok()),
+            aspect: get("aspect").and_then(|s| s.parse().ok()),
+            kind: get("type").and_then(|s| s.parse().ok()),
+            license: get("license").and_then(|s| s.parse().ok()),
+        }
+    }
+
+    /// The url parameters for the filters, for keeping them in links.
+    pub fn to_params(self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(size) = self.size {
+            params.push(("size", size.to_string()));
+        }
+        if let Some(aspect) = self.aspect {
+            params.push(("aspect", aspect.to_string()));
+        }
+        if let Some(kind) = self.kind {
+            params.push(("type", kind.to_string()));
+        }
+        if let Some(license) = self.license {
+            params.push(("license", license.to_string()));
+        }
+        params
+    }
+
+    /// The value of Google's `tbs` parameter, like `isz:l,itp:photo`.
+    pub fn google_tbs(self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(size) = self.size {
+            parts.push(match size {
+                ImageSize::Large => "isz:l",
+                ImageSize::Medium => "isz:m",
+                ImageSize::Icon => "isz:i",
+            });
+        }
+        if let Some(aspect) = self.aspect {
+            parts.push(match aspect {
+                ImageAspect::Tall => "iar:t",
+                ImageAspect::Square => "iar:s",
+                ImageAspect::Wide => "iar:w",
+            });
+        }
+        if let Some(kind) = self.kind {
+            parts.push(match kind {
+                ImageType::Photo => "itp:photo",
+                ImageType::Clipart => "itp:clipart",
+                ImageType::Gif => "itp:animated",
+            });
+        }
+        if let Some(license) = self.license {
+            parts.push(match license {
+                ImageLicense::CreativeCommons => "il:cl",
+                ImageLicense::Commercial => "il:ol",
+            });
+        }
+        (!parts.is_empty()).then(|| parts.join(","))
+    }
+
+    /// The value of Bing's `qft` parameter, like
+    /// `+filterui:imagesize-large+filterui:photo-photo`.
+    pub fn bing_qft(self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(size) = self.size {
+            parts.push(match size {
+                ImageSize::Large => "imagesize-large",
+                ImageSize::Medium => "imagesize-medium",
+                ImageSize::Icon => "imagesize-small",
+            });
+        }
+        if let Some(aspect) = self.aspect {
+            parts.push(match aspect {
+                ImageAspect::Tall => "aspect-tall",
+                ImageAspect::Square => "aspect-square",
+                ImageAspect::Wide => "aspect-wide",
+            });
+        }
+        if let Some(kind) = self.kind {
+            parts.push(match kind {
+                ImageType::Photo => "photo-photo",
+                ImageType::Clipart => "photo-clipart",
+                ImageType::Gif => "photo-animatedgif",
+            });
+        }
+        if let Some(license) = self.license {
+            parts.push(match license {
+                // every creative commons license
+                ImageLicense::CreativeCommons => "license-L2_L3_L4_L5_L6_L7",
+                // the ones that allow commercial use
+                ImageLicense::Commercial => "license-L2_L3_L4",
+            });
+        }
+        (!parts.is_empty()).then(|| {
+            parts
+                .iter()
+                .map(|p| format!("+filterui:{p}"))
+                .collect::<String>()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_params() {
+        let filters = ImageFilters {
+            size: Some(ImageSize::Large),
+            kind: Some(ImageType::Gif),
+            ..Default::default()
+        };
+        assert_eq!(filters.google_tbs().as_deref(), Some("isz:l,itp:animated"));
+        assert_eq!(
+            filters.bing_qft().as_deref(),
+            Some("+filterui:imagesize-large+filterui:photo-animatedgif")
+        );
+        assert_eq!(ImageFilters::default().google_tbs(), None);
+        assert_eq!(ImageFilters::default().bing_qft(), None);
+    }
+
+    #[test]
+    fn test_params_round_trip() {
+        let filters = ImageFilters {
+            aspect: Some(ImageAspect::Wide),
+            license: Some(ImageLicense::CreativeCommons),
+            ..Default::default()
+        };
+        let params = filters.to_params();
+        let parsed = ImageFilters::from_params(|k| {
+            params
+                .iter()
+                .find(|(name, _)| *name == k)
+                .map(|(_, v)| v.clone())
+        });
+        assert_eq!(parsed, filters);
+    }
+}
@@ -0,0 +1,251 @@
+//! Checking that image results actually load, and getting their real sizes
+//! from the first bytes of the files. Engines (especially Bing) sometimes link
+//! to images that are gone, and some don't say how big their images are.
+
+use std::{
+    collections::HashMap,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use futures::{stream, StreamExt};
+use parking_lot::Mutex;
+use tracing::debug;
+use wreq::header;
+
+use super::ImagesResponse;
+
+/// How long an image can take to respond before we give up and keep it anyway.
+const TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_CONCURRENT: usize = 16;
+/// Enough for the headers of every format we read, unless a jpeg has a huge
+/// thumbnail or metadata before its size.
+const PROBE_BYTES: usize = 64 * 1024;
+/// How long probe results are remembered, so the same images aren't checked
+/// again for every search.
+const TTL: Duration = Duration::from_secs(60 * 60);
+const MAX_ENTRIES: usize = 10_000;
+
+static CACHE: LazyLock<Mutex<HashMap<String, (Instant, Probe)>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Probe {
+    Alive {
+        size: Option<(u64, u64)>,
+    },
+    Dead,
+    /// It timed out or something went wrong on our side, so we don't know.
+    Unknown,
+}
+
+/// Remove the images that don't load, and fix the sizes of the rest. Does
+/// nothing unless probing is enabled in the config.
+pub async fn probe_images(response: &mut ImagesResponse) {
+    if !response.config.image_search.probe {
+        return;
+    }
+
+    let urls = response
+        .image_results
+        .iter()
+        .map(|r| r.result.image_url.clone())
+        .collect::<Vec<_>>();
+    let probes = stream::iter(urls)
+        .map(|url| async move {
+            let probe = cached_probe(&url).await;
+            (url, probe)
+        })
+        .buffer_unordered(MAX_CONCURRENT)
+        .collect::<HashMap<_, _>>()
+        .await;
+
+    let before = response.image_results.len();
+    response.image_results.retain_mut(|r| {
+        match probes.get(&r.result.image_url) {
+            Some(Probe::Dead) => return false,
+            Some(Probe::Alive {
+                size: Some((width, height)),
+            }) => {
+                r.result.width = *width;
+                r.result.height = *height;
+            }
+            _ => {}
+        }
+        true
+    });
+    debug!(
+        "removed {} dead images",
+        before - response.image_results.len()
+    );
+}
+
+async fn cached_probe(url: &str) -> Probe {
+    if let Some((time, probe)) = CACHE.lock().get(url) {
+        if time.elapsed() < TTL {
+            return *probe;
+        }
+    }
+    let probe = tokio::time::timeout(TIMEOUT, probe(url))
+        .await
+        .unwrap_or(Probe::Unknown);
+    if probe != Probe::Unknown {
+        let mut cache = CACHE.lock();
+        if cache.len() >= MAX_ENTRIES {
+            cache.retain(|_, (time, _)| time.elapsed() < TTL);
+        }
+        if cache.len() < MAX_ENTRIES {
+            cache.insert(url.to_owned(), (Instant::now(), probe));
+        }
+    }
+    probe
+}
+
+async fn probe(url: &str) -> Probe {
+    // the same ssrf protection as the image proxy, since the urls come from
+    // the engines
+    let Ok(v) = url_jail::validate(url, url_jail::Policy::PublicOnly).await else {
+        return Probe::Dead;
+    };
+    let Ok(client) = wreq::Client::builder()
+        .resolve(&v.host, v.to_socket_addr())
+        .build()
+    else {
+        return Probe::Unknown;
+    };
+
+    let mut res = match client
+        .get(&v.url)
+        .header(header::ACCEPT, "image/*")
+        .header(header::RANGE, format!("bytes=0-{}", PROBE_BYTES - 1))
+        .send()
+        .await
+    {
+        Ok(res) => res,
+        Err(err) if err.is_timeout() => return Probe::Unknown,
+        Err(_) => return Probe::Dead,
+    };
+    if !res.status().is_success() {
+        return Probe::Dead;
+    }
+    // some sites send an html page instead of a 404
+    let is_image = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("image/"));
+    if !is_image {
+        return Probe::Dead;
+    }
+
+    // servers that ignore the range header send the whole file, so we stop
+    // reading once we have enough
+    let mut bytes = Vec::new();
+    while let Ok(Some(chunk)) = res.chunk().await {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() >= PROBE_BYTES {
+            break;
+        }
+    }
+    Probe::Alive {
+        size: image_size(&bytes),
+    }
+}
+
+/// The width and height of a png, gif, jpeg, or webp from the start of the
+/// file.
+fn image_size(bytes: &[u8]) -> Option<(u64, u64)> {
+    let u16_be = |i: usize| {
+        let b = bytes.get(i..i + 2)?;
+        Some(u16::from_be_bytes([b[0], b[1]]) as u64)
+    };
+    let u16_le = |i: usize| {
+        let b = bytes.get(i..i + 2)?;
+        Some(u16::from_le_bytes([b[0], b[1]]) as u64)
+    };
+    let u24_le = |i: usize| {
+        let b = bytes.get(i..i + 3)?;
+        Some((b[0] as u64) | ((b[1] as u64) << 8) | ((b[2] as u64) << 16))
+    };
+
+    let size = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+        (width as u64, height as u64)
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        (u16_le(6)?, u16_le(8)?)
+    } else if bytes.starts_with(b"\xff\xd8") {
+        // the size is in the first "start of frame" segment
+        let mut i = 2;
+        loop {
+            if *bytes.get(i)? != 0xff {
+                return None;
+            }
+            let marker = *bytes.get(i + 1)?;
+            match marker {
+                // padding
+                0xff => i += 1,
+                // markers without a length
+                0x01 | 0xd0..=0xd9 => i += 2,
+                0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                    break (u16_be(i + 7)?, u16_be(i + 5)?);
+                }
+                _ => i += 2 + u16_be(i + 2)? as usize,
+            }
+        }
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12)? == b"WEBP" {
+        match bytes.get(12..16)? {
+            b"VP8 " => (u16_le(26)? & 0x3fff, u16_le(28)? & 0x3fff),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?) as u64;
+                ((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1)
+            }
+            b"VP8X" => (u24_le(24)? + 1, u24_le(27)? + 1),
+            _ => return None,
+        }
+    } else {
+        return None;
+    };
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_size() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(image_size(&png), Some((640, 480)));
+        assert_eq!(image_size(&png[..20]), None);
+    }
+
+    #[test]
+    fn test_gif_size() {
+        assert_eq!(image_size(b"GIF89a\x20\x03\x58\x02"), Some((800, 600)));
+    }
+
+    #[test]
+    fn test_jpeg_size() {
+        let jpeg = [
+            0xff, 0xd8, // start of image
+            0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, // app0 with 2 bytes of data
+            0xff, 0xc0, 0x00, 0x11, 0x08, 0x01, 0xe0, 0x02, 0x80, // sof0, 640x480
+        ];
+        assert_eq!(image_size(&jpeg), Some((640, 480)));
+        assert_eq!(image_size(&jpeg[..12]), None);
+    }
+
+    #[test]
+    fn test_webp_size() {
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\0\0\0\0\0\0\0\0".to_vec();
+        webp.extend_from_slice(&[0x7f, 0x02, 0x00, 0xdf, 0x01, 0x00]);
+        assert_eq!(image_size(&webp), Some((640, 480)));
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        assert_eq!(image_size(b"<!doctype html>"), None);
+    }
+}
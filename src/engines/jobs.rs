@@ -0,0 +1,54 @@
+//! Filtering the jobs tab by the location in the `[geo]` config.
+
+use super::{EngineSearchResult, SearchResult};
+
+/// Locations that mean a job can be done from anywhere.
+const ANYWHERE: &[&str] = &["worldwide", "anywhere", "global"];
+
+/// Remove the jobs that aren't at the location. Results that aren't jobs, or
+/// don't say where they are, are kept.
+pub fn filter_by_location(results: &mut Vec<SearchResult<EngineSearchResult>>, location: &str) {
+    results.retain(|r| {
+        r.result
+            .extras
+            .job
+            .as_ref()
+            .is_none_or(|job| matches_location(&job.location, location))
+    });
+}
+
+/// Whether any part of the job's location (like "USA, Canada") is in the
+/// configured location (like "Toronto, Canada"), or the other way around.
+fn matches_location(job_location: &str, location: &str) -> bool {
+    let job_location = job_location.to_lowercase();
+    let location = location.to_lowercase();
+    if job_location.trim().is_empty() || ANYWHERE.iter().any(|a| job_location.contains(a)) {
+        return true;
+    }
+
+    let parts = |s: &str| {
+        s.split([',', '/', ';', '|'])
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+    };
+    let job_parts = parts(&job_location);
+    parts(&location).iter().any(|part| {
+        job_location.contains(part.as_str()) || job_parts.iter().any(|j| part.contains(j.as_str()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_location() {
+        assert!(matches_location("Worldwide", "Berlin, Germany"));
+        assert!(matches_location("", "Berlin, Germany"));
+        assert!(matches_location("Germany, Austria", "Berlin, Germany"));
+        assert!(matches_location("Berlin", "Berlin, Germany"));
+        assert!(!matches_location("USA", "Berlin, Germany"));
+        assert!(!matches_location("USA only", "Toronto, Canada"));
+    }
+}
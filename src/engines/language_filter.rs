@@ -0,0 +1,122 @@
+//! Dropping or downranking results that aren't in the user's language, since
+//! some engines ignore the language we ask for.
+
+use whatlang::Lang;
+
+use super::{EngineSearchResult, SearchResult};
+use crate::config::{Config, LanguageFilterMode};
+
+/// Drop or downrank the results whose title and description are in a language
+/// other than the configured ones. Results where the language couldn't be
+/// detected reliably, like ones with a short title and no description, are
+/// kept.
+pub fn filter_results(results: &mut Vec<SearchResult<EngineSearchResult>>, config: &Config) {
+    let filter = &config.language_filter;
+    if filter.mode == LanguageFilterMode::Off {
+        return;
+    }
+    let languages = if filter.languages.is_empty() {
+        vec![lang_for_tag(&config.language)]
+    } else {
+        filter.languages.iter().map(|l| lang_for_tag(l)).collect()
+    };
+    let languages = languages.into_iter().flatten().collect::<Vec<_>>();
+    if languages.is_empty() {
+        return;
+    }
+
+    let matches = |result: &EngineSearchResult| {
+        let text = format!("{} {}", result.title, result.description);
+        whatlang::detect(&text)
+            .filter(|info| info.is_reliable())
+            .is_none_or(|info| languages.contains(&info.lang()))
+    };
+    match filter.mode {
+        LanguageFilterMode::Off => {}
+        LanguageFilterMode::Drop => results.retain(|r| matches(&r.result)),
+        LanguageFilterMode::Downrank => {
+            for result in results.iter_mut() {
+                if !matches(&result.result) {
+                    result.score *= filter.downrank_factor;
+                }
+            }
+            results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
+    }
+}
+
+/// The language for a tag like `en-US`.
+fn lang_for_tag(tag: &str) -> Option<Lang> {
+    let code = tag.split(['-', '_']).next()?.to_lowercase();
+    Some(match code.as_str() {
+        "en" => Lang::Eng,
+        "de" => Lang::Deu,
+        "es" => Lang::Spa,
+        "fr" => Lang::Fra,
+        "it" => Lang::Ita,
+        "nl" => Lang::Nld,
+        "pl" => Lang::Pol,
+        "pt" => Lang::Por,
+        "ru" => Lang::Rus,
+        "uk" => Lang::Ukr,
+        "tr" => Lang::Tur,
+        "sv" => Lang::Swe,
+        "ja" => Lang::Jpn,
+        "ko" => Lang::Kor,
+        "zh" => Lang::Cmn,
+        // whatlang uses three letter codes
+        _ => return Lang::from_code(code),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(title: &str, description: &str) -> SearchResult<EngineSearchResult> {
+        SearchResult {
+            result: EngineSearchResult {
+                url: format!("https://example.com/{title}"),
+                title: title.to_string(),
+                description: description.to_string(),
+                description_from_page: false,
+                extras: Default::default(),
+            },
+            engines: Default::default(),
+            score: 1.,
+            explanation: None,
+        }
+    }
+
+    #[test]
+    fn test_filter_results() {
+        let mut config = Config::default();
+        config.language = "en-US".to_string();
+        config.language_filter.mode = LanguageFilterMode::Drop;
+        let mut results = vec![
+            result(
+                "The Rust programming language",
+                "A language empowering everyone to build reliable and efficient software.",
+            ),
+            result(
+                "Die Programmiersprache Rust",
+                "Eine Sprache, die es jedem ermöglicht, zuverlässige und effiziente Software zu schreiben.",
+            ),
+            result("Rust", ""),
+        ];
+        filter_results(&mut results, &config);
+        let titles = results
+            .iter()
+            .map(|r| r.result.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, ["The Rust programming language", "Rust"]);
+    }
+
+    #[test]
+    fn test_lang_for_tag() {
+        assert_eq!(lang_for_tag("en-US"), Some(Lang::Eng));
+        assert_eq!(lang_for_tag("pt_BR"), Some(Lang::Por));
+        assert_eq!(lang_for_tag("fin"), Some(Lang::Fin));
+        assert_eq!(lang_for_tag("auto"), None);
+    }
+}
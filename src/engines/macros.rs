@@ -4,18 +4,33 @@ macro_rules! engines {
         #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
         pub enum Engine {
             $($engine,)*
+            /// An engine that isn't built in, added with [`registry::register`].
+            #[serde(untagged)]
+            Custom(&'static str),
         }
 
         impl Engine {
+            const BUILTIN: &'static [Engine] = &[$(Engine::$engine,)*];
+
+            /// Every engine, including the ones that were registered at
+            /// startup.
             #[must_use]
             pub fn all() -> &'static [Engine] {
-                &[$(Engine::$engine,)*]
+                registry::all()
             }
 
             #[must_use]
             pub fn id(&self) -> &'static str {
                 match self {
                     $(Engine::$engine => $id,)*
+                    Engine::Custom(id) => *id,
+                }
+            }
+
+            fn builtin_from_id(id: &str) -> Option<Engine> {
+                match id {
+                    $($id => Some(Engine::$engine),)*
+                    _ => None,
                 }
             }
         }
@@ -24,10 +39,9 @@ macro_rules! engines {
             type Err = ();
 
             fn from_str(s: &str) -> Result<Self, Self::Err> {
-                match s {
-                    $($id => Ok(Engine::$engine),)*
-                    _ => Err(()),
-                }
+                Engine::builtin_from_id(s)
+                    .or_else(|| registry::find(s))
+                    .ok_or(())
             }
         }
     };
@@ -39,116 +53,135 @@ macro_rules! engine_parse_response {
         None
     };
     ($res:ident, $module:ident::$engine_id:ident::$parse_response:ident) => {
-        Some($module::$engine_id::$parse_response($res.into()))
+        Some($module::$engine_id::$parse_response(
+            $crate::engines::ParseInput::parse_input($res),
+        ))
     };
 }
 
 #[macro_export]
-macro_rules! engine_requests {
-    ($($engine:ident => $module:ident::$engine_id:ident::$request:ident, $parse_response:ident),* $(,)?) => {
-        impl Engine {
-            pub async fn request(&self, query: &SearchQuery) -> eyre::Result<RequestResponse> {
-                #[allow(clippy::useless_conversion)]
-                match self {
-                    $(
-                        Engine::$engine => $module::$engine_id::$request(query).await.into_request_response_result(),
-                    )*
-                    _ => Ok(RequestResponse::None),
-                }
-            }
-
-            #[tracing::instrument(skip(self, res), fields(engine = %self))]
-            pub fn parse_response(&self, res: &HttpResponse) -> eyre::Result<EngineResponse> {
-                #[allow(clippy::useless_conversion)]
-                match self {
-                    $(
-                        Engine::$engine => $crate::engine_parse_response! { res, $module::$engine_id::$parse_response }
-                            .ok_or_else(|| eyre::eyre!("engine {self:?} can't parse response"))?,
-                    )*
-                    _ => eyre::bail!("engine {self:?} can't parse response"),
-                }
-            }
-        }
+macro_rules! engine_has_capability {
+    () => {
+        false
+    };
+    ($($t:tt)+) => {
+        true
     };
 }
 
+/// Implement [`EngineImpl`] for the built-in engines. Each engine lists what it
+/// can do, and the functions for each capability. Image requests are only
+/// compiled in with the `images` feature.
 #[macro_export]
-macro_rules! engine_autocomplete_requests {
-    ($($engine:ident => $module:ident::$engine_id:ident::$request:ident, $parse_response:ident),* $(,)?) => {
-        impl Engine {
-            #[must_use]
-            pub fn request_autocomplete(&self, query: &str) -> Option<RequestAutocompleteResponse> {
-                match self {
-                    $(
-                        Engine::$engine => Some($module::$engine_id::$request(query).into()),
-                    )*
-                    _ => None,
-                }
-            }
+macro_rules! engine_impls {
+    ($(
+        $(#[$attr:meta])*
+        $engine:ident {
+            $(tab: $tab:ident;)?
+            $(web: $web_module:ident::$web_id:ident::$web_request:ident, $web_parse:ident;)?
+            $(images: $images_module:ident::$images_id:ident::$images_request:ident, $images_parse:ident;)?
+            $(autocomplete: $autocomplete_module:ident::$autocomplete_id:ident::$autocomplete_request:ident, $autocomplete_parse:ident;)?
+            $(postsearch: $postsearch_module:ident::$postsearch_id:ident::$postsearch_request:ident, $postsearch_parse:ident;)?
+        }
+    )*) => {
+        mod builtin {
+            use std::sync::Arc;
+
+            use futures::future::BoxFuture;
+
+            use super::*;
+
+            $(
+                $(#[$attr])*
+                pub struct $engine;
+
+                $(#[$attr])*
+                impl EngineImpl for $engine {
+                    fn capabilities(&self) -> Capabilities {
+                        Capabilities {
+                            web: $crate::engine_has_capability!($($web_request)?),
+                            images: cfg!(feature = "images")
+                                && $crate::engine_has_capability!($($images_request)?),
+                            autocomplete: $crate::engine_has_capability!($($autocomplete_request)?),
+                            postsearch: $crate::engine_has_capability!($($postsearch_request)?),
+                        }
+                    }
 
-            pub fn parse_autocomplete_response(&self, body: &str) -> eyre::Result<Vec<String>> {
-                match self {
                     $(
-                        Engine::$engine => $crate::engine_parse_response! { body, $module::$engine_id::$parse_response }
-                            .ok_or_else(|| eyre::eyre!("engine {self:?} can't parse autocomplete response"))?,
-                    )*
-                    _ => eyre::bail!("engine {self:?} can't parse autocomplete response"),
-                }
-            }
-        }
-    };
-}
+                        fn tab(&self) -> SearchTab {
+                            SearchTab::$tab
+                        }
+                    )?
 
-#[macro_export]
-macro_rules! engine_postsearch_requests {
-    ($($engine:ident => $module:ident::$engine_id:ident::$request:ident, $parse_response:ident),* $(,)?) => {
-        impl Engine {
-            #[must_use]
-            pub async fn postsearch_request(&self, response: &Response) -> Option<wreq::RequestBuilder> {
-                match self {
                     $(
-                        Engine::$engine => $module::$engine_id::$request(response).await,
-                    )*
-                    _ => None,
-                }
-            }
+                        fn is_answer(&self) -> bool {
+                            stringify!($web_module) == "answer"
+                        }
+
+                        fn request<'a>(
+                            &'a self,
+                            query: &'a SearchQuery,
+                        ) -> BoxFuture<'a, eyre::Result<RequestResponse>> {
+                            Box::pin(async move {
+                                $web_module::$web_id::$web_request(query)
+                                    .await
+                                    .into_request_response_result()
+                            })
+                        }
+
+                        fn parse_response(&self, res: &HttpResponse) -> eyre::Result<EngineResponse> {
+                            $crate::engine_parse_response! { res, $web_module::$web_id::$web_parse }
+                                .ok_or_else(|| eyre::eyre!("engine {} can't parse response", stringify!($engine)))?
+                        }
+                    )?
 
-            #[must_use]
-            pub fn postsearch_parse_response(&self, res: &HttpResponse) -> Option<maud::PreEscaped<String>> {
-                match self {
                     $(
-                        Engine::$engine => $crate::engine_parse_response! { res, $module::$engine_id::$parse_response }?,
-                    )*
-                    _ => None,
-                }
-            }
-        }
-    };
-}
+                        #[cfg(feature = "images")]
+                        fn request_images(&self, query: &SearchQuery) -> RequestResponse {
+                            $images_module::$images_id::$images_request(query).into_request_response()
+                        }
+
+                        #[cfg(feature = "images")]
+                        fn parse_images_response(&self, res: &HttpResponse) -> eyre::Result<EngineImagesResponse> {
+                            $crate::engine_parse_response! { res, $images_module::$images_id::$images_parse }
+                                .ok_or_else(|| eyre::eyre!("engine {} can't parse images response", stringify!($engine)))?
+                        }
+                    )?
 
-#[macro_export]
-macro_rules! engine_image_requests {
-    ($($engine:ident => $module:ident::$engine_id:ident::$request:ident, $parse_response:ident),* $(,)?) => {
-        impl Engine {
-            #[must_use]
-            pub fn request_images(&self, query: &SearchQuery) -> RequestResponse {
-                match self {
                     $(
-                        Engine::$engine => $module::$engine_id::$request(query).into(),
-                    )*
-                    _ => RequestResponse::None,
-                }
-            }
+                        fn request_autocomplete(&self, query: &str) -> Option<RequestAutocompleteResponse> {
+                            Some($autocomplete_module::$autocomplete_id::$autocomplete_request(query).into())
+                        }
+
+                        fn parse_autocomplete_response(&self, body: &str) -> eyre::Result<Vec<String>> {
+                            $crate::engine_parse_response! { body, $autocomplete_module::$autocomplete_id::$autocomplete_parse }
+                                .ok_or_else(|| eyre::eyre!("engine {} can't parse autocomplete response", stringify!($engine)))?
+                        }
+                    )?
 
-            pub fn parse_images_response(&self, res: &HttpResponse) -> eyre::Result<EngineImagesResponse> {
-                #[allow(clippy::useless_conversion)]
-                match self {
                     $(
-                        Engine::$engine => $crate::engine_parse_response! { res, $module::$engine_id::$parse_response }
-                            .ok_or_else(|| eyre::eyre!("engine {self:?} can't parse images response"))?,
-                    )*
-                    _ => eyre::bail!("engine {self:?} can't parse response"),
+                        fn postsearch_request<'a>(
+                            &'a self,
+                            response: &'a Response,
+                        ) -> BoxFuture<'a, Option<wreq::RequestBuilder>> {
+                            Box::pin($postsearch_module::$postsearch_id::$postsearch_request(response))
+                        }
+
+                        fn postsearch_parse_response(&self, res: &HttpResponse) -> Option<maud::PreEscaped<String>> {
+                            $crate::engine_parse_response! { res, $postsearch_module::$postsearch_id::$postsearch_parse }?
+                        }
+                    )?
                 }
+            )*
+
+            /// The built-in engines, for the registry to start with.
+            pub fn engines() -> Vec<(Engine, Arc<dyn EngineImpl>)> {
+                let mut engines: Vec<(Engine, Arc<dyn EngineImpl>)> = Vec::new();
+                $(
+                    $(#[$attr])*
+                    engines.push((Engine::$engine, Arc::new($engine)));
+                )*
+                engines
             }
         }
     };
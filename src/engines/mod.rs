@@ -1,11 +1,12 @@
 use std::{
     collections::{BTreeSet, HashMap},
     fmt::{self, Display},
-    net::IpAddr,
+    future::Future,
+    hash::{DefaultHasher, Hash, Hasher},
     ops::Deref,
     str::FromStr,
     sync::{Arc, LazyLock},
-    time::{Duration, Instant},
+    time::Instant,
 };
 
 use eyre::bail;
@@ -13,15 +14,43 @@ use futures::future::join_all;
 use maud::PreEscaped;
 use serde::{Deserialize, Deserializer, Serialize};
 use tokio::sync::mpsc;
-use tracing::{error, info};
-use wreq_util::Emulation;
-
+use tracing::{error, info, warn};
+
+mod adult_filter;
+pub mod canary;
+pub mod client;
+mod coalesce;
+pub mod cookies;
+mod dead_links;
+pub mod drift;
+pub mod endpoints;
+mod fallback;
+pub mod host_labels;
+pub use host_labels::HostLabel;
+pub mod image_filters;
+mod image_probe;
+pub use image_filters::ImageFilters;
+mod jobs;
+mod language_filter;
 mod macros;
+mod operators;
+pub use operators::{Operator, QueryOperators};
+mod page_cache;
+mod page_meta;
+pub mod peers;
+pub mod plugins;
+mod prefetch;
+pub mod preprocess;
 mod ranking;
-use crate::{
-    config::Config, engine_autocomplete_requests, engine_image_requests,
-    engine_postsearch_requests, engine_requests, engines,
-};
+mod recipes;
+pub mod refine;
+pub mod registry;
+mod rewrite;
+mod sanitize;
+pub mod scheduler;
+mod wayback;
+use crate::{config::Config, engine_impls, engines, parse::ConsentPage};
+pub use registry::{Capabilities, EngineImpl};
 
 pub mod answer;
 pub mod postsearch;
@@ -33,20 +62,49 @@ engines! {
     GoogleScholar = "google_scholar",
     Bing = "bing",
     Brave = "brave",
+    Kagi = "kagi",
     Marginalia = "marginalia",
+    Mwmbl = "mwmbl",
     RightDao = "rightdao",
     Stract = "stract",
+    Wiby = "wiby",
     Yep = "yep",
+    FourGet = "4get",
+    LibreY = "librey",
+    SearXng = "searxng",
+    Local = "local",
+    Visited = "visited",
+    Petal = "petal",
+    Sogou = "sogou",
+    OpenAlex = "openalex",
+    AnnasArchive = "annas_archive",
+    StackExchangeSearch = "stackexchange_search",
+    // code
+    Codeberg = "codeberg",
+    SourceHut = "sourcehut",
+    // media
+    PodcastIndex = "podcastindex",
+    // recipes
+    TheMealDb = "themealdb",
+    // jobs
+    Remotive = "remotive",
     // answer
+    Cheatsheet = "cheatsheet",
     Dictionary = "dictionary",
+    Dns = "dns",
+    Encoding = "encoding",
     Fend = "fend",
     Ip = "ip",
+    Lyrics = "lyrics",
     Notepad = "notepad",
     ColorPicker = "colorpicker",
     Numbat = "numbat",
+    Packages = "packages",
     Thesaurus = "thesaurus",
+    Ticker = "ticker",
     Timezone = "timezone",
     Useragent = "useragent",
+    Wikidata = "wikidata",
     Wikipedia = "wikipedia",
     // post-search
     DocsRs = "docs_rs",
@@ -56,46 +114,264 @@ engines! {
     StackExchange = "stackexchange",
 }
 
-engine_requests! {
+engine_impls! {
     // search
-    Bing => search::bing::request, parse_response,
-    Brave => search::brave::request, parse_response,
-    GoogleScholar => search::google_scholar::request, parse_response,
-    Google => search::google::request, parse_response,
-    Marginalia => search::marginalia::request, parse_response,
-    RightDao => search::rightdao::request, parse_response,
-    Stract => search::stract::request, parse_response,
-    Yep => search::yep::request, parse_response,
+    Google {
+        web: search::google::request, parse_response;
+        images: search::google::request_images, parse_images_response;
+        autocomplete: search::google::request_autocomplete, parse_autocomplete_response;
+    }
+    GoogleScholar {
+        web: search::google_scholar::request, parse_response;
+    }
+    Bing {
+        web: search::bing::request, parse_response;
+        images: search::bing::request_images, parse_images_response;
+    }
+    Brave {
+        web: search::brave::request, parse_any_response;
+        images: search::brave::request_images, parse_images_response;
+    }
+    Kagi {
+        web: search::kagi::request, parse_response;
+    }
+    Marginalia {
+        web: search::marginalia::request, parse_response;
+    }
+    Mwmbl {
+        web: search::mwmbl::request, parse_response;
+    }
+    RightDao {
+        web: search::rightdao::request, parse_response;
+    }
+    Stract {
+        web: search::stract::request, parse_response;
+    }
+    Wiby {
+        web: search::wiby::request, parse_response;
+    }
+    Yep {
+        web: search::yep::request, parse_response;
+    }
+    FourGet {
+        web: search::fourget::request, parse_response;
+    }
+    LibreY {
+        web: search::librey::request, parse_response;
+    }
+    SearXng {
+        web: search::searxng::request, parse_response;
+    }
+    Local {
+        web: search::local::request, None;
+    }
+    Visited {
+        web: search::visited::request, None;
+    }
+    Petal {
+        web: search::petal::request, parse_response;
+    }
+    Sogou {
+        web: search::sogou::request, parse_response;
+    }
+    OpenAlex {
+        web: search::openalex::request, parse_response;
+    }
+    AnnasArchive {
+        web: search::annas_archive::request, parse_response;
+    }
+    StackExchangeSearch {
+        web: search::stackexchange::request, None;
+    }
+    // code
+    Codeberg {
+        tab: Code;
+        web: search::codeberg::request, parse_response;
+    }
+    SourceHut {
+        tab: Code;
+        web: search::sourcehut::request, parse_response;
+    }
+    // media
+    PodcastIndex {
+        tab: Media;
+        web: search::podcastindex::request, None;
+    }
+    // recipes
+    TheMealDb {
+        tab: Recipes;
+        web: search::themealdb::request, parse_response;
+    }
+    // jobs
+    Remotive {
+        tab: Jobs;
+        web: search::remotive::request, parse_response;
+    }
     // answer
-    Dictionary => answer::dictionary::request, parse_response,
-    Fend => answer::fend::request, None,
-    Ip => answer::ip::request, None,
-    Notepad => answer::notepad::request, None,
-    ColorPicker => answer::colorpicker::request, None,
-    Numbat => answer::numbat::request, None,
-    Thesaurus => answer::thesaurus::request, parse_response,
-    Timezone => answer::timezone::request, None,
-    Useragent => answer::useragent::request, None,
-    Wikipedia => answer::wikipedia::request, parse_response,
+    #[cfg(feature = "answers")]
+    Cheatsheet {
+        web: answer::cheatsheet::request, parse_response;
+    }
+    #[cfg(feature = "answers")]
+    Dictionary {
+        web: answer::dictionary::request, parse_response;
+    }
+    #[cfg(feature = "answers")]
+    Dns {
+        web: answer::dns::request, None;
+    }
+    #[cfg(feature = "answers")]
+    Encoding {
+        web: answer::encoding::request, None;
+    }
+    #[cfg(feature = "answers")]
+    Fend {
+        web: answer::fend::request, None;
+        autocomplete: answer::fend::request_autocomplete, None;
+    }
+    #[cfg(feature = "answers")]
+    Ip {
+        web: answer::ip::request, None;
+    }
+    #[cfg(feature = "answers")]
+    Lyrics {
+        web: answer::lyrics::request, None;
+    }
+    #[cfg(feature = "answers")]
+    Notepad {
+        web: answer::notepad::request, None;
+    }
+    #[cfg(feature = "answers")]
+    ColorPicker {
+        web: answer::colorpicker::request, None;
+    }
+    #[cfg(feature = "answers")]
+    Numbat {
+        web: answer::numbat::request, None;
+        autocomplete: answer::numbat::request_autocomplete, None;
+    }
+    #[cfg(feature = "answers")]
+    Packages {
+        web: answer::packages::request, None;
+    }
+    #[cfg(feature = "answers")]
+    Thesaurus {
+        web: answer::thesaurus::request, parse_response;
+    }
+    #[cfg(feature = "answers")]
+    Ticker {
+        web: answer::ticker::request, parse_response;
+    }
+    #[cfg(feature = "answers")]
+    Timezone {
+        web: answer::timezone::request, None;
+    }
+    #[cfg(feature = "answers")]
+    Useragent {
+        web: answer::useragent::request, None;
+    }
+    #[cfg(feature = "answers")]
+    Wikidata {
+        web: answer::wikidata::request, parse_response;
+    }
+    #[cfg(feature = "answers")]
+    Wikipedia {
+        web: answer::wikipedia::request, parse_response;
+    }
+    // post-search
+    DocsRs {
+        postsearch: postsearch::docs_rs::request, parse_response;
+    }
+    GitHub {
+        postsearch: postsearch::github::request, parse_response;
+    }
+    Mdn {
+        postsearch: postsearch::mdn::request, parse_response;
+    }
+    MinecraftWiki {
+        postsearch: postsearch::minecraft_wiki::request, parse_response;
+    }
+    StackExchange {
+        postsearch: postsearch::stackexchange::request, parse_response;
+    }
 }
 
-engine_autocomplete_requests! {
-    Google => search::google::request_autocomplete, parse_autocomplete_response,
-    Fend => answer::fend::request_autocomplete, None,
-    Numbat => answer::numbat::request_autocomplete, None,
-}
+impl Engine {
+    #[must_use]
+    pub fn capabilities(&self) -> Capabilities {
+        registry::get(*self)
+            .map(|e| e.capabilities())
+            .unwrap_or_default()
+    }
 
-engine_postsearch_requests! {
-    DocsRs => postsearch::docs_rs::request, parse_response,
-    GitHub => postsearch::github::request, parse_response,
-    Mdn => postsearch::mdn::request, parse_response,
-    MinecraftWiki => postsearch::minecraft_wiki::request, parse_response,
-    StackExchange => postsearch::stackexchange::request, parse_response,
-}
+    /// Whether the engine gives instant answers instead of search results.
+    #[must_use]
+    pub fn is_answer(&self) -> bool {
+        registry::get(*self).is_some_and(|e| e.is_answer())
+    }
+
+    /// The tab that the engine's search results are shown on. Engines for the
+    /// images tab are the ones with an image request instead.
+    #[must_use]
+    pub fn tab(&self) -> SearchTab {
+        registry::get(*self).map_or(SearchTab::All, |e| e.tab())
+    }
+
+    pub async fn request(&self, query: &SearchQuery) -> eyre::Result<RequestResponse> {
+        if let Some(response) = preprocess::engine_policy(*self, query) {
+            return Ok(response);
+        }
+        let rewritten = rewrite::rewrite(*self, query);
+        let query = rewritten.as_ref().unwrap_or(query);
+        match registry::get(*self) {
+            Some(engine) => engine.request(query).await,
+            None => Ok(RequestResponse::None),
+        }
+    }
+
+    #[tracing::instrument(skip(self, res), fields(engine = %self))]
+    pub fn parse_response(&self, res: &HttpResponse) -> eyre::Result<EngineResponse> {
+        match registry::get(*self) {
+            Some(engine) => engine.parse_response(res),
+            None => bail!("engine {self:?} can't parse response"),
+        }
+    }
+
+    #[must_use]
+    pub fn request_autocomplete(&self, query: &str) -> Option<RequestAutocompleteResponse> {
+        registry::get(*self)?.request_autocomplete(query)
+    }
+
+    pub fn parse_autocomplete_response(&self, body: &str) -> eyre::Result<Vec<String>> {
+        match registry::get(*self) {
+            Some(engine) => engine.parse_autocomplete_response(body),
+            None => bail!("engine {self:?} can't parse autocomplete response"),
+        }
+    }
+
+    pub async fn postsearch_request(&self, response: &Response) -> Option<wreq::RequestBuilder> {
+        registry::get(*self)?.postsearch_request(response).await
+    }
+
+    #[must_use]
+    pub fn postsearch_parse_response(&self, res: &HttpResponse) -> Option<PreEscaped<String>> {
+        registry::get(*self)?.postsearch_parse_response(res)
+    }
+
+    #[must_use]
+    pub fn request_images(&self, query: &SearchQuery) -> RequestResponse {
+        match registry::get(*self) {
+            Some(engine) => engine.request_images(query),
+            None => RequestResponse::None,
+        }
+    }
 
-engine_image_requests! {
-    Google => search::google::request_images, parse_images_response,
-    Bing => search::bing::request_images, parse_images_response,
+    pub fn parse_images_response(&self, res: &HttpResponse) -> eyre::Result<EngineImagesResponse> {
+        match registry::get(*self) {
+            Some(engine) => engine.parse_images_response(res),
+            None => bail!("engine {self:?} can't parse response"),
+        }
+    }
 }
 
 impl fmt::Display for Engine {
@@ -114,9 +390,36 @@ impl<'de> Deserialize<'de> for Engine {
     }
 }
 
+/// How many results we ask each engine for by default.
+pub const DEFAULT_REQUESTED_COUNT: usize = 10;
+
+#[derive(Clone)]
 pub struct SearchQuery {
     pub query: String,
     pub tab: SearchTab,
+    /// Whether the user asked for literal matching of their query, without
+    /// engines applying synonyms or spelling corrections.
+    pub verbatim: bool,
+    /// The engines that the user restricted this search to with `!only` or
+    /// `&engines=`. Empty if every enabled engine should be used.
+    pub only_engines: Vec<Engine>,
+    /// Only show results from this kind of site, from `&label=`.
+    pub label: Option<HostLabel>,
+    /// The language from `&lang=`, which overrides the config and the user's
+    /// settings for this search. It's already in `config.language`, this is so
+    /// it can be kept for the next search.
+    pub language: Option<String>,
+    /// Filters for the images tab, like `&size=large`.
+    pub image_filters: ImageFilters,
+    /// How many results we'd like from each engine. Not every engine lets us
+    /// choose this.
+    pub requested_count: usize,
+    /// What preprocessing found out about the query.
+    pub info: preprocess::QueryInfo,
+    /// Whether to include how each result was ranked in the response. This is
+    /// set with `&debug=1`, and for the results page if `ui.explain_results`
+    /// is on.
+    pub debug: bool,
     pub request_headers: HashMap<String, String>,
     pub ip: String,
     /// The config is part of the query so it's possible to make a query with a
@@ -124,6 +427,81 @@ pub struct SearchQuery {
     pub config: Arc<Config>,
 }
 
+impl SearchQuery {
+    /// How many pages to request from an engine that returns `page_size`
+    /// results per page, to get `requested_count` results. It's always 1 unless
+    /// `max_pages` is set in the config.
+    #[must_use]
+    pub fn page_count(&self, page_size: usize) -> usize {
+        self.requested_count
+            .div_ceil(page_size)
+            .clamp(1, self.config.max_pages.max(1))
+    }
+
+    /// The query for an engine that natively supports the given operators.
+    /// Other operators are removed from the query, and the results are
+    /// filtered for them after merging.
+    #[must_use]
+    pub fn with_operators(&self, supported: &[Operator]) -> String {
+        QueryOperators::parse(&self.query).to_query(supported, false)
+    }
+
+    #[must_use]
+    pub fn has_operator(&self, operator: Operator) -> bool {
+        QueryOperators::parse(&self.query)
+            .operators
+            .iter()
+            .any(|(o, _)| *o == operator)
+    }
+
+    /// Like [`Self::with_operators`], but the search terms are wrapped in
+    /// quotes if verbatim mode is enabled. This is for engines that don't have
+    /// a dedicated verbatim option but do support phrase search.
+    #[must_use]
+    pub fn quoted_if_verbatim(&self, supported: &[Operator]) -> String {
+        QueryOperators::parse(&self.query).to_query(supported, self.verbatim)
+    }
+
+    /// Everything that can change the results, for caching them and for
+    /// noticing identical searches. The user's settings are hashed into it,
+    /// since they can change the enabled engines and how results are ranked.
+    /// This is `None` if the results can't be shared with anyone else.
+    #[must_use]
+    pub fn cache_key(&self) -> Option<String> {
+        if self.depends_on_request() {
+            return None;
+        }
+        let mut config_hasher = DefaultHasher::new();
+        format!("{:?}", self.config).hash(&mut config_hasher);
+        Some(format!(
+            "{}\n{}\n{}\n{:?}\n{:?}\n{}\n{:?}\n{:x}",
+            self.query,
+            self.tab,
+            self.verbatim,
+            self.label,
+            self.image_filters,
+            self.debug,
+            self.only_engines,
+            config_hasher.finish()
+        ))
+    }
+
+    /// Whether an answer would show something from the request itself, like
+    /// the user's ip address.
+    fn depends_on_request(&self) -> bool {
+        #[cfg(feature = "answers")]
+        {
+            let enabled = |engine| self.config.engines.get(engine).enabled;
+            if (enabled(Engine::Ip) && answer::ip::matches(&self.query))
+                || (enabled(Engine::Useragent) && answer::useragent::matches(&self.query))
+            {
+                return true;
+            }
+        }
+        false
+    }
+}
+
 impl Deref for SearchQuery {
     type Target = str;
 
@@ -137,6 +515,10 @@ pub enum SearchTab {
     #[default]
     All,
     Images,
+    Code,
+    Media,
+    Recipes,
+    Jobs,
 }
 impl FromStr for SearchTab {
     type Err = ();
@@ -144,6 +526,10 @@ impl FromStr for SearchTab {
         match s {
             "all" => Ok(Self::All),
             "images" => Ok(Self::Images),
+            "code" => Ok(Self::Code),
+            "media" => Ok(Self::Media),
+            "recipes" => Ok(Self::Recipes),
+            "jobs" => Ok(Self::Jobs),
             _ => Err(()),
         }
     }
@@ -153,6 +539,48 @@ impl Display for SearchTab {
         match self {
             Self::All => write!(f, "all"),
             Self::Images => write!(f, "images"),
+            Self::Code => write!(f, "code"),
+            Self::Media => write!(f, "media"),
+            Self::Recipes => write!(f, "recipes"),
+            Self::Jobs => write!(f, "jobs"),
+        }
+    }
+}
+
+impl Engine {
+    /// Whether the engine is searched on the tab. The recipes tab uses the
+    /// normal engines too, since most recipes are on blogs.
+    pub fn searches_tab(&self, tab: SearchTab) -> bool {
+        self.tab() == tab || (tab == SearchTab::Recipes && self.tab() == SearchTab::All)
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SafeSearch {
+    Off,
+    /// Let the engines use their default filtering.
+    #[default]
+    Moderate,
+    Strict,
+}
+impl FromStr for SafeSearch {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "moderate" => Ok(Self::Moderate),
+            "strict" => Ok(Self::Strict),
+            _ => Err(()),
+        }
+    }
+}
+impl Display for SafeSearch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::Moderate => write!(f, "moderate"),
+            Self::Strict => write!(f, "strict"),
         }
     }
 }
@@ -160,7 +588,16 @@ impl Display for SearchTab {
 pub enum RequestResponse {
     None,
     Http(Box<wreq::RequestBuilder>),
+    /// Requests for each of the engine's endpoints, which are tried in order
+    /// until one works.
+    Failover(Vec<endpoints::EndpointRequest>),
     Instant(Box<EngineResponse>),
+    /// Requests for several pages of results, which are sent at the same time.
+    /// The results from the later pages are added after the first page's.
+    Pages(Vec<wreq::RequestBuilder>),
+    /// The engine can't handle this query (like Brave with verbatim search), so
+    /// it wasn't requested. The reason is shown to the user.
+    Skipped(&'static str),
 }
 impl From<wreq::RequestBuilder> for RequestResponse {
     fn from(req: wreq::RequestBuilder) -> Self {
@@ -193,6 +630,24 @@ impl IntoRequestResponseResult for eyre::Result<RequestResponse> {
     }
 }
 
+#[cfg(feature = "images")]
+trait IntoRequestResponse {
+    fn into_request_response(self) -> RequestResponse;
+}
+
+#[cfg(feature = "images")]
+impl IntoRequestResponse for wreq::RequestBuilder {
+    fn into_request_response(self) -> RequestResponse {
+        RequestResponse::Http(Box::new(self))
+    }
+}
+#[cfg(feature = "images")]
+impl IntoRequestResponse for RequestResponse {
+    fn into_request_response(self) -> RequestResponse {
+        self
+    }
+}
+
 pub enum RequestAutocompleteResponse {
     Http(Box<wreq::RequestBuilder>),
     Instant(Vec<String>),
@@ -220,6 +675,27 @@ impl<'a> From<&'a HttpResponse> for &'a str {
     }
 }
 
+/// What an engine's parse function takes, which is either the whole response
+/// or only its body. Autocomplete parsers get the body already.
+trait ParseInput<T> {
+    fn parse_input(res: T) -> Self;
+}
+impl<'a> ParseInput<&'a HttpResponse> for &'a HttpResponse {
+    fn parse_input(res: &'a HttpResponse) -> Self {
+        res
+    }
+}
+impl<'a> ParseInput<&'a HttpResponse> for &'a str {
+    fn parse_input(res: &'a HttpResponse) -> Self {
+        &res.body
+    }
+}
+impl<'a> ParseInput<&'a str> for &'a str {
+    fn parse_input(body: &'a str) -> Self {
+        body
+    }
+}
+
 impl From<HttpResponse> for wreq::Response {
     fn from(res: HttpResponse) -> Self {
         res.res
@@ -230,7 +706,121 @@ impl From<HttpResponse> for wreq::Response {
 pub struct EngineSearchResult {
     pub url: String,
     pub title: String,
+    /// Empty if neither the engines nor the page gave us a description.
     pub description: String,
+    /// Whether the description came from the page's meta tags because the
+    /// engines didn't have one.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub description_from_page: bool,
+    #[serde(skip_serializing_if = "ResultExtras::is_empty")]
+    pub extras: ResultExtras,
+}
+
+/// Structured data that engines sometimes show for a result, usually from the
+/// page's schema.org markup.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct ResultExtras {
+    pub rating: Option<Rating>,
+    /// How long a recipe takes to make, like "1 hr 10 min".
+    pub total_time: Option<String>,
+    /// How many ingredients a recipe has.
+    pub ingredient_count: Option<u32>,
+    /// The id of the element that the recipe card starts at, so the story
+    /// before it can be skipped.
+    pub recipe_anchor: Option<String>,
+    /// The number of answers, for results from Q&A sites.
+    pub answer_count: Option<u32>,
+    /// Where and when a paper was published, like "Nature, 2020", or the show
+    /// that a podcast episode is from.
+    pub venue: Option<String>,
+    /// How many times a paper has been cited.
+    pub cited_by: Option<u32>,
+    /// The open access status of a paper, like "gold" or "green". None if it's
+    /// closed or we don't know.
+    pub open_access: Option<String>,
+    /// The site in a network of sites that the result is from, like "Super User".
+    pub site: Option<String>,
+    /// A link to the audio file, for podcast episodes.
+    pub audio_url: Option<String>,
+    pub job: Option<JobPosting>,
+    /// The authors, year, and pdf of a paper from Google Scholar. The venue
+    /// and citations are in `venue` and `cited_by`.
+    pub scholar: Option<EngineScholarResult>,
+    /// What kind of site the result is from. This is set after merging, from
+    /// the url.
+    pub host_label: Option<HostLabel>,
+    /// The Wayback Machine's copy of the page, if it has one. This is set after
+    /// merging too.
+    pub archived: Option<ArchivedCopy>,
+    /// Whether the page was dead when we last checked.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub unreachable: bool,
+}
+
+impl ResultExtras {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fill in anything that's missing with the extras from another engine.
+    pub fn merge(&mut self, other: ResultExtras) {
+        self.rating = self.rating.take().or(other.rating);
+        self.total_time = self.total_time.take().or(other.total_time);
+        self.ingredient_count = self.ingredient_count.or(other.ingredient_count);
+        self.recipe_anchor = self.recipe_anchor.take().or(other.recipe_anchor);
+        self.answer_count = self.answer_count.or(other.answer_count);
+        self.venue = self.venue.take().or(other.venue);
+        self.cited_by = self.cited_by.or(other.cited_by);
+        self.open_access = self.open_access.take().or(other.open_access);
+        self.site = self.site.take().or(other.site);
+        self.audio_url = self.audio_url.take().or(other.audio_url);
+        self.job = self.job.take().or(other.job);
+        self.scholar = self.scholar.take().or(other.scholar);
+        self.host_label = self.host_label.or(other.host_label);
+        self.archived = self.archived.take().or(other.archived);
+        self.unreachable |= other.unreachable;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Rating {
+    pub value: f32,
+    /// The highest possible rating, usually 5.
+    pub best: f32,
+    /// The number of reviews, as it was shown by the engine (so it might be
+    /// something like "1.2K").
+    pub count: Option<String>,
+}
+
+/// A job listing, for results on the jobs tab.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct JobPosting {
+    pub company: String,
+    /// Where the job is, as the engine wrote it, like "USA" or "Worldwide".
+    pub location: String,
+    /// When the job was posted, like "2024-05-01".
+    pub posted: Option<String>,
+}
+
+/// What Google Scholar shows about a paper, other than its venue and how many
+/// times it was cited.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct EngineScholarResult {
+    /// The names as Scholar shortens them, like "A Vaswani". Scholar only shows
+    /// the first few authors.
+    pub authors: Vec<String>,
+    pub year: Option<u32>,
+    /// A link to a pdf of the paper, which is often on a different site than
+    /// the result.
+    pub pdf_url: Option<String>,
+}
+
+/// A copy of a page on the Wayback Machine.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ArchivedCopy {
+    pub url: String,
+    /// When the copy was made, like "2013-09-19".
+    pub date: String,
 }
 
 #[derive(Debug)]
@@ -244,8 +834,16 @@ pub struct EngineFeaturedSnippet {
 pub struct EngineResponse {
     pub search_results: Vec<EngineSearchResult>,
     pub featured_snippet: Option<EngineFeaturedSnippet>,
+    /// Html copied from other sites has to go through [`sanitize::clean`]
+    /// before it's put in the answer or infobox.
     pub answer_html: Option<PreEscaped<String>>,
     pub infobox_html: Option<PreEscaped<String>>,
+    /// What the engine thinks the query should've been, from its "did you mean"
+    /// link. Engines are asked not to autocorrect, so this is only a suggestion.
+    pub corrected_query: Option<String>,
+    /// Other queries that the engine suggests, like Google's "related
+    /// searches" at the bottom of the page.
+    pub related_searches: Vec<String>,
 }
 
 #[derive(Default)]
@@ -290,18 +888,23 @@ pub struct EngineImageResult {
     pub title: String,
     pub width: u64,
     pub height: u64,
+    /// Whether the image is probably not safe for work, so it's blurred until
+    /// it's clicked. Engines can set this if they mark explicit results, and
+    /// it's also set for sites and titles that our adult filter lists match.
+    pub nsfw: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum EngineProgressUpdate {
     Requesting,
     Downloading,
     Parsing,
-    Done,
+    /// The engine's response was parsed, with this many results.
+    Done(usize),
     Error(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ProgressUpdateData {
     Engine {
         engine: Engine,
@@ -309,9 +912,21 @@ pub enum ProgressUpdateData {
     },
     Response(ResponseForTab),
     PostSearchInfobox(Infobox),
+    /// The indexes of results that turned out to be dead after the response
+    /// was sent, starting at 1.
+    DeadLinks(Vec<usize>),
+    /// The Wayback Machine's copies of results that were looked up after the
+    /// response was sent, by index starting at 1.
+    Archived(Vec<(usize, ArchivedCopy)>),
+    /// The descriptions from the meta tags of results that didn't have one,
+    /// fetched after the response was sent, by index starting at 1.
+    Descriptions(Vec<(usize, String)>),
+    /// The results that turned out to be recipes after the response was sent,
+    /// with their recipe data added, by index starting at 1.
+    Recipes(Vec<(usize, EngineSearchResult)>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProgressUpdate {
     pub data: ProgressUpdateData,
     pub time_ms: u64,
@@ -327,15 +942,17 @@ impl ProgressUpdate {
     }
 }
 
+#[tracing::instrument(skip_all, fields(engine = %engine))]
 async fn make_request(
     request: wreq::RequestBuilder,
     engine: Engine,
     query: &SearchQuery,
     send_engine_progress_update: impl Fn(Engine, EngineProgressUpdate),
 ) -> eyre::Result<HttpResponse> {
+    let start_time = Instant::now();
     send_engine_progress_update(engine, EngineProgressUpdate::Requesting);
 
-    let mut res = request.send().await?;
+    let mut res = client::send(engine, request).await?;
 
     send_engine_progress_update(engine, EngineProgressUpdate::Downloading);
 
@@ -344,6 +961,12 @@ async fn make_request(
         body_bytes.extend_from_slice(&chunk);
     }
     let body = String::from_utf8_lossy(&body_bytes).to_string();
+    info!(
+        status = res.status().as_u16(),
+        bytes = body.len(),
+        elapsed_ms = start_time.elapsed().as_millis() as u64,
+        "engine responded"
+    );
 
     send_engine_progress_update(engine, EngineProgressUpdate::Parsing);
 
@@ -355,64 +978,235 @@ async fn make_request(
     Ok(http_response)
 }
 
+/// Add the results from another page of an engine's results, without the ones
+/// that were on an earlier page too.
+fn append_page(response: &mut EngineResponse, page: EngineResponse) {
+    for result in page.search_results {
+        if !response.search_results.iter().any(|r| r.url == result.url) {
+            response.search_results.push(result);
+        }
+    }
+    for related in page.related_searches {
+        if !response.related_searches.contains(&related) {
+            response.related_searches.push(related);
+        }
+    }
+}
+
+/// Search with the engine again after it got a consent page, now that it sends
+/// its consent cookies.
+async fn search_again(
+    engine: Engine,
+    query: &SearchQuery,
+    send_engine_progress_update: impl Fn(Engine, EngineProgressUpdate),
+) -> eyre::Result<EngineResponse> {
+    let requests = match engine.request(query).await? {
+        RequestResponse::Http(request) => vec![*request],
+        RequestResponse::Pages(requests) => requests,
+        _ => bail!("{engine} can't be searched again"),
+    };
+    let mut pages = join_all(
+        requests
+            .into_iter()
+            .map(|request| make_request(request, engine, query, &send_engine_progress_update)),
+    )
+    .await
+    .into_iter();
+    let first_page = pages
+        .next()
+        .unwrap_or_else(|| Err(eyre::eyre!("no pages were requested")))?;
+    let mut response = engine.parse_response(&first_page)?;
+    for page in pages {
+        if let Ok(page) = page.and_then(|page| engine.parse_response(&page)) {
+            append_page(&mut response, page);
+        }
+    }
+    Ok(response)
+}
+
 async fn make_requests(
     query: &SearchQuery,
     progress_tx: &mpsc::UnboundedSender<ProgressUpdate>,
     start_time: Instant,
     send_engine_progress_update: &impl Fn(Engine, EngineProgressUpdate),
 ) -> eyre::Result<()> {
-    let mut requests = Vec::new();
-    for &engine in Engine::all() {
-        let engine_config = query.config.engines.get(engine);
-        if !engine_config.enabled {
-            continue;
+    let engines = Engine::all()
+        .iter()
+        .copied()
+        .filter(|&engine| {
+            query.config.engines.get(engine).enabled && engine.searches_tab(query.tab)
+        })
+        .collect::<Vec<_>>();
+
+    // the engines that are skipped are known before anything is requested, so
+    // the other engines can be asked for more results to make up for them
+    let mut known_skips = Vec::new();
+    for &engine in &engines {
+        let reason = if query.config.canary.disable_broken && canary::is_broken(engine) {
+            Some("failed its self-check")
+        } else {
+            match preprocess::engine_policy(engine, query) {
+                Some(RequestResponse::Skipped(reason)) => Some(reason),
+                _ => None,
+            }
+        };
+        if let Some(reason) = reason {
+            known_skips.push((engine, reason));
+        }
+    }
+    let searched_count = engines
+        .iter()
+        .filter(|e| !e.is_answer() && !known_skips.iter().any(|(s, _)| s == *e))
+        .count();
+    let compensated_query;
+    let request_query = if !known_skips.is_empty() && searched_count > 0 {
+        compensated_query = SearchQuery {
+            requested_count: (query.requested_count * (searched_count + known_skips.len()))
+                .div_ceil(searched_count),
+            ..query.clone()
+        };
+        &compensated_query
+    } else {
+        query
+    };
+
+    let known_skips = &known_skips;
+    let request_responses = join_all(engines.into_iter().map(|engine| async move {
+        if let Some((_, reason)) = known_skips.iter().find(|(s, _)| *s == engine) {
+            return Some((engine, RequestResponse::Skipped(*reason)));
+        }
+        match engine.request(request_query).await {
+            Ok(r) => Some((engine, r)),
+            Err(e) => {
+                error!("request error for {engine}: {e}");
+                send_engine_progress_update(engine, EngineProgressUpdate::Error(e.to_string()));
+                None
+            }
         }
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    // engines can also skip the query themselves, like when they need an api key
+    // that isn't set
+    let skipped_engines = request_responses
+        .iter()
+        .filter_map(|(engine, r)| match r {
+            RequestResponse::Skipped(reason) => Some(SkippedEngine {
+                engine: *engine,
+                reason,
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
 
+    let mut requests = Vec::new();
+    for (engine, request_response) in request_responses {
         requests.push(async move {
-            let request_response = match engine.request(query).await {
-                Ok(r) => r,
+            // the responses for the pages after the first one
+            let mut more_pages = Vec::new();
+            let http_response = match request_response {
+                RequestResponse::Http(request) => {
+                    make_request(*request, engine, query, send_engine_progress_update).await
+                }
+                RequestResponse::Pages(requests) => {
+                    let mut pages = join_all(requests.into_iter().map(|request| {
+                        make_request(request, engine, query, send_engine_progress_update)
+                    }))
+                    .await
+                    .into_iter();
+                    let first_page = pages
+                        .next()
+                        .unwrap_or_else(|| Err(eyre::eyre!("no pages were requested")));
+                    more_pages = pages.collect();
+                    first_page
+                }
+                RequestResponse::Failover(requests) => {
+                    endpoints::make_failover_request(
+                        requests,
+                        engine,
+                        query,
+                        send_engine_progress_update,
+                    )
+                    .await
+                }
+                RequestResponse::Instant(response) => return Ok((engine, *response)),
+                RequestResponse::None | RequestResponse::Skipped(_) => {
+                    return Ok((engine, EngineResponse::new()))
+                }
+            };
+
+            let http_response = match http_response {
+                Ok(http_response) => http_response,
                 Err(e) => {
-                    error!("request error for {engine}: {e}");
                     send_engine_progress_update(engine, EngineProgressUpdate::Error(e.to_string()));
                     return Err(e);
                 }
             };
 
-            let response = match request_response {
-                RequestResponse::Http(request) => {
-                    let http_response =
-                        match make_request(*request, engine, query, send_engine_progress_update)
-                            .await
-                        {
-                            Ok(http_response) => http_response,
-                            Err(e) => {
-                                send_engine_progress_update(
-                                    engine,
-                                    EngineProgressUpdate::Error(e.to_string()),
-                                );
-                                return Err(e);
-                            }
-                        };
-
-                    let response = match engine.parse_response(&http_response) {
+            let mut response = match engine.parse_response(&http_response) {
+                Ok(response) => response,
+                Err(e) if e.downcast_ref::<ConsentPage>().is_some() => {
+                    warn!("{engine} showed a consent page, searching again");
+                    // the other pages were consent pages too
+                    more_pages.clear();
+                    match search_again(engine, query, send_engine_progress_update).await {
                         Ok(response) => response,
                         Err(e) => {
-                            error!("parse error for {engine}: {e}");
+                            error!("error searching {engine} again: {e}");
                             send_engine_progress_update(
                                 engine,
                                 EngineProgressUpdate::Error(e.to_string()),
                             );
                             return Err(e);
                         }
+                    }
+                }
+                Err(e) if fallback::should_retry(engine, &e, query) => {
+                    warn!("{engine} is blocking us, searching through the fallback instances: {e}");
+                    return match fallback::search(engine, query, send_engine_progress_update).await
+                    {
+                        Ok(response) => {
+                            send_engine_progress_update(
+                                engine,
+                                EngineProgressUpdate::Done(response.search_results.len()),
+                            );
+                            Ok((engine, response))
+                        }
+                        Err(e) => {
+                            error!("fallback error for {engine}: {e}");
+                            send_engine_progress_update(
+                                engine,
+                                EngineProgressUpdate::Error(e.to_string()),
+                            );
+                            Err(e)
+                        }
                     };
-
-                    send_engine_progress_update(engine, EngineProgressUpdate::Done);
-
-                    response
                 }
-                RequestResponse::Instant(response) => *response,
-                RequestResponse::None => EngineResponse::new(),
+                Err(e) => {
+                    error!("parse error for {engine}: {e}");
+                    send_engine_progress_update(engine, EngineProgressUpdate::Error(e.to_string()));
+                    return Err(e);
+                }
             };
+            for page in more_pages {
+                match page.and_then(|page| engine.parse_response(&page)) {
+                    Ok(page) => append_page(&mut response, page),
+                    // the first page is still useful without the others
+                    Err(e) => warn!("couldn't get another page from {engine}: {e}"),
+                }
+            }
+            // an error page would look like drift, but that's not what this is
+            // looking for
+            if http_response.res.status().is_success() {
+                drift::record(engine, response.search_results.len(), &query.config.drift);
+            }
+
+            send_engine_progress_update(
+                engine,
+                EngineProgressUpdate::Done(response.search_results.len()),
+            );
 
             Ok((engine, response))
         });
@@ -421,18 +1215,71 @@ async fn make_requests(
     let mut responses = HashMap::new();
     for response_result in join_all(requests).await {
         let response_result: eyre::Result<_> = response_result; // this line is necessary to make type inference work
-        if let Ok((engine, response)) = response_result {
+        if let Ok((engine, mut response)) = response_result {
+            // some engines ignore how many results we asked for
+            if let Some(max_results) = query.config.max_results_per_engine {
+                response.search_results.truncate(max_results);
+            }
             responses.insert(engine, response);
         }
     }
 
-    let response = ranking::merge_engine_responses(query.config.clone(), responses);
+    let mut response =
+        ranking::merge_engine_responses(query.config.clone(), responses, query.debug);
+    response.skipped_engines = skipped_engines;
+    // most engines ignore -term, so we remove those results ourselves
+    operators::remove_excluded_results(
+        &mut response.search_results,
+        &operators::negative_terms(&query.query),
+    );
+    QueryOperators::parse(&query.query).filter_results(&mut response.search_results);
+    host_labels::label_results(&mut response.search_results, query.label);
+    language_filter::filter_results(&mut response.search_results, &query.config);
+    if query.config.enrich_descriptions {
+        page_meta::add_cached(&mut response.search_results);
+    }
+    if query.config.wayback.enabled {
+        wayback::add_cached(&mut response.search_results, &query.config.wayback);
+    }
+    if query.tab == SearchTab::Recipes {
+        recipes::add_cached(&mut response.search_results);
+    }
+    if let (SearchTab::Jobs, Some(location)) = (query.tab, &query.config.geo.location) {
+        jobs::filter_by_location(&mut response.search_results, location);
+    }
+    if query.config.dead_links.enabled {
+        dead_links::flag_cached(&mut response.search_results);
+    }
+    response.refinements = refine::refinements(&query.query, &response.search_results);
     let has_infobox = response.infobox.is_some();
     progress_tx.send(ProgressUpdate::new(
-        ProgressUpdateData::Response(ResponseForTab::All(response.clone())),
+        ProgressUpdateData::Response(ResponseForTab::All(Box::new(response.clone()))),
         start_time,
     ))?;
 
+    // checked and looked up while the post-search requests are made
+    let dead_links_check = query.config.dead_links.enabled.then(|| {
+        let urls = response
+            .search_results
+            .iter()
+            .filter(|r| !r.result.extras.unreachable)
+            .map(|r| r.result.url.clone())
+            .collect();
+        tokio::spawn(dead_links::check(urls, query.config.dead_links.clone()))
+    });
+    let descriptions_fetch = query.config.enrich_descriptions.then(|| {
+        let urls = page_meta::uncached(&response.search_results);
+        tokio::spawn(page_meta::fetch(urls))
+    });
+    let recipes_fetch = (query.tab == SearchTab::Recipes).then(|| {
+        let results = recipes::uncached(&response.search_results);
+        tokio::spawn(recipes::fetch(results))
+    });
+    let wayback_lookup = query.config.wayback.enabled.then(|| {
+        let urls = wayback::uncached(&response.search_results, &query.config.wayback);
+        tokio::spawn(wayback::look_up(urls))
+    });
+
     if !has_infobox {
         // post-search
 
@@ -445,7 +1292,7 @@ async fn make_requests(
 
             if let Some(request) = engine.postsearch_request(&response).await {
                 postsearch_requests.push(async move {
-                    let response = match request.send().await {
+                    let response = match client::send(engine, request).await {
                         Ok(mut res) => {
                             let mut body_bytes = Vec::new();
                             while let Some(chunk) = res.chunk().await? {
@@ -471,10 +1318,7 @@ async fn make_requests(
         }
 
         let postsearch_responses_result: eyre::Result<HashMap<_, _>> =
-            join_all(postsearch_requests)
-                .await
-                .into_iter()
-                .collect();
+            join_all(postsearch_requests).await.into_iter().collect();
         let postsearch_responses = postsearch_responses_result?;
 
         for (engine, response) in postsearch_responses {
@@ -489,6 +1333,43 @@ async fn make_requests(
         }
     }
 
+    if let Some(dead_links_check) = dead_links_check {
+        let dead_links = dead_links_check.await?;
+        if !dead_links.is_empty() {
+            progress_tx.send(ProgressUpdate::new(
+                ProgressUpdateData::DeadLinks(dead_links),
+                start_time,
+            ))?;
+        }
+    }
+    if let Some(descriptions_fetch) = descriptions_fetch {
+        let descriptions = descriptions_fetch.await?;
+        if !descriptions.is_empty() {
+            progress_tx.send(ProgressUpdate::new(
+                ProgressUpdateData::Descriptions(descriptions),
+                start_time,
+            ))?;
+        }
+    }
+    if let Some(recipes_fetch) = recipes_fetch {
+        let recipes = recipes_fetch.await?;
+        if !recipes.is_empty() {
+            progress_tx.send(ProgressUpdate::new(
+                ProgressUpdateData::Recipes(recipes),
+                start_time,
+            ))?;
+        }
+    }
+    if let Some(wayback_lookup) = wayback_lookup {
+        let archived = wayback_lookup.await?;
+        if !archived.is_empty() {
+            progress_tx.send(ProgressUpdate::new(
+                ProgressUpdateData::Archived(archived),
+                start_time,
+            ))?;
+        }
+    }
+
     Ok(())
 }
 
@@ -498,6 +1379,22 @@ async fn make_image_requests(
     start_time: Instant,
     send_engine_progress_update: &impl Fn(Engine, EngineProgressUpdate),
 ) -> eyre::Result<()> {
+    let response = match prefetch::take_images(query) {
+        Some(response) => response,
+        None => fetch_images(query, send_engine_progress_update).await?,
+    };
+    progress_tx.send(ProgressUpdate::new(
+        ProgressUpdateData::Response(ResponseForTab::Images(response)),
+        start_time,
+    ))?;
+
+    Ok(())
+}
+
+async fn fetch_images(
+    query: &SearchQuery,
+    send_engine_progress_update: &impl Fn(Engine, EngineProgressUpdate),
+) -> eyre::Result<ImagesResponse> {
     let mut requests = Vec::new();
     for &engine in Engine::all() {
         let engine_config = query.config.engines.get(engine);
@@ -521,15 +1418,20 @@ async fn make_image_requests(
                         }
                     };
 
-                    send_engine_progress_update(engine, EngineProgressUpdate::Done);
+                    send_engine_progress_update(
+                        engine,
+                        EngineProgressUpdate::Done(response.image_results.len()),
+                    );
 
                     response
                 }
-                RequestResponse::Instant(_) => {
-                    error!("unexpected instant response for image request");
+                RequestResponse::Failover(_)
+                | RequestResponse::Pages(_)
+                | RequestResponse::Instant(_) => {
+                    error!("unexpected response type for image request");
                     EngineImagesResponse::new()
                 }
-                RequestResponse::None => EngineImagesResponse::new(),
+                RequestResponse::None | RequestResponse::Skipped(_) => EngineImagesResponse::new(),
             };
 
             Ok((engine, response))
@@ -545,13 +1447,9 @@ async fn make_image_requests(
         join_all(response_futures).await.into_iter().collect();
     let responses = responses_result?;
 
-    let response = ranking::merge_images_responses(query.config.clone(), responses);
-    progress_tx.send(ProgressUpdate::new(
-        ProgressUpdateData::Response(ResponseForTab::Images(response.clone())),
-        start_time,
-    ))?;
-
-    Ok(())
+    let mut response = ranking::merge_images_responses(query.config.clone(), responses);
+    image_probe::probe_images(&mut response).await;
+    Ok(response)
 }
 
 #[tracing::instrument(fields(query = %query.query), skip(progress_tx))]
@@ -559,6 +1457,23 @@ pub async fn search(
     query: &SearchQuery,
     progress_tx: mpsc::UnboundedSender<ProgressUpdate>,
 ) -> eyre::Result<()> {
+    coalesce::search(query, progress_tx, search_uncoalesced).await
+}
+
+/// Get the progress updates from an identical search that's already running,
+/// without searching again. Returns None if it isn't running.
+pub fn follow_search(
+    query: &SearchQuery,
+    progress_tx: mpsc::UnboundedSender<ProgressUpdate>,
+) -> Option<impl Future<Output = eyre::Result<()>>> {
+    coalesce::follow(query, progress_tx)
+}
+
+async fn search_uncoalesced(
+    query: SearchQuery,
+    progress_tx: mpsc::UnboundedSender<ProgressUpdate>,
+) -> eyre::Result<()> {
+    let query = &query;
     let start_time = Instant::now();
 
     info!("Doing search");
@@ -573,8 +1488,21 @@ pub async fn search(
 
     match query.tab {
         SearchTab::All => {
+            prefetch::prefetch_images(query);
             make_requests(query, progress_tx, start_time, &send_engine_progress_update).await?
         }
+        SearchTab::Code | SearchTab::Media | SearchTab::Jobs => {
+            make_requests(query, progress_tx, start_time, &send_engine_progress_update).await?
+        }
+        SearchTab::Recipes => {
+            make_requests(
+                &recipes::recipe_query(query),
+                progress_tx,
+                start_time,
+                &send_engine_progress_update,
+            )
+            .await?
+        }
         SearchTab::Images if query.config.image_search.enabled => {
             make_image_requests(query, progress_tx, start_time, &send_engine_progress_update)
                 .await?
@@ -587,10 +1515,20 @@ pub async fn search(
     Ok(())
 }
 
-pub async fn autocomplete(config: &Config, query: &str) -> eyre::Result<Vec<String>> {
+/// Get autocomplete suggestions from our engines and any configured peers.
+/// `include_peers` should be false if the request came from a peer.
+pub async fn autocomplete(
+    config: &Config,
+    query: &str,
+    include_peers: bool,
+) -> eyre::Result<Vec<String>> {
+    if !config.ui.show_autocomplete {
+        return Ok(Vec::new());
+    }
+
     let mut requests = Vec::new();
     for &engine in Engine::all() {
-        if !config.ui.show_autocomplete {
+        if !config.autocomplete.use_engines {
             break;
         }
 
@@ -603,7 +1541,7 @@ pub async fn autocomplete(config: &Config, query: &str) -> eyre::Result<Vec<Stri
             requests.push(async move {
                 let response = match request {
                     RequestAutocompleteResponse::Http(request) => {
-                        let res = request.send().await?;
+                        let res = client::send(engine, *request).await?;
                         let body = res.text().await?;
                         engine.parse_autocomplete_response(&body)?
                     }
@@ -619,36 +1557,59 @@ pub async fn autocomplete(config: &Config, query: &str) -> eyre::Result<Vec<Stri
         autocomplete_futures.push(request);
     }
 
-    let autocomplete_results_result: eyre::Result<HashMap<_, _>> =
+    let autocomplete_results_result: eyre::Result<Vec<_>> =
         join_all(autocomplete_futures).await.into_iter().collect();
-    let autocomplete_results = autocomplete_results_result?;
+    let mut autocomplete_results = autocomplete_results_result?
+        .into_iter()
+        .map(|(engine, response)| (config.engines.get(engine).weight, response))
+        .collect::<Vec<_>>();
+
+    if include_peers {
+        let peers = &config.autocomplete.peers;
+        let peer_responses = join_all(peers.iter().map(|peer| peers::autocomplete(peer, query)));
+        for (peer, response) in peers.iter().zip(peer_responses.await) {
+            match response {
+                Ok(response) => autocomplete_results.push((peer.weight, response)),
+                // peers being down shouldn't break autocomplete
+                Err(err) => warn!("autocomplete error from peer {}: {err}", peer.url),
+            }
+        }
+    }
 
-    Ok(ranking::merge_autocomplete_responses(
-        config,
-        autocomplete_results,
-    ))
+    Ok(ranking::merge_autocomplete_responses(autocomplete_results))
 }
 
-pub static CLIENT: LazyLock<wreq::Client> = LazyLock::new(|| {
-    wreq::ClientBuilder::new()
-        .local_address(IpAddr::from_str("0.0.0.0").unwrap())
-        // we pretend to be a normal browser so websites don't block us
-        .emulation(Emulation::Firefox139)
-        .timeout(Duration::from_secs(10))
-        .build()
-        .unwrap()
-});
+/// The client that engines use unless they have their own `http` settings, see
+/// [`client`].
+pub static CLIENT: LazyLock<wreq::Client> =
+    LazyLock::new(|| client::build(&client::http_config(), None));
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Response {
     pub search_results: Vec<SearchResult<EngineSearchResult>>,
     pub featured_snippet: Option<FeaturedSnippet>,
     pub answer: Option<Answer>,
+    /// The answer that lost to `answer`, if multiple answer engines matched.
+    pub secondary_answer: Option<Answer>,
+    pub skipped_engines: Vec<SkippedEngine>,
     pub infobox: Option<Infobox>,
+    pub refinements: Vec<refine::Refinement>,
+    /// The spelling suggestion from the engine with the highest weight that had
+    /// one.
+    pub corrected_query: Option<String>,
+    /// From every engine that had them, the ones from engines with higher
+    /// weights first.
+    pub related_searches: Vec<String>,
     #[serde(skip)]
     pub config: Arc<Config>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedEngine {
+    pub engine: Engine,
+    pub reason: &'static str,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ImagesResponse {
     pub image_results: Vec<SearchResult<EngineImageResult>>,
@@ -659,7 +1620,7 @@ pub struct ImagesResponse {
 #[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum ResponseForTab {
-    All(Response),
+    All(Box<Response>),
     Images(ImagesResponse),
 }
 
@@ -668,6 +1629,32 @@ pub struct SearchResult<R: Serialize> {
     pub result: R,
     pub engines: BTreeSet<Engine>,
     pub score: f64,
+    /// How the score was calculated. Only set if [`SearchQuery::debug`] is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Box<Explanation>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Explanation {
+    pub ranks: Vec<EngineRank>,
+    /// The multiplier from the `urls.weight` config.
+    pub url_weight: f64,
+    /// The engine that the title and description are from.
+    pub title_from: Engine,
+}
+
+/// Where an engine had a result, and how much that added to its score.
+#[derive(Debug, Clone, Serialize)]
+pub struct EngineRank {
+    pub engine: Engine,
+    /// Starts at 1.
+    pub position: usize,
+    /// The engine's weight from the config.
+    pub weight: f64,
+    pub score: f64,
+    /// The url as the engine returned it, before the url config was applied.
+    /// Results with the same url after that are merged.
+    pub url: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
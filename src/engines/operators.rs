@@ -0,0 +1,356 @@
+//! Parsing search operators out of queries so they can be translated for each
+//! engine or applied locally, since not every engine supports them.
+
+use url::Url;
+
+use super::{EngineSearchResult, SearchResult};
+
+/// Get the terms that were excluded from the query with `-term` or
+/// `-"some phrase"`. The returned terms are lowercase and phrases are split
+/// into words.
+pub fn negative_terms(query: &str) -> Vec<Vec<String>> {
+    let mut terms = Vec::new();
+
+    let mut rest = query;
+    while let Some(index) = rest.find('-') {
+        let is_start_of_word = rest[..index]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        let after = &rest[index + 1..];
+        if !is_start_of_word {
+            rest = after;
+            continue;
+        }
+
+        let (term, remaining) = if let Some(phrase) = after.strip_prefix('"') {
+            let end = phrase.find('"').unwrap_or(phrase.len());
+            (&phrase[..end], phrase.get(end + 1..).unwrap_or_default())
+        } else {
+            let end = after.find(char::is_whitespace).unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        rest = remaining;
+
+        // operators like -site: are handled separately, and things like "-5" are
+        // probably math
+        if term.contains(':') || term.parse::<f64>().is_ok() {
+            continue;
+        }
+
+        let words = split_words(term);
+        if !words.is_empty() {
+            terms.push(words);
+        }
+    }
+
+    terms
+}
+
+/// Remove results whose title, description, or host contain any of the given
+/// negative terms.
+pub fn remove_excluded_results(
+    results: &mut Vec<SearchResult<EngineSearchResult>>,
+    negative_terms: &[Vec<String>],
+) {
+    if negative_terms.is_empty() {
+        return;
+    }
+
+    results.retain(|r| {
+        let host = Url::parse(&r.result.url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_owned))
+            .unwrap_or_default();
+        let words = [r.result.title.as_str(), &r.result.description, &host]
+            .into_iter()
+            .flat_map(split_words)
+            .collect::<Vec<_>>();
+        !negative_terms
+            .iter()
+            .any(|term| words.windows(term.len()).any(|w| w == term.as_slice()))
+    });
+}
+
+/// An operator that some engines support natively. For engines that don't
+/// support it, it's removed from the query and the results are filtered
+/// locally instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `site:example.com`
+    Site,
+    /// `-site:example.com`
+    ExcludeSite,
+    /// `filetype:pdf`, or `ext:pdf`
+    Filetype,
+    /// `intitle:word` or `intitle:"some phrase"`
+    Intitle,
+}
+
+impl Operator {
+    /// Every operator, for engines that support all of them.
+    pub const ALL: &[Operator] = &[
+        Operator::Site,
+        Operator::ExcludeSite,
+        Operator::Filetype,
+        Operator::Intitle,
+    ];
+
+    fn format(self, value: &str) -> String {
+        match self {
+            Operator::Site => format!("site:{value}"),
+            Operator::ExcludeSite => format!("-site:{value}"),
+            Operator::Filetype => format!("filetype:{value}"),
+            Operator::Intitle if value.contains(' ') => format!("intitle:\"{value}\""),
+            Operator::Intitle => format!("intitle:{value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryOperators {
+    /// The query without any of the operators.
+    pub terms: String,
+    /// The operators and their normalized values, in the order they appeared.
+    pub operators: Vec<(Operator, String)>,
+}
+
+impl QueryOperators {
+    pub fn parse(query: &str) -> Self {
+        let mut terms = Vec::new();
+        let mut operators = Vec::new();
+
+        let mut rest = query.trim();
+        while !rest.is_empty() {
+            // quotes can contain spaces, like intitle:"some phrase"
+            let mut in_quotes = false;
+            let end = rest
+                .char_indices()
+                .find(|&(_, c)| {
+                    if c == '"' {
+                        in_quotes = !in_quotes;
+                    }
+                    c.is_whitespace() && !in_quotes
+                })
+                .map_or(rest.len(), |(i, _)| i);
+            let token = &rest[..end];
+            rest = rest[end..].trim_start();
+
+            match parse_operator(token) {
+                Some(operator) => operators.push(operator),
+                None => terms.push(token),
+            }
+        }
+
+        Self {
+            terms: terms.join(" "),
+            operators,
+        }
+    }
+
+    /// Build the query for an engine that supports the given operators. The
+    /// terms are wrapped in quotes if `quote_terms` is true.
+    pub fn to_query(&self, supported: &[Operator], quote_terms: bool) -> String {
+        let mut query = if quote_terms && !self.terms.is_empty() && !self.terms.contains('"') {
+            format!("\"{}\"", self.terms)
+        } else {
+            self.terms.clone()
+        };
+        for (operator, value) in &self.operators {
+            if supported.contains(operator) {
+                if !query.is_empty() {
+                    query.push(' ');
+                }
+                query.push_str(&operator.format(value));
+            }
+        }
+        query
+    }
+
+    /// Remove results that don't match the operators. This is done for every
+    /// engine since some of them only loosely follow the operators even when
+    /// they claim to support them.
+    pub fn filter_results(&self, results: &mut Vec<SearchResult<EngineSearchResult>>) {
+        if self.operators.is_empty() {
+            return;
+        }
+
+        let values = |kind: Operator| {
+            self.operators
+                .iter()
+                .filter(move |(operator, _)| *operator == kind)
+                .map(|(_, value)| value.as_str())
+                .collect::<Vec<_>>()
+        };
+        let sites = values(Operator::Site);
+        let excluded_sites = values(Operator::ExcludeSite);
+        let filetypes = values(Operator::Filetype);
+        let intitle = values(Operator::Intitle);
+
+        results.retain(|r| {
+            let Ok(url) = Url::parse(&r.result.url) else {
+                return false;
+            };
+            let title = r.result.title.to_lowercase();
+
+            (sites.is_empty() || sites.iter().any(|site| matches_site(&url, site)))
+                && !excluded_sites.iter().any(|site| matches_site(&url, site))
+                && (filetypes.is_empty()
+                    || filetypes
+                        .iter()
+                        .any(|ext| url.path().to_lowercase().ends_with(&format!(".{ext}"))))
+                && intitle
+                    .iter()
+                    .all(|word| title.contains(&word.to_lowercase()))
+        });
+    }
+}
+
+fn parse_operator(token: &str) -> Option<(Operator, String)> {
+    let (negated, token) = match token.strip_prefix('-') {
+        Some(token) => (true, token),
+        None => (false, token),
+    };
+    let (name, value) = token.split_once(':')?;
+    let value = value.trim_matches('"');
+    if value.is_empty() {
+        return None;
+    }
+
+    let operator = match (name.to_ascii_lowercase().as_str(), negated) {
+        ("site", false) => Operator::Site,
+        ("site", true) => Operator::ExcludeSite,
+        ("filetype" | "ext", false) => Operator::Filetype,
+        ("intitle", false) => Operator::Intitle,
+        _ => return None,
+    };
+    let value = match operator {
+        Operator::Site | Operator::ExcludeSite => normalize_site(value)?,
+        Operator::Filetype => value.trim_start_matches('.').to_ascii_lowercase(),
+        Operator::Intitle => value.to_string(),
+    };
+    Some((operator, value))
+}
+
+/// Turn things like `https://Example.com/` into `example.com`. Paths are kept
+/// since most engines support them.
+fn normalize_site(site: &str) -> Option<String> {
+    let site = site
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_end_matches('/');
+    let (host, path) = site.split_once('/').unwrap_or((site, ""));
+    if host.is_empty() {
+        return None;
+    }
+    let host = host.to_ascii_lowercase();
+    Some(if path.is_empty() {
+        host
+    } else {
+        format!("{host}/{path}")
+    })
+}
+
+fn matches_site(url: &Url, site: &str) -> bool {
+    let (site_host, site_path) = site.split_once('/').unwrap_or((site, ""));
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let host_matches = host == site_host || host.ends_with(&format!(".{site_host}"));
+    host_matches && url.path().trim_start_matches('/').starts_with(site_path)
+}
+
+fn split_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negative_terms() {
+        assert_eq!(
+            negative_terms("rust -game -\"steam store\" async-std"),
+            vec![
+                vec!["game".to_string()],
+                vec!["steam".to_string(), "store".to_string()]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_negative_terms_ignores_operators_and_numbers() {
+        assert!(negative_terms("cats -site:pinterest.com").is_empty());
+        assert!(negative_terms("10 -5").is_empty());
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        let parsed = QueryOperators::parse(
+            "rust Site:https://GitHub.com/ -site:reddit.com ext:.PDF intitle:\"async traits\"",
+        );
+        assert_eq!(parsed.terms, "rust");
+        assert_eq!(
+            parsed.operators,
+            vec![
+                (Operator::Site, "github.com".to_string()),
+                (Operator::ExcludeSite, "reddit.com".to_string()),
+                (Operator::Filetype, "pdf".to_string()),
+                (Operator::Intitle, "async traits".to_string()),
+            ]
+        );
+        assert_eq!(
+            parsed.to_query(&[Operator::Site, Operator::Intitle], true),
+            "\"rust\" site:github.com intitle:\"async traits\""
+        );
+        assert_eq!(parsed.to_query(&[], false), "rust");
+    }
+
+    #[test]
+    fn test_filter_results() {
+        let mut results = vec![
+            result("https://docs.rs/tokio/latest/tokio.pdf", "Tokio docs"),
+            result("https://www.rust-lang.org/learn", "Learn Rust"),
+            result("https://blog.rust-lang.org/paper.pdf", "Rust paper"),
+            result("https://rust-lang.org.evil.com/paper.pdf", "Rust paper"),
+        ];
+        QueryOperators::parse("rust site:rust-lang.org filetype:pdf intitle:paper")
+            .filter_results(&mut results);
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].result.url,
+            "https://blog.rust-lang.org/paper.pdf"
+        );
+    }
+
+    fn result(url: &str, title: &str) -> SearchResult<EngineSearchResult> {
+        SearchResult {
+            result: EngineSearchResult {
+                url: url.to_string(),
+                title: title.to_string(),
+                description: String::new(),
+                description_from_page: false,
+                extras: Default::default(),
+            },
+            engines: Default::default(),
+            score: 1.,
+            explanation: None,
+        }
+    }
+
+    #[test]
+    fn test_remove_excluded_results() {
+        let mut results = vec![
+            result("https://www.rust-lang.org", "Rust Programming Language"),
+            result("https://store.steampowered.com/app/252490", "Rust on Steam"),
+            result("https://www.pinterest.com/rust", "Rust pins"),
+        ];
+        remove_excluded_results(&mut results, &negative_terms("rust -steam -pinterest"));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].result.url, "https://www.rust-lang.org");
+    }
+}
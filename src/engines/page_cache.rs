@@ -0,0 +1,69 @@
+//! A cache for what we found out about the pages in the results, like their
+//! descriptions or whether they were archived, and for searches that just
+//! finished. Entries expire, and when it's full the least recently used ones
+//! make room for new ones.
+
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use parking_lot::Mutex;
+
+pub struct PageCache<V> {
+    ttl: Duration,
+    entries: Mutex<LruCache<String, (Instant, V)>>,
+}
+
+impl<V: Clone> PageCache<V> {
+    pub fn new(max_entries: usize, ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(LruCache::new(
+                NonZeroUsize::new(max_entries).expect("max_entries must be more than 0"),
+            )),
+        }
+    }
+
+    /// The value for the key (usually a url), or None if it isn't cached or
+    /// it expired.
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut entries = self.entries.lock();
+        match entries.get(key) {
+            Some((time, value)) if time.elapsed() < self.ttl => return Some(value.clone()),
+            Some(_) => {}
+            None => return None,
+        }
+        entries.pop(key);
+        None
+    }
+
+    pub fn insert(&self, key: String, value: V) {
+        self.entries.lock().put(key, (Instant::now(), value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = PageCache::new(2, Duration::from_secs(60));
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+        assert_eq!(cache.get("a"), Some(1));
+        cache.insert("c".to_string(), 3);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("c"), Some(3));
+    }
+
+    #[test]
+    fn test_expires() {
+        let cache = PageCache::new(2, Duration::ZERO);
+        cache.insert("a".to_string(), 1);
+        assert_eq!(cache.get("a"), None);
+    }
+}
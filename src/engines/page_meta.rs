@@ -0,0 +1,140 @@
+//! Getting descriptions from the meta tags of result pages, for results that
+//! the engines didn't give a description for. The descriptions that were
+//! already fetched are shown with the results, and the rest are fetched after
+//! the results are sent, like the dead link checks.
+
+use std::{sync::LazyLock, time::Duration};
+
+use futures::future::join_all;
+use scraper::{Html, Selector};
+use tracing::debug;
+
+use super::{client, page_cache::PageCache, EngineSearchResult, SearchResult, CLIENT};
+
+const TTL: Duration = Duration::from_secs(60 * 60);
+const MAX_ENTRIES: usize = 1000;
+/// Only the first few results without a description are fetched, so a page of
+/// bare links doesn't make us download dozens of websites.
+const MAX_FETCHES: usize = 5;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+/// The meta tags are in the head, so there's no point downloading the rest.
+const MAX_PAGE_SIZE: usize = 256 * 1024;
+
+/// Descriptions by url. Pages that didn't have one are cached as `None` so we
+/// don't keep fetching them.
+static CACHE: LazyLock<PageCache<Option<String>>> =
+    LazyLock::new(|| PageCache::new(MAX_ENTRIES, TTL));
+
+/// Fill in the descriptions of results that don't have one from the cache.
+pub fn add_cached(results: &mut [SearchResult<EngineSearchResult>]) {
+    for result in results
+        .iter_mut()
+        .filter(|r| r.result.description.is_empty())
+    {
+        if let Some(Some(description)) = CACHE.get(&result.result.url) {
+            result.result.description = description;
+            result.result.description_from_page = true;
+        }
+    }
+}
+
+/// The results without a description that still have to be fetched, with
+/// their indexes (starting at 1, like the `#result-N` anchors).
+pub fn uncached(results: &[SearchResult<EngineSearchResult>]) -> Vec<(usize, String)> {
+    results
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.result.description.is_empty() && CACHE.get(&r.result.url).is_none())
+        .take(MAX_FETCHES)
+        .map(|(i, r)| (i + 1, r.result.url.clone()))
+        .collect()
+}
+
+/// Fetch the pages all at once, and return the descriptions that were found.
+pub async fn fetch(urls: Vec<(usize, String)>) -> Vec<(usize, String)> {
+    join_all(urls.into_iter().map(|(index, url)| async move {
+        let description = match fetch_html(&url, MAX_PAGE_SIZE).await {
+            Ok(html) => html.and_then(|html| parse_description(&html)),
+            // errors aren't cached, so the page is fetched again next time
+            Err(err) => {
+                debug!("couldn't get description for {url}: {err}");
+                return None;
+            }
+        };
+        CACHE.insert(url, description.clone());
+        Some((index, description?))
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Download the start of a result page, or None if it isn't html.
+pub(super) async fn fetch_html(url: &str, max_size: usize) -> eyre::Result<Option<String>> {
+    let mut res = client::send_to_page(
+        CLIENT
+            .get(url)
+            .header("accept", "text/html")
+            .timeout(FETCH_TIMEOUT),
+    )
+    .await?;
+    let is_html = res
+        .headers()
+        .get(wreq::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    if !is_html {
+        return Ok(None);
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() > max_size {
+            break;
+        }
+    }
+
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn parse_description(html: &str) -> Option<String> {
+    let dom = Html::parse_document(html);
+    [
+        "meta[name='description']",
+        "meta[property='og:description']",
+        "meta[name='twitter:description']",
+    ]
+    .iter()
+    .filter_map(|s| dom.select(&Selector::parse(s).unwrap()).next())
+    .filter_map(|el| el.value().attr("content"))
+    .map(|content| content.split_whitespace().collect::<Vec<_>>().join(" "))
+    .find(|content| !content.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_description() {
+        assert_eq!(
+            parse_description(
+                r#"<html><head>
+                <meta property="og:description" content="  An  example
+                    page. ">
+                <meta name="description" content="">
+                </head><body>hi</body></html>"#
+            ),
+            Some("An example page.".to_string())
+        );
+        assert_eq!(
+            parse_description(
+                r#"<meta name="description" content="First"><meta property="og:description" content="Second">"#
+            ),
+            Some("First".to_string())
+        );
+        assert_eq!(parse_description("<title>No meta</title>"), None);
+    }
+}
@@ -0,0 +1,45 @@
+//! Getting autocomplete suggestions from other metasearch or SearXNG
+//! instances.
+
+use std::time::Duration;
+
+use url::Url;
+
+use super::CLIENT;
+use crate::config::{AutocompletePeer, PeerKind};
+
+/// Sent with requests to peers so they don't ask their own peers, which would
+/// loop forever if two instances had each other as peers.
+pub const FEDERATED_HEADER: &str = "x-metasearch-federated";
+
+/// Peers are only there to help, so a slow one shouldn't hold up autocomplete.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+pub async fn autocomplete(peer: &AutocompletePeer, query: &str) -> eyre::Result<Vec<String>> {
+    let path = match peer.kind {
+        PeerKind::Metasearch => "autocomplete",
+        PeerKind::Searxng => "autocompleter",
+    };
+    // the trailing slash makes join keep the path, for instances that aren't at the
+    // root of their domain
+    let mut url = Url::parse(&format!("{}/", peer.url.trim_end_matches('/')))?.join(path)?;
+    url.query_pairs_mut().append_pair("q", query);
+
+    let mut request = CLIENT
+        .get(url)
+        .header(FEDERATED_HEADER, "1")
+        .timeout(TIMEOUT);
+    if let Some(token) = &peer.token {
+        request = request.header("Authorization", format!("Bearer {token}"));
+    }
+
+    let body = request.send().await?.text().await?;
+    parse_response(&body)
+}
+
+fn parse_response(body: &str) -> eyre::Result<Vec<String>> {
+    // both metasearch and searxng use the opensearch suggestions format, which is
+    // [query, [suggestions...]]
+    let (_, suggestions): (String, Vec<String>) = serde_json::from_str(body)?;
+    Ok(suggestions)
+}
@@ -0,0 +1,264 @@
+//! Engines that are defined in the config instead of code, so operators can add
+//! niche engines (like a company wiki) without recompiling. Each `.toml` file
+//! in `plugins.directory` is one engine named after the file, and so is each
+//! `[engines.custom.<id>]` section:
+//!
+//! ```toml
+//! url_template = "https://wiki.example.com/search?q={query}"
+//! result_selector = "li.result"
+//! title_selector = "a.title"
+//! href_selector = "a.title"
+//! description_selector = "p.snippet"
+//! ```
+//!
+//! There's no scripting for engines that need more than selectors, Rhai and
+//! WASM runtimes are big dependencies for that and neither is bundled. Those
+//! engines should be added in `src/engines/search/` instead.
+
+use std::{fs, path::Path};
+
+use eyre::eyre;
+use futures::future::BoxFuture;
+use scraper::Selector;
+use serde::Deserialize;
+use tracing::{error, info};
+use url::Url;
+
+use super::{
+    registry, Capabilities, Engine, EngineImpl, EngineResponse, HttpResponse, RequestResponse,
+    SearchQuery, SearchTab, CLIENT,
+};
+use crate::{
+    parse::{parse_html_response_with_opts, ParseOpts},
+    urls::normalize_url,
+};
+
+/// How to search a site and find the results in its html.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ScraperDefinition {
+    /// The search url, with `{query}` where the query goes.
+    pub url_template: String,
+    /// A css selector for each result.
+    pub result_selector: String,
+    /// The rest of the selectors are inside each result.
+    pub title_selector: String,
+    /// The element's `href`, or its text if it doesn't have one.
+    pub href_selector: String,
+    pub description_selector: Option<String>,
+    /// The tab the results are shown on, like "code". Defaults to "all".
+    pub tab: Option<String>,
+}
+
+/// An engine that gets results by scraping a page with css selectors.
+pub struct ScraperEngine {
+    url_template: String,
+    tab: SearchTab,
+    // leaked since ParseOpts only takes static strings, engines are only made at
+    // startup
+    result: &'static str,
+    title: &'static str,
+    href: &'static str,
+    description: Option<&'static str>,
+}
+
+impl ScraperEngine {
+    pub fn new(definition: ScraperDefinition) -> eyre::Result<Self> {
+        if !definition.url_template.contains("{query}") {
+            return Err(eyre!("url_template doesn't have {{query}} in it"));
+        }
+        Url::parse(&definition.url_template.replace("{query}", ""))?;
+        let tab = match definition.tab {
+            Some(tab) => tab
+                .parse()
+                .map_err(|()| eyre!("there's no tab called {tab:?}"))?,
+            None => SearchTab::All,
+        };
+
+        Ok(Self {
+            url_template: definition.url_template,
+            tab,
+            result: selector(definition.result_selector)?,
+            title: selector(definition.title_selector)?,
+            href: selector(definition.href_selector)?,
+            description: definition.description_selector.map(selector).transpose()?,
+        })
+    }
+
+    fn url(&self, query: &str) -> String {
+        self.url_template
+            .replace("{query}", &urlencoding::encode(query))
+    }
+
+    fn parse(&self, body: &str) -> eyre::Result<EngineResponse> {
+        let mut opts = ParseOpts::new()
+            .result(self.result)
+            .title(self.title)
+            .href(self.href);
+        if let Some(description) = self.description {
+            opts = opts.description(description);
+        }
+        let mut response = parse_html_response_with_opts(body, opts)?;
+
+        // sites usually link to their own pages with relative urls
+        let base = Url::parse(&self.url(""))?;
+        for result in &mut response.search_results {
+            if Url::parse(&result.url).is_err() {
+                if let Ok(url) = base.join(&result.url) {
+                    result.url = normalize_url(url.as_str());
+                }
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Check that the selector is valid, since [`ParseOpts`] panics otherwise.
+fn selector(selector: String) -> eyre::Result<&'static str> {
+    Selector::parse(&selector).map_err(|_| eyre!("invalid css selector {selector:?}"))?;
+    Ok(String::leak(selector))
+}
+
+impl EngineImpl for ScraperEngine {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            web: true,
+            ..Default::default()
+        }
+    }
+
+    fn tab(&self) -> SearchTab {
+        self.tab
+    }
+
+    fn request<'a>(
+        &'a self,
+        query: &'a SearchQuery,
+    ) -> BoxFuture<'a, eyre::Result<RequestResponse>> {
+        let url = self.url(&query.quoted_if_verbatim(&[]));
+        Box::pin(async move { Ok(RequestResponse::Http(Box::new(CLIENT.get(url)))) })
+    }
+
+    fn parse_response(&self, res: &HttpResponse) -> eyre::Result<EngineResponse> {
+        self.parse(&res.body)
+    }
+}
+
+/// Register an engine from an `[engines.custom.<id>]` section. The rest of the
+/// section (like `weight`) is read with the other engine configs.
+pub fn register_custom(id: &str, mut extra: toml::Table) -> eyre::Result<Engine> {
+    // read by the engines' http clients, see `engines::client`, and by
+    // `engines::rewrite`
+    extra.remove("http");
+    extra.remove("rewrite_query");
+    let definition: ScraperDefinition = toml::Value::Table(extra).try_into()?;
+    Ok(registry::register(id, ScraperEngine::new(definition)?))
+}
+
+/// Register every engine in the plugins directory. This happens before the rest
+/// of the config is read, so the engines can be configured in `[engines]`.
+pub fn load(directory: &Path) -> Vec<Engine> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Couldn't read the plugins directory {directory:?}: {err}");
+            return Vec::new();
+        }
+    };
+
+    let mut engines = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "toml") {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let engine = fs::read_to_string(&path)
+            .map_err(eyre::Report::from)
+            .and_then(|s| Ok(toml::from_str::<ScraperDefinition>(&s)?))
+            .and_then(ScraperEngine::new);
+        match engine {
+            Ok(engine) => {
+                info!("Loaded the {id} engine from {path:?}");
+                engines.push(registry::register(id, engine));
+            }
+            Err(err) => error!("Couldn't load the engine in {path:?}: {err}"),
+        }
+    }
+    engines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> ScraperEngine {
+        ScraperEngine::new(
+            toml::from_str(
+                r#"
+                url_template = "https://wiki.example.com/search?q={query}"
+                result_selector = "li.result"
+                title_selector = "a"
+                href_selector = "a"
+                description_selector = "p"
+                tab = "code"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_scraper_engine() {
+        let engine = engine();
+        assert_eq!(
+            engine.url("rust & c"),
+            "https://wiki.example.com/search?q=rust%20%26%20c"
+        );
+        assert_eq!(engine.tab, SearchTab::Code);
+
+        let response = engine
+            .parse(
+                r#"<ul>
+                <li class="result"><a href="/wiki/Rust">Rust</a><p>A language</p></li>
+                <li class="result"><a href="https://example.org/c">C</a></li>
+                </ul>"#,
+            )
+            .unwrap();
+        let results = &response.search_results;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].url, "https://wiki.example.com/wiki/Rust");
+        assert_eq!(results[0].description, "A language");
+        assert_eq!(results[1].url, "https://example.org/c");
+    }
+
+    #[test]
+    fn test_invalid_definitions() {
+        let definition = |url_template: &str, result_selector: &str| ScraperDefinition {
+            url_template: url_template.to_owned(),
+            result_selector: result_selector.to_owned(),
+            title_selector: "a".to_owned(),
+            href_selector: "a".to_owned(),
+            description_selector: None,
+            tab: None,
+        };
+        assert!(ScraperEngine::new(definition("https://example.com/?q={query}", "li")).is_ok());
+        assert!(ScraperEngine::new(definition("https://example.com/", "li")).is_err());
+        assert!(ScraperEngine::new(definition("https://example.com/?q={query}", "li[")).is_err());
+
+        // typos in the config aren't ignored
+        let typo: Result<ScraperDefinition, _> = toml::from_str(
+            r#"
+            url_template = "https://example.com/?q={query}"
+            result_selector = "li"
+            title_selector = "a"
+            href_selector = "a"
+            descripton_selector = "p"
+            "#,
+        );
+        assert!(typo.is_err());
+    }
+}
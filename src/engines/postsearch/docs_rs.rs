@@ -1,7 +1,7 @@
 use maud::{html, PreEscaped};
 use scraper::{Html, Selector};
 
-use crate::engines::{HttpResponse, Response, CLIENT};
+use crate::engines::{sanitize, HttpResponse, Response, CLIENT};
 
 pub async fn request(response: &Response) -> Option<wreq::RequestBuilder> {
     for search_result in response.search_results.iter().take(8) {
@@ -46,11 +46,7 @@ pub fn parse_response(HttpResponse { res, body, .. }: &HttpResponse) -> Option<P
         .map(|el| el.html())
         .unwrap_or_default();
 
-    let doc_html = ammonia::Builder::default()
-        .link_rel(None)
-        .url_relative(ammonia::UrlRelative::RewriteWithBase(url.clone()))
-        .clean(&format!("{item_decl}{doc_html}"))
-        .to_string();
+    let doc_html = sanitize::clean(&format!("{item_decl}{doc_html}"), url.clone());
 
     let (category, title) = page_title.split_once(' ').unwrap_or(("", &page_title));
 
@@ -2,7 +2,7 @@ use maud::{html, PreEscaped};
 use scraper::{Html, Selector};
 use url::Url;
 
-use crate::engines::{answer::regex, Response, CLIENT};
+use crate::engines::{answer::regex, sanitize, Response, CLIENT};
 
 pub async fn request(response: &Response) -> Option<wreq::RequestBuilder> {
     for search_result in response.search_results.iter().take(8) {
@@ -42,13 +42,9 @@ pub fn parse_response(body: &str) -> Option<PreEscaped<String>> {
         .get("richText")?
         .as_str()?;
 
-    let mut readme_html = ammonia::Builder::default()
-        .link_rel(None)
+    let mut readme_html = sanitize::builder(Url::parse("https://github.com").unwrap())
         .add_allowed_classes("div", &["markdown-alert"])
         .add_allowed_classes("p", &["markdown-alert-title"])
-        .url_relative(ammonia::UrlRelative::RewriteWithBase(
-            Url::parse("https://github.com").unwrap(),
-        ))
         .clean(readme_html)
         .to_string();
 
@@ -3,7 +3,7 @@ use scraper::{Html, Selector};
 use serde::Deserialize;
 use tracing::error;
 
-use crate::engines::{Engine, HttpResponse, Response, CLIENT};
+use crate::engines::{sanitize, Engine, HttpResponse, Response, CLIENT};
 
 #[derive(Deserialize)]
 pub struct MdnConfig {
@@ -63,11 +63,7 @@ pub fn parse_response(
         .collect::<Vec<_>>()
         .join("<br>");
 
-    let doc_html = ammonia::Builder::default()
-        .link_rel(None)
-        .url_relative(ammonia::UrlRelative::RewriteWithBase(url.clone()))
-        .clean(&doc_html)
-        .to_string();
+    let doc_html = sanitize::clean(&doc_html, url.clone());
 
     Some(html! {
         h2 {
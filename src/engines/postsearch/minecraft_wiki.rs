@@ -1,7 +1,7 @@
 use maud::{html, PreEscaped};
 use scraper::{Html, Selector};
 
-use crate::engines::{HttpResponse, Response, CLIENT};
+use crate::engines::{sanitize, HttpResponse, Response, CLIENT};
 
 pub async fn request(response: &Response) -> Option<wreq::RequestBuilder> {
     for search_result in response.search_results.iter().take(8) {
@@ -38,11 +38,9 @@ pub fn parse_response(HttpResponse { res, body, .. }: &HttpResponse) -> Option<P
         .map(|doc| doc.html())
         .unwrap_or_default();
 
-    let doc_html = ammonia::Builder::default()
-        .link_rel(None)
+    let doc_html = sanitize::builder(url.clone())
         .add_allowed_classes("div", ["notaninfobox", "mcw-mainpage-icon"])
         .add_allowed_classes("pre", ["noexcerpt", "navigation-not-searchable"])
-        .url_relative(ammonia::UrlRelative::RewriteWithBase(url.clone()))
         .clean(&doc_html)
         .to_string();
 
@@ -2,11 +2,11 @@ use maud::{html, PreEscaped};
 use scraper::{Html, Selector};
 use url::Url;
 
-use crate::engines::{answer::regex, Response, CLIENT};
+use crate::engines::{answer::regex, sanitize, Response, CLIENT};
 
 pub async fn request(response: &Response) -> Option<wreq::RequestBuilder> {
     for search_result in response.search_results.iter().take(8) {
-        if regex!(r"^https:\/\/(stackoverflow\.com|serverfault\.com|superuser\.com|\w{1,}\.stackexchange\.com)\/questions\/\d+")
+        if regex!(r"^https:\/\/(stackoverflow\.com|serverfault\.com|superuser\.com|askubuntu\.com|\w{1,}\.stackexchange\.com)\/questions\/\d+")
             .is_match(&search_result.result.url)
         {
             return Some(CLIENT.get(search_result.result.url.as_str()));
@@ -49,10 +49,7 @@ pub fn parse_response(body: &str) -> Option<PreEscaped<String>> {
         .html()
         .to_string();
 
-    let answer_html = ammonia::Builder::default()
-        .url_relative(ammonia::UrlRelative::RewriteWithBase(url.clone()))
-        .clean(&answer_html)
-        .to_string();
+    let answer_html = sanitize::clean(&answer_html, url.clone());
 
     let url = format!("{url}#{answer_id}");
 
@@ -0,0 +1,60 @@
+//! Fetching the images tab in the background while the user is on the "all"
+//! tab, so switching tabs is instant.
+
+use std::{
+    collections::HashMap,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use tracing::error;
+
+use super::{Engine, EngineProgressUpdate, ImagesResponse, SearchQuery, SearchTab};
+
+/// How long prefetched results are kept. Most people switch tabs right after
+/// searching, if at all.
+const TTL: Duration = Duration::from_secs(60 * 5);
+const MAX_ENTRIES: usize = 100;
+
+static CACHE: LazyLock<Mutex<HashMap<String, (Instant, ImagesResponse)>>> =
+    LazyLock::new(Default::default);
+
+/// Start fetching the images tab for this query in the background, if
+/// prefetching is enabled. The requests go through the scheduler like any
+/// other search, so they share its limits for each host.
+pub fn prefetch_images(query: &SearchQuery) {
+    let image_search = &query.config.image_search;
+    if !image_search.enabled || !image_search.prefetch {
+        return;
+    }
+    let query = SearchQuery {
+        tab: SearchTab::Images,
+        ..query.clone()
+    };
+    let Some(key) = query.cache_key() else {
+        return;
+    };
+    if CACHE.lock().contains_key(&key) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        match super::fetch_images(&query, &|_: Engine, _: EngineProgressUpdate| {}).await {
+            Ok(response) => {
+                let mut cache = CACHE.lock();
+                cache.retain(|_, (time, _)| time.elapsed() < TTL);
+                if cache.len() < MAX_ENTRIES {
+                    cache.insert(key, (Instant::now(), response));
+                }
+            }
+            Err(err) => error!("error prefetching images: {err}"),
+        }
+    });
+}
+
+/// Get the prefetched images for this query, if there are any.
+pub fn take_images(query: &SearchQuery) -> Option<ImagesResponse> {
+    let (time, response) = CACHE.lock().remove(&query.cache_key()?)?;
+    (time.elapsed() < TTL).then_some(response)
+}
@@ -0,0 +1,453 @@
+//! Syntax that's parsed out of the query before it's sent to the engines, and
+//! the pipeline of steps that every query goes through first.
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+use whatlang::Lang;
+
+use super::{Engine, Operator, QueryOperators, RequestResponse, SearchQuery, SearchTab};
+use crate::config::Config;
+
+/// Something that looks at or changes a query before it's searched.
+pub trait QueryPreprocessor {
+    fn process(&self, query: &mut PreprocessedQuery);
+}
+
+/// The built-in steps, which run in the order they're listed in
+/// `preprocess.steps` in the config.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PreprocessStep {
+    Trim,
+    /// Turn newlines, tabs, and runs of spaces into single spaces.
+    NormalizeWhitespace,
+    StripEmoji,
+    /// Take `!only`, `!random`, and tab bangs like `!code` out of the query.
+    DetectBangs,
+    /// Find operators like `site:`, for the engines that don't support them.
+    DetectOperators,
+    /// Notice queries that an instant answer is for, like "define rust".
+    DetectAnswerTriggers,
+}
+
+pub const DEFAULT_STEPS: &[PreprocessStep] = &[
+    PreprocessStep::Trim,
+    PreprocessStep::NormalizeWhitespace,
+    PreprocessStep::DetectBangs,
+    PreprocessStep::DetectOperators,
+    PreprocessStep::DetectAnswerTriggers,
+];
+
+/// A query after the steps ran, with what they found in it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PreprocessedQuery {
+    pub query: String,
+    pub bang: Option<Bang>,
+    pub info: QueryInfo,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bang {
+    Only(Vec<Engine>),
+    Tab(SearchTab),
+    /// Go to a random site from Wiby instead of searching.
+    Random,
+}
+
+/// What the steps found out about a query, which is kept in the
+/// [`SearchQuery`] for [`engine_policy`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryInfo {
+    /// The operators in the query, in the order they appeared.
+    pub operators: Vec<Operator>,
+    /// Whether the query looks like it's asking for an instant answer.
+    pub answer_trigger: bool,
+}
+
+impl QueryPreprocessor for PreprocessStep {
+    fn process(&self, query: &mut PreprocessedQuery) {
+        match self {
+            PreprocessStep::Trim => query.query = query.query.trim().to_string(),
+            PreprocessStep::NormalizeWhitespace => {
+                query.query = query.query.split_whitespace().collect::<Vec<_>>().join(" ");
+            }
+            PreprocessStep::StripEmoji => {
+                query.query = query.query.chars().filter(|&c| !is_emoji(c)).collect();
+            }
+            PreprocessStep::DetectBangs => {
+                if let Some((engines, rest)) = parse_only_engines(&query.query) {
+                    query.bang = Some(Bang::Only(engines));
+                    query.query = rest.to_string();
+                } else if let Some((tab, rest)) = parse_tab_bang(&query.query) {
+                    query.bang = Some(Bang::Tab(tab));
+                    query.query = rest.to_string();
+                } else if query.query == "!random" {
+                    query.bang = Some(Bang::Random);
+                    query.query.clear();
+                }
+            }
+            PreprocessStep::DetectOperators => {
+                query.info.operators = QueryOperators::parse(&query.query)
+                    .operators
+                    .into_iter()
+                    .map(|(operator, _)| operator)
+                    .collect();
+            }
+            PreprocessStep::DetectAnswerTriggers => {
+                query.info.answer_trigger = is_answer_trigger(&query.query);
+            }
+        }
+    }
+}
+
+/// Run the steps on the query, in order.
+pub fn preprocess(query: &str, steps: &[impl QueryPreprocessor]) -> PreprocessedQuery {
+    let mut preprocessed = PreprocessedQuery {
+        query: query.to_string(),
+        ..Default::default()
+    };
+    for step in steps {
+        step.process(&mut preprocessed);
+    }
+    preprocessed
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c,
+        '\u{1f000}'..='\u{1faff}'
+        | '\u{2600}'..='\u{27bf}'
+        // variation selector and zero width joiner, which emoji are built with
+        | '\u{fe0f}'
+        | '\u{200d}'
+    )
+}
+
+/// Starts of queries that the instant answers are for.
+const ANSWER_PREFIXES: &[&str] = &[
+    "define ",
+    "dns ",
+    "dig ",
+    "nslookup ",
+    "synonyms for ",
+    "synonym for ",
+    "tldr ",
+    "cheat ",
+    "time in ",
+    "lyrics ",
+];
+
+/// Whether the query looks like it's for an instant answer, like "define rust"
+/// or "2 + 2".
+fn is_answer_trigger(query: &str) -> bool {
+    let query = query.to_lowercase();
+    let is_math = query.chars().any(|c| c.is_ascii_digit())
+        && query.chars().any(|c| "+-*/^%".contains(c))
+        && query
+            .chars()
+            .all(|c| c.is_ascii_digit() || " .,+-*/^%()".contains(c));
+    is_math
+        || ANSWER_PREFIXES
+            .iter()
+            .any(|prefix| query.starts_with(prefix))
+        || matches!(query.as_str(), "my ip" | "what is my ip" | "user agent")
+}
+
+/// Whether an engine shouldn't be sent the query, because it would only return
+/// noise for it. These rules are here instead of in each engine so they're in
+/// one place.
+pub fn engine_policy(engine: Engine, query: &SearchQuery) -> Option<RequestResponse> {
+    let has_site = query.info.operators.contains(&Operator::Site);
+    if engine == Engine::Marginalia {
+        // it only does well with a few plain words
+        let terms = QueryOperators::parse(query).terms;
+        if query.info.answer_trigger
+            || terms.split_whitespace().count() > 3
+            || !terms.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ')
+        {
+            return Some(RequestResponse::None);
+        }
+    }
+    if matches!(
+        engine,
+        Engine::Yep | Engine::RightDao | Engine::Wiby | Engine::Mwmbl
+    ) && has_site
+    {
+        // without site: the results would almost all get filtered out
+        // afterwards
+        return Some(RequestResponse::Skipped("doesn't support site:"));
+    }
+    if engine == Engine::Brave && query.verbatim {
+        // brave ignores quotes and doesn't have a verbatim option, so its
+        // results would just be noise
+        return Some(RequestResponse::Skipped("doesn't support verbatim search"));
+    }
+    None
+}
+
+/// Parse a `!only google,brave` prefix from the query. Returns the engines and
+/// the rest of the query, or None if the query doesn't start with a valid
+/// `!only`.
+pub fn parse_only_engines(query: &str) -> Option<(Vec<Engine>, &str)> {
+    let rest = query.strip_prefix("!only ")?.trim_start();
+    let (engine_list, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    let engines = parse_engine_list(engine_list);
+    if engines.is_empty() {
+        return None;
+    }
+    Some((engines, rest.trim()))
+}
+
+/// Bangs that search a tab, like `!podcast`. There's no news or maps tab, so
+/// `!news` and `!maps` aren't bangs.
+const TAB_BANGS: &[(&str, SearchTab)] = &[
+    ("!all", SearchTab::All),
+    ("!i", SearchTab::Images),
+    ("!img", SearchTab::Images),
+    ("!image", SearchTab::Images),
+    ("!images", SearchTab::Images),
+    ("!code", SearchTab::Code),
+    ("!media", SearchTab::Media),
+    ("!podcast", SearchTab::Media),
+    ("!podcasts", SearchTab::Media),
+    ("!recipe", SearchTab::Recipes),
+    ("!recipes", SearchTab::Recipes),
+    ("!jobs", SearchTab::Jobs),
+];
+
+/// Parse a bang like `!podcast` from the start or end of the query. Returns the
+/// tab and the rest of the query.
+pub fn parse_tab_bang(query: &str) -> Option<(SearchTab, &str)> {
+    TAB_BANGS.iter().find_map(|(bang, tab)| {
+        let rest = query
+            .strip_prefix(bang)
+            .filter(|rest| rest.starts_with(' '))
+            .or_else(|| query.strip_suffix(bang).filter(|rest| rest.ends_with(' ')))?
+            .trim();
+        (!rest.is_empty()).then_some((*tab, rest))
+    })
+}
+
+/// Top-level domains that are common enough that `example.com rust` is
+/// probably a site, and not a file like `main.rs` or `index.js`.
+const SITE_TLDS: &[&str] = &[
+    "com", "org", "net", "io", "dev", "app", "edu", "gov", "info", "wiki", "me", "co", "uk", "de",
+    "fr", "nl", "eu", "ch", "se", "jp", "ca", "au",
+];
+
+/// Parse a domain from the start of the query, like `docs.python.org asyncio`,
+/// so only that site is searched. Returns the domain and the rest of the query.
+pub fn parse_site_prefix(query: &str) -> Option<(&str, &str)> {
+    let (site, rest) = query.split_once(' ')?;
+    let site = site.trim_end_matches('/');
+    let rest = rest.trim();
+    if rest.is_empty() || rest.to_ascii_lowercase().contains("site:") {
+        return None;
+    }
+    let labels = site.split('.').collect::<Vec<_>>();
+    let is_domain = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    let tld = labels.last()?.to_ascii_lowercase();
+    (is_domain && SITE_TLDS.contains(&tld.as_str())).then_some((site, rest))
+}
+
+/// Parse a comma-separated list of engine ids, ignoring ones that don't exist.
+pub fn parse_engine_list(s: &str) -> Vec<Engine> {
+    let mut engines = Vec::new();
+    for engine in s
+        .split(',')
+        .filter_map(|id| Engine::from_str(id.trim()).ok())
+    {
+        if !engines.contains(&engine) {
+            engines.push(engine);
+        }
+    }
+    engines
+}
+
+/// Disable every engine that isn't in `only`. Engines in the list that aren't
+/// already enabled stay disabled, so `!only` can't turn on engines that the
+/// user or the config turned off.
+pub fn restrict_engines(config: &mut Config, only: &[Engine]) {
+    let mut engines = config.engines.as_ref().clone();
+    for &engine in Engine::all() {
+        if !only.contains(&engine) {
+            engines.set_enabled(engine, false);
+        }
+    }
+    config.engines = engines.into();
+}
+
+/// Whether a `&lang=` parameter is `auto` or looks like a BCP 47 language tag,
+/// like `de` or `de-DE`, so junk isn't sent to the engines.
+pub fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    tag == "auto"
+        || ((2..=3).contains(&primary.len())
+            && primary.chars().all(|c| c.is_ascii_alphabetic())
+            && subtags.all(|s| {
+                (1..=8).contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphanumeric())
+            }))
+}
+
+/// Pick a language tag for the query when the configured language is `auto`.
+/// If we can't tell what language the query is in, the first language from
+/// the browser's Accept-Language header is used instead, as long as it looks
+/// like a language tag since it ends up in headers and cookies sent upstream.
+pub fn auto_language(query: &str, accept_language: Option<&str>) -> String {
+    if let Some(language) = detect_language(query) {
+        return language.to_string();
+    }
+    accept_language
+        .and_then(|h| h.split(',').next())
+        .map(|l| l.split(';').next().unwrap_or_default().trim())
+        .filter(|l| *l != "auto" && is_language_tag(l))
+        .unwrap_or("en-US")
+        .to_string()
+}
+
+fn detect_language(query: &str) -> Option<&'static str> {
+    let info = whatlang::detect(query)?;
+    if !info.is_reliable() {
+        return None;
+    }
+    Some(match info.lang() {
+        Lang::Eng => "en-US",
+        Lang::Deu => "de-DE",
+        Lang::Spa => "es-ES",
+        Lang::Fra => "fr-FR",
+        Lang::Ita => "it-IT",
+        Lang::Nld => "nl-NL",
+        Lang::Pol => "pl-PL",
+        Lang::Por => "pt-BR",
+        Lang::Rus => "ru-RU",
+        Lang::Ukr => "uk-UA",
+        Lang::Tur => "tr-TR",
+        Lang::Swe => "sv-SE",
+        Lang::Jpn => "ja-JP",
+        Lang::Kor => "ko-KR",
+        Lang::Cmn => "zh-CN",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preprocess() {
+        let preprocessed = preprocess("  !code  serde\n json ", DEFAULT_STEPS);
+        assert_eq!(preprocessed.query, "serde json");
+        assert_eq!(preprocessed.bang, Some(Bang::Tab(SearchTab::Code)));
+
+        let preprocessed = preprocess("rust 🦀 site:docs.rs", DEFAULT_STEPS);
+        assert_eq!(preprocessed.query, "rust 🦀 site:docs.rs");
+        assert_eq!(preprocessed.info.operators, vec![Operator::Site]);
+
+        let steps = [
+            PreprocessStep::StripEmoji,
+            PreprocessStep::NormalizeWhitespace,
+        ];
+        assert_eq!(preprocess("rust 🦀 lang", &steps).query, "rust lang");
+        assert!(preprocess("define rust", DEFAULT_STEPS).info.answer_trigger);
+        assert!(preprocess("2 * (3 + 4)", DEFAULT_STEPS).info.answer_trigger);
+        assert!(!preprocess("rust 2024", DEFAULT_STEPS).info.answer_trigger);
+        assert_eq!(
+            preprocess(" !random", DEFAULT_STEPS).bang,
+            Some(Bang::Random)
+        );
+    }
+
+    #[test]
+    fn test_restrict_engines() {
+        let mut config = Config::default();
+        // wiby is disabled by default
+        restrict_engines(&mut config, &[Engine::Google, Engine::Wiby]);
+        let enabled = |engine| config.engines.get(engine).enabled;
+        assert!(enabled(Engine::Google));
+        assert!(!enabled(Engine::Wiby));
+        assert!(!enabled(Engine::Bing));
+    }
+
+    #[test]
+    fn test_parse_only_engines() {
+        assert_eq!(
+            parse_only_engines("!only google,brave rust traits"),
+            Some((vec![Engine::Google, Engine::Brave], "rust traits"))
+        );
+        assert_eq!(parse_only_engines("!only notanengine rust"), None);
+        assert_eq!(parse_only_engines("rust traits"), None);
+    }
+
+    #[test]
+    fn test_parse_tab_bang() {
+        assert_eq!(
+            parse_tab_bang("!podcast rust"),
+            Some((SearchTab::Media, "rust"))
+        );
+        assert_eq!(
+            parse_tab_bang("serde json !code"),
+            Some((SearchTab::Code, "serde json"))
+        );
+        assert_eq!(
+            parse_tab_bang("!recipes banana bread"),
+            Some((SearchTab::Recipes, "banana bread"))
+        );
+        assert_eq!(parse_tab_bang("!i cats"), Some((SearchTab::Images, "cats")));
+        assert_eq!(
+            parse_tab_bang("!images cats"),
+            Some((SearchTab::Images, "cats"))
+        );
+        assert_eq!(parse_tab_bang("cats !all"), Some((SearchTab::All, "cats")));
+        assert_eq!(parse_tab_bang("!news ukraine"), None);
+        assert_eq!(parse_tab_bang("!podcasts"), None);
+        assert_eq!(parse_tab_bang("rust!code"), None);
+    }
+
+    #[test]
+    fn test_parse_site_prefix() {
+        assert_eq!(
+            parse_site_prefix("docs.python.org asyncio gather"),
+            Some(("docs.python.org", "asyncio gather"))
+        );
+        assert_eq!(
+            parse_site_prefix("github.com/ tokio"),
+            Some(("github.com", "tokio"))
+        );
+        assert_eq!(parse_site_prefix("main.rs error"), None);
+        assert_eq!(parse_site_prefix("node index.js"), None);
+        assert_eq!(parse_site_prefix("python.org"), None);
+        assert_eq!(parse_site_prefix("e.g. rust"), None);
+        assert_eq!(parse_site_prefix("github.com site:gitlab.com tokio"), None);
+    }
+
+    #[test]
+    fn test_is_language_tag() {
+        assert!(is_language_tag("de-DE"));
+        assert!(is_language_tag("de"));
+        assert!(is_language_tag("zh-Hant-TW"));
+        assert!(is_language_tag("auto"));
+        assert!(!is_language_tag(""));
+        assert!(!is_language_tag("de-"));
+        assert!(!is_language_tag("german"));
+        assert!(!is_language_tag("de&gl=us"));
+    }
+
+    #[test]
+    fn test_auto_language() {
+        assert_eq!(
+            auto_language("wie spät ist es gerade in der schweiz", None),
+            "de-DE"
+        );
+        assert_eq!(auto_language("rust", Some("fr-CH, fr;q=0.9")), "fr-CH");
+        assert_eq!(auto_language("rust", None), "en-US");
+        assert_eq!(auto_language("rust", Some("*")), "en-US");
+        assert_eq!(auto_language("rust", Some("en;US&u=x")), "en");
+        assert_eq!(auto_language("rust", Some("de; u=x")), "de");
+        assert_eq!(auto_language("rust", Some("fr&u=x, de")), "en-US");
+    }
+}
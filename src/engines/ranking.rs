@@ -1,48 +1,112 @@
 use std::{collections::HashMap, sync::Arc};
 
+use url::Url;
+
 use crate::{
-    config::Config,
+    blocklists,
+    config::{Config, RankingAlgorithm},
     urls::{apply_url_replacements, get_url_weight},
 };
 
 use super::{
-    Answer, AutocompleteResult, Engine, EngineImageResult, EngineImagesResponse, EngineResponse,
-    EngineSearchResult, FeaturedSnippet, ImagesResponse, Infobox, Response, SearchResult,
+    adult_filter::{is_adult, looks_adult},
+    Answer, AutocompleteResult, Engine, EngineImageResult, EngineImagesResponse, EngineRank,
+    EngineResponse, EngineSearchResult, Explanation, FeaturedSnippet, ImagesResponse, Infobox,
+    Response, SearchResult,
 };
 
+/// How much a result adds to its score, based on its position in an engine's
+/// results. The scores from every engine that returned a result are added up.
+pub trait Ranker {
+    /// `position` starts at 1, and `weight` is the engine's configured weight.
+    fn score(&self, position: usize, weight: f64) -> f64;
+}
+
+pub struct RankSum;
+
+impl Ranker for RankSum {
+    fn score(&self, position: usize, weight: f64) -> f64 {
+        // position 1 has a score of 1, position 2 has a score of 0.5, position 3 has a
+        // score of 0.33, etc.
+        weight / position as f64
+    }
+}
+
+pub struct ReciprocalRankFusion {
+    pub k: f64,
+}
+
+impl Ranker for ReciprocalRankFusion {
+    fn score(&self, position: usize, weight: f64) -> f64 {
+        weight / (self.k + position as f64)
+    }
+}
+
+fn ranker(config: &Config) -> Box<dyn Ranker> {
+    match config.ranking.algorithm {
+        RankingAlgorithm::RankSum => Box::new(RankSum),
+        RankingAlgorithm::ReciprocalRankFusion => Box::new(ReciprocalRankFusion {
+            k: config.ranking.rrf_k,
+        }),
+    }
+}
+
+/// If `explain` is true, each result gets an explanation of how its score was
+/// calculated.
 pub fn merge_engine_responses(
     config: Arc<Config>,
     responses: HashMap<Engine, EngineResponse>,
+    explain: bool,
 ) -> Response {
     let mut search_results: Vec<SearchResult<EngineSearchResult>> = Vec::new();
     let mut featured_snippet: Option<FeaturedSnippet> = None;
-    let mut answer: Option<Answer> = None;
+    let mut answers: Vec<Answer> = Vec::new();
     let mut infobox: Option<Infobox> = None;
+    let mut corrected_query: Option<(Engine, String)> = None;
+    let mut related_searches: Vec<(Engine, Vec<String>)> = Vec::new();
+    let ranker = ranker(&config);
 
     for (engine, response) in responses {
         let engine_config = config.engines.get(engine);
 
         for (result_index, mut search_result) in response.search_results.into_iter().enumerate() {
-            // position 1 has a score of 1, position 2 has a score of 0.5, position 3 has a
-            // score of 0.33, etc.
-            let base_result_score = 1. / (result_index + 1) as f64;
-            let result_score = base_result_score * engine_config.weight;
+            let result_score = ranker.score(result_index + 1, engine_config.weight);
+
+            let engine_url = explain.then(|| search_result.url.clone());
+
+            search_result.description = normalize_description(
+                &search_result.description,
+                config.ranking.max_description_length,
+            );
 
             // apply url config here
             search_result.url = apply_url_replacements(&search_result.url, &config.urls);
             let url_weight = get_url_weight(&search_result.url, &config.urls);
-            if url_weight <= 0. {
+            let text = format!("{} {}", search_result.title, search_result.description);
+            if url_weight <= 0.
+                || blocklists::is_blocked(&search_result.url)
+                || is_adult(&search_result.url, &text, &config.adult_filter)
+            {
                 continue;
             }
             let result_score = result_score * url_weight;
+            let rank = engine_url.map(|url| EngineRank {
+                engine,
+                position: result_index + 1,
+                weight: engine_config.weight,
+                score: result_score,
+                url,
+            });
 
             if let Some(existing_result) = search_results
                 .iter_mut()
                 .find(|r| r.result.url == search_result.url)
             {
+                existing_result.result.extras.merge(search_result.extras);
+
                 // if the weight of this engine is higher than every other one then replace the
                 // title and description
-                if engine_config.weight
+                let replace_title = engine_config.weight
                     > existing_result
                         .engines
                         .iter()
@@ -51,12 +115,23 @@ pub fn merge_engine_responses(
                             other_engine_config.weight
                         })
                         .max_by(|a, b| a.total_cmp(b))
-                        .unwrap_or(0.)
-                {
+                        .unwrap_or(0.);
+                if replace_title {
                     existing_result.result.title = search_result.title;
+                    if !search_result.description.is_empty() {
+                        existing_result.result.description = search_result.description;
+                    }
+                } else if existing_result.result.description.is_empty() {
                     existing_result.result.description = search_result.description;
                 }
 
+                if let (Some(explanation), Some(rank)) = (&mut existing_result.explanation, rank) {
+                    explanation.ranks.push(rank);
+                    if replace_title {
+                        explanation.title_from = engine;
+                    }
+                }
+
                 existing_result.engines.insert(engine);
                 existing_result.score += result_score;
             } else {
@@ -64,6 +139,13 @@ pub fn merge_engine_responses(
                     result: search_result,
                     engines: [engine].iter().copied().collect(),
                     score: result_score,
+                    explanation: rank.map(|rank| {
+                        Box::new(Explanation {
+                            ranks: vec![rank],
+                            url_weight,
+                            title_from: engine,
+                        })
+                    }),
                 });
             }
         }
@@ -79,7 +161,14 @@ pub fn merge_engine_responses(
             engine_featured_snippet.url =
                 apply_url_replacements(&engine_featured_snippet.url, &config.urls);
             let url_weight = get_url_weight(&engine_featured_snippet.url, &config.urls);
-            if url_weight <= 0. {
+            let text = format!(
+                "{} {}",
+                engine_featured_snippet.title, engine_featured_snippet.description
+            );
+            if url_weight <= 0.
+                || blocklists::is_blocked(&engine_featured_snippet.url)
+                || is_adult(&engine_featured_snippet.url, &text, &config.adult_filter)
+            {
                 continue;
             }
             let featured_snippet_weight = featured_snippet_weight * url_weight;
@@ -95,17 +184,10 @@ pub fn merge_engine_responses(
         }
 
         if let Some(engine_answer_html) = response.answer_html {
-            // if it has a higher weight than the current answer
-            let answer_weight = answer.as_ref().map_or(0., |s| {
-                let other_engine_config = config.engines.get(s.engine);
-                other_engine_config.weight
+            answers.push(Answer {
+                html: engine_answer_html,
+                engine,
             });
-            if engine_config.weight > answer_weight {
-                answer = Some(Answer {
-                    html: engine_answer_html,
-                    engine,
-                });
-            }
         }
 
         if let Some(engine_infobox_html) = response.infobox_html {
@@ -121,33 +203,114 @@ pub fn merge_engine_responses(
                 });
             }
         }
+
+        if let Some(engine_corrected_query) = response.corrected_query {
+            // the engine id breaks ties so it doesn't depend on which engine
+            // responded first
+            let is_better = corrected_query.as_ref().is_none_or(|(other, _)| {
+                let other_weight = config.engines.get(*other).weight;
+                engine_config.weight > other_weight
+                    || (engine_config.weight == other_weight && engine.id() < other.id())
+            });
+            if is_better {
+                corrected_query = Some((engine, engine_corrected_query));
+            }
+        }
+
+        if !response.related_searches.is_empty() {
+            related_searches.push((engine, response.related_searches));
+        }
     }
 
     search_results.sort_by(|a, b| b.score.total_cmp(&a.score));
 
+    sort_answers(&mut answers, &config);
+    let mut answers = answers.into_iter();
+    let answer = answers.next();
+    let secondary_answer = answers.next().filter(|_| config.answers.show_secondary);
+
     Response {
         search_results,
         featured_snippet,
         answer,
+        secondary_answer,
+        skipped_engines: Vec::new(),
         infobox,
+        refinements: Vec::new(),
+        corrected_query: corrected_query.map(|(_, query)| query),
+        related_searches: merge_related_searches(related_searches, &config),
         config,
     }
 }
 
-pub fn merge_autocomplete_responses(
+/// The related searches from every engine, with the ones from engines with
+/// higher weights first and without duplicates.
+fn merge_related_searches(
+    mut related_searches: Vec<(Engine, Vec<String>)>,
     config: &Config,
-    responses: HashMap<Engine, Vec<String>>,
 ) -> Vec<String> {
-    let mut autocomplete_results: Vec<AutocompleteResult> = Vec::new();
+    const MAX_RELATED_SEARCHES: usize = 8;
 
-    for (engine, response) in responses {
-        let engine_config = config.engines.get(engine);
+    related_searches.sort_by(|(a, _), (b, _)| {
+        let a_weight = config.engines.get(*a).weight;
+        let b_weight = config.engines.get(*b).weight;
+        b_weight.total_cmp(&a_weight).then(a.id().cmp(b.id()))
+    });
+    let mut merged: Vec<String> = Vec::new();
+    for query in related_searches
+        .into_iter()
+        .flat_map(|(_, queries)| queries)
+    {
+        if merged.len() >= MAX_RELATED_SEARCHES {
+            break;
+        }
+        if !merged
+            .iter()
+            .any(|q| q.to_lowercase() == query.to_lowercase())
+        {
+            merged.push(query);
+        }
+    }
+    merged
+}
 
+/// Sort answers by the configured precedence, then by weight. The engine id is
+/// used as a tiebreaker so the result doesn't depend on which engine responded
+/// first.
+fn sort_answers(answers: &mut [Answer], config: &Config) {
+    let precedence = |engine: Engine| {
+        config
+            .answers
+            .precedence
+            .iter()
+            .position(|&e| e == engine)
+            .unwrap_or(usize::MAX)
+    };
+    answers.sort_by(|a, b| {
+        precedence(a.engine)
+            .cmp(&precedence(b.engine))
+            .then_with(|| {
+                config
+                    .engines
+                    .get(b.engine)
+                    .weight
+                    .total_cmp(&config.engines.get(a.engine).weight)
+            })
+            .then_with(|| a.engine.id().cmp(b.engine.id()))
+    });
+}
+
+/// Merge autocomplete suggestions from engines and peers. Each response comes
+/// with the weight of the engine or peer it's from.
+pub fn merge_autocomplete_responses(responses: Vec<(f64, Vec<String>)>) -> Vec<String> {
+    let mut autocomplete_results: Vec<AutocompleteResult> = Vec::new();
+
+    for (weight, response) in responses {
         for (result_index, autocomplete_result) in response.into_iter().enumerate() {
             // position 1 has a score of 1, position 2 has a score of 0.5, position 3 has a
             // score of 0.33, etc.
             let base_result_score = 1. / (result_index + 1) as f64;
-            let result_score = base_result_score * engine_config.weight;
+            let result_score = base_result_score * weight;
 
             if let Some(existing_result) = autocomplete_results
                 .iter_mut()
@@ -173,20 +336,40 @@ pub fn merge_images_responses(
     responses: HashMap<Engine, EngineImagesResponse>,
 ) -> ImagesResponse {
     let mut image_results: Vec<SearchResult<EngineImageResult>> = Vec::new();
+    let ranker = ranker(&config);
 
     for (engine, response) in responses {
         let engine_config = config.engines.get(engine);
 
-        for (result_index, image_result) in response.image_results.into_iter().enumerate() {
-            // position 1 has a score of 1, position 2 has a score of 0.5, position 3 has a
-            // score of 0.33, etc.
-            let base_result_score = 1. / (result_index + 1) as f64;
-            let result_score = base_result_score * engine_config.weight;
+        for (result_index, mut image_result) in response.image_results.into_iter().enumerate() {
+            if is_adult(
+                &image_result.page_url,
+                &image_result.title,
+                &config.adult_filter,
+            ) {
+                continue;
+            }
+            image_result.nsfw |= looks_adult(
+                &image_result.page_url,
+                &image_result.title,
+                &config.adult_filter,
+            );
+            let result_score = ranker.score(result_index + 1, engine_config.weight);
 
             if let Some(existing_result) = image_results
                 .iter_mut()
-                .find(|r| r.result.image_url == image_result.image_url)
+                .find(|r| is_same_image(&r.result, &image_result))
             {
+                existing_result.result.nsfw |= image_result.nsfw;
+                // keep the biggest version of the image
+                if image_result.width * image_result.height
+                    > existing_result.result.width * existing_result.result.height
+                {
+                    existing_result.result.image_url = image_result.image_url.clone();
+                    existing_result.result.width = image_result.width;
+                    existing_result.result.height = image_result.height;
+                }
+
                 // if the weight of this engine is higher than every other one then replace the
                 // title and page url
                 if engine_config.weight
@@ -211,6 +394,7 @@ pub fn merge_images_responses(
                     result: image_result,
                     engines: [engine].iter().copied().collect(),
                     score: result_score,
+                    explanation: None,
                 });
             }
         }
@@ -223,3 +407,286 @@ pub fn merge_images_responses(
         config,
     }
 }
+
+/// Whether two image results are probably the same image, even if the engines
+/// gave different urls for it (like a thumbnail and the original). That's the
+/// case if the urls only differ in the scheme or query, or if they're from the
+/// same page or have the same file name on the same host, and have the same
+/// aspect ratio.
+fn is_same_image(a: &EngineImageResult, b: &EngineImageResult) -> bool {
+    if image_key(&a.image_url) == image_key(&b.image_url) {
+        return true;
+    }
+    if !same_aspect_ratio(a, b) {
+        return false;
+    }
+    let same_page = image_key(&a.page_url) == image_key(&b.page_url);
+    let host = |url: &Url| {
+        url.host_str()
+            .map(|h| h.trim_start_matches("www.").to_owned())
+    };
+    let file_name = |url: &Url| {
+        url.path_segments()
+            .and_then(|mut s| s.next_back())
+            .filter(|name| !name.is_empty())
+            .map(str::to_owned)
+    };
+    let same_file = match (Url::parse(&a.image_url), Url::parse(&b.image_url)) {
+        (Ok(a), Ok(b)) => {
+            host(&a) == host(&b) && file_name(&a).is_some() && file_name(&a) == file_name(&b)
+        }
+        _ => false,
+    };
+    same_page || same_file
+}
+
+/// The url without the scheme, `www.`, query, or fragment.
+fn image_key(url: &str) -> String {
+    let url = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .trim_start_matches("www.");
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    url.trim_end_matches('/').to_lowercase()
+}
+
+/// Whether both images have known dimensions and their aspect ratios are
+/// within 2% of each other.
+fn same_aspect_ratio(a: &EngineImageResult, b: &EngineImageResult) -> bool {
+    if a.width == 0 || a.height == 0 || b.width == 0 || b.height == 0 {
+        return false;
+    }
+    let a_ratio = a.width as f64 / a.height as f64;
+    let b_ratio = b.width as f64 / b.height as f64;
+    (a_ratio / b_ratio - 1.).abs() < 0.02
+}
+
+/// Collapse the whitespace in a description, and shorten it to at most
+/// `max_length` characters if it's longer. It's cut after the last sentence
+/// that fits, or after the last word if that would make it too short. A
+/// `max_length` of 0 doesn't shorten anything.
+fn normalize_description(description: &str, max_length: usize) -> String {
+    let description = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    if max_length == 0 || description.chars().count() <= max_length {
+        return description;
+    }
+
+    let end = description
+        .char_indices()
+        .nth(max_length)
+        .map_or(description.len(), |(i, _)| i);
+    let truncated = &description[..end];
+    // don't cut so early that most of the description is gone
+    let min_end = truncated
+        .char_indices()
+        .nth(max_length / 2)
+        .map_or(0, |(i, _)| i);
+
+    let sentence_end = truncated
+        .char_indices()
+        .filter(|&(i, c)| {
+            // full-width punctuation isn't followed by a space
+            let ends_sentence = (matches!(c, '.' | '!' | '?')
+                && truncated[i + c.len_utf8()..].starts_with(' '))
+                || matches!(c, '。' | '！' | '？');
+            ends_sentence && i >= min_end
+        })
+        .last()
+        .map(|(i, c)| i + c.len_utf8());
+    if let Some(sentence_end) = sentence_end {
+        return truncated[..sentence_end].to_string();
+    }
+
+    let word_end = truncated
+        .rfind(' ')
+        .filter(|&i| i >= min_end)
+        .unwrap_or(truncated.len());
+    let truncated = truncated[..word_end].trim_end_matches([',', ';', ':', '-', ' ']);
+    format!("{truncated}…")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(urls: &[&str]) -> EngineResponse {
+        let mut response = EngineResponse::new();
+        response.search_results = urls
+            .iter()
+            .map(|url| EngineSearchResult {
+                url: url.to_string(),
+                title: url.to_string(),
+                description: String::new(),
+                description_from_page: false,
+                extras: Default::default(),
+            })
+            .collect();
+        response
+    }
+
+    #[test]
+    fn test_explanation() {
+        let config = Arc::new(Config::default());
+        let responses = || {
+            HashMap::from([
+                (
+                    Engine::Google,
+                    response(&[
+                        "https://a.com/",
+                        "https://minecraft.fandom.com/wiki/Creeper",
+                    ]),
+                ),
+                (
+                    Engine::Bing,
+                    response(&["https://minecraft.wiki/w/Creeper"]),
+                ),
+            ])
+        };
+
+        let response = merge_engine_responses(config.clone(), responses(), false);
+        assert!(response
+            .search_results
+            .iter()
+            .all(|r| r.explanation.is_none()));
+
+        let response = merge_engine_responses(config, responses(), true);
+        let creeper = response
+            .search_results
+            .iter()
+            .find(|r| r.result.url == "https://minecraft.wiki/w/Creeper")
+            .unwrap();
+        let explanation = creeper.explanation.as_ref().unwrap();
+        assert_eq!(explanation.title_from, Engine::Google);
+        let mut ranks = explanation
+            .ranks
+            .iter()
+            .map(|r| (r.engine, r.position, r.url.as_str()))
+            .collect::<Vec<_>>();
+        ranks.sort();
+        assert_eq!(
+            ranks,
+            [
+                (
+                    Engine::Google,
+                    2,
+                    "https://minecraft.fandom.com/wiki/Creeper"
+                ),
+                (Engine::Bing, 1, "https://minecraft.wiki/w/Creeper"),
+            ]
+        );
+        let total = explanation.ranks.iter().map(|r| r.score).sum::<f64>();
+        assert!((creeper.score - total).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_normalize_description() {
+        assert_eq!(
+            normalize_description("  A short\n\n description. ", 100),
+            "A short description."
+        );
+        assert_eq!(
+            normalize_description("First sentence here. Second one is long.", 30),
+            "First sentence here."
+        );
+        assert_eq!(
+            normalize_description("Abstract: one two three, four five six", 30),
+            "Abstract: one two three, four…"
+        );
+        assert_eq!(
+            normalize_description("Short. Then a much longer sentence follows", 30),
+            "Short. Then a much longer…"
+        );
+        assert_eq!(normalize_description("ééééé", 3), "ééé…");
+        let long = "word ".repeat(100);
+        assert_eq!(normalize_description(&long, 0), long.trim_end());
+    }
+
+    fn image(image_url: &str, page_url: &str, width: u64, height: u64) -> EngineImageResult {
+        EngineImageResult {
+            image_url: image_url.to_string(),
+            page_url: page_url.to_string(),
+            title: String::new(),
+            width,
+            height,
+            nsfw: false,
+        }
+    }
+
+    #[test]
+    fn test_image_dedup() {
+        let responses = HashMap::from([
+            (
+                Engine::Google,
+                EngineImagesResponse {
+                    image_results: vec![
+                        image(
+                            "https://cdn.example/cat.jpg?w=400",
+                            "https://a.example/cats",
+                            400,
+                            300,
+                        ),
+                        image(
+                            "https://b.example/dog.png",
+                            "https://b.example/dogs",
+                            100,
+                            100,
+                        ),
+                    ],
+                },
+            ),
+            (
+                Engine::Bing,
+                EngineImagesResponse {
+                    image_results: vec![
+                        image(
+                            "http://cdn.example/cat.jpg",
+                            "https://a.example/cats/",
+                            1600,
+                            1200,
+                        ),
+                        // same page, but a different shape so it's a different image
+                        image(
+                            "https://b.example/dog-wide.png",
+                            "https://b.example/dogs",
+                            300,
+                            100,
+                        ),
+                    ],
+                },
+            ),
+        ]);
+        let response = merge_images_responses(Arc::new(Config::default()), responses);
+        assert_eq!(response.image_results.len(), 3);
+        let cat = &response.image_results[0];
+        assert_eq!(cat.engines.len(), 2);
+        assert_eq!(cat.result.image_url, "http://cdn.example/cat.jpg");
+        assert_eq!((cat.result.width, cat.result.height), (1600, 1200));
+    }
+
+    #[test]
+    fn test_rankers() {
+        let responses = || {
+            HashMap::from([
+                (
+                    Engine::Google,
+                    response(&["https://x.com", "https://y.com"]),
+                ),
+                (Engine::Bing, response(&["https://z.com", "https://y.com"])),
+            ])
+        };
+        let top_url = |config: Config| {
+            merge_engine_responses(Arc::new(config), responses(), false).search_results[0]
+                .result
+                .url
+                .clone()
+        };
+
+        // the first result from the highest weighted engine wins with rank sum...
+        assert_eq!(top_url(Config::default()), "https://x.com");
+
+        // ...but with rrf, the result that both engines returned does
+        let mut config = Config::default();
+        config.ranking.algorithm = RankingAlgorithm::ReciprocalRankFusion;
+        assert_eq!(top_url(config), "https://y.com");
+    }
+}
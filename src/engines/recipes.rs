@@ -0,0 +1,156 @@
+//! The recipes tab. It searches the normal engines for recipes and then reads
+//! the schema.org Recipe data from the pages, so results can show their cook
+//! time, rating, and ingredients, and link straight to the recipe card. Pages
+//! that were already read are shown with the results, with the recipes at the
+//! top, and the rest are read after the results are sent.
+
+use std::{sync::LazyLock, time::Duration};
+
+use futures::future::join_all;
+use scraper::{Html, Selector};
+use tracing::debug;
+
+use super::{
+    page_cache::PageCache, page_meta, EngineSearchResult, ResultExtras, SearchQuery, SearchResult,
+};
+
+const TTL: Duration = Duration::from_secs(60 * 60);
+const MAX_ENTRIES: usize = 1000;
+const MAX_FETCHES: usize = 8;
+/// Recipe cards are usually at the very end of a long page.
+const MAX_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Elements that recipe plugins put at the start of their recipe cards, in
+/// order of preference.
+const RECIPE_ANCHORS: &[&str] = &[
+    "[id^='wprm-recipe-container-']",
+    "[id^='tasty-recipes-']:not([id^='tasty-recipes-jump'])",
+    "[id^='mv-creation-']",
+    "[itemtype$='schema.org/Recipe'][id]",
+    "#recipe",
+];
+
+/// Recipe extras by url. Pages that aren't recipes are cached as `None`.
+static CACHE: LazyLock<PageCache<Option<ResultExtras>>> =
+    LazyLock::new(|| PageCache::new(MAX_ENTRIES, TTL));
+
+/// The query that's sent to the engines for the recipes tab.
+pub fn recipe_query(query: &SearchQuery) -> SearchQuery {
+    let mut query = query.clone();
+    if !query.query.to_lowercase().contains("recipe") {
+        query.query.push_str(" recipe");
+    }
+    query
+}
+
+/// Add the recipe data that was already read to the results, and move the
+/// results that are recipes to the top.
+pub fn add_cached(results: &mut [SearchResult<EngineSearchResult>]) {
+    for result in results.iter_mut() {
+        if let Some(Some(extras)) = CACHE.get(&result.result.url) {
+            result.result.extras.merge(extras);
+        }
+    }
+    // stable, so the ranking is otherwise kept
+    results.sort_by_cached_key(|r| !matches!(CACHE.get(&r.result.url), Some(Some(_))));
+}
+
+/// The top results whose pages still have to be read, with their indexes
+/// (starting at 1, like the `#result-N` anchors).
+pub fn uncached(results: &[SearchResult<EngineSearchResult>]) -> Vec<(usize, EngineSearchResult)> {
+    results
+        .iter()
+        .enumerate()
+        .take(MAX_FETCHES)
+        .filter(|(_, r)| CACHE.get(&r.result.url).is_none())
+        .map(|(i, r)| (i + 1, r.result.clone()))
+        .collect()
+}
+
+/// Read the pages all at once, and return the results that turned out to be
+/// recipes with their recipe data added.
+pub async fn fetch(results: Vec<(usize, EngineSearchResult)>) -> Vec<(usize, EngineSearchResult)> {
+    join_all(results.into_iter().map(|(index, mut result)| async move {
+        let extras = match page_meta::fetch_html(&result.url, MAX_PAGE_SIZE).await {
+            Ok(html) => html.and_then(|html| parse_recipe(&html)),
+            // errors aren't cached, so the page is read again next time
+            Err(err) => {
+                debug!("couldn't get recipe for {}: {err}", result.url);
+                return None;
+            }
+        };
+        CACHE.insert(result.url.clone(), extras.clone());
+        result.extras.merge(extras?);
+        Some((index, result))
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+fn parse_recipe(html: &str) -> Option<ResultExtras> {
+    let dom = Html::parse_document(html);
+    let recipe = dom
+        .select(&Selector::parse("script[type='application/ld+json']").unwrap())
+        .filter_map(|script| {
+            serde_json::from_str::<serde_json::Value>(&script.text().collect::<String>()).ok()
+        })
+        .find_map(|json| find_recipe(&json).cloned())?;
+
+    let mut extras = crate::parse::json_ld_extras(&recipe);
+    extras.recipe_anchor = RECIPE_ANCHORS.iter().find_map(|selector| {
+        dom.select(&Selector::parse(selector).unwrap())
+            .next()
+            .and_then(|el| el.value().id())
+            .map(str::to_string)
+    });
+    Some(extras)
+}
+
+/// Find the Recipe in JSON-LD, which might be in a list or a `@graph`.
+fn find_recipe(json: &serde_json::Value) -> Option<&serde_json::Value> {
+    let is_recipe = match json.get("@type") {
+        Some(serde_json::Value::String(t)) => t == "Recipe",
+        Some(serde_json::Value::Array(types)) => types.iter().any(|t| t == "Recipe"),
+        _ => false,
+    };
+    if is_recipe {
+        return Some(json);
+    }
+    json.as_array()
+        .or_else(|| json.get("@graph").and_then(|g| g.as_array()))?
+        .iter()
+        .find_map(find_recipe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recipe() {
+        let html = r#"<html><head><script type="application/ld+json">
+            {"@context":"https://schema.org","@graph":[
+                {"@type":"WebPage","name":"Pancakes"},
+                {"@type":["Recipe"],"name":"Pancakes","totalTime":"PT20M",
+                 "recipeIngredient":["1 cup flour","1 egg"]}
+            ]}</script></head>
+            <body><p>A long story about pancakes</p>
+            <div id="recipe"></div>
+            <div id="wprm-recipe-container-42" class="wprm-recipe-container">...</div>
+            </body></html>"#;
+        let extras = parse_recipe(html).unwrap();
+        assert_eq!(extras.total_time.as_deref(), Some("20 min"));
+        assert_eq!(extras.ingredient_count, Some(2));
+        assert_eq!(
+            extras.recipe_anchor.as_deref(),
+            Some("wprm-recipe-container-42")
+        );
+
+        assert_eq!(
+            parse_recipe(r#"<script type="application/ld+json">{"@type":"Article"}</script>"#),
+            None
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! "Refine by" suggestions, made from words that show up a lot in the result
+//! titles.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::{EngineSearchResult, SearchResult};
+
+/// Only the top results are looked at since the ones further down are usually
+/// less relevant.
+const MAX_TITLES: usize = 20;
+const MAX_REFINEMENTS: usize = 6;
+/// How many titles a phrase has to be in to be suggested.
+const MIN_BIGRAM_COUNT: usize = 2;
+const MIN_UNIGRAM_COUNT: usize = 3;
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "as", "at", "be", "best", "by", "can", "do", "for", "from",
+    "how", "i", "in", "is", "it", "my", "of", "on", "or", "the", "this", "to", "vs", "what",
+    "when", "why", "with", "you", "your",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Refinement {
+    /// The phrase that's shown to the user.
+    pub text: String,
+    /// The new query, which is the original query with the phrase added.
+    pub query: String,
+}
+
+pub fn refinements(query: &str, results: &[SearchResult<EngineSearchResult>]) -> Vec<Refinement> {
+    let query_words = split_words(query);
+
+    // phrase -> (number of titles it's in, index of the first title it was in)
+    let mut bigrams = HashMap::<String, (usize, usize)>::new();
+    let mut unigrams = HashMap::<String, (usize, usize)>::new();
+
+    for (i, result) in results.iter().take(MAX_TITLES).enumerate() {
+        let words = split_words(strip_site_name(&result.result.title));
+
+        let mut seen_bigrams = Vec::new();
+        for pair in words.windows(2) {
+            if pair.iter().any(|w| is_stopword(w)) || pair[0] == pair[1] {
+                continue;
+            }
+            // refining by the query itself is pointless
+            if pair.iter().all(|w| query_words.contains(w)) {
+                continue;
+            }
+            let bigram = pair.join(" ");
+            if !seen_bigrams.contains(&bigram) {
+                seen_bigrams.push(bigram);
+            }
+        }
+        for bigram in seen_bigrams {
+            bigrams.entry(bigram).or_insert((0, i)).0 += 1;
+        }
+
+        let mut seen_unigrams = Vec::new();
+        for word in words {
+            if is_stopword(&word) || query_words.contains(&word) || seen_unigrams.contains(&word) {
+                continue;
+            }
+            seen_unigrams.push(word);
+        }
+        for unigram in seen_unigrams {
+            unigrams.entry(unigram).or_insert((0, i)).0 += 1;
+        }
+    }
+
+    let bigrams = sort_by_count(bigrams, MIN_BIGRAM_COUNT);
+    // single words are only suggested if they're not already part of a bigram
+    let unigrams = sort_by_count(unigrams, MIN_UNIGRAM_COUNT)
+        .into_iter()
+        .filter(|w| !bigrams.iter().any(|b| b.split(' ').any(|bw| bw == w)))
+        .collect::<Vec<_>>();
+
+    bigrams
+        .into_iter()
+        .chain(unigrams)
+        .take(MAX_REFINEMENTS)
+        .map(|text| {
+            let mut query = query.to_string();
+            for word in text.split(' ') {
+                if !query_words.iter().any(|w| w == word) {
+                    query.push(' ');
+                    query.push_str(word);
+                }
+            }
+            Refinement { text, query }
+        })
+        .collect()
+}
+
+fn sort_by_count(phrases: HashMap<String, (usize, usize)>, min_count: usize) -> Vec<String> {
+    let mut phrases = phrases
+        .into_iter()
+        .filter(|(_, (count, _))| *count >= min_count)
+        .collect::<Vec<_>>();
+    phrases.sort_by(|(_, (a_count, a_first)), (_, (b_count, b_first))| {
+        b_count.cmp(a_count).then(a_first.cmp(b_first))
+    });
+    phrases.into_iter().map(|(phrase, _)| phrase).collect()
+}
+
+/// Remove things like " - Wikipedia" or " | Reddit" from the end of titles, since
+/// site names would otherwise be the most common phrases.
+fn strip_site_name(title: &str) -> &str {
+    [" - ", " | ", " — ", " – ", " · "]
+        .iter()
+        .filter_map(|sep| title.rfind(sep))
+        .max()
+        .map_or(title, |index| &title[..index])
+}
+
+fn is_stopword(word: &str) -> bool {
+    word.len() < 2 || STOPWORDS.contains(&word)
+}
+
+fn split_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refinements() {
+        let results = [
+            "Async traits in Rust - The Rust Blog",
+            "Rust async traits explained | Reddit",
+            "Tokio tutorial: async Rust",
+            "Getting started with Tokio",
+            "Rust async book - Tokio",
+            "Tokio and async traits",
+        ]
+        .into_iter()
+        .map(|title| SearchResult {
+            result: EngineSearchResult {
+                url: String::new(),
+                title: title.to_string(),
+                description: String::new(),
+                description_from_page: false,
+                extras: Default::default(),
+            },
+            engines: Default::default(),
+            score: 1.,
+            explanation: None,
+        })
+        .collect::<Vec<_>>();
+
+        let refinements = refinements("rust", &results);
+        let texts = refinements
+            .iter()
+            .map(|r| r.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["async traits", "rust async", "tokio"]);
+        assert_eq!(refinements[0].query, "rust async traits");
+        assert_eq!(refinements[1].query, "rust async");
+    }
+}
@@ -0,0 +1,192 @@
+//! Engines are looked up in a registry instead of being matched on, so engines
+//! that aren't built in can be added at startup with [`register`]. Each engine
+//! implements [`EngineImpl`], and says what it can do with [`Capabilities`].
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
+
+use futures::future::BoxFuture;
+use maud::PreEscaped;
+use parking_lot::RwLock;
+
+use super::{
+    builtin, Engine, EngineImagesResponse, EngineResponse, HttpResponse,
+    RequestAutocompleteResponse, RequestResponse, Response, SearchQuery, SearchTab,
+};
+
+/// What an engine can be used for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Results for the normal tabs, or an instant answer.
+    pub web: bool,
+    pub images: bool,
+    pub autocomplete: bool,
+    /// Extra information that's shown next to the results, based on the results
+    /// from the other engines.
+    pub postsearch: bool,
+}
+
+/// An engine. Only the methods for what's in its [`Capabilities`] have to be
+/// implemented.
+pub trait EngineImpl: Send + Sync + 'static {
+    fn capabilities(&self) -> Capabilities;
+
+    /// The tab that the engine's search results are shown on.
+    fn tab(&self) -> SearchTab {
+        SearchTab::All
+    }
+
+    /// Whether the engine gives instant answers instead of search results.
+    fn is_answer(&self) -> bool {
+        false
+    }
+
+    fn request<'a>(
+        &'a self,
+        _query: &'a SearchQuery,
+    ) -> BoxFuture<'a, eyre::Result<RequestResponse>> {
+        Box::pin(async { Ok(RequestResponse::None) })
+    }
+
+    fn parse_response(&self, _res: &HttpResponse) -> eyre::Result<EngineResponse> {
+        eyre::bail!("this engine can't parse responses")
+    }
+
+    fn request_images(&self, _query: &SearchQuery) -> RequestResponse {
+        RequestResponse::None
+    }
+
+    fn parse_images_response(&self, _res: &HttpResponse) -> eyre::Result<EngineImagesResponse> {
+        eyre::bail!("this engine can't parse images responses")
+    }
+
+    fn request_autocomplete(&self, _query: &str) -> Option<RequestAutocompleteResponse> {
+        None
+    }
+
+    fn parse_autocomplete_response(&self, _body: &str) -> eyre::Result<Vec<String>> {
+        eyre::bail!("this engine can't parse autocomplete responses")
+    }
+
+    fn postsearch_request<'a>(
+        &'a self,
+        _response: &'a Response,
+    ) -> BoxFuture<'a, Option<wreq::RequestBuilder>> {
+        Box::pin(async { None })
+    }
+
+    fn postsearch_parse_response(&self, _res: &HttpResponse) -> Option<PreEscaped<String>> {
+        None
+    }
+}
+
+struct Registry {
+    engines: HashMap<Engine, Arc<dyn EngineImpl>>,
+    /// Leaked when an engine is added, so [`Engine::all`] can keep returning a
+    /// static slice. Engines are only added at startup so it's not much.
+    all: &'static [Engine],
+}
+
+impl Registry {
+    fn builtin() -> Self {
+        Self {
+            engines: builtin::engines().into_iter().collect(),
+            all: Engine::BUILTIN,
+        }
+    }
+
+    fn register(&mut self, id: &str, implementation: Arc<dyn EngineImpl>) -> Engine {
+        let engine = match Engine::builtin_from_id(id).or_else(|| self.find(id)) {
+            Some(engine) => engine,
+            None => {
+                let engine = Engine::Custom(String::leak(id.to_owned()));
+                let mut all = self.all.to_vec();
+                all.push(engine);
+                self.all = Vec::leak(all);
+                engine
+            }
+        };
+        self.engines.insert(engine, implementation);
+        engine
+    }
+
+    fn find(&self, id: &str) -> Option<Engine> {
+        self.all
+            .iter()
+            .copied()
+            .find(|e| matches!(e, Engine::Custom(custom_id) if *custom_id == id))
+    }
+}
+
+static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(|| RwLock::new(Registry::builtin()));
+
+/// Add an engine, or replace the implementation of one with the same id. This
+/// should be done before the config is read, so the engine can be configured
+/// like the built-in ones.
+pub fn register(id: &str, implementation: impl EngineImpl) -> Engine {
+    REGISTRY.write().register(id, Arc::new(implementation))
+}
+
+pub(super) fn get(engine: Engine) -> Option<Arc<dyn EngineImpl>> {
+    REGISTRY.read().engines.get(&engine).cloned()
+}
+
+pub(super) fn all() -> &'static [Engine] {
+    REGISTRY.read().all
+}
+
+/// A registered engine that isn't built in.
+pub(super) fn find(id: &str) -> Option<Engine> {
+    REGISTRY.read().find(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    impl EngineImpl for Echo {
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                autocomplete: true,
+                ..Default::default()
+            }
+        }
+
+        fn request_autocomplete(&self, query: &str) -> Option<RequestAutocompleteResponse> {
+            Some(vec![query.to_owned()].into())
+        }
+    }
+
+    #[test]
+    fn test_register_engine() {
+        // not the global registry, since other tests use every engine
+        let mut registry = Registry::builtin();
+        let engine = registry.register("echo", Arc::new(Echo));
+        assert_eq!(engine, Engine::Custom("echo"));
+        assert_eq!(registry.find("echo"), Some(engine));
+        assert!(registry.all.contains(&engine));
+        assert!(registry.engines[&engine].capabilities().autocomplete);
+
+        // registering it again replaces it instead of adding another one
+        let count = registry.all.len();
+        assert_eq!(registry.register("echo", Arc::new(Echo)), engine);
+        assert_eq!(registry.all.len(), count);
+
+        // built-in engines can be replaced too
+        assert_eq!(registry.register("google", Arc::new(Echo)), Engine::Google);
+        assert!(!registry.engines[&Engine::Google].capabilities().web);
+    }
+
+    #[test]
+    fn test_builtin_capabilities() {
+        let google = Engine::Google.capabilities();
+        assert!(google.web && google.autocomplete);
+        assert!(!google.postsearch);
+        assert!(Engine::GitHub.capabilities().postsearch);
+        assert_eq!(Engine::Codeberg.tab(), SearchTab::Code);
+    }
+}
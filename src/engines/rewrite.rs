@@ -0,0 +1,81 @@
+//! Changing the query before it's sent to one engine, with `rewrite_query` in
+//! the engine's config, like
+//! `google = { rewrite_query = { append = "-site:pinterest.com" } }`.
+
+use serde::Deserialize;
+use tracing::error;
+
+use super::{Engine, SearchQuery};
+
+#[derive(Deserialize, Debug, Default)]
+pub struct RewriteQuery {
+    /// Done in order, before `append`.
+    #[serde(default)]
+    replace: Vec<Replacement>,
+    /// Terms that are added to the end of the query.
+    append: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Replacement {
+    find: String,
+    with: String,
+}
+
+#[derive(Deserialize)]
+struct RewriteConfig {
+    rewrite_query: Option<RewriteQuery>,
+}
+
+impl RewriteQuery {
+    pub fn apply(&self, query: &str) -> String {
+        let mut query = self.replace.iter().fold(query.to_string(), |query, r| {
+            query.replace(&r.find, &r.with)
+        });
+        if let Some(append) = &self.append {
+            query = format!("{} {}", query.trim_end(), append.trim());
+        }
+        query.trim().to_string()
+    }
+}
+
+/// The query with the engine's `rewrite_query` applied, or None if it doesn't
+/// have one.
+pub fn rewrite(engine: Engine, query: &SearchQuery) -> Option<SearchQuery> {
+    let extra = &query.config.engines.get(engine).extra;
+    if !extra.contains_key("rewrite_query") {
+        return None;
+    }
+    let rewrite = match toml::Value::Table(extra.clone()).try_into::<RewriteConfig>() {
+        Ok(RewriteConfig { rewrite_query }) => rewrite_query?,
+        Err(err) => {
+            error!("Failed to parse rewrite_query for {engine}: {err}");
+            return None;
+        }
+    };
+    Some(SearchQuery {
+        query: rewrite.apply(&query.query),
+        ..query.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_query() {
+        let rewrite: RewriteQuery = toml::from_str(
+            r#"
+            replace = [{ find = "js", with = "javascript" }]
+            append = "-site:pinterest.com"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            rewrite.apply("js array sort "),
+            "javascript array sort -site:pinterest.com"
+        );
+        assert_eq!(RewriteQuery::default().apply("rust"), "rust");
+    }
+}
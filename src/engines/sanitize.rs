@@ -0,0 +1,129 @@
+//! Cleaning html from other sites before it goes in an answer or infobox, so a
+//! page can't put scripts, styles, forms, or event handlers on the results page.
+//! The html that engines make themselves with maud is already escaped, so only
+//! the parts copied from upstream go through this.
+//!
+//! Ids, inline styles, and inputs are always removed, and classes are only kept
+//! if an engine allows them by name, so upstream html can't reuse our styles,
+//! cover the page, or replace the elements that script.js looks up.
+
+use std::collections::{HashMap, HashSet};
+
+use ammonia::{Builder, UrlRelative};
+use url::Url;
+
+/// The tags that are kept. Anything else is removed, but the text in it is
+/// kept unless it's a script or style.
+const TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "dd",
+    "del",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "samp",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "tr",
+    "u",
+    "ul",
+    "var",
+    "wbr",
+];
+
+const TAG_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("a", &["href"]),
+    ("img", &["src", "alt", "width", "height"]),
+    ("ol", &["start"]),
+    ("td", &["colspan", "rowspan"]),
+    ("th", &["colspan", "rowspan", "scope"]),
+    ("time", &["datetime"]),
+];
+
+/// A sanitizer with our allowlist, where relative links are made absolute with
+/// the page's url. Engines can allow more classes on it if their css needs
+/// them.
+pub fn builder(base: Url) -> Builder<'static> {
+    let mut builder = Builder::default();
+    builder
+        .tags(TAGS.iter().copied().collect())
+        .tag_attributes(
+            TAG_ATTRIBUTES
+                .iter()
+                .map(|(tag, attributes)| (*tag, attributes.iter().copied().collect()))
+                .collect::<HashMap<_, HashSet<_>>>(),
+        )
+        .generic_attributes(["lang", "title"].into_iter().collect())
+        .url_schemes(["http", "https", "mailto"].into_iter().collect())
+        .url_relative(UrlRelative::RewriteWithBase(base))
+        .link_rel(Some("noopener noreferrer"));
+    builder
+}
+
+/// Clean html from the page at `base`.
+pub fn clean(html: &str, base: Url) -> String {
+    builder(base).clean(html).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean() {
+        let base = Url::parse("https://example.com/docs/page").unwrap();
+        let html = clean(
+            r#"<p onclick="alert(1)" style="color: red">hi<script>alert(1)</script></p><form><input name="q"></form><a href="other">link</a><a href="javascript:alert(1)">bad</a>"#,
+            base,
+        );
+        assert!(html.starts_with("<p>hi</p><a href=\"https://example.com/docs/other\""));
+        assert!(!html.contains("alert"));
+        assert!(!html.contains("input"));
+
+        let html = clean(
+            r#"<div id="search-input" class="search-result" style="position: fixed">hi</div>"#,
+            Url::parse("https://example.com").unwrap(),
+        );
+        assert_eq!(html, "<div>hi</div>");
+    }
+}
@@ -0,0 +1,76 @@
+//! Limits on how often requests are sent to each host, shared by every search
+//! that's running, so a lot of people searching at once doesn't get us rate
+//! limited by small engines like Marginalia.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, OnceLock},
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+
+use crate::config::{Config, HostLimits, SchedulerConfig};
+
+static CONFIG: OnceLock<SchedulerConfig> = OnceLock::new();
+
+/// The state for each host that has limits, by hostname.
+static HOSTS: LazyLock<Mutex<HashMap<String, Arc<Host>>>> = LazyLock::new(Default::default);
+
+struct Host {
+    /// None if there's no limit on how many requests can be sent at once.
+    slots: Option<Arc<Semaphore>>,
+    /// The soonest the next request can be sent.
+    next_start: Mutex<Instant>,
+}
+
+pub fn init(config: &Config) {
+    let _ = CONFIG.set(config.scheduler.clone());
+}
+
+fn limits(host: &str) -> HostLimits {
+    let Some(config) = CONFIG.get() else {
+        return HostLimits::default();
+    };
+    config.hosts.get(host).copied().unwrap_or(config.default)
+}
+
+/// Wait until a request can be sent to the host. The request counts towards
+/// the host's `max_concurrent` until the returned permit is dropped.
+pub async fn wait(host: &str) -> Option<OwnedSemaphorePermit> {
+    let limits = limits(host);
+    if limits.min_delay_ms == 0 && limits.max_concurrent == 0 {
+        return None;
+    }
+    let state = HOSTS
+        .lock()
+        .entry(host.to_string())
+        .or_insert_with(|| {
+            Arc::new(Host {
+                slots: (limits.max_concurrent > 0)
+                    .then(|| Arc::new(Semaphore::new(limits.max_concurrent))),
+                next_start: Mutex::new(Instant::now()),
+            })
+        })
+        .clone();
+
+    // the semaphore is never closed, so this doesn't fail
+    let permit = match &state.slots {
+        Some(slots) => slots.clone().acquire_owned().await.ok(),
+        None => None,
+    };
+    if limits.min_delay_ms > 0 {
+        let start = {
+            let mut next_start = state.next_start.lock();
+            let start = (*next_start).max(Instant::now());
+            *next_start = start + Duration::from_millis(limits.min_delay_ms);
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+    permit
+}
@@ -1,8 +1,27 @@
+pub mod annas_archive;
 pub mod bing;
 pub mod brave;
+pub mod brave_api;
+pub mod codeberg;
+pub mod fourget;
 pub mod google;
 pub mod google_scholar;
+pub mod kagi;
+pub mod librey;
+pub mod local;
 pub mod marginalia;
+pub mod mwmbl;
+pub mod openalex;
+pub mod petal;
+pub mod podcastindex;
+pub mod remotive;
 pub mod rightdao;
+pub mod searxng;
+pub mod sogou;
+pub mod sourcehut;
+pub mod stackexchange;
 pub mod stract;
+pub mod themealdb;
+pub mod visited;
+pub mod wiby;
 pub mod yep;
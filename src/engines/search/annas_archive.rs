@@ -0,0 +1,114 @@
+//! Anna's Archive, which searches Library Genesis, Z-Library, and other shadow
+//! libraries for books and papers. This is opt-in: it's disabled by default and
+//! can't be enabled from the settings page unless it's enabled in the config,
+//! since using it isn't legal everywhere.
+
+use scraper::{ElementRef, Selector};
+use url::Url;
+
+use crate::{
+    engines::{
+        endpoints, Engine, EngineResponse, HttpResponse, RequestResponse, SearchQuery, CLIENT,
+    },
+    parse::{check_block_page, parse_html_response_with_opts, ParseOpts, QueryMethod},
+};
+
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    endpoints::requests(
+        Engine::AnnasArchive,
+        query,
+        &[
+            "https://annas-archive.org",
+            "https://annas-archive.li",
+            "https://annas-archive.se",
+        ],
+        |endpoint| {
+            CLIENT.get(
+                Url::parse_with_params(
+                    &format!("{endpoint}/search"),
+                    &[("q", query.query.as_str())],
+                )
+                .unwrap(),
+            )
+        },
+    )
+}
+
+pub fn parse_response(res: &HttpResponse) -> eyre::Result<EngineResponse> {
+    let base = res.res.url().origin().ascii_serialization();
+    parse_results(&res.body, base)
+}
+
+fn parse_results(body: &str, base: String) -> eyre::Result<EngineResponse> {
+    check_block_page(body, &["DDoS-Guard", "cf-turnstile"])?;
+
+    // only the first few results are rendered, the rest are in html comments that
+    // the page's javascript uncomments when they're scrolled to
+    let body = body.replace("<!--", "").replace("-->", "");
+
+    parse_html_response_with_opts(
+        &body,
+        ParseOpts::new()
+            .result("a[href^='/md5/']")
+            .title("h3")
+            .href(QueryMethod::Manual(Box::new(move |el: &ElementRef| {
+                let href = el.value().attr("href").unwrap_or_default();
+                Ok(format!("{base}{href}"))
+            })))
+            .description(QueryMethod::Manual(Box::new(|el: &ElementRef| {
+                // the file info (language, format, size), publisher, and author
+                // are the siblings of the title
+                let Some(info) = el
+                    .select(&Selector::parse("h3").unwrap())
+                    .next()
+                    .and_then(|title| title.parent())
+                    .and_then(ElementRef::wrap)
+                else {
+                    return Ok(String::new());
+                };
+                Ok(info
+                    .child_elements()
+                    .filter(|child| child.value().name() != "h3")
+                    .map(|child| child.text().collect::<String>().trim().to_string())
+                    .filter(|text| !text.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" · "))
+            }))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_results() {
+        let result = |md5: &str, title: &str| {
+            format!(
+                r#"<div class="h-[125px]"><a href="/md5/{md5}" class="js-vim-focus">
+                <div class="flex-none"><div><img src="cover.jpg"></div></div>
+                <div class="relative">
+                  <div class="text-gray-500">English [en], .pdf, 5.0MB</div>
+                  <h3 class="font-bold">{title}</h3>
+                  <div class="truncate">O'Reilly, 2019</div>
+                  <div class="italic">Jim Blandy</div>
+                </div></a></div>"#
+            )
+        };
+        let body = format!(
+            "<html><body>{}<div class=\"h-[125px]\"><!-- {} --></div></body></html>",
+            result("abc", "Programming Rust"),
+            result("def", "The Rust Programming Language"),
+        );
+        let response = parse_results(&body, "https://annas-archive.org".to_string()).unwrap();
+        let results = response.search_results;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].url, "https://annas-archive.org/md5/abc");
+        assert_eq!(results[0].title, "Programming Rust");
+        assert_eq!(
+            results[0].description,
+            "English [en], .pdf, 5.0MB · O'Reilly, 2019 · Jim Blandy"
+        );
+        assert_eq!(results[1].title, "The Rust Programming Language");
+    }
+}
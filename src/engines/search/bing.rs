@@ -6,17 +6,39 @@ use tracing::warn;
 use url::Url;
 
 use crate::{
-    engines::{EngineImageResult, EngineImagesResponse, EngineResponse, CLIENT},
-    parse::{parse_html_response_with_opts, ParseOpts, QueryMethod},
+    engines::{
+        EngineImageResult, EngineImagesResponse, EngineResponse, Operator, RequestResponse,
+        SafeSearch, SearchQuery, CLIENT, DEFAULT_REQUESTED_COUNT,
+    },
+    parse::{check_block_page, parse_html_response_with_opts, ParseOpts, QueryMethod},
 };
 
-pub async fn request(query: &str) -> wreq::RequestBuilder {
+/// How many results are on a page if `count` isn't set.
+const PAGE_SIZE: usize = 10;
+
+pub async fn request(search: &SearchQuery) -> RequestResponse {
+    let pages = search.page_count(PAGE_SIZE);
+    if pages > 1 {
+        // `first` starts at 1
+        return RequestResponse::Pages(
+            (0..pages)
+                .map(|page| request_page(search, Some(page * PAGE_SIZE + 1)))
+                .collect(),
+        );
+    }
+    request_page(search, None).into()
+}
+
+/// A request for the page of results that starts at `first`, or for
+/// `requested_count` results if it's None.
+fn request_page(search: &SearchQuery, first: Option<usize>) -> wreq::RequestBuilder {
     let cvid = generate_cvid();
-    let url = Url::parse_with_params(
+    let query = search.quoted_if_verbatim(Operator::ALL);
+    let mut url = Url::parse_with_params(
         "https://www.bing.com/search",
         &[
-            ("q", query),
-            ("pq", query),
+            ("q", query.as_str()),
+            ("pq", query.as_str()),
             ("cvid", &cvid),
             ("filters", "rcrse:\"1\""), // filters=rcrse:"1" makes it not try to autocorrect
             ("FORM", "PERE"),
@@ -28,9 +50,34 @@ pub async fn request(query: &str) -> wreq::RequestBuilder {
         ],
     )
     .unwrap();
-    CLIENT
-        .get(url)
-        .header("Cookie", &format!("SRCHHPGUSR=IG={}", cvid))
+    if let Some(adlt) = safesearch_param(search.config.safesearch) {
+        url.query_pairs_mut().append_pair("adlt", adlt);
+    }
+    if let Some(first) = first {
+        url.query_pairs_mut()
+            .append_pair("first", &first.to_string());
+    } else if search.requested_count != DEFAULT_REQUESTED_COUNT {
+        // bing doesn't return more than 50
+        url.query_pairs_mut()
+            .append_pair("count", &search.requested_count.min(50).to_string());
+    }
+    // bing picks the market and ui language from these cookies
+    let market = search.config.language.to_lowercase();
+    let language = market.split('-').next().unwrap_or_default();
+    CLIENT.get(url).header(
+        "Cookie",
+        &format!(
+            "SRCHHPGUSR=IG={cvid}; _EDGE_CD=m={market}&u={language}; _EDGE_S=mkt={market}&ui={language}"
+        ),
+    )
+}
+
+fn safesearch_param(safesearch: SafeSearch) -> Option<&'static str> {
+    match safesearch {
+        SafeSearch::Off => Some("off"),
+        SafeSearch::Moderate => None,
+        SafeSearch::Strict => Some("strict"),
+    }
 }
 
 fn generate_cvid() -> String {
@@ -40,6 +87,7 @@ fn generate_cvid() -> String {
 }
 
 pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    check_block_page(body, &["/turing/captcha/", "id=\"b_captcha"])?;
     parse_html_response_with_opts(
         body,
         ParseOpts::new()
@@ -81,23 +129,39 @@ pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
                 }
 
                 Ok(description)
-            }))),
+            })))
+            // things like "Rating: 4.5/5 (123)" and "Total Time: 45 mins"
+            .extras(QueryMethod::Manual(Box::new(|el: &ElementRef| {
+                Ok(el
+                    .select(&Selector::parse(".b_factrow").unwrap())
+                    .map(|row| row.text().collect::<String>())
+                    .collect::<Vec<_>>()
+                    .join(" · "))
+            })))
+            // "did you mean", since rcrse stops it from autocorrecting
+            .correction("#sp_requery a")
+            .related_searches(".b_rs li a"),
     )
 }
 
-pub fn request_images(query: &str) -> wreq::RequestBuilder {
-    CLIENT.get(
-        Url::parse_with_params(
-            "https://www.bing.com/images/async",
-            &[
-                ("q", query),
-                ("async", "content"),
-                ("first", "1"),
-                ("count", "35"),
-            ],
-        )
-        .unwrap(),
+pub fn request_images(query: &SearchQuery) -> wreq::RequestBuilder {
+    let mut url = Url::parse_with_params(
+        "https://www.bing.com/images/async",
+        &[
+            ("q", query.query.as_str()),
+            ("async", "content"),
+            ("first", "1"),
+            ("count", "35"),
+        ],
     )
+    .unwrap();
+    if let Some(adlt) = safesearch_param(query.config.safesearch) {
+        url.query_pairs_mut().append_pair("adlt", adlt);
+    }
+    if let Some(qft) = query.image_filters.bing_qft() {
+        url.query_pairs_mut().append_pair("qft", &qft);
+    }
+    CLIENT.get(url)
 }
 
 #[tracing::instrument(skip(body))]
@@ -161,6 +225,7 @@ pub fn parse_images_response(body: &str) -> eyre::Result<EngineImagesResponse> {
             title: page_title.to_string(),
             width,
             height,
+            nsfw: false,
         });
     }
 
@@ -1,14 +1,52 @@
 use url::Url;
 
+use super::brave_api;
 use crate::{
-    engines::{EngineResponse, RequestResponse, CLIENT},
+    engines::{
+        EngineImagesResponse, EngineResponse, HttpResponse, Operator, RequestResponse, SearchQuery,
+        CLIENT,
+    },
     parse::{parse_html_response_with_opts, ParseOpts},
 };
 
-pub async fn request(query: &str) -> RequestResponse {
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    if let Some(api_key) = brave_api::api_key(&query.config) {
+        return brave_api::request(query, &api_key).into();
+    }
+    let pages = query.page_count(PAGE_SIZE);
+    if pages > 1 {
+        return RequestResponse::Pages((0..pages).map(|page| request_page(query, page)).collect());
+    }
+    request_page(query, 0).into()
+}
+
+/// How many results brave shows on a page.
+const PAGE_SIZE: usize = 20;
+
+/// `page` starts at 0.
+fn request_page(query: &SearchQuery, page: usize) -> wreq::RequestBuilder {
+    let mut url = Url::parse_with_params(
+        "https://search.brave.com/search",
+        &[("q", query.with_operators(Operator::ALL).as_str())],
+    )
+    .unwrap();
+    if page > 0 {
+        url.query_pairs_mut()
+            .append_pair("offset", &page.to_string());
+    }
     CLIENT
-        .get(Url::parse_with_params("https://search.brave.com/search", &[("q", query)]).unwrap())
-        .into()
+        .get(url)
+        .header("Cookie", format!("safesearch={}", query.config.safesearch))
+}
+
+/// Parse the api's response if there's an api key, or the scraped page if
+/// there isn't.
+pub fn parse_any_response(res: &HttpResponse) -> eyre::Result<EngineResponse> {
+    if brave_api::api_key(&res.config).is_some() {
+        brave_api::parse_response(&res.body)
+    } else {
+        parse_response(&res.body)
+    }
 }
 
 pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
@@ -18,6 +56,20 @@ pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
             .result("#results > .snippet[data-pos]:not(.standalone)")
             .title(".title")
             .href("a")
-            .description(".generic-snippet, .video-snippet > .snippet-description"),
+            .description(".generic-snippet, .video-snippet > .snippet-description")
+            .related_searches("#related-queries a"),
     )
 }
+
+/// Images are only searched with the api, since they're loaded with javascript
+/// on the site.
+pub fn request_images(query: &SearchQuery) -> RequestResponse {
+    match brave_api::api_key(&query.config) {
+        Some(api_key) => brave_api::request_images(query, &api_key).into(),
+        None => RequestResponse::None,
+    }
+}
+
+pub fn parse_images_response(body: &str) -> eyre::Result<EngineImagesResponse> {
+    brave_api::parse_images_response(body)
+}
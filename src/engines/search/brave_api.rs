@@ -0,0 +1,243 @@
+//! Brave's official search API, which the brave engine uses instead of
+//! scraping when it has an `api_key` (from https://brave.com/search/api). The
+//! api is paid past the free tier, but it doesn't break when Brave changes its
+//! markup.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    config::Config,
+    engines::{
+        Engine, EngineImageResult, EngineImagesResponse, EngineResponse, EngineSearchResult,
+        Operator, SafeSearch, SearchQuery, CLIENT,
+    },
+};
+
+/// The most results the api gives for each request.
+const MAX_COUNT: usize = 20;
+const MAX_IMAGE_COUNT: usize = 100;
+
+#[derive(Deserialize, Default)]
+struct BraveApiConfig {
+    api_key: Option<String>,
+}
+
+/// The key from the brave engine's config, if scraping should be skipped for
+/// the api.
+pub fn api_key(config: &Config) -> Option<String> {
+    toml::Value::Table(config.engines.get(Engine::Brave).extra.clone())
+        .try_into::<BraveApiConfig>()
+        .ok()?
+        .api_key
+        .filter(|key| !key.is_empty())
+}
+
+fn get(path: &str, params: &[(&str, &str)], api_key: &str) -> wreq::RequestBuilder {
+    CLIENT
+        .get(
+            Url::parse_with_params(
+                &format!("https://api.search.brave.com/res/v1/{path}"),
+                params,
+            )
+            .unwrap(),
+        )
+        .header("Accept", "application/json")
+        .header("X-Subscription-Token", api_key)
+}
+
+pub fn request(query: &SearchQuery, api_key: &str) -> wreq::RequestBuilder {
+    let safesearch = match query.config.safesearch {
+        SafeSearch::Off => "off",
+        SafeSearch::Moderate => "moderate",
+        SafeSearch::Strict => "strict",
+    };
+    get(
+        "web/search",
+        &[
+            ("q", query.with_operators(Operator::ALL).as_str()),
+            ("count", &query.requested_count.min(MAX_COUNT).to_string()),
+            ("safesearch", safesearch),
+            // the news and videos verticals are mixed into the web results
+            ("result_filter", "web,news,videos"),
+        ],
+        api_key,
+    )
+}
+
+pub fn request_images(query: &SearchQuery, api_key: &str) -> wreq::RequestBuilder {
+    // images are either filtered or not
+    let safesearch = match query.config.safesearch {
+        SafeSearch::Off => "off",
+        SafeSearch::Moderate | SafeSearch::Strict => "strict",
+    };
+    get(
+        "images/search",
+        &[
+            ("q", query.query.as_str()),
+            ("count", &MAX_IMAGE_COUNT.to_string()),
+            ("safesearch", safesearch),
+        ],
+        api_key,
+    )
+}
+
+#[derive(Deserialize)]
+struct WebSearchResponse {
+    query: Option<QueryInfo>,
+    web: Option<Results>,
+    news: Option<Results>,
+    videos: Option<Results>,
+}
+
+#[derive(Deserialize)]
+struct QueryInfo {
+    /// The query that Brave searched instead, if it corrected a typo.
+    altered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Results {
+    results: Vec<WebResult>,
+}
+
+#[derive(Deserialize)]
+struct WebResult {
+    url: String,
+    title: String,
+    #[serde(default)]
+    description: String,
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    let response: WebSearchResponse = serde_json::from_str(body)?;
+    // news and videos go after the web results, since they're less likely to be
+    // what the user wanted
+    let search_results = [response.web, response.news, response.videos]
+        .into_iter()
+        .flatten()
+        .flat_map(|r| r.results)
+        .map(|r| EngineSearchResult {
+            url: r.url,
+            title: strip_html(&r.title),
+            description: strip_html(&r.description),
+            description_from_page: false,
+            extras: Default::default(),
+        })
+        .collect();
+    Ok(EngineResponse {
+        search_results,
+        corrected_query: response.query.and_then(|q| q.altered),
+        ..Default::default()
+    })
+}
+
+#[derive(Deserialize)]
+struct ImageSearchResponse {
+    results: Vec<ImageResult>,
+}
+
+#[derive(Deserialize)]
+struct ImageResult {
+    /// The page that the image is on.
+    url: String,
+    #[serde(default)]
+    title: String,
+    properties: ImageProperties,
+    thumbnail: Option<Thumbnail>,
+}
+
+#[derive(Deserialize)]
+struct ImageProperties {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct Thumbnail {
+    width: Option<u64>,
+    height: Option<u64>,
+}
+
+pub fn parse_images_response(body: &str) -> eyre::Result<EngineImagesResponse> {
+    let response: ImageSearchResponse = serde_json::from_str(body)?;
+    Ok(EngineImagesResponse {
+        image_results: response
+            .results
+            .into_iter()
+            .map(|r| {
+                // the api doesn't give the size of the full image, but the
+                // thumbnail has the same aspect ratio
+                let thumbnail = r.thumbnail.as_ref();
+                EngineImageResult {
+                    image_url: r.properties.url,
+                    page_url: r.url,
+                    title: strip_html(&r.title),
+                    width: thumbnail.and_then(|t| t.width).unwrap_or_default(),
+                    height: thumbnail.and_then(|t| t.height).unwrap_or_default(),
+                    nsfw: false,
+                }
+            })
+            .collect(),
+    })
+}
+
+/// Titles and descriptions have the matching words in `<strong>` tags, and
+/// html entities.
+fn strip_html(s: &str) -> String {
+    let text = scraper::Html::parse_fragment(s)
+        .root_element()
+        .text()
+        .collect::<String>();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let body = r#"{
+            "type": "search",
+            "query": {"original": "rust programing", "altered": "rust programming"},
+            "web": {"type": "search", "results": [
+                {"title": "<strong>Rust</strong> Programming Language", "url": "https://www.rust-lang.org/", "description": "A language empowering everyone &amp; more"}
+            ]},
+            "news": {"type": "news", "results": [
+                {"title": "Rust 2.0 released", "url": "https://news.example.com/rust", "description": "It's out", "age": "2 hours ago"}
+            ]}
+        }"#;
+        let response = parse_response(body).unwrap();
+        let results = response
+            .search_results
+            .iter()
+            .map(|r| (r.title.as_str(), r.description.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            [
+                (
+                    "Rust Programming Language",
+                    "A language empowering everyone & more"
+                ),
+                ("Rust 2.0 released", "It's out"),
+            ]
+        );
+        assert_eq!(
+            response.corrected_query.as_deref(),
+            Some("rust programming")
+        );
+    }
+
+    #[test]
+    fn test_parse_images_response() {
+        let body = r#"{"type": "images", "results": [{"title": "Ferris", "url": "https://rustacean.net/", "properties": {"url": "https://rustacean.net/assets/rustacean-flat-happy.png"}, "thumbnail": {"src": "https://imgs.search.brave.com/abc", "width": 500, "height": 333}}]}"#;
+        let response = parse_images_response(body).unwrap();
+        assert_eq!(response.image_results.len(), 1);
+        assert_eq!(
+            response.image_results[0].image_url,
+            "https://rustacean.net/assets/rustacean-flat-happy.png"
+        );
+        assert_eq!(response.image_results[0].width, 500);
+    }
+}
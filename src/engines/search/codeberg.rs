@@ -0,0 +1,103 @@
+//! Repositories on Codeberg, for the code tab. Other Forgejo and Gitea
+//! instances can be used with `endpoints`, and a `token` raises the rate limit.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::engines::{
+    endpoints, Engine, EngineResponse, EngineSearchResult, RequestResponse, SearchQuery, CLIENT,
+};
+
+#[derive(Deserialize, Default)]
+struct CodebergConfig {
+    token: Option<String>,
+}
+
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    let config: CodebergConfig =
+        toml::Value::Table(query.config.engines.get(Engine::Codeberg).extra.clone())
+            .try_into()
+            .unwrap_or_default();
+
+    endpoints::requests(
+        Engine::Codeberg,
+        query,
+        &["https://codeberg.org"],
+        |endpoint| {
+            let request = CLIENT.get(
+                Url::parse_with_params(
+                    &format!("{endpoint}/api/v1/repos/search"),
+                    &[
+                        ("q", query.query.as_str()),
+                        ("limit", &query.requested_count.to_string()),
+                        ("sort", "stars"),
+                        ("order", "desc"),
+                    ],
+                )
+                .unwrap(),
+            );
+            match &config.token {
+                Some(token) => request.header("Authorization", format!("token {token}")),
+                None => request,
+            }
+        },
+    )
+}
+
+#[derive(Deserialize, Debug)]
+struct CodebergResponse {
+    ok: bool,
+    #[serde(default)]
+    data: Vec<CodebergRepo>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CodebergRepo {
+    full_name: String,
+    html_url: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    language: String,
+    #[serde(default)]
+    stars_count: u32,
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    let response: CodebergResponse = serde_json::from_str(body)?;
+    if !response.ok {
+        eyre::bail!("codeberg returned ok: false");
+    }
+
+    let search_results = response
+        .data
+        .into_iter()
+        .map(|repo| {
+            let mut details = vec![format!("★ {}", repo.stars_count)];
+            if !repo.language.is_empty() {
+                details.push(repo.language);
+            }
+            let description = if repo.description.is_empty() {
+                details.join(" · ")
+            } else {
+                format!("{} · {}", repo.description.trim(), details.join(" · "))
+            };
+            EngineSearchResult {
+                url: repo.html_url,
+                title: repo.full_name,
+                description,
+                description_from_page: false,
+                extras: Default::default(),
+            }
+        })
+        .collect();
+
+    Ok(EngineResponse {
+        search_results,
+        featured_snippet: None,
+        answer_html: None,
+        infobox_html: None,
+        corrected_query: None,
+        related_searches: Vec::new(),
+    })
+}
@@ -0,0 +1,75 @@
+//! 4get, a meta-frontend that scrapes other engines. Instances can be set with
+//! `endpoints` in the engine config.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::engines::{
+    endpoints, Engine, EngineResponse, EngineSearchResult, Operator, RequestResponse, SafeSearch,
+    SearchQuery, CLIENT,
+};
+
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    endpoints::requests(Engine::FourGet, query, &["https://4get.ca"], |endpoint| {
+        request_to(endpoint, query)
+    })
+}
+
+/// A search request to one instance, like `https://4get.ca`.
+pub fn request_to(endpoint: &str, query: &SearchQuery) -> wreq::RequestBuilder {
+    let search_query = query.quoted_if_verbatim(&[Operator::Site, Operator::ExcludeSite]);
+    let nsfw = match query.config.safesearch {
+        SafeSearch::Off => "yes",
+        SafeSearch::Moderate => "maybe",
+        SafeSearch::Strict => "no",
+    };
+    CLIENT.get(
+        Url::parse_with_params(
+            &format!("{endpoint}/api/v1/web"),
+            &[("s", search_query.as_str()), ("nsfw", nsfw)],
+        )
+        .unwrap(),
+    )
+}
+
+#[derive(Deserialize, Debug)]
+struct FourGetResponse {
+    status: String,
+    #[serde(default)]
+    web: Vec<FourGetResult>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FourGetResult {
+    url: String,
+    title: String,
+    description: Option<String>,
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    let response: FourGetResponse = serde_json::from_str(body)?;
+    if response.status != "ok" {
+        eyre::bail!("4get returned status {:?}", response.status);
+    }
+
+    let search_results = response
+        .web
+        .into_iter()
+        .map(|result| EngineSearchResult {
+            url: result.url,
+            title: result.title,
+            description: result.description.unwrap_or_default(),
+            description_from_page: false,
+            extras: Default::default(),
+        })
+        .collect();
+
+    Ok(EngineResponse {
+        search_results,
+        featured_snippet: None,
+        answer_html: None,
+        infobox_html: None,
+        corrected_query: None,
+        related_searches: Vec::new(),
+    })
+}
@@ -1,33 +1,185 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use eyre::eyre;
 use scraper::{ElementRef, Selector};
-use tracing::warn;
+use serde::Deserialize;
+use tracing::{error, warn};
 use url::Url;
 
 use crate::{
     engines::{
-        EngineImageResult, EngineImagesResponse, EngineResponse, RequestResponse, SearchQuery,
-        CLIENT,
+        client, Engine, EngineImageResult, EngineImagesResponse, EngineResponse, Operator,
+        RequestResponse, SafeSearch, SearchQuery, CLIENT, DEFAULT_REQUESTED_COUNT,
     },
-    parse::{parse_html_response_with_opts, ParseOpts, QueryMethod},
+    parse::{check_block_page, parse_html_response_with_opts, ConsentPage, ParseOpts, QueryMethod},
 };
 
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct GoogleConfig {
+    /// Use the Google domain for the region in the configured language, like
+    /// google.de for `de-DE`.
+    pub regional_domain: bool,
+}
+
+/// How many results are on a page if `num` isn't set.
+const PAGE_SIZE: usize = 10;
+
+/// What the form on Google's consent page submits to. Servers in the EU get
+/// this page instead of results until they send the consent cookies.
+const CONSENT_PAGE_MARKER: &str = "action=\"https://consent.google.com/save\"";
+
+/// The cookies that the consent page would set if every option was declined.
+const CONSENT_COOKIES: &[&str] = &[
+    "SOCS=CAESEwgDEgk0ODE3Nzk3MjQaAmVuIAEaBgiA_LyaBg",
+    "CONSENT=PENDING+987",
+];
+
+/// Set when Google first shows us its consent page, so the requests after that
+/// send the consent cookies.
+static SEND_CONSENT: AtomicBool = AtomicBool::new(false);
+
+/// A GET request to Google, with the consent cookies if they're needed.
+fn get(url: Url) -> wreq::RequestBuilder {
+    if !SEND_CONSENT.load(Ordering::Relaxed) {
+        return CLIENT.get(url);
+    }
+    // a Cookie header would replace the cookies from Google's cookie jar, so
+    // they go in the jar if it has one, which keeps them from then on
+    if client::add_cookies(Engine::Google, &url, CONSENT_COOKIES) {
+        SEND_CONSENT.store(false, Ordering::Relaxed);
+        return CLIENT.get(url);
+    }
+    CLIENT.get(url).header("Cookie", CONSENT_COOKIES.join("; "))
+}
+
 pub async fn request(search: &SearchQuery) -> eyre::Result<RequestResponse> {
-    let url = Url::parse_with_params(
-        "https://www.google.com/search",
+    let pages = search.page_count(PAGE_SIZE);
+    if pages > 1 {
+        return Ok(RequestResponse::Pages(
+            (0..pages)
+                .map(|page| get(web_url(search, page * PAGE_SIZE)))
+                .collect(),
+        ));
+    }
+
+    let mut url = web_url(search, 0);
+    if search.requested_count != DEFAULT_REQUESTED_COUNT {
+        // google doesn't return more than 100
+        url.query_pairs_mut()
+            .append_pair("num", &search.requested_count.min(100).to_string());
+    }
+    Ok(get(url).into())
+}
+
+/// The url for the page of results that starts at the `start`th result.
+fn web_url(search: &SearchQuery, start: usize) -> Url {
+    let mut url = search_url(
+        search,
         &[
-            ("q", search.query.as_str()),
+            ("q", search.with_operators(Operator::ALL).as_str()),
             // nfpr makes it not try to autocorrect
             ("nfpr", "1"),
             ("filter", "0"),
-            ("start", "0"),
+            ("start", &start.to_string()),
         ],
-    )
-    .unwrap();
+    );
+    if search.verbatim {
+        // this is the same as clicking Tools -> All results -> Verbatim
+        url.query_pairs_mut().append_pair("tbs", "li:1");
+    }
+    if let Some(safe) = safesearch_param(search.config.safesearch) {
+        url.query_pairs_mut().append_pair("safe", safe);
+    }
+    url
+}
+
+/// Make a /search url on the right Google domain, with the `hl` and `gl`
+/// parameters set from the configured language.
+fn search_url(search: &SearchQuery, params: &[(&str, &str)]) -> Url {
+    let config_toml = search.config.engines.get(Engine::Google).extra.clone();
+    let config: GoogleConfig = toml::Value::Table(config_toml)
+        .try_into()
+        .unwrap_or_else(|err| {
+            error!("Failed to parse Google config: {err}");
+            GoogleConfig::default()
+        });
+
+    let language = &search.config.language;
+    let region = gl(language);
+    let domain = match region {
+        Some(region) if config.regional_domain => regional_domain(region),
+        _ => "google.com",
+    };
 
-    Ok(CLIENT.get(url).into())
+    let mut url = Url::parse_with_params(&format!("https://www.{domain}/search"), params).unwrap();
+    url.query_pairs_mut().append_pair("hl", hl(language));
+    if let Some(region) = region {
+        url.query_pairs_mut()
+            .append_pair("gl", &region.to_lowercase());
+    }
+    url
+}
+
+/// Google's interface language, which is just the primary subtag (like `en`)
+/// except for a few languages where it also uses the region.
+fn hl(language: &str) -> &str {
+    match language {
+        "pt-BR" | "pt-PT" | "zh-CN" | "zh-TW" => language,
+        _ => language.split('-').next().unwrap_or(language),
+    }
+}
+
+/// The region from a language tag, like `DE` in `de-DE`.
+fn gl(language: &str) -> Option<&str> {
+    language
+        .split('-')
+        .nth(1)
+        .filter(|region| region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+fn regional_domain(region: &str) -> &'static str {
+    match region.to_ascii_uppercase().as_str() {
+        "AT" => "google.at",
+        "AU" => "google.com.au",
+        "BE" => "google.be",
+        "BR" => "google.com.br",
+        "CA" => "google.ca",
+        "CH" => "google.ch",
+        "DE" => "google.de",
+        "DK" => "google.dk",
+        "ES" => "google.es",
+        "FI" => "google.fi",
+        "FR" => "google.fr",
+        "GB" => "google.co.uk",
+        "IE" => "google.ie",
+        "IN" => "google.co.in",
+        "IT" => "google.it",
+        "JP" => "google.co.jp",
+        "KR" => "google.co.kr",
+        "MX" => "google.com.mx",
+        "NL" => "google.nl",
+        "NO" => "google.no",
+        "NZ" => "google.co.nz",
+        "PL" => "google.pl",
+        "PT" => "google.pt",
+        "RU" => "google.ru",
+        "SE" => "google.se",
+        "TR" => "google.com.tr",
+        "TW" => "google.com.tw",
+        "UA" => "google.com.ua",
+        // google.cn doesn't do search anymore
+        _ => "google.com",
+    }
 }
 
 pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    // the "unusual traffic" captcha
+    check_block_page(body, &["/sorry/index", "id=\"captcha-form\""])?;
+    if body.contains(CONSENT_PAGE_MARKER) {
+        SEND_CONSENT.store(true, Ordering::Relaxed);
+        return Err(ConsentPage.into());
+    }
     parse_html_response_with_opts(
         body,
         ParseOpts::new()
@@ -40,7 +192,14 @@ pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
             .description(
                 "div[data-sncf='2'], div[data-sncf='1,2'], div[style='-webkit-line-clamp:2']",
             )
+            // the row with ratings, cooking times, etc
+            .extras("div.fG8Fp, div.uo4vr")
             .featured_snippet("block-component")
+            // "did you mean", which is shown instead of autocorrecting because
+            // of nfpr
+            .correction("a.gL9Hy")
+            // the "people also search for" chips at the bottom
+            .related_searches("a.k8XOCe")
             .featured_snippet_description(QueryMethod::Manual(Box::new(|el: &ElementRef| {
                 let mut description = String::new();
 
@@ -142,15 +301,31 @@ pub fn parse_autocomplete_response(body: &str) -> eyre::Result<Vec<String>> {
         .collect())
 }
 
-pub fn request_images(query: &str) -> wreq::RequestBuilder {
+pub fn request_images(query: &SearchQuery) -> wreq::RequestBuilder {
     // ok so google also has a json api for images BUT it gives us less results
-    CLIENT.get(
-        Url::parse_with_params(
-            "https://www.google.com/search",
-            &[("q", query), ("udm", "2"), ("prmd", "ivsnmbtz")],
-        )
-        .unwrap(),
-    )
+    let mut url = search_url(
+        query,
+        &[
+            ("q", query.query.as_str()),
+            ("udm", "2"),
+            ("prmd", "ivsnmbtz"),
+        ],
+    );
+    if let Some(safe) = safesearch_param(query.config.safesearch) {
+        url.query_pairs_mut().append_pair("safe", safe);
+    }
+    if let Some(tbs) = query.image_filters.google_tbs() {
+        url.query_pairs_mut().append_pair("tbs", &tbs);
+    }
+    get(url)
+}
+
+fn safesearch_param(safesearch: SafeSearch) -> Option<&'static str> {
+    match safesearch {
+        SafeSearch::Off => Some("off"),
+        SafeSearch::Moderate => None,
+        SafeSearch::Strict => Some("active"),
+    }
 }
 
 pub fn parse_images_response(body: &str) -> eyre::Result<EngineImagesResponse> {
@@ -222,6 +397,7 @@ pub fn parse_images_response(body: &str) -> eyre::Result<EngineImagesResponse> {
             title,
             width,
             height,
+            nsfw: false,
         });
     }
 
@@ -1,29 +1,173 @@
+use scraper::{ElementRef, Html, Selector};
 use url::Url;
 
 use crate::{
-    engines::{EngineResponse, RequestResponse, CLIENT},
+    engines::{
+        answer::regex, EngineResponse, EngineScholarResult, Operator, RequestResponse, SearchQuery,
+        CLIENT, DEFAULT_REQUESTED_COUNT,
+    },
     parse::{parse_html_response_with_opts, ParseOpts},
+    urls::normalize_url,
 };
 
-pub async fn request(query: &str) -> RequestResponse {
-    CLIENT
-        .get(
-            Url::parse_with_params(
-                "https://scholar.google.com/scholar",
-                &[("hl", "en"), ("as_sdt", "0,5"), ("q", query), ("btnG", "")],
-            )
-            .unwrap(),
-        )
-        .into()
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    let mut url = Url::parse_with_params(
+        "https://scholar.google.com/scholar",
+        &[
+            ("hl", "en"),
+            ("as_sdt", "0,5"),
+            (
+                "q",
+                query
+                    .quoted_if_verbatim(&[Operator::Site, Operator::ExcludeSite])
+                    .as_str(),
+            ),
+            ("btnG", ""),
+        ],
+    )
+    .unwrap();
+    if query.requested_count != DEFAULT_REQUESTED_COUNT {
+        // scholar doesn't return more than 20
+        url.query_pairs_mut()
+            .append_pair("num", &query.requested_count.min(20).to_string());
+    }
+    CLIENT.get(url).into()
 }
 
 pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
-    parse_html_response_with_opts(
+    let mut response = parse_html_response_with_opts(
         body,
         ParseOpts::new()
             .result("div.gs_r")
             .title("h3")
             .href("h3 > a[href]")
             .description("div.gs_rs"),
-    )
+    )?;
+
+    // the details are matched to the results by url, since the generic parser
+    // skips some results
+    let dom = Html::parse_document(body);
+    let result_sel = Selector::parse("div.gs_r").unwrap();
+    let href_sel = Selector::parse("h3 > a[href]").unwrap();
+    for el in dom.select(&result_sel) {
+        let Some(href) = el
+            .select(&href_sel)
+            .next()
+            .and_then(|a| a.value().attr("href"))
+        else {
+            continue;
+        };
+        let url = normalize_url(href);
+        let Some(result) = response.search_results.iter_mut().find(|r| r.url == url) else {
+            continue;
+        };
+        let details = parse_details(&el);
+        result.extras.venue = details.venue;
+        result.extras.cited_by = details.cited_by;
+        result.extras.scholar = Some(details.scholar);
+    }
+
+    Ok(response)
+}
+
+struct Details {
+    venue: Option<String>,
+    cited_by: Option<u32>,
+    scholar: EngineScholarResult,
+}
+
+/// The authors line, the "Cited by" link, and the pdf link of a result.
+fn parse_details(el: &ElementRef) -> Details {
+    let text = |selector: &str| {
+        el.select(&Selector::parse(selector).unwrap())
+            .next()
+            .map(|e| e.text().collect::<String>().replace('\u{a0}', " "))
+    };
+
+    // like "A Vaswani, N Shazeer, N Parmar… - Advances in neural …, 2017 -
+    // proceedings.neurips.cc"
+    let authors_line = text("div.gs_a").unwrap_or_default();
+    let mut parts = authors_line.split(" - ").map(str::trim);
+    let authors = parts
+        .next()
+        .unwrap_or_default()
+        .split(',')
+        .map(|a| a.trim().trim_end_matches('…').trim())
+        .filter(|a| !a.is_empty())
+        .map(str::to_string)
+        .collect();
+    let publication = parts.next().unwrap_or_default();
+    let year = regex!(r"\b(1[5-9]\d{2}|20\d{2})\b")
+        .captures_iter(publication)
+        .last()
+        .and_then(|c| c[1].parse().ok());
+    let venue = regex!(r",?\s*\d{4}$")
+        .replace(publication, "")
+        .trim()
+        .to_string();
+    let venue = match (venue.is_empty(), year) {
+        (false, Some(year)) => Some(format!("{venue}, {year}")),
+        (false, None) => Some(venue),
+        (true, Some(year)) => Some(year.to_string()),
+        (true, None) => None,
+    };
+
+    let cited_by = el
+        .select(&Selector::parse("div.gs_fl a").unwrap())
+        .find_map(|a| {
+            a.text()
+                .collect::<String>()
+                .strip_prefix("Cited by ")?
+                .trim()
+                .parse()
+                .ok()
+        });
+    let pdf_url = el
+        .select(&Selector::parse("div.gs_or_ggsm a[href]").unwrap())
+        .next()
+        .and_then(|a| a.value().attr("href"))
+        .map(str::to_string);
+
+    Details {
+        venue,
+        cited_by,
+        scholar: EngineScholarResult {
+            authors,
+            year,
+            pdf_url,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_details() {
+        let body = r##"
+            <div class="gs_r gs_or gs_scl">
+                <div class="gs_ggs gs_fl"><div class="gs_ggsd"><div class="gs_or_ggsm">
+                    <a href="https://arxiv.org/pdf/1706.03762"><span class="gs_ctg2">[PDF]</span> arxiv.org</a>
+                </div></div></div>
+                <div class="gs_ri">
+                    <h3 class="gs_rt"><a href="https://proceedings.neurips.cc/paper/7181">Attention is all you need</a></h3>
+                    <div class="gs_a">A Vaswani, N Shazeer, N Parmar… - Advances in neural …, 2017 - proceedings.neurips.cc</div>
+                    <div class="gs_rs">The dominant sequence transduction models are based on …</div>
+                    <div class="gs_fl gs_flb"><a href="/scholar?cites=1">Cited by 180000</a> <a href="#">Related articles</a></div>
+                </div>
+            </div>
+        "##;
+        let response = parse_response(body).unwrap();
+        let extras = &response.search_results[0].extras;
+        assert_eq!(extras.venue.as_deref(), Some("Advances in neural …, 2017"));
+        assert_eq!(extras.cited_by, Some(180000));
+        let scholar = extras.scholar.as_ref().unwrap();
+        assert_eq!(scholar.authors, ["A Vaswani", "N Shazeer", "N Parmar"]);
+        assert_eq!(scholar.year, Some(2017));
+        assert_eq!(
+            scholar.pdf_url.as_deref(),
+            Some("https://arxiv.org/pdf/1706.03762")
+        );
+    }
 }
@@ -0,0 +1,117 @@
+//! Kagi's search API, for instances that pay for it. It needs an `api_key`
+//! from https://kagi.com/settings?p=api, and every search costs money, so it's
+//! off unless it's enabled in the config.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::engines::{
+    Engine, EngineResponse, EngineSearchResult, Operator, RequestResponse, SearchQuery, CLIENT,
+};
+
+#[derive(Deserialize, Default)]
+struct KagiConfig {
+    api_key: Option<String>,
+}
+
+pub async fn request(query: &SearchQuery) -> eyre::Result<RequestResponse> {
+    let config: KagiConfig =
+        toml::Value::Table(query.config.engines.get(Engine::Kagi).extra.clone()).try_into()?;
+    let Some(api_key) = config.api_key else {
+        return Ok(RequestResponse::Skipped("needs an api key"));
+    };
+
+    Ok(CLIENT
+        .get(
+            Url::parse_with_params(
+                "https://kagi.com/api/v0/search",
+                &[
+                    ("q", query.quoted_if_verbatim(&[Operator::Site]).as_str()),
+                    ("limit", &query.requested_count.to_string()),
+                ],
+            )
+            .unwrap(),
+        )
+        .header("Authorization", format!("Bot {api_key}"))
+        .into())
+}
+
+#[derive(Deserialize)]
+struct KagiResponse {
+    /// null when there's an error.
+    data: Option<Vec<KagiObject>>,
+    error: Option<Vec<KagiError>>,
+}
+
+/// Objects are either results (`t` is 0) or related searches (`t` is 1).
+#[derive(Deserialize)]
+struct KagiObject {
+    t: u8,
+    url: Option<String>,
+    title: Option<String>,
+    snippet: Option<String>,
+    #[serde(default)]
+    list: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct KagiError {
+    msg: String,
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    let response: KagiResponse = serde_json::from_str(body)?;
+    if let Some(error) = response.error.and_then(|e| e.into_iter().next()) {
+        eyre::bail!("kagi returned an error: {}", error.msg);
+    }
+
+    let mut search_results = Vec::new();
+    let mut related_searches = Vec::new();
+    for object in response.data.unwrap_or_default() {
+        match object.t {
+            0 => {
+                let (Some(url), Some(title)) = (object.url, object.title) else {
+                    continue;
+                };
+                search_results.push(EngineSearchResult {
+                    url,
+                    title,
+                    description: object.snippet.unwrap_or_default(),
+                    description_from_page: false,
+                    extras: Default::default(),
+                });
+            }
+            1 => related_searches.extend(object.list),
+            _ => {}
+        }
+    }
+    Ok(EngineResponse {
+        search_results,
+        related_searches,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let body = r#"{
+            "meta": {"id": "120145e5-c3b2-4d2a-8a25-f6b1fcc6b12d", "node": "us-east", "ms": 420},
+            "data": [
+                {"t": 0, "rank": 1, "url": "https://www.rust-lang.org/", "title": "Rust Programming Language", "snippet": "A language empowering everyone"},
+                {"t": 1, "list": ["rust tutorial", "rust vs go"]}
+            ]
+        }"#;
+        let response = parse_response(body).unwrap();
+        assert_eq!(response.search_results.len(), 1);
+        assert_eq!(response.search_results[0].url, "https://www.rust-lang.org/");
+        assert_eq!(response.related_searches, ["rust tutorial", "rust vs go"]);
+
+        let body =
+            r#"{"meta": {}, "data": null, "error": [{"code": 1, "msg": "Insufficient credit"}]}"#;
+        assert!(parse_response(body).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+//! LibreY (and LibreX, which has the same API), a meta-frontend for Google and
+//! others. Instances can be set with `endpoints` in the engine config.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::engines::{
+    endpoints, Engine, EngineResponse, EngineSearchResult, Operator, RequestResponse, SearchQuery,
+    CLIENT,
+};
+
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    endpoints::requests(
+        Engine::LibreY,
+        query,
+        &["https://search.ahwx.org"],
+        |endpoint| request_to(endpoint, query),
+    )
+}
+
+/// A search request to one instance, like `https://search.ahwx.org`.
+pub fn request_to(endpoint: &str, query: &SearchQuery) -> wreq::RequestBuilder {
+    let search_query = query.quoted_if_verbatim(&[Operator::Site, Operator::ExcludeSite]);
+    CLIENT.get(
+        Url::parse_with_params(
+            &format!("{endpoint}/api.php"),
+            // t=0 is the general (text) results
+            &[("q", search_query.as_str()), ("p", "0"), ("t", "0")],
+        )
+        .unwrap(),
+    )
+}
+
+/// Special results (like answers) are mixed in with the normal ones and don't
+/// have a title or url, so they're optional here and skipped.
+#[derive(Deserialize, Debug)]
+struct LibreYResult {
+    url: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    let results: Vec<LibreYResult> = serde_json::from_str(body)?;
+
+    let search_results = results
+        .into_iter()
+        .filter_map(|result| {
+            Some(EngineSearchResult {
+                url: result.url?,
+                title: result.title?,
+                description: result.description.unwrap_or_default(),
+                description_from_page: false,
+                extras: Default::default(),
+            })
+        })
+        .collect();
+
+    Ok(EngineResponse {
+        search_results,
+        featured_snippet: None,
+        answer_html: None,
+        infobox_html: None,
+        corrected_query: None,
+        related_searches: Vec::new(),
+    })
+}
@@ -0,0 +1,392 @@
+//! Notes and documents (markdown, text, and html files) in a directory on the
+//! server, so personal notes can be searched along with the web. The files are
+//! kept in an sqlite full-text index, which is updated when they change.
+//!
+//! The index is sqlite's FTS5 rather than Tantivy. sqlite is already bundled
+//! for the history and snapshots, and FTS5's bm25 ranking is plenty for a
+//! directory of notes, so it's not worth adding a second search library.
+//!
+//! The directory is set with `directory` in the engine config, and `base_url`
+//! can be set if the files are served somewhere, since browsers won't open
+//! `file://` links from a web page. The engine is opt-in since it shows the
+//! server's files to everyone who can search.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use eyre::eyre;
+use parking_lot::Mutex;
+use rusqlite::{params, Connection};
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use tracing::{debug, error, warn};
+use url::Url;
+
+use crate::{
+    config::Config,
+    engines::{Engine, EngineResponse, EngineSearchResult, RequestResponse, SearchQuery},
+};
+
+/// How often the directory is checked for changes.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(60);
+const EXTENSIONS: &[&str] = &["md", "markdown", "txt", "html", "htm"];
+/// Bigger files are probably not notes.
+const MAX_FILE_SIZE: u64 = 5_000_000;
+
+const SCHEMA: &str = "
+CREATE TABLE files (
+    path TEXT PRIMARY KEY,
+    modified INTEGER NOT NULL
+);
+CREATE VIRTUAL TABLE documents USING fts5(path UNINDEXED, title, body);
+";
+
+#[derive(Deserialize)]
+struct LocalConfig {
+    directory: Option<String>,
+    base_url: Option<String>,
+}
+
+/// The index of each directory. They're in memory, so they're built again from
+/// the files after a restart.
+static INDEXES: LazyLock<Mutex<HashMap<PathBuf, Arc<Mutex<Connection>>>>> =
+    LazyLock::new(Default::default);
+
+fn index(directory: &Path) -> eyre::Result<Arc<Mutex<Connection>>> {
+    let mut indexes = INDEXES.lock();
+    if let Some(index) = indexes.get(directory) {
+        return Ok(index.clone());
+    }
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch(SCHEMA)?;
+    let index = Arc::new(Mutex::new(conn));
+    indexes.insert(directory.to_owned(), index.clone());
+    Ok(index)
+}
+
+/// Keep the index of the directory up to date in the background, if the
+/// engine is enabled, so searches never wait for the files to be read.
+pub fn spawn(config: Arc<Config>) {
+    let engine_config = config.engines.get(Engine::Local);
+    if !engine_config.enabled {
+        return;
+    }
+    let directory = match toml::Value::Table(engine_config.extra.clone()).try_into() {
+        Ok(LocalConfig {
+            directory: Some(directory),
+            ..
+        }) => PathBuf::from(directory),
+        _ => {
+            warn!("The local engine is enabled, but it needs a directory");
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        loop {
+            let scan_directory = directory.clone();
+            let result = tokio::task::spawn_blocking(move || update_index(&scan_directory)).await;
+            if let Err(err) = result.map_err(eyre::Report::from).and_then(|r| r) {
+                error!("Couldn't update the local index for {directory:?}: {err}");
+            }
+            tokio::time::sleep(RESCAN_INTERVAL).await;
+        }
+    });
+}
+
+pub async fn request(query: &SearchQuery) -> eyre::Result<RequestResponse> {
+    let config: LocalConfig =
+        toml::Value::Table(query.config.engines.get(Engine::Local).extra.clone()).try_into()?;
+    let directory = PathBuf::from(
+        config
+            .directory
+            .ok_or_else(|| eyre!("the local engine needs a directory"))?,
+    );
+    let search_query = query.query.clone();
+    let limit = query.requested_count;
+
+    // sqlite blocks
+    let search_directory = directory.clone();
+    let matches =
+        tokio::task::spawn_blocking(move || search(&search_directory, &search_query, limit))
+            .await??;
+
+    let search_results = matches
+        .into_iter()
+        .map(|m| EngineSearchResult {
+            url: file_url(&directory, &m.path, config.base_url.as_deref()),
+            title: m.title,
+            description: m.snippet,
+            description_from_page: false,
+            extras: Default::default(),
+        })
+        .collect();
+    Ok(RequestResponse::Instant(Box::new(EngineResponse {
+        search_results,
+        ..EngineResponse::new()
+    })))
+}
+
+#[derive(Debug)]
+struct Match {
+    /// Relative to the directory.
+    path: String,
+    title: String,
+    snippet: String,
+}
+
+fn search(directory: &Path, query: &str, limit: usize) -> eyre::Result<Vec<Match>> {
+    let Some(fts_query) = fts_query(query) else {
+        return Ok(vec![]);
+    };
+
+    // the index is empty until the first scan is done
+    let index = index(directory)?;
+    let conn = index.lock();
+    let mut statement = conn.prepare(
+        "SELECT path, title, snippet(documents, 2, '', '', '…', 32) FROM documents
+        WHERE documents MATCH ?1 ORDER BY rank LIMIT ?2",
+    )?;
+    let matches = statement
+        .query_map(params![fts_query, limit as i64], |row| {
+            Ok(Match {
+                path: row.get(0)?,
+                title: row.get(1)?,
+                snippet: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(matches)
+}
+
+/// Every word in the query as a quoted string, so they all have to match and
+/// nothing in the query is read as fts5 syntax.
+fn fts_query(query: &str) -> Option<String> {
+    let words = query
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\""))
+        .collect::<Vec<_>>();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Index the files that were added or changed since the last scan, and remove
+/// the ones that were deleted. The files are read before the index is locked,
+/// so searches aren't held up by a big directory.
+fn update_index(directory: &Path) -> eyre::Result<()> {
+    let index = index(directory)?;
+    let mut files = Vec::new();
+    find_files(directory, directory, &mut files);
+
+    let indexed = {
+        let conn = index.lock();
+        let mut statement = conn.prepare("SELECT path, modified FROM files")?;
+        let indexed = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        indexed
+    };
+
+    let mut changed = Vec::new();
+    for (path, modified) in &files {
+        if indexed.get(path) == Some(modified) {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(directory.join(path)) else {
+            warn!("Couldn't read {path} for the local index");
+            continue;
+        };
+        let (title, body) = extract_text(path, &content);
+        changed.push((path, modified, title, body));
+    }
+    let current = files.iter().map(|(path, _)| path).collect::<Vec<_>>();
+    let removed = indexed
+        .keys()
+        .filter(|path| !current.contains(path))
+        .collect::<Vec<_>>();
+    if changed.is_empty() && removed.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = index.lock();
+    let tx = conn.transaction()?;
+    for (path, modified, title, body) in &changed {
+        tx.execute("DELETE FROM documents WHERE path = ?1", params![path])?;
+        tx.execute(
+            "INSERT INTO documents (path, title, body) VALUES (?1, ?2, ?3)",
+            params![path, title, body],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO files (path, modified) VALUES (?1, ?2)",
+            params![path, modified],
+        )?;
+    }
+    for path in &removed {
+        tx.execute("DELETE FROM documents WHERE path = ?1", params![path])?;
+        tx.execute("DELETE FROM files WHERE path = ?1", params![path])?;
+    }
+    tx.commit()?;
+
+    debug!(
+        "updated {} files in the local index for {directory:?}",
+        changed.len() + removed.len()
+    );
+    Ok(())
+}
+
+/// The files that can be indexed and when they were modified, with their paths
+/// relative to the root.
+fn find_files(root: &Path, dir: &Path, files: &mut Vec<(String, i64)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        warn!("Couldn't read {dir:?} for the local index");
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        // hidden files and directories, like .git
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if metadata.is_dir() {
+            find_files(root, &path, files);
+            continue;
+        }
+        let has_extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        if !has_extension || metadata.len() > MAX_FILE_SIZE {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |time| time.as_millis() as i64);
+        files.push((relative.to_string_lossy().into_owned(), modified));
+    }
+}
+
+/// The title and text of a file. The title is the html `<title>` or the first
+/// markdown heading, or the file name if there isn't one.
+fn extract_text(path: &str, content: &str) -> (String, String) {
+    let file_name = Path::new(path)
+        .file_stem()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_owned());
+
+    if path.ends_with(".html") || path.ends_with(".htm") {
+        let dom = Html::parse_document(content);
+        let title = dom
+            .select(&Selector::parse("title").unwrap())
+            .next()
+            .map(|el| el.text().collect::<String>().trim().to_owned())
+            .filter(|title| !title.is_empty());
+        let body = dom
+            .select(&Selector::parse("body").unwrap())
+            .next()
+            .map(|el| el.text().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default();
+        return (title.unwrap_or(file_name), body);
+    }
+
+    let title = content
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_owned())
+        .filter(|title| !title.is_empty());
+    (title.unwrap_or(file_name), content.to_owned())
+}
+
+/// A link to the file, under `base_url` if it's set.
+fn file_url(directory: &Path, path: &str, base_url: Option<&str>) -> String {
+    if let Some(base) =
+        base_url.and_then(|base| Url::parse(&format!("{}/", base.trim_end_matches('/'))).ok())
+    {
+        if let Ok(url) = base.join(&path.replace('\\', "/")) {
+            return url.to_string();
+        }
+    }
+    let path = directory.join(path);
+    let path = path.canonicalize().unwrap_or(path);
+    Url::from_file_path(&path).map_or_else(
+        |()| path.to_string_lossy().into_owned(),
+        |url| url.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(
+            fts_query("rust \"async\" OR traits").as_deref(),
+            Some("\"rust\" \"async\" \"OR\" \"traits\"")
+        );
+        assert_eq!(fts_query("  \"\" "), None);
+    }
+
+    #[test]
+    fn test_extract_text() {
+        let (title, body) = extract_text("notes/setup.md", "Intro\n# Server setup\nsteps");
+        assert_eq!(title, "Server setup");
+        assert!(body.contains("steps"));
+        assert_eq!(extract_text("todo.txt", "buy milk").0, "todo");
+
+        let html = "<title>Wiki</title><body><p>some text</p></body>";
+        assert_eq!(
+            extract_text("wiki.html", html),
+            ("Wiki".to_owned(), "some text".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let dir = std::env::temp_dir().join(format!("metasearch-local-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.md"), "# Tokio\nasync runtime for rust").unwrap();
+        fs::write(dir.join("sub/b.txt"), "rust borrow checker").unwrap();
+        fs::write(dir.join("c.png"), "rust").unwrap();
+
+        update_index(&dir).unwrap();
+        let matches = search(&dir, "rust", 10).unwrap();
+        let mut paths = matches.iter().map(|m| m.path.as_str()).collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(paths, ["a.md", "sub/b.txt"]);
+
+        let matches = search(&dir, "async rust", 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].title, "Tokio");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_url() {
+        let dir = Path::new("/srv/notes");
+        assert_eq!(
+            file_url(
+                dir,
+                "sub/my note.md",
+                Some("https://notes.example.com/files")
+            ),
+            "https://notes.example.com/files/sub/my%20note.md"
+        );
+        assert_eq!(file_url(dir, "a.md", None), "file:///srv/notes/a.md");
+    }
+}
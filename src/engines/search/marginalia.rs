@@ -3,7 +3,7 @@ use tracing::error;
 use url::Url;
 
 use crate::{
-    engines::{Engine, EngineResponse, RequestResponse, SearchQuery, CLIENT},
+    engines::{endpoints, Engine, EngineResponse, Operator, RequestResponse, SearchQuery, CLIENT},
     parse::{parse_html_response_with_opts, ParseOpts},
 };
 
@@ -19,13 +19,6 @@ pub struct MarginaliaArgs {
 }
 
 pub async fn request(query: &SearchQuery) -> RequestResponse {
-    // if the query is more than 3 words or has any special characters then abort
-    if query.split_whitespace().count() > 3
-        || !query.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ')
-    {
-        return RequestResponse::None;
-    }
-
     let config_toml = query.config.engines.get(Engine::Marginalia).extra.clone();
     let config: MarginaliaConfig = match toml::Value::Table(config_toml).try_into() {
         Ok(args) => args,
@@ -35,20 +28,26 @@ pub async fn request(query: &SearchQuery) -> RequestResponse {
         }
     };
 
-    CLIENT
-        .get(
-            Url::parse_with_params(
-                "https://old-search.marginalia.nu/search",
-                &[
-                    ("query", query.query.as_str()),
-                    ("profile", config.args.profile.as_str()),
-                    ("js", config.args.js.as_str()),
-                    ("adtech", config.args.adtech.as_str()),
-                ],
+    let search_query = query.quoted_if_verbatim(&[Operator::Site]);
+    endpoints::requests(
+        Engine::Marginalia,
+        query,
+        &["https://old-search.marginalia.nu"],
+        |endpoint| {
+            CLIENT.get(
+                Url::parse_with_params(
+                    &format!("{endpoint}/search"),
+                    &[
+                        ("query", search_query.as_str()),
+                        ("profile", config.args.profile.as_str()),
+                        ("js", config.args.js.as_str()),
+                        ("adtech", config.args.adtech.as_str()),
+                    ],
+                )
+                .unwrap(),
             )
-            .unwrap(),
-        )
-        .into()
+        },
+    )
 }
 
 pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
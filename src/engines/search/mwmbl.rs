@@ -0,0 +1,99 @@
+//! Mwmbl, a non-profit search engine with a community-built index. The index
+//! is small, so it often has nothing for a query.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::engines::{EngineResponse, EngineSearchResult, SearchQuery, CLIENT};
+
+pub async fn request(query: &SearchQuery) -> wreq::RequestBuilder {
+    CLIENT.get(
+        Url::parse_with_params(
+            "https://api.mwmbl.org/api/v1/search/",
+            &[("s", query.quoted_if_verbatim(&[]).as_str())],
+        )
+        .unwrap(),
+    )
+}
+
+#[derive(Deserialize)]
+struct MwmblResult {
+    url: String,
+    /// The title and extract are split into parts so the matching terms can be
+    /// bold.
+    #[serde(default)]
+    title: Vec<MwmblText>,
+    #[serde(default)]
+    extract: Vec<MwmblText>,
+}
+
+#[derive(Deserialize)]
+struct MwmblText {
+    value: String,
+}
+
+fn join_text(parts: &[MwmblText]) -> String {
+    parts
+        .iter()
+        .map(|p| p.value.as_str())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    // it answers with an empty body or null instead of an empty list sometimes,
+    // which just means the index doesn't have anything for the query
+    if body.trim().is_empty() {
+        return Ok(EngineResponse::new());
+    }
+    let results: Option<Vec<MwmblResult>> = serde_json::from_str(body)?;
+    Ok(EngineResponse {
+        search_results: results
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|r| !r.url.is_empty())
+            .map(|r| {
+                let title = join_text(&r.title);
+                EngineSearchResult {
+                    // results without a title are shown with their url instead
+                    title: if title.is_empty() {
+                        r.url.clone()
+                    } else {
+                        title
+                    },
+                    url: r.url,
+                    description: join_text(&r.extract),
+                    description_from_page: false,
+                    extras: Default::default(),
+                }
+            })
+            .collect(),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let body = r#"[{"url": "https://www.rust-lang.org/", "title": [{"value": "Rust ", "is_bold": true}, {"value": "Programming Language", "is_bold": false}], "extract": [{"value": "A language empowering everyone", "is_bold": false}], "source": "google"}]"#;
+        let response = parse_response(body).unwrap();
+        assert_eq!(response.search_results.len(), 1);
+        assert_eq!(
+            response.search_results[0].title,
+            "Rust Programming Language"
+        );
+        assert_eq!(
+            response.search_results[0].description,
+            "A language empowering everyone"
+        );
+
+        for body in ["[]", "null", ""] {
+            assert!(parse_response(body).unwrap().search_results.is_empty());
+        }
+    }
+}
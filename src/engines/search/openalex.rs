@@ -0,0 +1,176 @@
+//! OpenAlex, an open index of scholarly works.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::engines::{
+    Engine, EngineResponse, EngineSearchResult, ResultExtras, SearchQuery, CLIENT,
+};
+
+/// Abstracts can be very long, and the description is only meant to be a
+/// preview.
+const MAX_ABSTRACT_LENGTH: usize = 300;
+
+#[derive(Deserialize, Default)]
+struct OpenAlexConfig {
+    /// Requests with an email address go to OpenAlex's faster "polite pool".
+    mailto: Option<String>,
+}
+
+pub async fn request(query: &SearchQuery) -> wreq::RequestBuilder {
+    let config: OpenAlexConfig =
+        toml::Value::Table(query.config.engines.get(Engine::OpenAlex).extra.clone())
+            .try_into()
+            .unwrap_or_default();
+
+    let mut url = Url::parse_with_params(
+        "https://api.openalex.org/works",
+        &[
+            ("search", query.quoted_if_verbatim(&[]).as_str()),
+            ("per-page", &query.requested_count.to_string()),
+        ],
+    )
+    .unwrap();
+    if let Some(mailto) = &config.mailto {
+        url.query_pairs_mut().append_pair("mailto", mailto);
+    }
+    CLIENT.get(url)
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAlexResponse {
+    results: Vec<OpenAlexWork>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAlexWork {
+    id: String,
+    doi: Option<String>,
+    display_name: Option<String>,
+    publication_year: Option<u32>,
+    #[serde(default)]
+    cited_by_count: u32,
+    open_access: Option<OpenAlexOpenAccess>,
+    primary_location: Option<OpenAlexLocation>,
+    /// Abstracts are given as a map of words to the positions they're at.
+    abstract_inverted_index: Option<HashMap<String, Vec<usize>>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAlexOpenAccess {
+    is_oa: bool,
+    oa_status: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAlexLocation {
+    landing_page_url: Option<String>,
+    source: Option<OpenAlexSource>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAlexSource {
+    display_name: String,
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    let response: OpenAlexResponse = serde_json::from_str(body)?;
+
+    let search_results = response
+        .results
+        .into_iter()
+        .filter_map(|work| {
+            let title = work.display_name?;
+            let landing_page_url = work
+                .primary_location
+                .as_ref()
+                .and_then(|l| l.landing_page_url.clone());
+            let url = work.doi.or(landing_page_url).unwrap_or(work.id);
+
+            let source = work
+                .primary_location
+                .and_then(|l| l.source)
+                .map(|s| s.display_name);
+            let venue = match (source, work.publication_year) {
+                (Some(source), Some(year)) => Some(format!("{source}, {year}")),
+                (Some(source), None) => Some(source),
+                (None, Some(year)) => Some(year.to_string()),
+                (None, None) => None,
+            };
+
+            Some(EngineSearchResult {
+                url,
+                title,
+                description: work
+                    .abstract_inverted_index
+                    .map(|index| truncate(&rebuild_abstract(&index)))
+                    .unwrap_or_default(),
+                description_from_page: false,
+                extras: ResultExtras {
+                    venue,
+                    cited_by: Some(work.cited_by_count),
+                    open_access: work
+                        .open_access
+                        .filter(|oa| oa.is_oa)
+                        .map(|oa| oa.oa_status.unwrap_or_else(|| "open".to_string())),
+                    ..Default::default()
+                },
+            })
+        })
+        .collect();
+
+    Ok(EngineResponse {
+        search_results,
+        featured_snippet: None,
+        answer_html: None,
+        infobox_html: None,
+        corrected_query: None,
+        related_searches: Vec::new(),
+    })
+}
+
+fn rebuild_abstract(index: &HashMap<String, Vec<usize>>) -> String {
+    let mut words = index
+        .iter()
+        .flat_map(|(word, positions)| positions.iter().map(move |&p| (p, word.as_str())))
+        .collect::<Vec<_>>();
+    words.sort_unstable_by_key(|(position, _)| *position);
+    words
+        .into_iter()
+        .map(|(_, word)| word)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn truncate(text: &str) -> String {
+    if text.len() <= MAX_ABSTRACT_LENGTH {
+        return text.to_string();
+    }
+    let mut end = MAX_ABSTRACT_LENGTH;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let text = &text[..end];
+    // cut at the last word so we don't end in the middle of one
+    let text = text.rsplit_once(' ').map_or(text, |(text, _)| text);
+    format!("{text}…")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebuild_abstract() {
+        let index = HashMap::from([
+            ("Rust".to_string(), vec![0]),
+            ("is".to_string(), vec![1, 4]),
+            ("fast".to_string(), vec![2]),
+            ("and".to_string(), vec![3]),
+            ("safe.".to_string(), vec![5]),
+        ]);
+        assert_eq!(rebuild_abstract(&index), "Rust is fast and is safe.");
+    }
+}
@@ -0,0 +1,42 @@
+use url::Url;
+
+use crate::{
+    engines::{EngineResponse, Operator, SafeSearch, SearchQuery, CLIENT},
+    parse::{check_block_page, parse_html_response_with_opts, ParseOpts},
+};
+
+pub async fn request(query: &SearchQuery) -> wreq::RequestBuilder {
+    let safesearch = match query.config.safesearch {
+        SafeSearch::Off => "0",
+        SafeSearch::Moderate => "1",
+        SafeSearch::Strict => "2",
+    };
+    CLIENT.get(
+        Url::parse_with_params(
+            "https://petalsearch.com/search",
+            &[
+                (
+                    "query",
+                    query.quoted_if_verbatim(&[Operator::Site]).as_str(),
+                ),
+                ("channel", "all"),
+                ("ps", safesearch),
+                ("lang", &query.config.language),
+            ],
+        )
+        .unwrap(),
+    )
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    check_block_page(body, &["id=\"captcha", "/captcha/", "verifycode"])?;
+
+    parse_html_response_with_opts(
+        body,
+        ParseOpts::new()
+            .result("div.webpage-content")
+            .title("h3 a, a.title")
+            .href("h3 a[href], a.title[href]")
+            .description("div.webpage-text, p.content"),
+    )
+}
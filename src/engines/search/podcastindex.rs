@@ -0,0 +1,211 @@
+//! Podcasts and episodes from the Podcast Index, for the media tab. This needs
+//! an `api_key` and `api_secret`, which are free from
+//! https://api.podcastindex.org.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use url::Url;
+
+use crate::engines::{
+    Engine, EngineResponse, EngineSearchResult, RequestResponse, ResultExtras, SearchQuery, CLIENT,
+};
+
+#[derive(Deserialize, Default)]
+struct PodcastIndexConfig {
+    api_key: Option<String>,
+    api_secret: Option<String>,
+}
+
+pub async fn request(query: &SearchQuery) -> eyre::Result<RequestResponse> {
+    let config: PodcastIndexConfig =
+        toml::Value::Table(query.config.engines.get(Engine::PodcastIndex).extra.clone())
+            .try_into()?;
+    let (Some(api_key), Some(api_secret)) = (config.api_key, config.api_secret) else {
+        return Ok(RequestResponse::Skipped("needs an api key"));
+    };
+
+    // shows match the query in their title or author, and episodes match in their
+    // title, description, or people
+    let (shows, episodes) = tokio::join!(
+        search(query, "byterm", &api_key, &api_secret),
+        search(query, "byperson", &api_key, &api_secret),
+    );
+    let mut search_results = parse_shows(&shows?)?;
+    search_results.extend(parse_episodes(&episodes?)?);
+
+    Ok(RequestResponse::Instant(Box::new(EngineResponse {
+        search_results,
+        featured_snippet: None,
+        answer_html: None,
+        infobox_html: None,
+        corrected_query: None,
+        related_searches: Vec::new(),
+    })))
+}
+
+async fn search(
+    query: &SearchQuery,
+    endpoint: &str,
+    api_key: &str,
+    api_secret: &str,
+) -> eyre::Result<String> {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .to_string();
+    let authorization = Sha1::digest(format!("{api_key}{api_secret}{time}").as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+
+    let url = Url::parse_with_params(
+        &format!("https://api.podcastindex.org/api/1.0/search/{endpoint}"),
+        &[
+            ("q", query.query.as_str()),
+            ("max", &query.requested_count.to_string()),
+        ],
+    )
+    .unwrap();
+    let res = CLIENT
+        .get(url)
+        .header("X-Auth-Key", api_key)
+        .header("X-Auth-Date", time)
+        .header("Authorization", authorization)
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        eyre::bail!("podcast index returned {}", res.status());
+    }
+    Ok(res.text().await?)
+}
+
+#[derive(Deserialize, Debug)]
+struct ShowsResponse {
+    #[serde(default)]
+    feeds: Vec<Show>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Show {
+    title: String,
+    /// The RSS feed.
+    url: String,
+    /// The podcast's website.
+    #[serde(default)]
+    link: String,
+    #[serde(default)]
+    author: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct EpisodesResponse {
+    #[serde(default)]
+    items: Vec<Episode>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Episode {
+    title: String,
+    #[serde(default)]
+    link: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    feed_title: String,
+    #[serde(default)]
+    enclosure_url: String,
+}
+
+fn parse_shows(body: &str) -> eyre::Result<Vec<EngineSearchResult>> {
+    let response: ShowsResponse = serde_json::from_str(body)?;
+    Ok(response
+        .feeds
+        .into_iter()
+        .map(|show| EngineSearchResult {
+            url: if show.link.is_empty() {
+                show.url
+            } else {
+                show.link
+            },
+            title: show.title,
+            description: clean_description(&show.description),
+            description_from_page: false,
+            extras: ResultExtras {
+                venue: (!show.author.is_empty()).then_some(show.author),
+                ..Default::default()
+            },
+        })
+        .collect())
+}
+
+fn parse_episodes(body: &str) -> eyre::Result<Vec<EngineSearchResult>> {
+    let response: EpisodesResponse = serde_json::from_str(body)?;
+    Ok(response
+        .items
+        .into_iter()
+        .filter(|episode| !episode.link.is_empty() || !episode.enclosure_url.is_empty())
+        .map(|episode| EngineSearchResult {
+            url: if episode.link.is_empty() {
+                episode.enclosure_url.clone()
+            } else {
+                episode.link
+            },
+            title: episode.title,
+            description: clean_description(&episode.description),
+            description_from_page: false,
+            extras: ResultExtras {
+                venue: (!episode.feed_title.is_empty()).then_some(episode.feed_title),
+                audio_url: (!episode.enclosure_url.is_empty()).then_some(episode.enclosure_url),
+                ..Default::default()
+            },
+        })
+        .collect())
+}
+
+/// Descriptions come from the feeds, so they're often html.
+fn clean_description(description: &str) -> String {
+    let text = scraper::Html::parse_fragment(description)
+        .root_element()
+        .text()
+        .collect::<String>();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let shows = r#"{"status":"true","feeds":[
+            {"id":1,"title":"Rustacean Station","url":"https://rustacean-station.org/podcast.rss",
+             "link":"","author":"Rustacean Station","description":"<p>A podcast about <b>Rust</b></p>"}
+        ],"count":1}"#;
+        let results = parse_shows(shows).unwrap();
+        assert_eq!(results[0].url, "https://rustacean-station.org/podcast.rss");
+        assert_eq!(results[0].description, "A podcast about Rust");
+
+        let episodes = r#"{"status":"true","items":[
+            {"id":2,"title":"Rust 1.80","link":"https://rustacean-station.org/episode/rust-1.80/",
+             "description":"","feedTitle":"Rustacean Station",
+             "enclosureUrl":"https://example.com/rust-1.80.mp3"},
+            {"id":3,"title":"No links","link":"","enclosureUrl":""}
+        ],"count":2}"#;
+        let results = parse_episodes(episodes).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].extras.audio_url.as_deref(),
+            Some("https://example.com/rust-1.80.mp3")
+        );
+        assert_eq!(
+            results[0].extras.venue.as_deref(),
+            Some("Rustacean Station")
+        );
+    }
+}
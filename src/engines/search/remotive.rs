@@ -0,0 +1,89 @@
+//! Remote jobs from Remotive, for the jobs tab.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::engines::{
+    EngineResponse, EngineSearchResult, JobPosting, ResultExtras, SearchQuery, CLIENT,
+};
+
+pub async fn request(query: &SearchQuery) -> wreq::RequestBuilder {
+    CLIENT.get(
+        Url::parse_with_params(
+            "https://remotive.com/api/remote-jobs",
+            &[
+                ("search", query.query.as_str()),
+                ("limit", &query.requested_count.to_string()),
+            ],
+        )
+        .unwrap(),
+    )
+}
+
+#[derive(Deserialize, Debug)]
+struct RemotiveResponse {
+    #[serde(default)]
+    jobs: Vec<RemotiveJob>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RemotiveJob {
+    url: String,
+    title: String,
+    company_name: String,
+    #[serde(default)]
+    candidate_required_location: String,
+    #[serde(default)]
+    job_type: String,
+    #[serde(default)]
+    salary: String,
+    /// Like "2024-05-01T10:14:05".
+    #[serde(default)]
+    publication_date: String,
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    let response: RemotiveResponse = serde_json::from_str(body)?;
+
+    let search_results = response
+        .jobs
+        .into_iter()
+        .map(|job| {
+            // the descriptions are whole html pages, so the type and salary are more useful
+            let description = [job.job_type.replace('_', " "), job.salary]
+                .into_iter()
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join(" · ");
+            let posted = job
+                .publication_date
+                .split('T')
+                .next()
+                .filter(|d| !d.is_empty())
+                .map(str::to_string);
+            EngineSearchResult {
+                url: job.url,
+                title: job.title,
+                description,
+                description_from_page: false,
+                extras: ResultExtras {
+                    job: Some(JobPosting {
+                        company: job.company_name,
+                        location: job.candidate_required_location,
+                        posted,
+                    }),
+                    ..Default::default()
+                },
+            }
+        })
+        .collect();
+
+    Ok(EngineResponse {
+        search_results,
+        featured_snippet: None,
+        answer_html: None,
+        infobox_html: None,
+        corrected_query: None,
+        related_searches: Vec::new(),
+    })
+}
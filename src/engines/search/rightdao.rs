@@ -1,13 +1,19 @@
 use url::Url;
 
 use crate::{
-    engines::{EngineResponse, RequestResponse, CLIENT},
+    engines::{EngineResponse, RequestResponse, SearchQuery, CLIENT},
     parse::{parse_html_response_with_opts, ParseOpts},
 };
 
-pub async fn request(query: &str) -> RequestResponse {
+pub async fn request(query: &SearchQuery) -> RequestResponse {
     CLIENT
-        .get(Url::parse_with_params("https://rightdao.com/search", &[("q", query)]).unwrap())
+        .get(
+            Url::parse_with_params(
+                "https://rightdao.com/search",
+                &[("q", query.quoted_if_verbatim(&[]).as_str())],
+            )
+            .unwrap(),
+        )
         .into()
 }
 
@@ -0,0 +1,77 @@
+//! A SearXNG instance, for letting a trusted instance scrape the engines that
+//! block us. There's no default since most public instances turn off the json
+//! format, so the instance has to be set with `endpoints` in the engine config.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::engines::{
+    endpoints, Engine, EngineResponse, EngineSearchResult, Operator, RequestResponse, SafeSearch,
+    SearchQuery, CLIENT,
+};
+
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    let search_query = query.quoted_if_verbatim(&[Operator::Site, Operator::ExcludeSite]);
+    let safesearch = match query.config.safesearch {
+        SafeSearch::Off => "0",
+        SafeSearch::Moderate => "1",
+        SafeSearch::Strict => "2",
+    };
+    let language = query.config.language.clone();
+    endpoints::requests(Engine::SearXng, query, &[], |endpoint| {
+        CLIENT.get(
+            Url::parse_with_params(
+                &format!("{endpoint}/search"),
+                &[
+                    ("q", search_query.as_str()),
+                    ("format", "json"),
+                    ("categories", "general"),
+                    ("language", language.as_str()),
+                    ("safesearch", safesearch),
+                ],
+            )
+            .unwrap(),
+        )
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct SearXngResponse {
+    results: Vec<SearXngResult>,
+    #[serde(default)]
+    corrections: Vec<String>,
+    #[serde(default)]
+    suggestions: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearXngResult {
+    url: String,
+    title: String,
+    content: Option<String>,
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    let response: SearXngResponse = serde_json::from_str(body)?;
+
+    let search_results = response
+        .results
+        .into_iter()
+        .map(|result| EngineSearchResult {
+            url: result.url,
+            title: result.title,
+            description: result.content.unwrap_or_default(),
+            description_from_page: false,
+            extras: Default::default(),
+        })
+        .collect();
+
+    Ok(EngineResponse {
+        search_results,
+        featured_snippet: None,
+        answer_html: None,
+        infobox_html: None,
+        corrected_query: response.corrections.into_iter().next(),
+        related_searches: response.suggestions,
+    })
+}
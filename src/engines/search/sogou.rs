@@ -0,0 +1,46 @@
+use scraper::{ElementRef, Selector};
+use url::Url;
+
+use crate::{
+    engines::{EngineResponse, Operator, SearchQuery, CLIENT},
+    parse::{check_block_page, parse_html_response_with_opts, ParseOpts, QueryMethod},
+};
+
+pub async fn request(query: &SearchQuery) -> wreq::RequestBuilder {
+    CLIENT.get(
+        Url::parse_with_params(
+            "https://www.sogou.com/web",
+            &[(
+                "query",
+                query.quoted_if_verbatim(&[Operator::Site]).as_str(),
+            )],
+        )
+        .unwrap(),
+    )
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    // sogou sends suspicious requests to its "antispider" captcha page
+    check_block_page(body, &["/antispider/", "antispiderCaptcha"])?;
+
+    parse_html_response_with_opts(
+        body,
+        ParseOpts::new()
+            .result("div.vrwrap, div.rb")
+            .title("h3 a")
+            .href(QueryMethod::Manual(Box::new(|el: &ElementRef| {
+                let url = el
+                    .select(&Selector::parse("h3 a[href]").unwrap())
+                    .next()
+                    .and_then(|n| n.value().attr("href"))
+                    .unwrap_or_default();
+                // results link to a redirect on sogou's domain
+                Ok(if url.starts_with('/') {
+                    format!("https://www.sogou.com{url}")
+                } else {
+                    url.to_string()
+                })
+            })))
+            .description("p.star-wiki, div.fz-mid.space-txt, p.str_info, div.ft"),
+    )
+}
@@ -0,0 +1,78 @@
+//! Projects on SourceHut's hub, for the code tab. Self-hosted instances can be
+//! used with `endpoints`. The project search is public, so unlike Codeberg
+//! there's no token.
+
+use url::Url;
+
+use crate::{
+    engines::{
+        endpoints, Engine, EngineResponse, HttpResponse, RequestResponse, SearchQuery, CLIENT,
+    },
+    parse::{parse_html_response_with_opts, ParseOpts, QueryMethod},
+};
+
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    endpoints::requests(Engine::SourceHut, query, &["https://sr.ht"], |endpoint| {
+        CLIENT.get(
+            Url::parse_with_params(
+                &format!("{endpoint}/projects"),
+                &[("search", query.query.as_str())],
+            )
+            .unwrap(),
+        )
+    })
+}
+
+pub fn parse_response(res: &HttpResponse) -> eyre::Result<EngineResponse> {
+    let base = res.res.url().origin().ascii_serialization();
+    parse_results(&res.body, base)
+}
+
+fn parse_results(body: &str, base: String) -> eyre::Result<EngineResponse> {
+    parse_html_response_with_opts(
+        body,
+        ParseOpts::new()
+            .result(".event-list > .event")
+            .title("h4")
+            .href(QueryMethod::Manual(Box::new(move |el| {
+                // the heading links to the owner and then the project
+                let href = el
+                    .select(&scraper::Selector::parse("h4 a[href]").unwrap())
+                    .last()
+                    .and_then(|a| a.value().attr("href"))
+                    .unwrap_or_default();
+                Ok(if href.starts_with('/') {
+                    format!("{base}{href}")
+                } else {
+                    href.to_string()
+                })
+            })))
+            .description("p"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_results() {
+        let body = r#"<div class="event-list">
+            <div class="event">
+              <h4><a href="/~sircmpwn/">~sircmpwn</a>/<a href="/~sircmpwn/sourcehut">sourcehut</a></h4>
+              <p>The sr.ht software suite</p>
+            </div>
+            <div class="event">
+              <h4><a href="/~emersion/">~emersion</a>/<a href="/~emersion/soju">soju</a></h4>
+              <p>A user-friendly IRC bouncer</p>
+            </div>
+        </div>"#;
+        let results = parse_results(body, "https://sr.ht".to_string())
+            .unwrap()
+            .search_results;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].url, "https://sr.ht/~sircmpwn/sourcehut");
+        assert_eq!(results[0].title, "~sircmpwn/sourcehut");
+        assert_eq!(results[1].description, "A user-friendly IRC bouncer");
+    }
+}
@@ -0,0 +1,218 @@
+//! Questions from across the Stack Exchange network. The api can only search
+//! one site at a time, so every site in `sites` is searched and the results are
+//! interleaved.
+
+use futures::future::join_all;
+use serde::Deserialize;
+use tracing::warn;
+use url::Url;
+
+use crate::engines::{
+    answer::regex, Engine, EngineResponse, EngineSearchResult, RequestResponse, ResultExtras,
+    SearchQuery, CLIENT,
+};
+
+/// Sites that have their own domain, and their names. Every other site is at
+/// `<site>.stackexchange.com`.
+const SITES: &[(&str, &str, &str)] = &[
+    ("stackoverflow", "stackoverflow.com", "Stack Overflow"),
+    ("superuser", "superuser.com", "Super User"),
+    ("serverfault", "serverfault.com", "Server Fault"),
+    ("askubuntu", "askubuntu.com", "Ask Ubuntu"),
+    ("unix", "unix.stackexchange.com", "Unix & Linux"),
+    ("mathoverflow", "mathoverflow.net", "MathOverflow"),
+];
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct StackExchangeConfig {
+    /// The api site parameters, like "superuser" or "unix".
+    sites: Vec<String>,
+    /// Requests without a key are limited to 300 per day per ip, and every
+    /// search makes one request per site.
+    key: Option<String>,
+}
+
+impl Default for StackExchangeConfig {
+    fn default() -> Self {
+        Self {
+            sites: [
+                "stackoverflow",
+                "superuser",
+                "serverfault",
+                "askubuntu",
+                "unix",
+            ]
+            .map(String::from)
+            .to_vec(),
+            key: None,
+        }
+    }
+}
+
+pub async fn request(query: &SearchQuery) -> eyre::Result<RequestResponse> {
+    let config: StackExchangeConfig = toml::Value::Table(
+        query
+            .config
+            .engines
+            .get(Engine::StackExchangeSearch)
+            .extra
+            .clone(),
+    )
+    .try_into()?;
+
+    let responses = join_all(
+        config
+            .sites
+            .iter()
+            .map(|site| search_site(query, site, config.key.as_deref())),
+    )
+    .await;
+
+    let mut sites = Vec::new();
+    for (site, response) in config.sites.iter().zip(responses) {
+        match response {
+            Ok(results) => sites.push(results),
+            Err(err) => warn!("Stack Exchange search on {site} failed: {err}"),
+        }
+    }
+    if sites.is_empty() && !config.sites.is_empty() {
+        eyre::bail!("every Stack Exchange site failed");
+    }
+
+    Ok(RequestResponse::Instant(Box::new(EngineResponse {
+        search_results: interleave(sites),
+        featured_snippet: None,
+        answer_html: None,
+        infobox_html: None,
+        corrected_query: None,
+        related_searches: Vec::new(),
+    })))
+}
+
+async fn search_site(
+    query: &SearchQuery,
+    site: &str,
+    key: Option<&str>,
+) -> eyre::Result<Vec<EngineSearchResult>> {
+    let mut url = Url::parse_with_params(
+        "https://api.stackexchange.com/2.3/search/excerpts",
+        &[
+            ("order", "desc"),
+            ("sort", "relevance"),
+            ("q", query.query.as_str()),
+            ("site", site),
+            ("pagesize", "10"),
+        ],
+    )
+    .unwrap();
+    if let Some(key) = key {
+        url.query_pairs_mut().append_pair("key", key);
+    }
+    let body = CLIENT.get(url).send().await?.text().await?;
+    parse_site_response(&body, site)
+}
+
+#[derive(Deserialize, Debug)]
+struct ExcerptsResponse {
+    #[serde(default)]
+    items: Vec<Excerpt>,
+    error_message: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Excerpt {
+    question_id: u64,
+    title: String,
+    #[serde(default)]
+    excerpt: String,
+    answer_count: Option<u32>,
+}
+
+fn parse_site_response(body: &str, site: &str) -> eyre::Result<Vec<EngineSearchResult>> {
+    let response: ExcerptsResponse = serde_json::from_str(body)?;
+    if let Some(error_message) = response.error_message {
+        eyre::bail!("{error_message}");
+    }
+
+    let (domain, name) = match SITES.iter().find(|(id, _, _)| *id == site) {
+        Some((_, domain, name)) => (domain.to_string(), name.to_string()),
+        None => (format!("{site}.stackexchange.com"), site.to_string()),
+    };
+
+    let mut results: Vec<EngineSearchResult> = Vec::new();
+    for item in response.items {
+        // answers and questions are separate items, but we only link to questions
+        let url = format!("https://{domain}/questions/{}", item.question_id);
+        if results.iter().any(|r| r.url == url) {
+            continue;
+        }
+        results.push(EngineSearchResult {
+            url,
+            title: strip_html(&item.title),
+            description: strip_html(&item.excerpt),
+            description_from_page: false,
+            extras: ResultExtras {
+                answer_count: item.answer_count,
+                site: Some(name.clone()),
+                ..Default::default()
+            },
+        });
+    }
+    Ok(results)
+}
+
+/// Excerpts have the matching words in highlight spans, and both titles and
+/// excerpts have html entities.
+fn strip_html(s: &str) -> String {
+    let text = regex!("<[^>]*>").replace_all(s, "");
+    html_escape::decode_html_entities(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Take the first result from every site, then the second, and so on, so the
+/// first site doesn't push out the others.
+fn interleave(sites: Vec<Vec<EngineSearchResult>>) -> Vec<EngineSearchResult> {
+    let mut sites = sites
+        .into_iter()
+        .map(|results| results.into_iter())
+        .collect::<Vec<_>>();
+    let mut results = Vec::new();
+    loop {
+        let before = results.len();
+        results.extend(sites.iter_mut().filter_map(|site| site.next()));
+        if results.len() == before {
+            return results;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_site_response() {
+        let body = r#"{"items":[
+            {"item_type":"answer","question_id":12,"answer_id":34,"title":"How do I &quot;grep&quot; recursively?",
+             "excerpt":"Use <span class=\"highlight\">grep</span> -r","answer_count":null},
+            {"item_type":"question","question_id":12,"title":"How do I &quot;grep&quot; recursively?",
+             "excerpt":"I want to search every file","answer_count":5},
+            {"item_type":"question","question_id":56,"title":"Other","excerpt":"","answer_count":0}
+        ],"has_more":false}"#;
+        let results = parse_site_response(body, "superuser").unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].url, "https://superuser.com/questions/12");
+        assert_eq!(results[0].title, "How do I \"grep\" recursively?");
+        assert_eq!(results[0].description, "Use grep -r");
+        assert_eq!(results[0].extras.site.as_deref(), Some("Super User"));
+
+        let results = parse_site_response(body, "gis").unwrap();
+        assert_eq!(results[1].url, "https://gis.stackexchange.com/questions/56");
+
+        let error = r#"{"error_id":502,"error_message":"too many requests from this IP"}"#;
+        assert!(parse_site_response(error, "superuser").is_err());
+    }
+}
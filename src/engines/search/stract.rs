@@ -1,27 +1,29 @@
 use url::Url;
 
 use crate::{
-    engines::{EngineResponse, RequestResponse, CLIENT},
+    engines::{endpoints, Engine, EngineResponse, Operator, RequestResponse, SearchQuery, CLIENT},
     parse::{parse_html_response_with_opts, ParseOpts},
 };
 
-pub async fn request(query: &str) -> RequestResponse {
-    CLIENT
-        .get(
+pub async fn request(query: &SearchQuery) -> RequestResponse {
+    let search_query =
+        query.quoted_if_verbatim(&[Operator::Site, Operator::ExcludeSite, Operator::Intitle]);
+    endpoints::requests(Engine::Stract, query, &["https://stract.com"], |endpoint| {
+        CLIENT.get(
             Url::parse_with_params(
-                "https://stract.com/search",
+                &format!("{endpoint}/search"),
                 &[
                     ("ss", "false"),
                     // this is not a tracking parameter or token
                     // this is stract's default value for the search rankings parameter
                     ("sr", "N4IgNglg1gpgJiAXAbQLoBoRwgZ0rBFDEAIzAHsBjApNAXyA"),
-                    ("q", query),
+                    ("q", search_query.as_str()),
                     ("optic", ""),
                 ],
             )
             .unwrap(),
         )
-        .into()
+    })
 }
 
 pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
@@ -0,0 +1,95 @@
+//! Recipes from TheMealDB, for the recipes tab. It's a small database, but its
+//! recipes have no story to scroll past.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::engines::{EngineResponse, EngineSearchResult, ResultExtras, SearchQuery, CLIENT};
+
+pub async fn request(query: &SearchQuery) -> wreq::RequestBuilder {
+    CLIENT.get(
+        Url::parse_with_params(
+            "https://www.themealdb.com/api/json/v1/1/search.php",
+            // the recipes are searched by name, so "recipe" would only get in the way
+            &[("s", query.query.trim_end_matches(" recipe"))],
+        )
+        .unwrap(),
+    )
+}
+
+#[derive(Deserialize, Debug)]
+struct MealDbResponse {
+    meals: Option<Vec<Meal>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Meal {
+    id_meal: String,
+    str_meal: String,
+    str_category: Option<String>,
+    str_area: Option<String>,
+    str_instructions: Option<String>,
+    str_source: Option<String>,
+    /// The ingredients are in `strIngredient1` to `strIngredient20`.
+    #[serde(flatten)]
+    rest: serde_json::Map<String, serde_json::Value>,
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    let response: MealDbResponse = serde_json::from_str(body)?;
+
+    let search_results = response
+        .meals
+        .unwrap_or_default()
+        .into_iter()
+        .map(|meal| {
+            let ingredient_count = meal
+                .rest
+                .iter()
+                .filter(|(k, v)| {
+                    k.starts_with("strIngredient")
+                        && v.as_str().is_some_and(|v| !v.trim().is_empty())
+                })
+                .count() as u32;
+            let kind = [meal.str_area, meal.str_category]
+                .into_iter()
+                .flatten()
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let instructions = meal
+                .str_instructions
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            EngineSearchResult {
+                url: meal
+                    .str_source
+                    .filter(|s| !s.is_empty())
+                    .unwrap_or_else(|| format!("https://www.themealdb.com/meal/{}", meal.id_meal)),
+                title: meal.str_meal,
+                description: if kind.is_empty() {
+                    instructions
+                } else {
+                    format!("{kind} · {instructions}")
+                },
+                description_from_page: false,
+                extras: ResultExtras {
+                    ingredient_count: (ingredient_count > 0).then_some(ingredient_count),
+                    ..Default::default()
+                },
+            }
+        })
+        .collect();
+
+    Ok(EngineResponse {
+        search_results,
+        featured_snippet: None,
+        answer_html: None,
+        infobox_html: None,
+        corrected_query: None,
+        related_searches: Vec::new(),
+    })
+}
@@ -0,0 +1,40 @@
+//! Results that were clicked before and match the query, see
+//! [`crate::visits`]. The engine has a high weight so they're shown first.
+
+use tracing::error;
+
+use crate::{
+    engines::{EngineResponse, EngineSearchResult, SearchQuery},
+    visits,
+};
+
+/// Only a few, so they don't push the new results too far down.
+const MAX_RESULTS: usize = 3;
+
+pub async fn request(query: &SearchQuery) -> EngineResponse {
+    if !query.config.visits.enabled {
+        return EngineResponse::new();
+    }
+    let visits = match visits::search(&query.query, MAX_RESULTS) {
+        Ok(visits) => visits,
+        Err(err) => {
+            error!("Couldn't search visited pages: {err}");
+            return EngineResponse::new();
+        }
+    };
+
+    EngineResponse {
+        search_results: visits
+            .into_iter()
+            .map(|visit| EngineSearchResult {
+                url: visit.url,
+                title: visit.title,
+                // the other engines usually have a description for it
+                description: String::new(),
+                description_from_page: false,
+                extras: Default::default(),
+            })
+            .collect(),
+        ..EngineResponse::new()
+    }
+}
@@ -0,0 +1,90 @@
+//! Wiby, a search engine for the small web of personal and old-school sites,
+//! which finds different things than Marginalia.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::engines::{answer::regex, EngineResponse, EngineSearchResult, SearchQuery, CLIENT};
+
+pub async fn request(query: &SearchQuery) -> wreq::RequestBuilder {
+    CLIENT.get(
+        Url::parse_with_params(
+            "https://wiby.me/json/",
+            &[("q", query.quoted_if_verbatim(&[]).as_str())],
+        )
+        .unwrap(),
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct WibyResult {
+    #[serde(rename = "URL")]
+    url: String,
+    title: String,
+    #[serde(default)]
+    snippet: String,
+}
+
+pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
+    let results: Vec<WibyResult> = serde_json::from_str(body)?;
+    Ok(EngineResponse {
+        search_results: results
+            .into_iter()
+            .map(|r| EngineSearchResult {
+                url: r.url,
+                title: r.title.trim().to_string(),
+                description: r.snippet.split_whitespace().collect::<Vec<_>>().join(" "),
+                description_from_page: false,
+                extras: Default::default(),
+            })
+            .collect(),
+        ..Default::default()
+    })
+}
+
+/// A random site from Wiby's "surprise me" page, for the `!random` bang.
+pub async fn surprise() -> eyre::Result<String> {
+    let body = CLIENT
+        .get("https://wiby.me/surprise/")
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_surprise(&body).ok_or_else(|| eyre::eyre!("no site on wiby's surprise page"))
+}
+
+/// The page redirects to the site with a meta refresh tag.
+fn parse_surprise(body: &str) -> Option<String> {
+    let url = regex!(r#"(?i)http-equiv="refresh"\s+content="\d+;\s*url='?([^'"]+)"#)
+        .captures(body)?
+        .get(1)?
+        .as_str();
+    let url = Url::parse(url).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let body = r#"[{"URL": "http://example.neocities.org/", "Title": "My Homepage ", "Snippet": "Welcome to\nmy site"}]"#;
+        let response = parse_response(body).unwrap();
+        assert_eq!(response.search_results.len(), 1);
+        assert_eq!(response.search_results[0].title, "My Homepage");
+        assert_eq!(response.search_results[0].description, "Welcome to my site");
+    }
+
+    #[test]
+    fn test_parse_surprise() {
+        let body = r#"<html><head><meta http-equiv="refresh" content="0; URL='http://example.neocities.org/'"></head></html>"#;
+        assert_eq!(
+            parse_surprise(body),
+            Some("http://example.neocities.org/".to_string())
+        );
+        assert_eq!(parse_surprise("<html></html>"), None);
+    }
+}
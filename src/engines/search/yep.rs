@@ -1,9 +1,9 @@
 use serde::Deserialize;
 use url::Url;
 
-use crate::engines::{EngineResponse, EngineSearchResult, RequestResponse, CLIENT};
+use crate::engines::{EngineResponse, EngineSearchResult, RequestResponse, SearchQuery, CLIENT};
 
-pub async fn request(query: &str) -> RequestResponse {
+pub async fn request(query: &SearchQuery) -> RequestResponse {
     CLIENT
         .get(
             Url::parse_with_params(
@@ -12,8 +12,8 @@ pub async fn request(query: &str) -> RequestResponse {
                     ("client", "web"),
                     ("gl", "all"),
                     ("no_correct", "true"),
-                    ("q", query),
-                    ("safeSearch", "off"),
+                    ("q", query.quoted_if_verbatim(&[]).as_str()),
+                    ("safeSearch", &query.config.safesearch.to_string()),
                     ("type", "web"),
                 ],
             )
@@ -50,6 +50,8 @@ pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
                 url: result.url,
                 title: result.title,
                 description,
+                description_from_page: false,
+                extras: Default::default(),
             }
         })
         .collect();
@@ -59,5 +61,7 @@ pub fn parse_response(body: &str) -> eyre::Result<EngineResponse> {
         featured_snippet: None,
         answer_html: None,
         infobox_html: None,
+        corrected_query: None,
+        related_searches: Vec::new(),
     })
 }
@@ -0,0 +1,182 @@
+//! Showing whether the Wayback Machine has a copy of a result, since results
+//! from small indexes like Marginalia's are often for pages that are gone. The
+//! copies that were already looked up are shown with the results, and the rest
+//! are looked up after the results are sent, like the dead link checks.
+
+use std::{sync::LazyLock, time::Duration};
+
+use serde::Deserialize;
+use tracing::debug;
+
+use super::{
+    client, page_cache::PageCache, ArchivedCopy, EngineSearchResult, SearchResult, CLIENT,
+};
+use crate::config::WaybackConfig;
+
+/// Pages rarely get archived for the first time, so this can be long.
+const TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_ENTRIES: usize = 5000;
+
+/// Archived copies by url. Pages that haven't been archived are cached as
+/// `None`.
+static CACHE: LazyLock<PageCache<Option<ArchivedCopy>>> =
+    LazyLock::new(|| PageCache::new(MAX_ENTRIES, TTL));
+
+/// The response to a batch of urls, in the same order they were sent.
+#[derive(Deserialize)]
+struct BatchResponse {
+    results: Vec<AvailabilityResponse>,
+}
+
+#[derive(Deserialize)]
+struct AvailabilityResponse {
+    archived_snapshots: ArchivedSnapshots,
+}
+
+#[derive(Deserialize)]
+struct ArchivedSnapshots {
+    closest: Option<Snapshot>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    available: bool,
+    url: String,
+    /// Like `20130919044612`.
+    timestamp: String,
+}
+
+fn is_wanted(result: &SearchResult<EngineSearchResult>, config: &WaybackConfig) -> bool {
+    config.engines.is_empty() || result.engines.iter().any(|e| config.engines.contains(e))
+}
+
+/// Add the archived copies that were already looked up to the results from the
+/// configured engines.
+pub fn add_cached(results: &mut [SearchResult<EngineSearchResult>], config: &WaybackConfig) {
+    for result in results.iter_mut().filter(|r| is_wanted(r, config)) {
+        if let Some(Some(archived)) = CACHE.get(&result.result.url) {
+            result.result.extras.archived = Some(archived);
+        }
+    }
+}
+
+/// The results that still have to be looked up, with their indexes (starting
+/// at 1, like the `#result-N` anchors).
+pub fn uncached(
+    results: &[SearchResult<EngineSearchResult>],
+    config: &WaybackConfig,
+) -> Vec<(usize, String)> {
+    results
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| is_wanted(r, config) && CACHE.get(&r.result.url).is_none())
+        .take(config.max_lookups)
+        .map(|(i, r)| (i + 1, r.result.url.clone()))
+        .collect()
+}
+
+/// Look up the results in one request, and return the ones that have an
+/// archived copy. The request goes through the scheduler, so archive.org's
+/// limits are shared by every search.
+pub async fn look_up(urls: Vec<(usize, String)>) -> Vec<(usize, ArchivedCopy)> {
+    if urls.is_empty() {
+        return Vec::new();
+    }
+    // errors aren't cached, so the urls are looked up again next time
+    let responses = match fetch_batch(&urls).await {
+        Ok(responses) => responses,
+        Err(err) => {
+            debug!(
+                "couldn't look up {} urls on the wayback machine: {err}",
+                urls.len()
+            );
+            return Vec::new();
+        }
+    };
+
+    urls.into_iter()
+        .zip(responses)
+        .filter_map(|((index, url), res)| {
+            let archived = parse_snapshot(res);
+            CACHE.insert(url, archived.clone());
+            Some((index, archived?))
+        })
+        .collect()
+}
+
+async fn fetch_batch(urls: &[(usize, String)]) -> eyre::Result<Vec<AvailabilityResponse>> {
+    let form = urls
+        .iter()
+        .map(|(_, url)| ("url", url.as_str()))
+        .collect::<Vec<_>>();
+    let res: BatchResponse = client::send_without_engine(
+        CLIENT
+            .post("https://archive.org/wayback/available")
+            .form(&form),
+    )
+    .await?
+    .error_for_status()?
+    .json()
+    .await?;
+    if res.results.len() != urls.len() {
+        eyre::bail!(
+            "asked for {} urls but got {} results",
+            urls.len(),
+            res.results.len()
+        );
+    }
+    Ok(res.results)
+}
+
+fn parse_snapshot(res: AvailabilityResponse) -> Option<ArchivedCopy> {
+    let snapshot = res.archived_snapshots.closest?;
+    if !snapshot.available {
+        return None;
+    }
+    let date = snapshot.timestamp.get(..8)?;
+    Some(ArchivedCopy {
+        // the api gives http links even though the site is https
+        url: snapshot.url.replacen("http://", "https://", 1),
+        date: format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_snapshot() {
+        let res = serde_json::from_str(
+            r#"{"url": "example.com", "archived_snapshots": {"closest": {"status": "200", "available": true, "url": "http://web.archive.org/web/20130919044612/http://example.com/", "timestamp": "20130919044612"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parse_snapshot(res),
+            Some(ArchivedCopy {
+                url: "https://web.archive.org/web/20130919044612/http://example.com/".to_string(),
+                date: "2013-09-19".to_string(),
+            })
+        );
+
+        let res =
+            serde_json::from_str(r#"{"url": "example.com", "archived_snapshots": {}}"#).unwrap();
+        assert_eq!(parse_snapshot(res), None);
+    }
+
+    #[test]
+    fn test_parse_batch() {
+        let res: BatchResponse = serde_json::from_str(
+            r#"{"results": [{"url": "example.com", "archived_snapshots": {"closest": {"status": "200", "available": true, "url": "http://web.archive.org/web/20130919044612/http://example.com/", "timestamp": "20130919044612"}}}, {"url": "gone.example", "archived_snapshots": {}}]}"#,
+        )
+        .unwrap();
+        let archived = res
+            .results
+            .into_iter()
+            .map(parse_snapshot)
+            .collect::<Vec<_>>();
+        assert_eq!(archived.len(), 2);
+        assert!(archived[0].is_some());
+        assert_eq!(archived[1], None);
+    }
+}
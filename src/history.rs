@@ -0,0 +1,231 @@
+//! Search history, which is opt-in. Queries are grouped into sessions of
+//! related searches on the /history page, so a line of research can be picked
+//! up again later.
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use eyre::eyre;
+use parking_lot::{Mutex, MutexGuard};
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::error;
+
+use crate::config::Config;
+
+static DB: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY,
+    query TEXT NOT NULL,
+    searched_at INTEGER NOT NULL
+);
+";
+
+/// How many searches the /history page groups into sessions.
+pub const MAX_ENTRIES: usize = 500;
+
+/// Words that don't make two queries related.
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "how", "what", "why", "with", "from", "does", "are", "can", "you",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub query: String,
+    pub searched_at: i64,
+}
+
+/// Related searches that were made close together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    /// Oldest first.
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl Session {
+    /// The id of the first search, which identifies the session.
+    pub fn id(&self) -> i64 {
+        self.entries[0].id
+    }
+
+    /// The queries without duplicates, in the order they were first searched.
+    pub fn queries(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        self.entries
+            .iter()
+            .map(|e| e.query.as_str())
+            .filter(|q| seen.insert(q.to_lowercase()))
+            .collect()
+    }
+}
+
+/// Open the database, if history is enabled in the config.
+pub fn init(config: &Arc<Config>) {
+    if !config.history.enabled {
+        return;
+    }
+    let conn = Connection::open(&config.history.database)
+        .and_then(|conn| conn.execute_batch(SCHEMA).map(|()| conn));
+    match conn {
+        Ok(conn) => {
+            let _ = DB.set(Mutex::new(conn));
+        }
+        Err(err) => error!(
+            "Couldn't open the history database at {}: {err}",
+            config.history.database
+        ),
+    }
+}
+
+fn db() -> eyre::Result<MutexGuard<'static, Connection>> {
+    DB.get()
+        .map(|db| db.lock())
+        .ok_or_else(|| eyre!("history is disabled"))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Add a search to the history. Searching for the same thing twice in a row
+/// (like when reloading the page) is only recorded once.
+pub fn record(query: &str) -> eyre::Result<()> {
+    let db = db()?;
+    let last = db
+        .query_row(
+            "SELECT query FROM history ORDER BY id DESC LIMIT 1",
+            [],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    if last.as_deref() == Some(query) {
+        return Ok(());
+    }
+    db.execute(
+        "INSERT INTO history (query, searched_at) VALUES (?1, ?2)",
+        params![query, now()],
+    )?;
+    Ok(())
+}
+
+/// The most recent searches, oldest first.
+pub fn recent(limit: usize) -> eyre::Result<Vec<HistoryEntry>> {
+    let db = db()?;
+    let mut statement = db.prepare(
+        "SELECT id, query, searched_at FROM
+        (SELECT * FROM history ORDER BY id DESC LIMIT ?1) ORDER BY id",
+    )?;
+    let entries = statement
+        .query_map([limit as i64], |row| {
+            Ok(HistoryEntry {
+                id: row.get(0)?,
+                query: row.get(1)?,
+                searched_at: row.get(2)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(entries)
+}
+
+/// The session that starts with the search with this id, if it's recent enough
+/// to still be in the history page.
+pub fn session(id: i64, window: i64) -> eyre::Result<Option<Session>> {
+    Ok(group_sessions(recent(MAX_ENTRIES)?, window)
+        .into_iter()
+        .find(|s| s.id() == id))
+}
+
+pub fn clear() -> eyre::Result<()> {
+    db()?.execute("DELETE FROM history", [])?;
+    Ok(())
+}
+
+/// Group searches into sessions. A search continues the current session if it
+/// was made within `window` seconds of the last one and shares a word with any
+/// of the session's queries.
+pub fn group_sessions(entries: Vec<HistoryEntry>, window: i64) -> Vec<Session> {
+    let mut sessions: Vec<Session> = Vec::new();
+    let mut session_words = HashSet::new();
+    for entry in entries {
+        let words = significant_words(&entry.query);
+        let continues = sessions.last().is_some_and(|session| {
+            let last = session.entries.last().unwrap();
+            entry.searched_at - last.searched_at <= window && !words.is_disjoint(&session_words)
+        });
+        if continues {
+            session_words.extend(words);
+            sessions.last_mut().unwrap().entries.push(entry);
+        } else {
+            session_words = words;
+            sessions.push(Session {
+                entries: vec![entry],
+            });
+        }
+    }
+    sessions
+}
+
+fn significant_words(query: &str) -> HashSet<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() > 2)
+        .map(str::to_lowercase)
+        .filter(|w| !STOP_WORDS.contains(&w.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: i64, query: &str, searched_at: i64) -> HistoryEntry {
+        HistoryEntry {
+            id,
+            query: query.to_string(),
+            searched_at,
+        }
+    }
+
+    #[test]
+    fn test_group_sessions() {
+        let sessions = group_sessions(
+            vec![
+                entry(1, "rust async traits", 0),
+                entry(2, "async trait object safety", 60),
+                entry(3, "object safety rules", 120),
+                entry(4, "weather berlin", 180),
+                // related, but too long after
+                entry(5, "rust async traits", 10_000),
+            ],
+            30 * 60,
+        );
+        assert_eq!(
+            sessions
+                .iter()
+                .map(|s| s.entries.iter().map(|e| e.id).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            [vec![1, 2, 3], vec![4], vec![5]]
+        );
+        assert_eq!(sessions[0].id(), 1);
+        // "how" isn't enough to make queries related
+        assert_eq!(
+            group_sessions(
+                vec![
+                    entry(1, "how to cook rice", 0),
+                    entry(2, "how to tie a tie", 1)
+                ],
+                60
+            )
+            .len(),
+            2
+        );
+    }
+}
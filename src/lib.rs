@@ -0,0 +1,13 @@
+//! Everything except the entrypoint lives in the library so the tests in
+//! `tests/` can use it.
+
+pub mod blocklists;
+pub mod config;
+pub mod engines;
+pub mod history;
+pub mod parse;
+pub mod saved_searches;
+pub mod snapshots;
+pub mod urls;
+pub mod visits;
+pub mod web;
@@ -3,26 +3,31 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use config::Config;
-use tracing::error;
-
-pub mod config;
-pub mod engines;
-pub mod parse;
-pub mod urls;
-pub mod web;
+use metasearch::{
+    config::{Config, LogFormat},
+    web,
+};
+use tracing::{error, info};
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
-    tracing_subscriber::fmt::init();
-
     if env::args().any(|arg| arg == "--help" || arg == "-h" || arg == "help" || arg == "h") {
         println!("Usage: metasearch [config_path]");
         return;
     }
 
+    // the log format is in the config, so logging starts after it's read
     let config_path = config_path();
-    let config = match Config::read_or_create(&config_path) {
+    let created_config = !config_path.exists();
+    let config = Config::read_or_create(&config_path);
+    match config.as_ref().map(|c| c.log_format) {
+        Ok(LogFormat::Json) => tracing_subscriber::fmt().json().init(),
+        _ => tracing_subscriber::fmt::init(),
+    }
+    if created_config {
+        info!("No config found, created one at {config_path:?}");
+    }
+    let config = match config {
         Ok(config) => config,
         Err(err) => {
             error!("Couldn't parse config:\n{err}");
@@ -1,7 +1,12 @@
 //! Helper functions for parsing search engine responses.
 
+use std::fmt;
+
 use crate::{
-    engines::{EngineFeaturedSnippet, EngineResponse, EngineSearchResult},
+    engines::{
+        answer::regex, EngineFeaturedSnippet, EngineResponse, EngineSearchResult, Rating,
+        ResultExtras,
+    },
     urls::normalize_url,
 };
 
@@ -14,11 +19,18 @@ pub struct ParseOpts {
     title: QueryMethod,
     href: QueryMethod,
     description: QueryMethod,
+    /// The text of rich snippets, like "Rating: 4.8 · 1,234 reviews · 45 min".
+    extras: QueryMethod,
 
     featured_snippet: &'static str,
     featured_snippet_title: QueryMethod,
     featured_snippet_href: QueryMethod,
     featured_snippet_description: QueryMethod,
+
+    /// The "did you mean" link, which is queried from the whole page.
+    correction: QueryMethod,
+    /// Each element that matches is a related search.
+    related_searches: &'static str,
 }
 
 impl ParseOpts {
@@ -51,6 +63,12 @@ impl ParseOpts {
         self
     }
 
+    #[must_use]
+    pub fn extras(mut self, extras: impl Into<QueryMethod>) -> Self {
+        self.extras = extras.into();
+        self
+    }
+
     #[must_use]
     pub fn featured_snippet(mut self, featured_snippet: &'static str) -> Self {
         self.featured_snippet = featured_snippet;
@@ -80,6 +98,18 @@ impl ParseOpts {
         self.featured_snippet_description = featured_snippet_description.into();
         self
     }
+
+    #[must_use]
+    pub fn correction(mut self, correction: impl Into<QueryMethod>) -> Self {
+        self.correction = correction.into();
+        self
+    }
+
+    #[must_use]
+    pub fn related_searches(mut self, related_searches: &'static str) -> Self {
+        self.related_searches = related_searches;
+        self
+    }
 }
 
 type ManualQueryMethod = Box<dyn Fn(&scraper::ElementRef) -> eyre::Result<String>>;
@@ -133,10 +163,13 @@ pub(super) fn parse_html_response_with_opts(
         title: title_query_method,
         href: href_query_method,
         description: description_query_method,
+        extras: extras_query_method,
         featured_snippet: featured_snippet_query,
         featured_snippet_title: featured_snippet_title_query_method,
         featured_snippet_href: featured_snippet_href_query_method,
         featured_snippet_description: featured_snippet_description_query_method,
+        correction: correction_query_method,
+        related_searches: related_searches_query,
     } = opts;
 
     let result_item_query = Selector::parse(result_item_query).unwrap();
@@ -163,18 +196,23 @@ pub(super) fn parse_html_response_with_opts(
             continue;
         }
 
-        // this can happen on google if it gives you a featured snippet
+        // results without a description are kept, since another engine or the page
+        // itself might have one
         if description.is_empty() {
-            trace!("empty description for {url} ({title}), skipping");
-            continue;
+            trace!("empty description for {url} ({title})");
         }
 
         let url = normalize_url(&url);
 
+        let mut extras = parse_schema_extras(&result);
+        extras.merge(parse_extras_text(&extras_query_method.call(&result)?));
+
         search_results.push(EngineSearchResult {
             url,
             title,
             description,
+            description_from_page: false,
+            extras,
         });
     }
 
@@ -204,11 +242,287 @@ pub(super) fn parse_html_response_with_opts(
         None
     };
 
+    let corrected_query = correction_query_method.call(&dom.root_element())?;
+    let corrected_query = corrected_query.trim();
+
+    let mut related_searches = Vec::new();
+    if !related_searches_query.is_empty() {
+        for el in dom.select(&Selector::parse(related_searches_query).unwrap()) {
+            let text = el.text().collect::<String>();
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !text.is_empty() && !related_searches.contains(&text) {
+                related_searches.push(text);
+            }
+        }
+    }
+
     Ok(EngineResponse {
         search_results,
         featured_snippet,
         // these fields are used by instant answers, not normal search engines
         answer_html: None,
         infobox_html: None,
+        corrected_query: (!corrected_query.is_empty()).then(|| corrected_query.to_string()),
+        related_searches,
     })
 }
+
+/// The error for a captcha or block page, so it can be told apart from other
+/// errors with `downcast_ref`.
+#[derive(Debug)]
+pub struct BlockPage {
+    marker: String,
+}
+
+impl fmt::Display for BlockPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "got a block page (found {:?})", self.marker)
+    }
+}
+
+impl std::error::Error for BlockPage {}
+
+/// The error for a cookie consent page that was shown instead of results. The
+/// engine sends its consent cookies after it returns this, so the search is
+/// tried again once.
+#[derive(Debug)]
+pub struct ConsentPage;
+
+impl fmt::Display for ConsentPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "got a cookie consent page")
+    }
+}
+
+impl std::error::Error for ConsentPage {}
+
+/// Fail if the page has any of the markers of a captcha or block page, so it
+/// shows up as an error instead of looking like there were no results.
+pub fn check_block_page(body: &str, markers: &[&str]) -> eyre::Result<()> {
+    if let Some(marker) = markers.iter().find(|m| body.contains(*m)) {
+        return Err(BlockPage {
+            marker: marker.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Get extras from schema.org microdata and JSON-LD in the result element.
+fn parse_schema_extras(el: &scraper::ElementRef) -> ResultExtras {
+    let mut extras = ResultExtras::default();
+
+    let itemprop = |name: &str| {
+        el.select(&Selector::parse(&format!("[itemprop='{name}']")).unwrap())
+            .next()
+            .map(|n| {
+                n.value()
+                    .attr("content")
+                    .map_or_else(|| n.text().collect::<String>(), str::to_string)
+                    .trim()
+                    .to_string()
+            })
+    };
+    if let Some(value) = itemprop("ratingValue").and_then(|v| v.parse().ok()) {
+        extras.rating = Some(Rating {
+            value,
+            best: itemprop("bestRating")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5.),
+            count: itemprop("reviewCount").or_else(|| itemprop("ratingCount")),
+        });
+    }
+    extras.total_time = itemprop("totalTime").map(|t| format_iso_duration(&t).unwrap_or(t));
+    extras.answer_count = itemprop("answerCount").and_then(|v| v.parse().ok());
+    let ingredient_count = el
+        .select(&Selector::parse("[itemprop='recipeIngredient']").unwrap())
+        .count();
+    extras.ingredient_count = (ingredient_count > 0).then_some(ingredient_count as u32);
+
+    for script in el.select(&Selector::parse("script[type='application/ld+json']").unwrap()) {
+        let Ok(json) =
+            serde_json::from_str::<serde_json::Value>(&script.text().collect::<String>())
+        else {
+            continue;
+        };
+        extras.merge(json_ld_extras(&json));
+    }
+
+    extras
+}
+
+/// Get extras from a JSON-LD object, like the ones in `<script
+/// type="application/ld+json">` tags.
+pub fn json_ld_extras(json: &serde_json::Value) -> ResultExtras {
+    // numbers are sometimes strings in json-ld
+    let as_string = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+
+    let mut extras = ResultExtras::default();
+    if let Some(rating) = json.get("aggregateRating") {
+        if let Some(value) = rating
+            .get("ratingValue")
+            .and_then(as_string)
+            .and_then(|v| v.parse().ok())
+        {
+            extras.rating = Some(Rating {
+                value,
+                best: rating
+                    .get("bestRating")
+                    .and_then(as_string)
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(5.),
+                count: rating
+                    .get("reviewCount")
+                    .or_else(|| rating.get("ratingCount"))
+                    .and_then(as_string),
+            });
+        }
+    }
+    extras.total_time = json
+        .get("totalTime")
+        .and_then(|t| t.as_str())
+        .and_then(format_iso_duration);
+    extras.ingredient_count = json
+        .get("recipeIngredient")
+        .and_then(|i| i.as_array())
+        .map(|i| i.len() as u32);
+    extras.answer_count = json
+        .get("mainEntity")
+        .unwrap_or(json)
+        .get("answerCount")
+        .and_then(as_string)
+        .and_then(|v| v.parse().ok());
+
+    // json-ld can also be a list or a @graph of items
+    let items = json
+        .as_array()
+        .or_else(|| json.get("@graph").and_then(|g| g.as_array()));
+    for item in items.into_iter().flatten() {
+        extras.merge(json_ld_extras(item));
+    }
+
+    extras
+}
+
+/// Get extras from the text that engines show under results, like
+/// "Rating: 4.8 · 1,234 reviews · 1 hr 10 min".
+fn parse_extras_text(text: &str) -> ResultExtras {
+    let mut extras = ResultExtras::default();
+    if text.is_empty() {
+        return extras;
+    }
+
+    let rating = regex!(r"(?i)(?:rating:\s*)?(\d(?:[.,]\d+)?)\s*(?:/|out of)\s*(\d+)")
+        .captures(text)
+        .or_else(|| regex!(r"(?i)rating:\s*(\d(?:[.,]\d+)?)").captures(text));
+    if let Some(rating) = rating {
+        if let Ok(value) = rating[1].replace(',', ".").parse() {
+            let count = regex!(r"(?i)([\d.,]+[kKmM]?)\s*(?:reviews?|votes?|ratings?)")
+                .captures(text)
+                .or_else(|| regex!(r"\(([\d.,]+[kKmM]?)\)").captures(text))
+                .map(|c| c[1].to_string());
+            extras.rating = Some(Rating {
+                value,
+                best: rating
+                    .get(2)
+                    .and_then(|b| b.as_str().parse().ok())
+                    .unwrap_or(5.),
+                count,
+            });
+        }
+    }
+
+    extras.total_time = regex!(r"(?i)\b(\d+\s*(?:hr|hrs|hours?)(?:\s*\d+\s*mins?)?|\d+\s*mins?)\b")
+        .captures(text)
+        .map(|c| c[1].to_string());
+    extras.answer_count = regex!(r"(?i)\b(\d+)\s*answers?\b")
+        .captures(text)
+        .and_then(|c| c[1].parse().ok());
+
+    extras
+}
+
+/// Turn an ISO 8601 duration like "PT1H10M" into "1 hr 10 min".
+fn format_iso_duration(duration: &str) -> Option<String> {
+    let captures = regex!(r"^PT(?:(\d+)H)?(?:(\d+)M)?").captures(duration)?;
+    let hours = captures.get(1).map(|h| h.as_str());
+    let minutes = captures.get(2).map(|m| m.as_str());
+    match (hours, minutes) {
+        (Some(h), Some(m)) => Some(format!("{h} hr {m} min")),
+        (Some(h), None) => Some(format!("{h} hr")),
+        (None, Some(m)) => Some(format!("{m} min")),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_page_error() {
+        let err = check_block_page("<form id=captcha>", &["/sorry/", "id=captcha"]).unwrap_err();
+        assert!(err.downcast_ref::<BlockPage>().is_some());
+        assert!(check_block_page("<ol>results</ol>", &["id=captcha"]).is_ok());
+    }
+
+    #[test]
+    fn test_correction() {
+        let opts = || {
+            ParseOpts::new()
+                .result("li")
+                .title("a")
+                .href("a")
+                .correction("#spell a")
+                .related_searches(".related a")
+        };
+        let response = parse_html_response_with_opts(
+            r#"<p id="spell">Did you mean <a href="/search?q=rust"> rust </a>?</p>
+            <ul><li><a href="https://a.example">A</a></li></ul>
+            <div class="related"><a>rust <b>book</b></a><a> rust  book </a><a>cargo</a></div>"#,
+            opts(),
+        )
+        .unwrap();
+        assert_eq!(response.corrected_query.as_deref(), Some("rust"));
+        assert_eq!(response.related_searches, ["rust book", "cargo"]);
+        let response = parse_html_response_with_opts("<ul></ul>", opts()).unwrap();
+        assert_eq!(response.corrected_query, None);
+        assert!(response.related_searches.is_empty());
+    }
+
+    #[test]
+    fn test_parse_extras_text() {
+        let extras = parse_extras_text("Rating: 4.8 · ‎1,234 reviews · ‎1 hr 10 min");
+        assert_eq!(
+            extras.rating,
+            Some(Rating {
+                value: 4.8,
+                best: 5.,
+                count: Some("1,234".to_string())
+            })
+        );
+        assert_eq!(extras.total_time.as_deref(), Some("1 hr 10 min"));
+
+        let extras = parse_extras_text("12 answers · Top answer: use tokio");
+        assert_eq!(extras.answer_count, Some(12));
+        assert_eq!(extras.rating, None);
+    }
+
+    #[test]
+    fn test_json_ld_extras() {
+        let json = serde_json::json!({
+            "@type": "Recipe",
+            "totalTime": "PT45M",
+            "aggregateRating": { "ratingValue": "4.5", "reviewCount": 321 },
+            "recipeIngredient": ["2 eggs", "1 cup flour", "1 cup milk"]
+        });
+        let extras = json_ld_extras(&json);
+        assert_eq!(extras.total_time.as_deref(), Some("45 min"));
+        assert_eq!(extras.ingredient_count, Some(3));
+        assert_eq!(extras.rating.unwrap().count.as_deref(), Some("321"));
+    }
+}
@@ -0,0 +1,374 @@
+//! Saved searches, which are searched again in the background so new results
+//! can be shown in an Atom feed or sent to a webhook. They're stored in sqlite
+//! so they survive restarts.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use eyre::eyre;
+use parking_lot::{Mutex, MutexGuard};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::{
+    config::Config,
+    engines::{
+        self, preprocess, ProgressUpdateData, ResponseForTab, SearchQuery, SearchTab, CLIENT,
+    },
+};
+
+static DB: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+/// How often the scheduler looks for searches that are due.
+const TICK: Duration = Duration::from_secs(60);
+/// Every saved search is searched again on each interval, so there's a limit
+/// to how much traffic they can make.
+pub const MAX_SAVED_SEARCHES: usize = 50;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS saved_searches (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    query TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    last_run_at INTEGER
+);
+CREATE TABLE IF NOT EXISTS saved_results (
+    search_id INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    found_at INTEGER NOT NULL,
+    -- the results from the first run aren't new, so they're not in the feed
+    is_new INTEGER NOT NULL,
+    PRIMARY KEY (search_id, url)
+);
+";
+
+#[derive(Debug, Clone)]
+pub struct SavedSearch {
+    pub id: i64,
+    pub name: String,
+    pub query: String,
+    pub created_at: i64,
+    /// None if it hasn't been searched yet.
+    pub last_run_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SavedResult {
+    pub url: String,
+    pub title: String,
+    pub description: String,
+    /// Unix timestamp of the run that first found the result.
+    pub found_at: i64,
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    id: i64,
+    name: &'a str,
+    query: &'a str,
+    results: &'a [SavedResult],
+}
+
+/// Open the database and start searching the saved searches periodically, if
+/// it's enabled in the config.
+pub fn spawn(config: Arc<Config>) {
+    if !config.saved_searches.enabled {
+        return;
+    }
+    let conn = match open(&config.saved_searches.database) {
+        Ok(conn) => conn,
+        Err(err) => {
+            error!(
+                "Couldn't open the saved searches database at {}: {err}",
+                config.saved_searches.database
+            );
+            return;
+        }
+    };
+    let _ = DB.set(Mutex::new(conn));
+
+    tokio::spawn(async move {
+        loop {
+            run_due(&config).await;
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
+
+fn open(path: &str) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+fn db() -> eyre::Result<MutexGuard<'static, Connection>> {
+    DB.get()
+        .map(|db| db.lock())
+        .ok_or_else(|| eyre!("saved searches are disabled"))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+fn row_to_saved_search(row: &rusqlite::Row) -> rusqlite::Result<SavedSearch> {
+    Ok(SavedSearch {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        query: row.get(2)?,
+        created_at: row.get(3)?,
+        last_run_at: row.get(4)?,
+    })
+}
+
+pub fn list() -> eyre::Result<Vec<SavedSearch>> {
+    let db = db()?;
+    let mut statement = db.prepare(
+        "SELECT id, name, query, created_at, last_run_at FROM saved_searches ORDER BY name",
+    )?;
+    let searches = statement
+        .query_map([], row_to_saved_search)?
+        .collect::<Result<_, _>>()?;
+    Ok(searches)
+}
+
+pub fn get(id: i64) -> eyre::Result<Option<SavedSearch>> {
+    let search = db()?
+        .query_row(
+            "SELECT id, name, query, created_at, last_run_at FROM saved_searches WHERE id = ?1",
+            [id],
+            row_to_saved_search,
+        )
+        .optional()?;
+    Ok(search)
+}
+
+/// Save a search. It's searched for the first time on the next tick, which
+/// only records the results so they don't show up as new. Returns None if
+/// there are already [`MAX_SAVED_SEARCHES`].
+pub fn add(name: &str, query: &str) -> eyre::Result<Option<i64>> {
+    let db = db()?;
+    let count: i64 = db.query_row("SELECT COUNT(*) FROM saved_searches", [], |row| row.get(0))?;
+    if count as usize >= MAX_SAVED_SEARCHES {
+        return Ok(None);
+    }
+    db.execute(
+        "INSERT INTO saved_searches (name, query, created_at) VALUES (?1, ?2, ?3)",
+        params![name, query, now()],
+    )?;
+    Ok(Some(db.last_insert_rowid()))
+}
+
+pub fn delete(id: i64) -> eyre::Result<()> {
+    let db = db()?;
+    db.execute("DELETE FROM saved_results WHERE search_id = ?1", [id])?;
+    db.execute("DELETE FROM saved_searches WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// The results that were found after the first search, newest first.
+pub fn new_results(id: i64, limit: usize) -> eyre::Result<Vec<SavedResult>> {
+    let db = db()?;
+    let mut statement = db.prepare(
+        "SELECT url, title, description, found_at FROM saved_results
+        WHERE search_id = ?1 AND is_new = 1 ORDER BY found_at DESC, rowid DESC LIMIT ?2",
+    )?;
+    let results = statement
+        .query_map(params![id, limit as i64], |row| {
+            Ok(SavedResult {
+                url: row.get(0)?,
+                title: row.get(1)?,
+                description: row.get(2)?,
+                found_at: row.get(3)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(results)
+}
+
+async fn run_due(config: &Arc<Config>) {
+    let interval = (config.saved_searches.interval_minutes.max(1) * 60) as i64;
+    let searches = match list() {
+        Ok(searches) => searches,
+        Err(err) => {
+            error!("Couldn't list saved searches: {err}");
+            return;
+        }
+    };
+
+    // one at a time, like the canary, so it doesn't look like a burst of traffic
+    for saved in searches {
+        if saved.last_run_at.is_some_and(|t| now() - t < interval) {
+            continue;
+        }
+        if let Err(err) = run(config, &saved).await {
+            warn!("Saved search {:?} failed: {err}", saved.name);
+        }
+        // searches that failed wait for the next interval too, so a broken one
+        // isn't retried on every tick
+        let marked = db().and_then(|db| {
+            db.execute(
+                "UPDATE saved_searches SET last_run_at = ?1 WHERE id = ?2",
+                params![now(), saved.id],
+            )?;
+            Ok(())
+        });
+        if let Err(err) = marked {
+            error!("Couldn't update saved search {:?}: {err}", saved.name);
+        }
+    }
+}
+
+async fn run(config: &Arc<Config>, saved: &SavedSearch) -> eyre::Result<()> {
+    let results = search(config, &saved.query).await?;
+
+    let new = {
+        let db = db()?;
+        // nothing is new the first time results are found, which isn't always
+        // the first run since runs can fail
+        let first_run = !db.query_row(
+            "SELECT EXISTS(SELECT 1 FROM saved_results WHERE search_id = ?1)",
+            [saved.id],
+            |row| row.get::<_, bool>(0),
+        )?;
+        record(&db, saved.id, first_run, &results, now())?
+    };
+    if new.is_empty() {
+        return Ok(());
+    }
+    info!(
+        "Saved search {:?} has {} new results",
+        saved.name,
+        new.len()
+    );
+
+    if let Some(webhook) = &config.saved_searches.webhook {
+        let payload = WebhookPayload {
+            id: saved.id,
+            name: &saved.name,
+            query: &saved.query,
+            results: &new,
+        };
+        CLIENT
+            .post(webhook)
+            .header("content-type", "application/json")
+            .body(serde_json::to_string(&payload)?)
+            .send()
+            .await?
+            .error_for_status()?;
+    }
+    Ok(())
+}
+
+/// The url, title, and description of the results for a query.
+async fn search(config: &Arc<Config>, query: &str) -> eyre::Result<Vec<(String, String, String)>> {
+    let mut config = config.as_ref().clone();
+    if config.language == "auto" {
+        config.language = preprocess::auto_language(query, None);
+    }
+    let query = SearchQuery {
+        query: query.to_string(),
+        tab: SearchTab::All,
+        verbatim: false,
+        only_engines: vec![],
+        label: None,
+        language: None,
+        image_filters: Default::default(),
+        requested_count: config.requested_count,
+        info: preprocess::preprocess(query, &config.preprocess.steps).info,
+        debug: false,
+        request_headers: HashMap::new(),
+        ip: "127.0.0.1".to_string(),
+        config: Arc::new(config),
+    };
+
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+    engines::search(&query, progress_tx).await?;
+
+    let mut results = Vec::new();
+    while let Some(progress_update) = progress_rx.recv().await {
+        if let ProgressUpdateData::Response(ResponseForTab::All(response)) = progress_update.data {
+            results.extend(
+                response
+                    .search_results
+                    .into_iter()
+                    .map(|r| (r.result.url, r.result.title, r.result.description)),
+            );
+        }
+    }
+    Ok(results)
+}
+
+/// Store the results that haven't been seen before for the search, and return
+/// them. Nothing is returned on the first run, since everything would be new.
+fn record(
+    db: &Connection,
+    search_id: i64,
+    first_run: bool,
+    results: &[(String, String, String)],
+    found_at: i64,
+) -> rusqlite::Result<Vec<SavedResult>> {
+    let mut statement = db.prepare(
+        "INSERT OR IGNORE INTO saved_results
+        (search_id, url, title, description, found_at, is_new) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    let mut new = Vec::new();
+    for (url, title, description) in results {
+        let inserted = statement.execute(params![
+            search_id,
+            url,
+            title,
+            description,
+            found_at,
+            !first_run
+        ])?;
+        if inserted > 0 && !first_run {
+            new.push(SavedResult {
+                url: url.clone(),
+                title: title.clone(),
+                description: description.clone(),
+                found_at,
+            });
+        }
+    }
+    Ok(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(url: &str) -> (String, String, String) {
+        (url.to_string(), format!("Title of {url}"), String::new())
+    }
+
+    #[test]
+    fn test_record() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(SCHEMA).unwrap();
+
+        let first = [result("https://a.example"), result("https://b.example")];
+        assert!(record(&db, 1, true, &first, 100).unwrap().is_empty());
+
+        let second = [result("https://b.example"), result("https://c.example")];
+        let new = record(&db, 1, false, &second, 200).unwrap();
+        assert_eq!(
+            new.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            ["https://c.example"]
+        );
+        assert!(record(&db, 1, false, &second, 300).unwrap().is_empty());
+
+        // other searches have their own results
+        assert_eq!(record(&db, 2, false, &second, 300).unwrap().len(), 2);
+    }
+}
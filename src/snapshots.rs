@@ -0,0 +1,198 @@
+//! Snapshots of result pages, so people can share exactly what they saw
+//! instead of a query that gives different results later. The rendered results
+//! of recent searches are kept in memory, and they're only saved to sqlite
+//! under an unguessable id when they're shared.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use eyre::eyre;
+use parking_lot::{Mutex, MutexGuard};
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::error;
+
+use crate::config::Config;
+
+static DB: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+/// Results that were rendered recently and can still be shared, by id.
+static PENDING: LazyLock<Mutex<HashMap<String, Snapshot>>> = LazyLock::new(Default::default);
+
+/// How many searches can be shared at once, so the memory used doesn't grow
+/// forever.
+const MAX_PENDING: usize = 256;
+/// How long after searching the results can still be shared.
+const PENDING_SECONDS: i64 = 60 * 60;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS snapshots (
+    id TEXT PRIMARY KEY,
+    query TEXT NOT NULL,
+    tab TEXT NOT NULL,
+    html TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub query: String,
+    pub tab: String,
+    /// The rendered results, which are shown as-is.
+    pub html: String,
+    /// When the search was made.
+    pub created_at: i64,
+}
+
+/// Open the database, if snapshots are enabled in the config.
+pub fn init(config: &Arc<Config>) {
+    if !config.snapshots.enabled {
+        return;
+    }
+    let conn = Connection::open(&config.snapshots.database)
+        .and_then(|conn| conn.execute_batch(SCHEMA).map(|()| conn));
+    match conn {
+        Ok(conn) => {
+            let _ = DB.set(Mutex::new(conn));
+        }
+        Err(err) => error!(
+            "Couldn't open the snapshots database at {}: {err}",
+            config.snapshots.database
+        ),
+    }
+}
+
+fn db() -> eyre::Result<MutexGuard<'static, Connection>> {
+    DB.get()
+        .map(|db| db.lock())
+        .ok_or_else(|| eyre!("snapshots are disabled"))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Keep the rendered results of a search around in case they're shared, and
+/// return the id they'll have.
+pub fn stash(query: &str, tab: &str, html: String) -> String {
+    let id = format!("{:032x}", rand::random::<u128>());
+    let created_at = now();
+
+    let mut pending = PENDING.lock();
+    pending.retain(|_, s| created_at - s.created_at < PENDING_SECONDS);
+    if pending.len() >= MAX_PENDING {
+        if let Some(oldest) = pending
+            .iter()
+            .min_by_key(|(_, s)| s.created_at)
+            .map(|(id, _)| id.clone())
+        {
+            pending.remove(&oldest);
+        }
+    }
+    pending.insert(
+        id.clone(),
+        Snapshot {
+            query: query.to_string(),
+            tab: tab.to_string(),
+            html,
+            created_at,
+        },
+    );
+    id
+}
+
+/// Save stashed results so they can be viewed at /s/<id>. Returns false if
+/// they were searched too long ago to be shared.
+pub fn share(id: &str, expiry_days: u64) -> eyre::Result<bool> {
+    let db = db()?;
+    let now = now();
+    prune(&db, now)?;
+    let Some(snapshot) = PENDING.lock().remove(id) else {
+        // sharing the same results twice is fine
+        return Ok(load(&db, id, now)?.is_some());
+    };
+    insert(&db, id, &snapshot, now + expiry_days as i64 * 24 * 60 * 60)?;
+    Ok(true)
+}
+
+/// A shared snapshot and when it expires, if it hasn't already.
+pub fn get(id: &str) -> eyre::Result<Option<(Snapshot, i64)>> {
+    let db = db()?;
+    Ok(load(&db, id, now())?)
+}
+
+fn insert(db: &Connection, id: &str, snapshot: &Snapshot, expires_at: i64) -> rusqlite::Result<()> {
+    db.execute(
+        "INSERT OR IGNORE INTO snapshots (id, query, tab, html, created_at, expires_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            id,
+            snapshot.query,
+            snapshot.tab,
+            snapshot.html,
+            snapshot.created_at,
+            expires_at
+        ],
+    )?;
+    Ok(())
+}
+
+fn load(db: &Connection, id: &str, now: i64) -> rusqlite::Result<Option<(Snapshot, i64)>> {
+    db.query_row(
+        "SELECT query, tab, html, created_at, expires_at FROM snapshots
+        WHERE id = ?1 AND expires_at > ?2",
+        params![id, now],
+        |row| {
+            Ok((
+                Snapshot {
+                    query: row.get(0)?,
+                    tab: row.get(1)?,
+                    html: row.get(2)?,
+                    created_at: row.get(3)?,
+                },
+                row.get(4)?,
+            ))
+        },
+    )
+    .optional()
+}
+
+fn prune(db: &Connection, now: i64) -> rusqlite::Result<()> {
+    db.execute("DELETE FROM snapshots WHERE expires_at <= ?1", [now])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(SCHEMA).unwrap();
+
+        let snapshot = Snapshot {
+            query: "rust".to_string(),
+            tab: "all".to_string(),
+            html: "<div class=\"search-result\"></div>".to_string(),
+            created_at: 100,
+        };
+        insert(&db, "abc", &snapshot, 200).unwrap();
+        assert_eq!(load(&db, "abc", 150).unwrap(), Some((snapshot, 200)));
+        assert_eq!(load(&db, "abc", 200).unwrap(), None);
+        assert_eq!(load(&db, "def", 150).unwrap(), None);
+
+        prune(&db, 200).unwrap();
+        let count: i64 = db
+            .query_row("SELECT COUNT(*) FROM snapshots", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
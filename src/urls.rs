@@ -180,6 +180,36 @@ pub fn get_url_weight(url: &str, urls_config: &UrlsConfig) -> f64 {
     1.
 }
 
+pub fn get_url_annotation<'a>(url: &str, urls_config: &'a UrlsConfig) -> Option<&'a str> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str().unwrap_or_default();
+    let path = url.path().strip_prefix("/").unwrap_or_default();
+    urls_config
+        .annotations
+        .iter()
+        .find(|(check, _)| check.contains(host, path))
+        .map(|(_, note)| note.as_str())
+}
+
+/// Parse annotations that look like uBlock filters, one per line:
+///
+/// ```text
+/// ! lines starting with ! are comments
+/// example.com ## Copies answers from Stack Overflow
+/// .fandom.com ## Includes subdomains
+/// ```
+pub fn parse_annotations(s: &str) -> Vec<(HostAndPath, String)> {
+    s.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('!'))
+        .filter_map(|line| {
+            let (url, note) = line.split_once("##")?;
+            let (url, note) = (url.trim(), note.trim());
+            (!url.is_empty() && !note.is_empty()).then(|| (HostAndPath::new(url), note.to_owned()))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::config::HostAndPath;
@@ -190,6 +220,7 @@ mod tests {
         let urls_config = UrlsConfig {
             replace: vec![(HostAndPath::new(from), HostAndPath::new(to))],
             weight: vec![],
+            annotations: vec![],
         };
         let normalized_url = apply_url_replacements(url, &urls_config);
         assert_eq!(normalized_url, expected);
@@ -240,4 +271,31 @@ mod tests {
             "https://example.com/asdf",
         );
     }
+
+    #[test]
+    fn test_annotations() {
+        let urls_config = UrlsConfig {
+            replace: vec![],
+            weight: vec![],
+            annotations: parse_annotations(
+                "! a comment\n\
+                example.com ## Copies answers\n\
+                .fandom.com ## Fandom wiki\n\
+                not an annotation\n",
+            ),
+        };
+        assert_eq!(urls_config.annotations.len(), 2);
+        assert_eq!(
+            get_url_annotation("https://example.com/questions/1", &urls_config),
+            Some("Copies answers")
+        );
+        assert_eq!(
+            get_url_annotation("https://minecraft.fandom.com/wiki/Stone", &urls_config),
+            Some("Fandom wiki")
+        );
+        assert_eq!(
+            get_url_annotation("https://sub.example.com/", &urls_config),
+            None
+        );
+    }
 }
@@ -0,0 +1,159 @@
+//! The results that were clicked, which is opt-in. The `visited` engine shows
+//! the ones that match a search above the other results, so pages that were
+//! useful before are easy to find again.
+
+use std::{
+    sync::{Arc, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use eyre::eyre;
+use parking_lot::{Mutex, MutexGuard};
+use rusqlite::{params, Connection};
+use tracing::error;
+
+use crate::config::Config;
+
+static DB: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS visits (
+    url TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    last_visited INTEGER NOT NULL
+);
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visit {
+    pub url: String,
+    pub title: String,
+    pub count: i64,
+    pub last_visited: i64,
+}
+
+/// Open the database, if visits are enabled in the config.
+pub fn init(config: &Arc<Config>) {
+    if !config.visits.enabled {
+        return;
+    }
+    match open(&config.visits.database) {
+        Ok(conn) => {
+            let _ = DB.set(Mutex::new(conn));
+        }
+        Err(err) => error!(
+            "Couldn't open the visits database at {}: {err}",
+            config.visits.database
+        ),
+    }
+}
+
+fn open(path: &str) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+fn db() -> eyre::Result<MutexGuard<'static, Connection>> {
+    DB.get()
+        .map(|db| db.lock())
+        .ok_or_else(|| eyre!("visits are disabled"))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Remember that a result was clicked.
+pub fn record(url: &str, title: &str) -> eyre::Result<()> {
+    record_in(&db()?, url, title, now())
+}
+
+fn record_in(db: &Connection, url: &str, title: &str, time: i64) -> eyre::Result<()> {
+    db.execute(
+        "INSERT INTO visits (url, title, count, last_visited) VALUES (?1, ?2, 1, ?3)
+        ON CONFLICT (url) DO UPDATE SET
+            title = excluded.title, count = count + 1, last_visited = excluded.last_visited",
+        params![url, title, time],
+    )?;
+    Ok(())
+}
+
+/// The visited pages with every word of the query in their title or url, most
+/// visited first.
+pub fn search(query: &str, limit: usize) -> eyre::Result<Vec<Visit>> {
+    search_in(&db()?, query, limit)
+}
+
+fn search_in(db: &Connection, query: &str, limit: usize) -> eyre::Result<Vec<Visit>> {
+    let words = query
+        .split_whitespace()
+        .map(|word| {
+            let word = word
+                .to_lowercase()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{word}%")
+        })
+        .collect::<Vec<_>>();
+    if words.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let conditions = (1..=words.len())
+        .map(|i| {
+            format!("(lower(title) LIKE ?{i} ESCAPE '\\' OR lower(url) LIKE ?{i} ESCAPE '\\')")
+        })
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let mut statement = db.prepare(&format!(
+        "SELECT url, title, count, last_visited FROM visits WHERE {conditions}
+        ORDER BY count DESC, last_visited DESC LIMIT {limit}"
+    ))?;
+    let visits = statement
+        .query_map(rusqlite::params_from_iter(&words), |row| {
+            Ok(Visit {
+                url: row.get(0)?,
+                title: row.get(1)?,
+                count: row.get(2)?,
+                last_visited: row.get(3)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(visits)
+}
+
+/// Forget every visited page.
+pub fn clear() -> eyre::Result<()> {
+    db()?.execute("DELETE FROM visits", [])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_visits() {
+        let db = open(":memory:").unwrap();
+        record_in(&db, "https://docs.rs/tokio", "tokio - Rust", 1).unwrap();
+        record_in(&db, "https://example.com/100%_rust", "Rust tips", 2).unwrap();
+        record_in(&db, "https://docs.rs/tokio", "tokio - Rust", 3).unwrap();
+
+        let visits = search_in(&db, "Rust", 10).unwrap();
+        assert_eq!(visits.len(), 2);
+        assert_eq!(visits[0].url, "https://docs.rs/tokio");
+        assert_eq!(visits[0].count, 2);
+        assert_eq!(visits[0].last_visited, 3);
+
+        assert_eq!(search_in(&db, "tokio rust", 10).unwrap().len(), 1);
+        assert_eq!(search_in(&db, "100%", 10).unwrap().len(), 1);
+        assert!(search_in(&db, "10%0", 10).unwrap().is_empty());
+        assert!(search_in(&db, " ", 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+//! A small json api for browser extensions and launchers. Unlike
+//! `/search?format=json`, which returns our internal responses, this has a
+//! format that's meant to stay the same, and the results are numbered like on
+//! the results page (where result 3 is `#result-3`).
+
+use std::{collections::HashMap, net::SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Serialize;
+use tracing::error;
+
+use crate::{
+    config::Config,
+    engines::{self, preprocess::Bang, ResponseForTab},
+};
+
+use super::search::{random_site, search_query, search_responses};
+
+#[derive(Serialize)]
+struct ApiSearchResponse {
+    query: String,
+    page: usize,
+    results: Vec<ApiResult>,
+    /// Other queries that the engines suggested.
+    related_searches: Vec<String>,
+    /// The url of the next page, if there is one. It's also in the `Link`
+    /// header.
+    next: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ApiResult {
+    /// The position of the result, starting at 1 and counting across pages.
+    index: usize,
+    url: String,
+    title: String,
+    description: String,
+    engines: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ApiSuggestResponse {
+    query: String,
+    suggestions: Vec<String>,
+}
+
+pub async fn search(
+    Query(mut params): Query<HashMap<String, String>>,
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> Response {
+    if !config.api {
+        return (StatusCode::FORBIDDEN, "API access is disabled").into_response();
+    }
+    let processed = engines::preprocess::preprocess(
+        params.get("q").map(String::as_str).unwrap_or_default(),
+        &config.preprocess.steps,
+    );
+    // bangs are turned into parameters, like the results page does with a redirect
+    match processed.bang {
+        Some(Bang::Only(engines)) => {
+            let ids = engines.iter().map(|e| e.id()).collect::<Vec<_>>().join(",");
+            params.insert("engines".to_string(), ids);
+        }
+        Some(Bang::Tab(tab)) => {
+            params.insert("tab".to_string(), tab.to_string());
+        }
+        Some(Bang::Random) => return random_site().await,
+        None => {}
+    }
+    let query = processed.query;
+    if query.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Missing q parameter").into_response();
+    }
+    let page = params
+        .get("page")
+        .and_then(|p| p.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let per_page = config.ui.results_per_page;
+
+    let search = search_query(query.clone(), &params, config, &headers, addr);
+    let responses = match search_responses(search).await {
+        Ok(responses) => responses,
+        Err(err) => {
+            error!("API search error for {query}: {err}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+        }
+    };
+    let response = responses.into_iter().next();
+    let related_searches = match &response {
+        Some(ResponseForTab::All(response)) => response.related_searches.clone(),
+        _ => Vec::new(),
+    };
+    let results = response.map(api_results).unwrap_or_default();
+
+    let (results, has_next) = paginate(results, page, per_page);
+    let next = has_next.then(|| {
+        let mut params = params.into_iter().collect::<Vec<_>>();
+        params.retain(|(k, _)| k != "page");
+        params.sort();
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        serializer.extend_pairs(params);
+        serializer.append_pair("page", &(page + 1).to_string());
+        format!("/api/search?{}", serializer.finish())
+    });
+
+    let link = next.as_ref().map(|next| format!("<{next}>; rel=\"next\""));
+    let mut response = Json(ApiSearchResponse {
+        query,
+        page,
+        results,
+        related_searches,
+        next,
+    })
+    .into_response();
+    if let Some(Ok(link)) = link.map(|l| l.parse()) {
+        response.headers_mut().insert(header::LINK, link);
+    }
+    response
+}
+
+pub async fn suggest(
+    Query(params): Query<HashMap<String, String>>,
+    Extension(config): Extension<Config>,
+) -> Response {
+    if !config.api {
+        return (StatusCode::FORBIDDEN, "API access is disabled").into_response();
+    }
+    let query = params
+        .get("q")
+        .cloned()
+        .unwrap_or_default()
+        .replace('\n', " ");
+
+    match engines::autocomplete(&config, &query, true).await {
+        Ok(suggestions) => Json(ApiSuggestResponse { query, suggestions }).into_response(),
+        Err(err) => {
+            error!("API suggest error for {query}: {err}");
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
+
+fn api_results(response: ResponseForTab) -> Vec<ApiResult> {
+    match response {
+        ResponseForTab::All(response) => response
+            .search_results
+            .into_iter()
+            .enumerate()
+            .map(|(i, r)| ApiResult {
+                index: i + 1,
+                url: r.result.url,
+                title: r.result.title,
+                description: r.result.description,
+                engines: r.engines.iter().map(|e| e.id()).collect(),
+                image_url: None,
+            })
+            .collect(),
+        ResponseForTab::Images(response) => response
+            .image_results
+            .into_iter()
+            .enumerate()
+            .map(|(i, r)| ApiResult {
+                index: i + 1,
+                url: r.result.page_url,
+                title: r.result.title,
+                description: String::new(),
+                engines: r.engines.iter().map(|e| e.id()).collect(),
+                image_url: Some(r.result.image_url),
+            })
+            .collect(),
+    }
+}
+
+/// The results on the page, and whether there's another page. Everything is on
+/// the first page if `results_per_page` isn't set.
+fn paginate(
+    results: Vec<ApiResult>,
+    page: usize,
+    per_page: Option<usize>,
+) -> (Vec<ApiResult>, bool) {
+    let Some(per_page) = per_page.filter(|n| *n > 0) else {
+        return if page == 1 {
+            (results, false)
+        } else {
+            (Vec::new(), false)
+        };
+    };
+    let start = (page - 1).saturating_mul(per_page);
+    let has_next = results.len() > start.saturating_add(per_page);
+    let results = results.into_iter().skip(start).take(per_page).collect();
+    (results, has_next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(n: usize) -> Vec<ApiResult> {
+        (1..=n)
+            .map(|index| ApiResult {
+                index,
+                url: format!("https://example.com/{index}"),
+                title: String::new(),
+                description: String::new(),
+                engines: vec![],
+                image_url: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_paginate() {
+        let (page, has_next) = paginate(results(25), 1, Some(10));
+        assert_eq!(page.len(), 10);
+        assert!(has_next);
+
+        let (page, has_next) = paginate(results(25), 3, Some(10));
+        assert_eq!(
+            page.iter().map(|r| r.index).collect::<Vec<_>>(),
+            (21..=25).collect::<Vec<_>>()
+        );
+        assert!(!has_next);
+
+        let (page, has_next) = paginate(results(25), 1, None);
+        assert_eq!(page.len(), 25);
+        assert!(!has_next);
+        assert!(paginate(results(25), 2, None).0.is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+//! The css, js, and other static files. They're built into the binary, and
+//! files in the config's `assets_dir` are served instead of them, so an
+//! instance can be themed without rebuilding.
+
+use std::{
+    borrow::Cow,
+    path::{Component, Path},
+};
+
+use axum::{
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use rust_embed::RustEmbed;
+use sha1::{Digest, Sha1};
+
+/// The assets aren't fingerprinted, so they're only cached for a while and
+/// then revalidated with the etag.
+const CACHE_CONTROL: &str = "public, max-age=3600";
+
+#[derive(RustEmbed)]
+#[folder = "src/web/assets/"]
+#[exclude = "*README"]
+struct Embedded;
+
+pub async fn get(assets_dir: Option<String>, uri: Uri, headers: HeaderMap) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    let Some(content) = load(assets_dir.as_deref(), path).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let etag = format!("\"{:x}\"", Sha1::digest(&content));
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+    ];
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|h| h.split(',').any(|t| t.trim() == etag || t.trim() == "*"));
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    (
+        cache_headers,
+        [(header::CONTENT_TYPE, guess_mime_type(path))],
+        content.into_owned(),
+    )
+        .into_response()
+}
+
+/// The file from the assets directory if it's there, and otherwise the one
+/// that's built in.
+async fn load(assets_dir: Option<&str>, path: &str) -> Option<Cow<'static, [u8]>> {
+    // don't let requests escape the assets directory
+    let is_safe = Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    if !is_safe || path.is_empty() {
+        return None;
+    }
+    if let Some(assets_dir) = assets_dir {
+        if let Ok(content) = tokio::fs::read(Path::new(assets_dir).join(path)).await {
+            return Some(Cow::Owned(content));
+        }
+    }
+    Embedded::get(path).map(|file| file.data)
+}
+
+fn guess_mime_type(path: &str) -> &'static str {
+    match path.rsplit('.').next() {
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load() {
+        let embedded = load(None, "style.css").await.unwrap();
+        assert!(!embedded.is_empty());
+        assert!(load(None, "README").await.is_none());
+        assert!(load(None, "nonexistent.css").await.is_none());
+
+        // files in the assets dir win, but the rest still come from the binary
+        let dir = std::env::temp_dir().join(format!("metasearch-assets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("style.css"), "body{}").unwrap();
+        let assets_dir = dir.to_str();
+        assert_eq!(&*load(assets_dir, "style.css").await.unwrap(), b"body{}");
+        assert!(load(assets_dir, "script.js").await.is_some());
+        assert!(load(assets_dir, "../Cargo.toml").await.is_none());
+        assert!(load(assets_dir, "/etc/passwd").await.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
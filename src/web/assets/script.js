@@ -128,6 +128,22 @@ if (searchInputEl) {
     )
       return;
 
+    // 1-9 and the up and down arrows move between results
+    const resultIndex = focusedResultIndex();
+    if (e.key.match(/^[1-9]$/) && focusResult(Number(e.key))) {
+      return;
+    } else if (e.key === "ArrowDown" && focusResult(resultIndex + 1)) {
+      e.preventDefault();
+      return;
+    } else if (
+      e.key === "ArrowUp" &&
+      resultIndex > 1 &&
+      focusResult(resultIndex - 1)
+    ) {
+      e.preventDefault();
+      return;
+    }
+
     // if the user starts typing but they don't have focus on the input, focus it
 
     // must be a letter or number
@@ -157,6 +173,22 @@ if (searchInputEl) {
     }
   });
 
+  // the index of the result that has focus, or 0 if none do
+  function focusedResultIndex() {
+    const resultEl = document.activeElement?.closest(".search-result");
+    return resultEl ? Number(resultEl.dataset.index) : 0;
+  }
+
+  // returns whether there was a result to focus
+  function focusResult(index) {
+    const anchorEl = document.querySelector(
+      `#result-${index} .search-result-anchor`
+    );
+    if (!anchorEl) return false;
+    anchorEl.focus();
+    return true;
+  }
+
   // update the input suggestions on input
   searchInputEl.addEventListener("input", () => {
     clearFocusedSuggestion();
@@ -170,6 +202,166 @@ if (searchInputEl) {
   });
 }
 
+// the discussions filter hides the results that aren't from forums or q&a sites
+document.addEventListener("click", (e) => {
+  const buttonEl = e.target.closest(".discussions-filter");
+  if (!buttonEl) return;
+  const pressed = buttonEl.getAttribute("aria-pressed") !== "true";
+  buttonEl.setAttribute("aria-pressed", pressed);
+  buttonEl.closest("main").classList.toggle("only-discussions", pressed);
+});
+
+// "load more results" adds the next page in place, and it's clicked
+// automatically when it's scrolled to
+async function loadMoreResults(linkEl) {
+  const loadMoreEl = linkEl.closest(".load-more");
+  if (loadMoreEl.classList.contains("loading")) return;
+  loadMoreEl.classList.add("loading");
+  const res = await fetch(linkEl.href);
+  if (!res.ok) {
+    loadMoreEl.classList.remove("loading");
+    return;
+  }
+  loadMoreEl.insertAdjacentHTML("afterend", await res.text());
+  loadMoreEl.remove();
+  observeLoadMore();
+}
+document.addEventListener("click", (e) => {
+  const linkEl = e.target.closest(".load-more-link");
+  if (!linkEl) return;
+  e.preventDefault();
+  loadMoreResults(linkEl);
+});
+const loadMoreObserver =
+  "IntersectionObserver" in window &&
+  new IntersectionObserver((entries) => {
+    for (const entry of entries) {
+      if (!entry.isIntersecting) continue;
+      loadMoreObserver.unobserve(entry.target);
+      loadMoreResults(entry.target.querySelector(".load-more-link"));
+    }
+  });
+function observeLoadMore() {
+  const loadMoreEl = document.querySelector(".load-more:not(.loading)");
+  if (loadMoreEl && loadMoreObserver) loadMoreObserver.observe(loadMoreEl);
+}
+// the results are streamed in after the script runs
+document.addEventListener("DOMContentLoaded", observeLoadMore);
+
+// results that turned out to be dead are flagged and moved to the bottom of
+// the first page
+function flagDeadLinks() {
+  for (const deadLinksEl of document.querySelectorAll(".dead-links")) {
+    for (const index of deadLinksEl.dataset.results.split(",")) {
+      const resultEl = document.getElementById(`result-${index}`);
+      if (!resultEl || resultEl.classList.contains("unreachable")) continue;
+      resultEl.classList.add("unreachable");
+      const flagEl = document.createElement("span");
+      flagEl.className = "search-result-unreachable";
+      flagEl.textContent = "Unreachable";
+      flagEl.title = "The page didn't load when we checked it";
+      resultEl.querySelector(".search-result-anchor").after(flagEl);
+      const resultEls = resultEl.parentElement.querySelectorAll(
+        ":scope > .search-result"
+      );
+      resultEls[resultEls.length - 1].after(resultEl);
+    }
+  }
+}
+document.addEventListener("DOMContentLoaded", flagDeadLinks);
+
+// the recipe data of results that turned out to be recipes after the results
+// were shown replaces their extras. this has to run before the archived copies
+// are added, since they go in the extras.
+function addRecipes() {
+  for (const recipeEl of document.querySelectorAll(".recipes > div")) {
+    const resultEl = document.getElementById(`result-${recipeEl.dataset.result}`);
+    if (!resultEl) continue;
+    resultEl.querySelector(".search-result-anchor").href = recipeEl.dataset.href;
+    const extrasEl = resultEl.querySelector(".search-result-extras");
+    if (extrasEl) {
+      extrasEl.replaceWith(recipeEl.firstElementChild);
+    } else {
+      const beforeEl =
+        resultEl.querySelector(".search-result-annotation") ??
+        resultEl.querySelector(".search-result-description");
+      beforeEl.after(recipeEl.firstElementChild);
+    }
+  }
+}
+document.addEventListener("DOMContentLoaded", addRecipes);
+
+// the wayback machine's copies that were looked up after the results were
+// shown are moved into their results
+function addArchivedCopies() {
+  for (const copyEl of document.querySelectorAll(".archived-copies > div")) {
+    const resultEl = document.getElementById(`result-${copyEl.dataset.result}`);
+    if (!resultEl || resultEl.querySelector(".search-result-archived")) continue;
+    let extrasEl = resultEl.querySelector(".search-result-extras");
+    if (!extrasEl) {
+      extrasEl = document.createElement("div");
+      extrasEl.className = "search-result-extras";
+      const beforeEl =
+        resultEl.querySelector(".search-result-annotation") ??
+        resultEl.querySelector(".search-result-description");
+      beforeEl.after(extrasEl);
+    }
+    extrasEl.append(copyEl.firstElementChild);
+  }
+}
+document.addEventListener("DOMContentLoaded", addArchivedCopies);
+
+// the descriptions from the meta tags of pages that were fetched after the
+// results were shown replace the results' "No description available."
+function addPageDescriptions() {
+  for (const descriptionEl of document.querySelectorAll(
+    ".page-descriptions > div"
+  )) {
+    const resultEl = document.getElementById(
+      `result-${descriptionEl.dataset.result}`
+    );
+    resultEl
+      ?.querySelector(".search-result-description.no-description")
+      ?.replaceWith(descriptionEl.firstElementChild);
+  }
+}
+document.addEventListener("DOMContentLoaded", addPageDescriptions);
+
+// while the results page is loading, show which engines it's still waiting
+// for. the updates come from the search that the page is doing, so this only
+// works while the page is still loading.
+const progressUpdatesEl = document.querySelector(".progress-updates");
+if (progressUpdatesEl && document.readyState === "loading" && window.EventSource) {
+  const pendingEl = document.createElement("p");
+  pendingEl.className = "progress-update progress-pending";
+  progressUpdatesEl.prepend(pendingEl);
+
+  const pending = new Set();
+  const events = new EventSource(`/search/events${location.search}`);
+  events.addEventListener("engine", (e) => {
+    const { engine, status } = JSON.parse(e.data);
+    if (status === "done" || status === "error") pending.delete(engine);
+    else pending.add(engine);
+    pendingEl.textContent =
+      pending.size > 0 ? `Waiting for ${[...pending].join(", ")}` : "";
+  });
+  // this is also the event for a lost connection, which isn't retried
+  events.addEventListener("error", () => events.close());
+  events.addEventListener("done", () => {
+    events.close();
+    pendingEl.remove();
+  });
+}
+
+// nsfw images are blurred, and the first click shows them instead of opening
+// them
+document.addEventListener("click", (e) => {
+  const anchorEl = e.target.closest(".image-result.nsfw .image-result-anchor");
+  if (!anchorEl) return;
+  e.preventDefault();
+  anchorEl.closest(".image-result").classList.remove("nsfw");
+});
+
 const customCssEl = document.getElementById("custom-css");
 if (customCssEl) {
   // tab to indent
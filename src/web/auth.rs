@@ -0,0 +1,269 @@
+//! Optional access control for private instances.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Form,
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use maud::{html, PreEscaped, DOCTYPE};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::{AuthConfig, Config, IpRange},
+    web::{head_html, settings::cookie_key},
+};
+
+const TOKEN_COOKIE_NAME: &str = "auth-token";
+
+pub async fn middleware(
+    State(config): State<Arc<Config>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    cookies: CookieJar,
+    req: Request,
+    next: Next,
+) -> Response {
+    let auth = &config.auth;
+    if !auth.is_enabled() {
+        return next.run(req).await;
+    }
+
+    let ip = client_ip(auth, req.headers(), addr);
+    if auth.allowed_ips.iter().any(|range| range.contains(ip))
+        || is_basic_auth_valid(auth, req.headers())
+        || is_token_valid(auth, req.headers(), &cookies)
+    {
+        return next.run(req).await;
+    }
+
+    if auth.token.is_some() {
+        (StatusCode::FOUND, [(header::LOCATION, "/login")]).into_response()
+    } else if auth.basic.is_some() {
+        (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, r#"Basic realm="metasearch""#)],
+            "Unauthorized",
+        )
+            .into_response()
+    } else {
+        (StatusCode::FORBIDDEN, "Forbidden").into_response()
+    }
+}
+
+fn client_ip(auth: &AuthConfig, headers: &HeaderMap, addr: SocketAddr) -> IpAddr {
+    if auth.trust_x_forwarded_for {
+        // the last address is the one that our reverse proxy saw, everything before
+        // it could've been set by the client
+        if let Some(ip) = headers
+            .get("x-forwarded-for")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok())
+        {
+            return ip;
+        }
+    }
+    addr.ip()
+}
+
+fn is_basic_auth_valid(auth: &AuthConfig, headers: &HeaderMap) -> bool {
+    let Some(basic) = &auth.basic else {
+        return false;
+    };
+    let Some(encoded) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Basic "))
+    else {
+        return false;
+    };
+    let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(encoded.trim()) else {
+        return false;
+    };
+    let expected = format!("{}:{}", basic.username, basic.password);
+    constant_time_eq(&decoded, expected.as_bytes())
+}
+
+fn is_token_valid(auth: &AuthConfig, headers: &HeaderMap, cookies: &CookieJar) -> bool {
+    let Some(token) = &auth.token else {
+        return false;
+    };
+
+    // api clients can send the token as a bearer token instead of a cookie
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    if bearer.is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes())) {
+        return true;
+    }
+    cookies.get(TOKEN_COOKIE_NAME).is_some_and(|c| {
+        constant_time_eq(c.value().as_bytes(), token_cookie_value(token).as_bytes())
+    })
+}
+
+/// What's stored in the cookie after logging in, which is a signature of the
+/// token so the token itself isn't kept in the browser. Changing the token or
+/// the cookie secret logs everyone out.
+fn token_cookie_value(token: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(cookie_key().signing())
+        .expect("hmac can take a key of any size");
+    mac.update(TOKEN_COOKIE_NAME.as_bytes());
+    mac.update(b"\0");
+    mac.update(token.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Whether the request has the admin token, for endpoints that only whoever
+/// runs the instance should use.
+pub fn is_admin(auth: &AuthConfig, headers: &HeaderMap) -> bool {
+    let Some(admin_token) = &auth.admin_token else {
+        return false;
+    };
+    headers
+        .get("x-admin-token")
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), admin_token.as_bytes()))
+}
+
+/// Whether a secret matches, in the same time however much of it matched. The
+/// secrets are hashed first, so the time doesn't depend on their lengths
+/// either.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let (a, b) = (Sha256::digest(a), Sha256::digest(b));
+    a.iter().zip(b.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+impl IpRange {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(range), IpAddr::V4(ip)) => {
+                let shift = 32 - u32::from(self.prefix_len);
+                u32::from(range).checked_shr(shift).unwrap_or(0)
+                    == u32::from(ip).checked_shr(shift).unwrap_or(0)
+            }
+            (IpAddr::V6(range), IpAddr::V6(ip)) => {
+                let shift = 128 - u32::from(self.prefix_len);
+                u128::from(range).checked_shr(shift).unwrap_or(0)
+                    == u128::from(ip).checked_shr(shift).unwrap_or(0)
+            }
+            // dual-stack sockets give us ipv4 clients as ipv4-mapped ipv6 addresses
+            (IpAddr::V4(_), IpAddr::V6(ip)) => ip
+                .to_ipv4_mapped()
+                .is_some_and(|ip| self.contains(IpAddr::V4(ip))),
+            (IpAddr::V6(_), IpAddr::V4(_)) => false,
+        }
+    }
+}
+
+pub async fn get(Extension(config): Extension<Config>) -> impl IntoResponse {
+    render_login_page(&config, false)
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    pub token: String,
+}
+
+pub async fn post(
+    Extension(config): Extension<Config>,
+    mut jar: CookieJar,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    let Some(token) = &config.auth.token else {
+        return (StatusCode::NOT_FOUND, "Token login is disabled").into_response();
+    };
+    if !constant_time_eq(form.token.as_bytes(), token.as_bytes()) {
+        return (StatusCode::UNAUTHORIZED, render_login_page(&config, true)).into_response();
+    }
+
+    let mut token_cookie = Cookie::new(TOKEN_COOKIE_NAME, token_cookie_value(token));
+    token_cookie.set_http_only(true);
+    token_cookie.set_same_site(SameSite::Lax);
+    token_cookie.make_permanent();
+    jar = jar.add(token_cookie);
+
+    (StatusCode::FOUND, [(header::LOCATION, "/")], jar).into_response()
+}
+
+fn render_login_page(config: &Config, incorrect: bool) -> impl IntoResponse {
+    let html = html! {
+        (PreEscaped("<!-- source code: https://github.com/mat-1/metasearch2 -->\n"))
+        (DOCTYPE)
+        html lang="en" {
+            {(head_html(Some("login"), config))}
+            body {
+                div.main-container.index-page.login-page {
+                    h1 { {(config.ui.site_name)} }
+                    form.search-form method="post" action="/login" {
+                        input type="password" name="token" placeholder="Token" autofocus autocomplete="current-password";
+                        input type="submit" value="Log in";
+                    }
+                    @if incorrect {
+                        p.login-error { "Incorrect token" }
+                    }
+                }
+            }
+        }
+    }
+    .into_string();
+
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn range_contains(range: &str, ip: &str) -> bool {
+        IpRange::from_str(range)
+            .unwrap()
+            .contains(IpAddr::from_str(ip).unwrap())
+    }
+
+    #[test]
+    fn test_ipv4_range() {
+        assert!(range_contains("192.168.0.0/16", "192.168.1.20"));
+        assert!(!range_contains("192.168.0.0/16", "192.169.1.20"));
+        assert!(range_contains("10.0.0.5", "10.0.0.5"));
+        assert!(!range_contains("10.0.0.5", "10.0.0.6"));
+        assert!(range_contains("0.0.0.0/0", "1.2.3.4"));
+    }
+
+    #[test]
+    fn test_ipv6_range() {
+        assert!(range_contains("fd00::/8", "fd12:3456::1"));
+        assert!(!range_contains("fd00::/8", "fe80::1"));
+    }
+
+    #[test]
+    fn test_ipv4_mapped_ipv6() {
+        assert!(range_contains("127.0.0.0/8", "::ffff:127.0.0.1"));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"hunter2", b"hunter2"));
+        assert!(!constant_time_eq(b"hunter2", b"hunter3"));
+        assert!(!constant_time_eq(b"hunter2", b"hunter22"));
+        assert!(!constant_time_eq(b"", b"hunter2"));
+    }
+}
@@ -1,6 +1,11 @@
 use std::collections::HashMap;
 
-use axum::{extract::Query, http::StatusCode, response::IntoResponse, Extension, Json};
+use axum::{
+    extract::Query,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
 use tracing::error;
 
 use crate::{config::Config, engines};
@@ -8,6 +13,7 @@ use crate::{config::Config, engines};
 pub async fn route(
     Query(params): Query<HashMap<String, String>>,
     Extension(config): Extension<Config>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let query = params
         .get("q")
@@ -15,13 +21,25 @@ pub async fn route(
         .unwrap_or_default()
         .replace('\n', " ");
 
-    let res = match engines::autocomplete(&config, &query).await {
+    let from_peer = headers.contains_key(engines::peers::FEDERATED_HEADER);
+    let res = match engines::autocomplete(&config, &query, !from_peer).await {
         Ok(res) => res,
         Err(err) => {
             error!("Autocomplete error for {query}: {err}");
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json((query, vec![])));
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [(header::CACHE_CONTROL, "no-store")],
+                Json((query, vec![])),
+            );
         }
     };
 
-    (StatusCode::OK, Json((query, res)))
+    // the browser asks again for every keystroke, so repeated prefixes (like
+    // after backspacing) are served from its cache. it's private since the
+    // suggestions can depend on the user's settings
+    (
+        StatusCode::OK,
+        [(header::CACHE_CONTROL, "private, max-age=300")],
+        Json((query, res)),
+    )
 }
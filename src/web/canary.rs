@@ -0,0 +1,32 @@
+use axum::{
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+
+use crate::{config::Config, engines::canary};
+
+use super::auth;
+
+/// The results of the last engine self-check, as JSON.
+pub async fn route(Extension(config): Extension<Config>, headers: HeaderMap) -> Response {
+    if !config.canary.enabled {
+        return (StatusCode::NOT_FOUND, "The engine self-check is disabled").into_response();
+    }
+    if !auth::is_admin(&config.auth, &headers) {
+        return (
+            StatusCode::FORBIDDEN,
+            "The engine self-check needs the admin token in the X-Admin-Token header",
+        )
+            .into_response();
+    }
+
+    let statuses = canary::statuses();
+    let status = if statuses.iter().any(|s| s.broken) {
+        // so uptime monitors can alert on it
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    (status, Json(statuses)).into_response()
+}
@@ -0,0 +1,212 @@
+//! Citations for papers in BibTeX or CSL-JSON, for the "Cite" links under
+//! Google Scholar results. They're made from what we parsed from the results,
+//! which the links carry, so Scholar isn't asked again.
+
+use axum::{
+    extract::Query,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::engines::{EngineScholarResult, EngineSearchResult};
+
+#[derive(Deserialize, Debug)]
+pub struct Citation {
+    title: String,
+    url: String,
+    /// The authors, separated by semicolons.
+    #[serde(default)]
+    authors: String,
+    year: Option<u32>,
+    venue: Option<String>,
+}
+
+/// Separate from [`Citation`] since flattening breaks parsing the year from a
+/// query string.
+#[derive(Deserialize)]
+pub struct FormatParam {
+    #[serde(default)]
+    format: CiteFormat,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum CiteFormat {
+    #[default]
+    Bibtex,
+    CslJson,
+}
+
+impl Citation {
+    fn authors(&self) -> impl Iterator<Item = &str> {
+        self.authors
+            .split(';')
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+    }
+
+    /// The venue without the year that Scholar puts at the end, or None if
+    /// Scholar cut it off.
+    fn venue(&self) -> Option<&str> {
+        let venue = self.venue.as_deref()?;
+        let venue = match self.year {
+            Some(year) => venue.trim_end_matches(&year.to_string()),
+            None => venue,
+        };
+        let venue = venue.trim().trim_end_matches(',').trim();
+        (!venue.is_empty() && !venue.ends_with('…')).then_some(venue)
+    }
+
+    /// A key like `vaswani2017attention`.
+    fn key(&self) -> String {
+        let last_name = self
+            .authors()
+            .next()
+            .and_then(|a| a.split_whitespace().last())
+            .unwrap_or("unknown");
+        let first_word = self
+            .title
+            .split(|c: char| !c.is_alphanumeric())
+            .find(|w| w.len() > 3)
+            .unwrap_or_default();
+        let year = self.year.map(|y| y.to_string()).unwrap_or_default();
+        format!("{last_name}{year}{first_word}").to_lowercase()
+    }
+
+    pub fn bibtex(&self) -> String {
+        let mut fields = vec![("title", format!("{{{}}}", escape_bibtex(&self.title)))];
+        let authors = self.authors().collect::<Vec<_>>();
+        if !authors.is_empty() {
+            fields.push(("author", escape_bibtex(&authors.join(" and "))));
+        }
+        if let Some(year) = self.year {
+            fields.push(("year", year.to_string()));
+        }
+        if let Some(venue) = self.venue() {
+            fields.push(("journal", escape_bibtex(venue)));
+        }
+        fields.push(("url", self.url.clone()));
+
+        let mut bibtex = format!("@article{{{},\n", self.key());
+        for (name, value) in fields {
+            bibtex.push_str(&format!("  {name} = {{{value}}},\n"));
+        }
+        bibtex.push_str("}\n");
+        bibtex
+    }
+
+    pub fn csl_json(&self) -> serde_json::Value {
+        let authors = self
+            .authors()
+            .map(|a| match a.rsplit_once(' ') {
+                Some((given, family)) => serde_json::json!({ "given": given, "family": family }),
+                None => serde_json::json!({ "literal": a }),
+            })
+            .collect::<Vec<_>>();
+        let mut item = serde_json::json!({
+            "id": self.key(),
+            "type": "article-journal",
+            "title": self.title,
+            "URL": self.url,
+            "author": authors,
+        });
+        if let Some(year) = self.year {
+            item["issued"] = serde_json::json!({ "date-parts": [[year]] });
+        }
+        if let Some(venue) = self.venue() {
+            item["container-title"] = venue.into();
+        }
+        serde_json::json!([item])
+    }
+}
+
+fn escape_bibtex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '&' | '%' | '$' | '#' | '_' | '{' | '}') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The link to the citation for a result, in the format.
+pub fn href(result: &EngineSearchResult, scholar: &EngineScholarResult, format: &str) -> String {
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    serializer.append_pair("format", format);
+    serializer.append_pair("title", &result.title);
+    serializer.append_pair("url", &result.url);
+    serializer.append_pair("authors", &scholar.authors.join("; "));
+    if let Some(year) = scholar.year {
+        serializer.append_pair("year", &year.to_string());
+    }
+    if let Some(venue) = &result.extras.venue {
+        serializer.append_pair("venue", venue);
+    }
+    format!("/cite?{}", serializer.finish())
+}
+
+pub async fn route(
+    Query(FormatParam { format }): Query<FormatParam>,
+    Query(citation): Query<Citation>,
+) -> Response {
+    if citation.title.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Missing title").into_response();
+    }
+    match format {
+        CiteFormat::Bibtex => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            citation.bibtex(),
+        )
+            .into_response(),
+        CiteFormat::CslJson => (
+            [(
+                header::CONTENT_TYPE,
+                "application/vnd.citationstyles.csl+json",
+            )],
+            citation.csl_json().to_string(),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn citation() -> Citation {
+        Citation {
+            title: "Attention is all you need".to_string(),
+            url: "https://proceedings.neurips.cc/paper/7181".to_string(),
+            authors: "A Vaswani; N Shazeer".to_string(),
+            year: Some(2017),
+            venue: Some("Advances in neural …, 2017".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_bibtex() {
+        assert_eq!(
+            citation().bibtex(),
+            "@article{vaswani2017attention,\n  \
+            title = {{Attention is all you need}},\n  \
+            author = {A Vaswani and N Shazeer},\n  \
+            year = {2017},\n  \
+            url = {https://proceedings.neurips.cc/paper/7181},\n\
+            }\n"
+        );
+        assert_eq!(escape_bibtex("R&D 100%"), "R\\&D 100\\%");
+    }
+
+    #[test]
+    fn test_csl_json() {
+        let mut citation = citation();
+        citation.venue = Some("Nature, 2017".to_string());
+        let json = citation.csl_json();
+        assert_eq!(json[0]["author"][0]["family"], "Vaswani");
+        assert_eq!(json[0]["issued"]["date-parts"][0][0], 2017);
+        assert_eq!(json[0]["container-title"], "Nature");
+    }
+}
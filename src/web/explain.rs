@@ -0,0 +1,236 @@
+//! `/api/explain`, which shows what would be sent to an engine for a query
+//! without sending it, for debugging things like Google's `async` parameter and
+//! Bing's cookies. It's only for whoever runs the instance, and secrets like
+//! cookies and api keys are hidden.
+
+use std::{collections::HashMap, net::SocketAddr, str::FromStr};
+
+use axum::{
+    extract::{ConnectInfo, Query},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Serialize;
+
+use crate::{
+    config::Config,
+    engines::{Engine, RequestResponse, SearchTab},
+};
+
+use super::{auth, search::search_query};
+
+/// Shown instead of the values of headers and params that could have secrets.
+const REDACTED: &str = "[redacted]";
+
+#[derive(Serialize)]
+struct Explanation {
+    engine: &'static str,
+    query: String,
+    /// More than one if the engine has several endpoints, in the order they'd
+    /// be tried.
+    requests: Vec<ExplainedRequest>,
+    /// Why there aren't any requests, if there aren't.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ExplainedRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    endpoint: Option<String>,
+    method: String,
+    url: String,
+    /// The query string of the url, decoded.
+    params: Vec<(String, String)>,
+    /// Only the headers that the engine sets. The browser headers that every
+    /// request has are added by the client when it's sent.
+    headers: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+impl ExplainedRequest {
+    fn new(endpoint: Option<String>, req: wreq::RequestBuilder) -> eyre::Result<Self> {
+        let req = req.build()?;
+        Ok(Self {
+            endpoint,
+            method: req.method().to_string(),
+            url: redact_url(req.url()),
+            params: req
+                .url()
+                .query_pairs()
+                .map(|(k, v)| {
+                    let v = if is_secret(&k) { REDACTED.into() } else { v };
+                    (k.into_owned(), v.into_owned())
+                })
+                .collect(),
+            headers: req
+                .headers()
+                .iter()
+                .map(|(k, v)| {
+                    let v = if is_secret(k.as_str()) {
+                        REDACTED.to_owned()
+                    } else {
+                        String::from_utf8_lossy(v.as_bytes()).into_owned()
+                    };
+                    (k.to_string(), v)
+                })
+                .collect(),
+            body: req
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(|b| String::from_utf8_lossy(b).into_owned()),
+        })
+    }
+}
+
+/// Whether a header or query param could have a secret in it, like a cookie or
+/// an api key.
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    [
+        "auth", "cookie", "key", "token", "secret", "password", "session",
+    ]
+    .iter()
+    .any(|secret| name.contains(secret))
+}
+
+fn redact_url(url: &url::Url) -> String {
+    let mut url = url.clone();
+    if url.query_pairs().any(|(k, _)| is_secret(&k)) {
+        let pairs = url
+            .query_pairs()
+            .map(|(k, v)| {
+                let v = if is_secret(&k) { REDACTED.into() } else { v };
+                (k.into_owned(), v.into_owned())
+            })
+            .collect::<Vec<_>>();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+pub async fn route(
+    Query(params): Query<HashMap<String, String>>,
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> Response {
+    if !config.explain {
+        return (StatusCode::FORBIDDEN, "The explain API is disabled").into_response();
+    }
+    if !auth::is_admin(&config.auth, &headers) {
+        return (
+            StatusCode::FORBIDDEN,
+            "The explain API needs the admin token in the X-Admin-Token header",
+        )
+            .into_response();
+    }
+    let Some(query) = params.get("q").map(|q| q.trim().to_owned()) else {
+        return (StatusCode::BAD_REQUEST, "Missing q parameter").into_response();
+    };
+    let Some(engine) = params.get("engine").and_then(|e| Engine::from_str(e).ok()) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Missing or unknown engine parameter",
+        )
+            .into_response();
+    };
+
+    let query = search_query(query, &params, config, &headers, addr);
+    if !query.config.engines.get(engine).enabled {
+        return (StatusCode::BAD_REQUEST, "The engine is disabled").into_response();
+    }
+    // engines that make requests first (like for a token) still make them here
+    let request = if query.tab == SearchTab::Images {
+        Ok(engine.request_images(&query))
+    } else {
+        engine.request(&query).await
+    };
+    let request = match request {
+        Ok(request) => request,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Engine couldn't make a request: {err}"),
+            )
+                .into_response()
+        }
+    };
+
+    let (requests, note) = match request {
+        RequestResponse::Http(req) => (vec![ExplainedRequest::new(None, *req)], None),
+        RequestResponse::Pages(reqs) => (
+            reqs.into_iter()
+                .map(|r| ExplainedRequest::new(None, r))
+                .collect(),
+            Some("Every page is requested at the same time".to_owned()),
+        ),
+        RequestResponse::Failover(reqs) => (
+            reqs.into_iter()
+                .map(|r| ExplainedRequest::new(Some(r.endpoint), r.request))
+                .collect(),
+            None,
+        ),
+        RequestResponse::Instant(_) => (
+            vec![],
+            Some("The engine answers without making a request".to_owned()),
+        ),
+        RequestResponse::Skipped(reason) => (vec![], Some(reason.to_owned())),
+        RequestResponse::None => (
+            vec![],
+            Some("The engine doesn't make a request for this query".to_owned()),
+        ),
+    };
+    let requests = match requests.into_iter().collect::<eyre::Result<Vec<_>>>() {
+        Ok(requests) => requests,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Engine made an invalid request: {err}"),
+            )
+                .into_response()
+        }
+    };
+
+    Json(Explanation {
+        engine: engine.id(),
+        query: query.query.clone(),
+        requests,
+        note,
+    })
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explained_request() {
+        let req = wreq::Client::new()
+            .post("https://example.com/search?q=rust+lang&page=2&api_key=hunter2")
+            .header("cookie", "SRCHHPGUSR=ADLT=OFF")
+            .body("form=1");
+        let explained = ExplainedRequest::new(None, req).unwrap();
+        assert_eq!(explained.method, "POST");
+        assert_eq!(
+            explained.url,
+            "https://example.com/search?q=rust+lang&page=2&api_key=%5Bredacted%5D"
+        );
+        assert_eq!(
+            explained.params,
+            [
+                ("q".to_owned(), "rust lang".to_owned()),
+                ("page".to_owned(), "2".to_owned()),
+                ("api_key".to_owned(), REDACTED.to_owned())
+            ]
+        );
+        assert_eq!(
+            explained.headers,
+            [("cookie".to_owned(), REDACTED.to_owned())]
+        );
+        assert_eq!(explained.body.as_deref(), Some("form=1"));
+    }
+}
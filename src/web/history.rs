@@ -0,0 +1,99 @@
+//! The /history page, with past searches grouped into sessions.
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use maud::{html, DOCTYPE};
+use tracing::error;
+
+use crate::{config::Config, history, web::head_html};
+
+use super::check_same_origin;
+
+fn disabled() -> Response {
+    (StatusCode::NOT_FOUND, "History is disabled").into_response()
+}
+
+fn internal_error(err: eyre::Report) -> Response {
+    error!("History error: {err}");
+    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+}
+
+pub async fn get(Extension(config): Extension<Config>) -> Response {
+    if !config.history.enabled {
+        return disabled();
+    }
+    let entries = match history::recent(history::MAX_ENTRIES) {
+        Ok(entries) => entries,
+        Err(err) => return internal_error(err),
+    };
+    let window = config.history.session_minutes as i64 * 60;
+    let mut sessions = history::group_sessions(entries, window);
+    sessions.reverse();
+
+    let format_time = |timestamp: i64| {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%d %H:%M UTC")
+            .to_string()
+    };
+
+    let html = html! {
+        (DOCTYPE)
+        html lang="en" {
+            {(head_html(Some("history"), &config))}
+            body {
+                div.main-container.settings-page {
+                    main {
+                        a.back-to-index-button href="/" { "Back" }
+                        h1 { "History" }
+                        @if sessions.is_empty() {
+                            p { "Nothing has been searched yet." }
+                        }
+                        @for session in &sessions {
+                            @let queries = session.queries();
+                            @let last_query = session.entries.last().map(|e| e.query.as_str()).unwrap_or_default();
+                            div.history-session {
+                                p.history-session-time {
+                                    (format_time(session.entries[0].searched_at))
+                                    @if queries.len() > 1 {
+                                        " · " (queries.len()) " searches · "
+                                        a href={ "/search?q=" (urlencoding::encode(last_query)) "&session=" (session.id()) } { "Resume session" }
+                                    }
+                                }
+                                div.session-chips {
+                                    @for query in &queries {
+                                        a.session-chip href={ "/search?q=" (urlencoding::encode(query)) "&session=" (session.id()) } { (query) }
+                                    }
+                                }
+                            }
+                        }
+                        @if !sessions.is_empty() {
+                            form method="post" action="/history/clear" {
+                                input type="submit" value="Clear history";
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    .into_string();
+
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response()
+}
+
+pub async fn clear(Extension(config): Extension<Config>, headers: HeaderMap) -> Response {
+    if !config.history.enabled {
+        return disabled();
+    }
+    if let Err(err) = check_same_origin(&headers) {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+    if let Err(err) = history::clear() {
+        return internal_error(err);
+    }
+    (StatusCode::FOUND, [(header::LOCATION, "/history")]).into_response()
+}
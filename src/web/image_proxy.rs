@@ -9,6 +9,7 @@ use axum::{
 use tracing::error;
 use wreq::header;
 
+use super::proxy_cache;
 use crate::config::Config;
 
 pub async fn route(
@@ -24,12 +25,15 @@ pub async fn route(
     if url.is_empty() {
         return (StatusCode::BAD_REQUEST, "Missing `url` parameter").into_response();
     }
+    // only images that passed the checks below are cached
+    if let Some(cached) = proxy_cache::get(&url).await {
+        return image_response(cached.content_type, cached.body);
+    }
 
     // ssrf protection. i sure hope this is good enough!
     let Ok(v) = url_jail::validate(&url, url_jail::Policy::PublicOnly).await else {
         return (StatusCode::BAD_REQUEST, "Invalid URL").into_response();
     };
-    drop(url);
     let Ok(client) = wreq::Client::builder()
         .resolve(&v.host, v.to_socket_addr())
         .build()
@@ -79,6 +83,11 @@ pub async fn route(
         }
     }
 
+    proxy_cache::put(&url, &content_type, &image_bytes).await;
+    image_response(content_type, image_bytes)
+}
+
+fn image_response(content_type: String, image_bytes: Vec<u8>) -> Response {
     (
         [
             (header::CONTENT_TYPE, content_type),
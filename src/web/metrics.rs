@@ -0,0 +1,70 @@
+use std::fmt::Write;
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+
+use crate::{config::Config, engines::drift};
+
+use super::auth;
+
+/// How many results each engine usually gives, in the Prometheus text format, so
+/// alerts can be set up for engines that are drifting.
+pub async fn route(Extension(config): Extension<Config>, headers: HeaderMap) -> Response {
+    if !config.drift.enabled {
+        return (StatusCode::NOT_FOUND, "Engine drift tracking is disabled").into_response();
+    }
+    if !auth::is_admin(&config.auth, &headers) {
+        return (
+            StatusCode::FORBIDDEN,
+            "The metrics need the admin token in the X-Admin-Token header",
+        )
+            .into_response();
+    }
+
+    let statuses = drift::statuses();
+    let mut body = String::new();
+    let metrics: [(&str, &str, fn(&drift::DriftStatus) -> f64); 4] = [
+        (
+            "metasearch_engine_results_baseline",
+            "The long-term average number of results from the engine.",
+            |s| s.baseline,
+        ),
+        (
+            "metasearch_engine_results_recent",
+            "The average number of results from the engine in recent searches.",
+            |s| s.recent,
+        ),
+        (
+            "metasearch_engine_responses_total",
+            "How many successful responses from the engine were counted.",
+            |s| s.samples as f64,
+        ),
+        (
+            "metasearch_engine_drifting",
+            "1 if the engine is giving much fewer results than usual.",
+            |s| if s.drifting { 1. } else { 0. },
+        ),
+    ];
+    for (name, help, value) in metrics {
+        let kind = if name.ends_with("_total") {
+            "counter"
+        } else {
+            "gauge"
+        };
+        let _ = writeln!(body, "# HELP {name} {help}");
+        let _ = writeln!(body, "# TYPE {name} {kind}");
+        for status in &statuses {
+            let _ = writeln!(
+                body,
+                "{name}{{engine=\"{}\"}} {}",
+                status.engine.id(),
+                value(status)
+            );
+        }
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
@@ -1,120 +1,181 @@
+mod api;
+mod assets;
+mod auth;
 mod autocomplete;
+#[cfg(feature = "admin")]
+mod canary;
+mod cite;
+#[cfg(feature = "admin")]
+mod explain;
+mod history;
+#[cfg(feature = "proxies")]
 mod image_proxy;
 mod index;
+#[cfg(feature = "admin")]
+mod metrics;
 mod opensearch;
+#[cfg(feature = "proxies")]
+mod proxy_cache;
+mod redirect;
+mod saved;
 mod search;
 mod settings;
+mod snapshots;
+mod themes;
 
-use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
     extract::{Request, State},
-    http::{header, StatusCode},
+    http::{header, Extensions, HeaderMap, HeaderValue, StatusCode, Version},
     middleware::{self, Next},
     response::Response,
-    routing::{get, post, MethodRouter},
+    routing::{get, post},
     Router,
 };
-use axum_extra::extract::CookieJar;
 use maud::{html, Markup, PreEscaped};
-use tracing::info;
+use tower_http::compression::{
+    predicate::{DefaultPredicate, Predicate},
+    CompressionLayer,
+};
+use tracing::{info, info_span, Instrument};
 
 use crate::config::Config;
 
-macro_rules! register_static_routes {
-    ( $app:ident, $( $x:expr ),* ) => {
-        {
-            $(
-                let $app = $app.route(
-                    concat!("/", $x),
-                    static_route(
-                        include_str!(concat!("assets/", $x)),
-                        guess_mime_type($x)
-                    ),
-                );
-            )*
-
-            $app
-        }
-    };
-}
+const REQUEST_ID_HEADER: &str = "x-request-id";
 
 pub async fn run(config: Config) {
     let bind_addr = config.bind;
-
     let config = Arc::new(config);
 
-    fn static_route<S>(
-        content: &'static str,
-        content_type: &'static str,
-    ) -> MethodRouter<S, Infallible>
-    where
-        S: Clone + Send + Sync + 'static,
-    {
-        let response = ([(header::CONTENT_TYPE, content_type)], content);
-        get(|| async { response })
-    }
+    crate::engines::canary::spawn(config.clone());
+    crate::saved_searches::spawn(config.clone());
+    crate::blocklists::spawn(config.clone());
+    crate::engines::cookies::spawn();
+    crate::engines::search::local::spawn(config.clone());
 
-    let app = Router::new()
+    let app = app(config);
+
+    info!("Listening on http://{bind_addr}");
+
+    let listener = tokio::net::TcpListener::bind(bind_addr).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
+}
+
+/// Every route, without the background tasks that [`run`] starts. The
+/// integration tests serve this on a random port.
+pub fn app(config: Arc<Config>) -> Router {
+    settings::init_cookie_key(&config);
+    crate::history::init(&config);
+    crate::snapshots::init(&config);
+    crate::visits::init(&config);
+    crate::engines::client::init(&config);
+    crate::engines::scheduler::init(&config);
+    #[cfg(feature = "proxies")]
+    proxy_cache::init(&config);
+
+    let assets_dir = config.assets_dir.clone();
+
+    // these routes are only accessible to authenticated users if auth is enabled
+    let protected_routes = Router::new()
         .route("/", get(index::get))
         .route("/search", get(search::get))
+        .route("/search/events", get(search::events))
+        .route("/fragment/results", get(search::fragment))
+        .route("/autocomplete", get(autocomplete::route))
+        .route("/api/search", get(api::search))
+        .route("/api/suggest", get(api::suggest))
+        .route("/history", get(history::get))
+        .route("/history/clear", post(history::clear))
+        .route("/saved", get(saved::get))
+        .route("/saved", post(saved::add))
+        .route("/saved/delete", post(saved::delete))
+        .route("/saved/{id}/feed.xml", get(saved::feed))
+        .route("/s", post(snapshots::share))
+        .route("/r", get(redirect::route))
+        .route("/cite", get(cite::route))
+        .route("/visits/clear", post(redirect::clear));
+    #[cfg(feature = "proxies")]
+    let protected_routes = protected_routes.route("/image-proxy", get(image_proxy::route));
+    let protected_routes = protected_routes.route_layer(middleware::from_fn_with_state(
+        config.clone(),
+        auth::middleware,
+    ));
+
+    let app = Router::new().merge(protected_routes);
+    // these check for the admin token themselves, whether or not auth is enabled
+    #[cfg(feature = "admin")]
+    let app = app
+        .route("/canary", get(canary::route))
+        .route("/metrics", get(metrics::route))
+        .route("/api/explain", get(explain::route));
+
+    let app = app
         .route("/settings", get(settings::get))
         .route("/settings", post(settings::post))
+        .route("/settings/block", post(settings::block_site))
+        .route("/login", get(auth::get))
+        .route("/login", post(auth::post))
         .route("/opensearch.xml", get(opensearch::route))
-        .route("/autocomplete", get(autocomplete::route))
-        .route("/image-proxy", get(image_proxy::route))
+        .route("/s/{id}", get(snapshots::get))
         .layer(middleware::from_fn_with_state(
             config.clone(),
             config_middleware,
         ))
+        .layer(middleware::from_fn(request_id_middleware))
         .with_state(config);
-    let app = register_static_routes![
-        app,
-        "style.css",
-        "script.js",
-        "robots.txt",
-        "scripts/colorpicker.js",
-        "themes/catppuccin-mocha.css",
-        "themes/catppuccin-macchiato.css",
-        "themes/catppuccin-latte.css",
-        "themes/nord-bluish.css",
-        "themes/discord.css"
-    ];
+    // anything else is an asset, like style.css
+    let app = app.fallback(move |uri, headers| assets::get(assets_dir.clone(), uri, headers));
 
-    info!("Listening on http://{bind_addr}");
+    app.layer(CompressionLayer::new().compress_when(DefaultPredicate::new().and(is_not_streamed)))
+}
 
-    let listener = tokio::net::TcpListener::bind(bind_addr).await.unwrap();
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await
-    .unwrap();
+/// Streamed pages aren't compressed, since the compressor would hold back the
+/// progress updates until it had enough to compress.
+fn is_not_streamed(_: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions) -> bool {
+    !headers.contains_key(header::TRANSFER_ENCODING)
 }
 
-fn guess_mime_type(path: &str) -> &'static str {
-    match path.rsplit('.').next() {
-        Some("css") => "text/css; charset=utf-8",
-        Some("js") => "text/javascript; charset=utf-8",
-        Some("txt") => "text/plain; charset=utf-8",
-        _ => "text/plain; charset=utf-8",
+/// Give every request an id that's in its log lines and the `X-Request-Id`
+/// header, so a slow search that a user reports can be found in the logs. An id
+/// that was set by a reverse proxy is kept.
+async fn request_id_middleware(req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 64
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(str::to_owned)
+        .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()));
+
+    let span = info_span!("request", request_id = %request_id, path = req.uri().path());
+    let mut res = next.run(req).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
     }
+    res
 }
 
 async fn config_middleware(
     State(config): State<Arc<Config>>,
-    cookies: CookieJar,
     mut req: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
     let mut config = config.clone().as_ref().clone();
 
-    let settings_cookie = cookies.get("settings");
-    if let Some(settings_cookie) = settings_cookie {
-        if let Ok(settings) = serde_json::from_str::<settings::Settings>(settings_cookie.value()) {
-            config.ui.stylesheet_url = settings.stylesheet_url;
-            config.ui.stylesheet_str = settings.stylesheet_str;
-        }
+    if let Some(settings) = settings::Settings::from_headers(req.headers()) {
+        settings.apply(&mut config);
     }
 
     // modify the state
@@ -123,7 +184,33 @@ async fn config_middleware(
     Ok(next.run(req).await)
 }
 
+/// Make sure a form was submitted from our own pages, since the cookies would
+/// be sent along with a form on any other site too. The error is meant to be
+/// sent with a 400.
+fn check_same_origin(headers: &HeaderMap) -> Result<(), &'static str> {
+    let Some(origin) = headers.get("origin").and_then(|h| h.to_str().ok()) else {
+        return Err("Missing or invalid Origin header");
+    };
+    let Some(host) = headers.get("host").and_then(|h| h.to_str().ok()) else {
+        return Err("Missing or invalid Host header");
+    };
+    if origin != format!("http://{host}") && origin != format!("https://{host}") {
+        return Err("Origin does not match Host");
+    }
+    Ok(())
+}
+
 pub fn head_html(title: Option<&str>, config: &Config) -> Markup {
+    head(title, config, true)
+}
+
+/// The head for the results page, which loads script.js after the search bar
+/// instead of deferring it, so the script runs while the results are streamed.
+pub fn head_html_without_script(title: Option<&str>, config: &Config) -> Markup {
+    head(title, config, false)
+}
+
+fn head(title: Option<&str>, config: &Config, script: bool) -> Markup {
     html! {
         head {
             meta charset="UTF-8";
@@ -135,7 +222,12 @@ pub fn head_html(title: Option<&str>, config: &Config) -> Markup {
                 }
                 {(config.ui.site_name)}
             }
+            @let theme = themes::get_or_default(&config.ui.theme);
+            meta name="color-scheme" content=(theme.color_scheme);
             link rel="stylesheet" href="/style.css";
+            @for (stylesheet, media) in theme.stylesheets {
+                link rel="stylesheet" href=(stylesheet) media=[media];
+            }
             @if !config.ui.stylesheet_url.is_empty() {
                 link rel="stylesheet" href=(config.ui.stylesheet_url);
             }
@@ -145,7 +237,9 @@ pub fn head_html(title: Option<&str>, config: &Config) -> Markup {
             @if !config.ui.favicon_url.is_empty() {
                 link rel="icon" href=(config.ui.favicon_url);
             }
-            script src="/script.js" defer {}
+            @if script {
+                script src="/script.js" defer {}
+            }
             link rel="search" type="application/opensearchdescription+xml" title="metasearch" href="/opensearch.xml";
         }
     }
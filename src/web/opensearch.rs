@@ -11,10 +11,15 @@ pub async fn route(headers: HeaderMap) -> impl IntoResponse {
         .unwrap_or("localhost");
 
     (
-        [(
-            header::CONTENT_TYPE,
-            "application/opensearchdescription+xml",
-        )],
+        [
+            (
+                header::CONTENT_TYPE,
+                "application/opensearchdescription+xml",
+            ),
+            // browsers only fetch this when the search engine is added, but it
+            // doesn't change anyways
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
         html! {
             (PreEscaped(r#"<?xml version="1.0" encoding="utf-8"?>"#))
             OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/" {
@@ -0,0 +1,281 @@
+//! A cache on disk for proxied files (like images), so each one is only
+//! downloaded once no matter how many people see it, and restarting doesn't
+//! empty it. When it's full, the files that were used least recently are
+//! deleted.
+//!
+//! Each file starts with its content type and the sha256 of its body, so files
+//! that were cut off or changed on disk are noticed and thrown away instead of
+//! being served.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use tracing::{debug, error, warn};
+
+use crate::config::Config;
+
+static CACHE: OnceLock<Cache> = OnceLock::new();
+
+struct Cache {
+    dir: PathBuf,
+    index: Mutex<Index>,
+}
+
+/// What's in the cache directory, so we don't have to look at it on every
+/// request.
+#[derive(Default)]
+struct Index {
+    max_size: u64,
+    total_size: u64,
+    entries: HashMap<String, Entry>,
+}
+
+struct Entry {
+    size: u64,
+    last_used: SystemTime,
+}
+
+impl Index {
+    /// Mark the file as just used, and return whether it's in the cache.
+    fn touch(&mut self, key: &str) -> bool {
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = SystemTime::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Add a file, and return the keys of the files that have to be deleted to
+    /// make room for it.
+    fn insert(&mut self, key: String, size: u64, last_used: SystemTime) -> Vec<String> {
+        if let Some(old) = self.entries.insert(key, Entry { size, last_used }) {
+            self.total_size -= old.size;
+        }
+        self.total_size += size;
+
+        let mut evicted = Vec::new();
+        while self.total_size > self.max_size {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+            evicted.push(oldest);
+        }
+        evicted
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_size -= entry.size;
+        }
+    }
+}
+
+pub struct Cached {
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+/// Look at what's already in the cache directory, if the cache is enabled in
+/// the config.
+pub fn init(config: &Config) {
+    let cache_config = &config.proxy_cache;
+    if !cache_config.enabled {
+        return;
+    }
+    let dir = PathBuf::from(&cache_config.directory);
+    if let Err(err) = fs::create_dir_all(&dir) {
+        error!(
+            "Couldn't create the proxy cache at {}: {err}",
+            dir.display()
+        );
+        return;
+    }
+
+    let mut index = Index {
+        max_size: cache_config.max_size,
+        ..Default::default()
+    };
+    let mut evicted = Vec::new();
+    for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let Some(key) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        // left over from a write that was interrupted
+        if key.ends_with(".tmp") {
+            let _ = fs::remove_file(&path);
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        // the modification time is updated when a file is used, so the order
+        // is kept across restarts
+        let last_used = metadata.modified().unwrap_or(UNIX_EPOCH);
+        evicted.extend(index.insert(key.to_owned(), metadata.len(), last_used));
+    }
+    // in case max_size was made smaller
+    for key in evicted {
+        let _ = fs::remove_file(dir.join(key));
+    }
+    debug!(
+        "proxy cache has {} files taking up {} bytes",
+        index.entries.len(),
+        index.total_size
+    );
+
+    let _ = CACHE.set(Cache {
+        dir,
+        index: Mutex::new(index),
+    });
+}
+
+/// Get a file that was proxied before, if it's still in the cache.
+pub async fn get(url: &str) -> Option<Cached> {
+    let cache = CACHE.get()?;
+    let key = key(url);
+    if !cache.index.lock().touch(&key) {
+        return None;
+    }
+    let path = cache.dir.join(&key);
+
+    match tokio::fs::read(&path)
+        .await
+        .ok()
+        .and_then(|data| decode(&data))
+    {
+        Some(cached) => {
+            tokio::task::spawn_blocking(move || {
+                let _ = fs::File::options()
+                    .write(true)
+                    .open(path)
+                    .and_then(|file| file.set_modified(SystemTime::now()));
+            });
+            Some(cached)
+        }
+        None => {
+            warn!(
+                "Removing broken file from the proxy cache: {}",
+                path.display()
+            );
+            cache.index.lock().remove(&key);
+            let _ = tokio::fs::remove_file(&path).await;
+            None
+        }
+    }
+}
+
+/// Save a file that was just proxied, deleting old files if the cache is full.
+pub async fn put(url: &str, content_type: &str, body: &[u8]) {
+    let Some(cache) = CACHE.get() else {
+        return;
+    };
+    let data = encode(content_type, body);
+    let size = data.len() as u64;
+    if size > cache.index.lock().max_size {
+        return;
+    }
+
+    let key = key(url);
+    let path = cache.dir.join(&key);
+    // written somewhere else first, so a file that's being written is never read
+    static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+    let tmp_path = cache.dir.join(format!(
+        "{key}.{}.tmp",
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = match tokio::fs::write(&tmp_path, &data).await {
+        Ok(()) => tokio::fs::rename(&tmp_path, &path).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = written {
+        error!("Couldn't write to the proxy cache: {err}");
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return;
+    }
+
+    let evicted = cache.index.lock().insert(key, size, SystemTime::now());
+    for key in evicted {
+        let _ = tokio::fs::remove_file(cache.dir.join(key)).await;
+    }
+}
+
+fn key(url: &str) -> String {
+    format!("{:x}", Sha256::digest(url))
+}
+
+fn encode(content_type: &str, body: &[u8]) -> Vec<u8> {
+    let mut data = format!("{content_type}\n{:x}\n", Sha256::digest(body)).into_bytes();
+    data.extend_from_slice(body);
+    data
+}
+
+/// The content type and body of a cached file, or `None` if the body doesn't
+/// match its hash.
+fn decode(data: &[u8]) -> Option<Cached> {
+    let mut parts = data.splitn(3, |&b| b == b'\n');
+    let content_type = std::str::from_utf8(parts.next()?).ok()?;
+    let hash = parts.next()?;
+    let body = parts.next()?;
+    if format!("{:x}", Sha256::digest(body)).as_bytes() != hash {
+        return None;
+    }
+    Some(Cached {
+        content_type: content_type.to_owned(),
+        body: body.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let mut index = Index {
+            max_size: 100,
+            ..Default::default()
+        };
+        let time = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        assert!(index.insert("a".to_owned(), 40, time(1)).is_empty());
+        assert!(index.insert("b".to_owned(), 40, time(2)).is_empty());
+        assert!(index.touch("a"));
+        assert_eq!(index.insert("c".to_owned(), 40, time(3)), ["b"]);
+        assert!(!index.touch("b"));
+        assert_eq!(index.total_size, 80);
+    }
+
+    #[test]
+    fn test_broken_files_are_rejected() {
+        let data = encode("image/png", b"not really a png\n");
+        let cached = decode(&data).unwrap();
+        assert_eq!(cached.content_type, "image/png");
+        assert_eq!(cached.body, b"not really a png\n");
+
+        let mut changed = data.clone();
+        *changed.last_mut().unwrap() = b'!';
+        assert!(decode(&changed).is_none());
+        assert!(decode(&data[..data.len() - 1]).is_none());
+        assert!(decode(b"image/png").is_none());
+    }
+}
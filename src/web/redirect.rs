@@ -0,0 +1,129 @@
+//! `/r`, which result links go through when visits are remembered or the user
+//! wants it, so the site that's opened doesn't get the search page as the
+//! referrer. The links are signed so the route can't be used to redirect
+//! anywhere.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::Query,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tracing::error;
+
+use crate::{config::Config, engines::Engine, visits};
+
+use super::{check_same_origin, settings::cookie_key};
+
+/// Whether clicked results are remembered for this user.
+fn records_visits(config: &Config) -> bool {
+    config.visits.enabled && config.engines.get(Engine::Visited).enabled
+}
+
+/// Where a link to a result should go, which is through `/r` if it's enabled.
+pub fn href(url: &str, title: &str, config: &Config) -> String {
+    if config.ui.redirect_links || records_visits(config) {
+        link(url, title)
+    } else {
+        url.to_owned()
+    }
+}
+
+fn link(url: &str, title: &str) -> String {
+    format!(
+        "/r?url={}&title={}&sig={}",
+        urlencoding::encode(url),
+        urlencoding::encode(title),
+        sign(url, title)
+    )
+}
+
+fn mac(url: &str, title: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(cookie_key().signing())
+        .expect("hmac can take a key of any size");
+    mac.update(url.as_bytes());
+    mac.update(b"\0");
+    mac.update(title.as_bytes());
+    mac
+}
+
+fn sign(url: &str, title: &str) -> String {
+    mac(url, title)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn verify(url: &str, title: &str, sig: &str) -> bool {
+    let Some(sig) = (0..sig.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(sig.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+    mac(url, title).verify_slice(&sig).is_ok()
+}
+
+pub async fn route(
+    Query(params): Query<HashMap<String, String>>,
+    Extension(config): Extension<Config>,
+) -> Response {
+    let (Some(url), Some(sig)) = (params.get("url"), params.get("sig")) else {
+        return (StatusCode::BAD_REQUEST, "Missing url or sig parameter").into_response();
+    };
+    let title = params.get("title").map(String::as_str).unwrap_or_default();
+    if !verify(url, title, sig) {
+        return (StatusCode::BAD_REQUEST, "Invalid signature").into_response();
+    }
+
+    if records_visits(&config) {
+        if let Err(err) = visits::record(url, title) {
+            error!("Couldn't record visit: {err}");
+        }
+    }
+    (
+        StatusCode::FOUND,
+        [
+            (header::LOCATION, url.as_str()),
+            (header::REFERRER_POLICY, "no-referrer"),
+        ],
+    )
+        .into_response()
+}
+
+/// Forget every visited page, from the button on the settings page.
+pub async fn clear(Extension(config): Extension<Config>, headers: HeaderMap) -> Response {
+    if !config.visits.enabled {
+        return (StatusCode::NOT_FOUND, "Visits are disabled").into_response();
+    }
+    if let Err(err) = check_same_origin(&headers) {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+    if let Err(err) = visits::clear() {
+        error!("Couldn't clear visits: {err}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+    }
+    (StatusCode::FOUND, [(header::LOCATION, "/settings")]).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_links() {
+        let sig = sign("https://example.com", "Example");
+        assert!(verify("https://example.com", "Example", &sig));
+        assert!(!verify("https://evil.example", "Example", &sig));
+        assert!(!verify("https://example.com", "Other", &sig));
+        assert!(!verify("https://example.com", "Example", "zz"));
+        assert!(!verify("https://example.com", "Example", ""));
+    }
+}
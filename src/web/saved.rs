@@ -0,0 +1,213 @@
+//! The page for managing saved searches, and their Atom feeds.
+
+use axum::{
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Form,
+};
+use maud::{html, PreEscaped, DOCTYPE};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::{config::Config, saved_searches, web::head_html};
+
+use super::check_same_origin;
+
+/// How many of the newest results are in a feed.
+const FEED_LENGTH: usize = 50;
+
+#[derive(Deserialize)]
+pub struct NewSavedSearch {
+    #[serde(default)]
+    q: String,
+}
+
+#[derive(Deserialize)]
+pub struct AddForm {
+    name: String,
+    q: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteForm {
+    id: i64,
+}
+
+fn disabled() -> Response {
+    (StatusCode::NOT_FOUND, "Saved searches are disabled").into_response()
+}
+
+fn internal_error(err: eyre::Report) -> Response {
+    error!("Saved searches error: {err}");
+    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+}
+
+pub async fn get(
+    Query(new): Query<NewSavedSearch>,
+    Extension(config): Extension<Config>,
+) -> Response {
+    if !config.saved_searches.enabled {
+        return disabled();
+    }
+    let searches = match saved_searches::list() {
+        Ok(searches) => searches,
+        Err(err) => return internal_error(err),
+    };
+
+    let html = html! {
+        (DOCTYPE)
+        html lang="en" {
+            {(head_html(Some("saved searches"), &config))}
+            body {
+                div.main-container.settings-page {
+                    main {
+                        a.back-to-index-button href="/" { "Back" }
+                        h1 { "Saved searches" }
+                        p {
+                            "Saved searches are searched again every "
+                            (config.saved_searches.interval_minutes)
+                            " minutes, and new results are added to their feeds."
+                        }
+                        @if searches.is_empty() {
+                            p { "Nothing is saved yet." }
+                        }
+                        @for saved in &searches {
+                            div.saved-search {
+                                a href={ "/search?q=" (urlencoding::encode(&saved.query)) } { (saved.name) }
+                                " · "
+                                a href={ "/saved/" (saved.id) "/feed.xml" } { "Feed" }
+                                form.saved-search-delete method="post" action="/saved/delete" {
+                                    input type="hidden" name="id" value=(saved.id);
+                                    input type="submit" value="Delete";
+                                }
+                                @if saved.name != saved.query {
+                                    p.saved-search-query { (saved.query) }
+                                }
+                            }
+                        }
+                        h2 { "Save a search" }
+                        form.settings-form method="post" action="/saved" {
+                            label for="saved-query" { "Query" }
+                            input #saved-query type="text" name="q" value=(new.q) required;
+                            label for="saved-name" { "Name" }
+                            input #saved-name type="text" name="name" placeholder="Same as the query";
+                            input type="submit" value="Save";
+                        }
+                    }
+                }
+            }
+        }
+    }
+    .into_string();
+
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response()
+}
+
+pub async fn add(
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+    Form(form): Form<AddForm>,
+) -> Response {
+    if !config.saved_searches.enabled {
+        return disabled();
+    }
+    if let Err(err) = check_same_origin(&headers) {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+    let query = form.q.trim();
+    if query.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Missing query").into_response();
+    }
+    let name = match form.name.trim() {
+        "" => query,
+        name => name,
+    };
+    match saved_searches::add(name, query) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "There can't be more than {} saved searches",
+                    saved_searches::MAX_SAVED_SEARCHES
+                ),
+            )
+                .into_response()
+        }
+        Err(err) => return internal_error(err),
+    }
+    (StatusCode::FOUND, [(header::LOCATION, "/saved")]).into_response()
+}
+
+pub async fn delete(
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+    Form(form): Form<DeleteForm>,
+) -> Response {
+    if !config.saved_searches.enabled {
+        return disabled();
+    }
+    if let Err(err) = check_same_origin(&headers) {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+    if let Err(err) = saved_searches::delete(form.id) {
+        return internal_error(err);
+    }
+    (StatusCode::FOUND, [(header::LOCATION, "/saved")]).into_response()
+}
+
+/// An Atom feed of the results that showed up after a search was saved.
+pub async fn feed(
+    Path(id): Path<i64>,
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+) -> Response {
+    if !config.saved_searches.enabled {
+        return disabled();
+    }
+    let saved = match saved_searches::get(id) {
+        Ok(Some(saved)) => saved,
+        Ok(None) => return (StatusCode::NOT_FOUND, "No such saved search").into_response(),
+        Err(err) => return internal_error(err),
+    };
+    let results = match saved_searches::new_results(id, FEED_LENGTH) {
+        Ok(results) => results,
+        Err(err) => return internal_error(err),
+    };
+
+    let host = headers
+        .get("host")
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost");
+    let rfc3339 = |timestamp: i64| {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .unwrap_or_default()
+            .to_rfc3339()
+    };
+    let updated = results.first().map_or(saved.created_at, |r| r.found_at);
+
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        html! {
+            (PreEscaped(r#"<?xml version="1.0" encoding="utf-8"?>"#))
+            feed xmlns="http://www.w3.org/2005/Atom" {
+                title { (saved.name) " - " (config.ui.site_name) }
+                id { "https://" (host) "/saved/" (saved.id) }
+                updated { (rfc3339(updated)) }
+                link rel="alternate" href={ "https://" (host) "/search?q=" (urlencoding::encode(&saved.query)) } {}
+                @for result in &results {
+                    entry {
+                        title { (result.title) }
+                        id { (result.url) }
+                        link href=(result.url) {}
+                        updated { (rfc3339(result.found_at)) }
+                        summary { (result.description) }
+                    }
+                }
+            }
+        }
+        .into_string(),
+    )
+        .into_response()
+}
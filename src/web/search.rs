@@ -1,43 +1,190 @@
 mod all;
+mod highlight;
 mod images;
+mod pages;
 
-use std::{collections::HashMap, net::SocketAddr, str::FromStr};
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, str::FromStr};
 
 use async_stream::stream;
 use axum::{
     body::Body,
     extract::{ConnectInfo, Query},
     http::{header, HeaderMap, StatusCode},
-    response::IntoResponse,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
     Extension, Json,
 };
 use bytes::Bytes;
 use maud::{html, PreEscaped, DOCTYPE};
+use tracing::{error, Instrument, Span};
 
 use crate::{
     config::Config,
     engines::{
-        self, Engine, EngineProgressUpdate, ProgressUpdateData, ResponseForTab, SearchQuery,
-        SearchTab,
+        self, preprocess::Bang, Engine, EngineProgressUpdate, HostLabel, ImageFilters, Operator,
+        ProgressUpdateData, QueryOperators, ResponseForTab, SearchQuery, SearchTab,
     },
-    web::head_html,
+    history::{self, Session},
+    snapshots,
+    web::head_html_without_script,
 };
 
-fn render_beginning_of_html(search: &SearchQuery) -> String {
+/// `session` is the history session that the search is part of, if it was
+/// resumed from the history page.
+fn render_beginning_of_html(search: &SearchQuery, session: Option<&Session>) -> String {
+    let only_engines = search
+        .only_engines
+        .iter()
+        .map(|e| e.id())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let verbatim_param = if search.verbatim { "&verbatim=1" } else { "" };
+    let mut extra_params = verbatim_param.to_string();
+    if !only_engines.is_empty() {
+        extra_params.push_str(&format!("&engines={only_engines}"));
+    }
+    // the url without the language, for going back to the usual one
+    let default_language_url = format!("?q={}{}", urlencoding::encode(&search.query), extra_params);
+    if let Some(language) = &search.language {
+        extra_params.push_str(&format!("&lang={language}"));
+    }
+    let tab_param = if search.tab == SearchTab::default() {
+        String::new()
+    } else {
+        format!("&tab={}", search.tab)
+    };
+
+    // the tabs other than all, if they have anything to search
+    let tabs = [
+        (SearchTab::Images, "Images"),
+        (SearchTab::Code, "Code"),
+        (SearchTab::Media, "Media"),
+        (SearchTab::Recipes, "Recipes"),
+        (SearchTab::Jobs, "Jobs"),
+    ]
+    .into_iter()
+    .filter(|(tab, _)| match tab {
+        SearchTab::Images => search.config.image_search.enabled,
+        _ => Engine::all()
+            .iter()
+            .any(|e| e.searches_tab(*tab) && search.config.engines.get(*e).enabled),
+    })
+    .collect::<Vec<_>>();
+
+    // for switching between searching one site and the whole web
+    let operators = QueryOperators::parse(&search.query);
+    let site_operators = operators
+        .operators
+        .iter()
+        .filter(|(o, _)| *o == Operator::Site)
+        .cloned()
+        .collect::<Vec<_>>();
+    let not_site = Operator::ALL
+        .iter()
+        .copied()
+        .filter(|o| *o != Operator::Site)
+        .collect::<Vec<_>>();
+    // queries that start with a domain are redirected to a site: search, so
+    // this is only set if the user asked to search the whole web
+    let site_prefix = engines::preprocess::parse_site_prefix(&search.query);
+    if site_prefix.is_some() {
+        extra_params.push_str("&site=0");
+    }
+
     let form_html = html! {
         form.search-form action="/search" method="get" {
             input #search-input  type="text" name="q" placeholder="Search" value=(search.query) autofocus onfocus="this.select()" autocomplete="off";
             @if search.tab != SearchTab::default() {
                 input type="hidden" name="tab" value=(search.tab.to_string());
             }
+            @if !only_engines.is_empty() {
+                input type="hidden" name="engines" value=(only_engines);
+            }
+            @if let Some(label) = search.label {
+                input type="hidden" name="label" value=(label.to_string());
+            }
+            @if let Some(language) = &search.language {
+                input type="hidden" name="lang" value=(language);
+            }
+            @for (name, value) in search.image_filters.to_params() {
+                input type="hidden" name=(name) value=(value);
+            }
+            @if site_prefix.is_some() {
+                input type="hidden" name="site" value="0";
+            }
+            @if let Some(session) = session {
+                input type="hidden" name="session" value=(session.id());
+            }
             input type="submit" value="Search";
+            label.verbatim-toggle title="Match your query literally, without synonyms or corrections" {
+                input type="checkbox" name="verbatim" value="1" checked[search.verbatim];
+                "Verbatim"
+            }
+            @if search.config.saved_searches.enabled {
+                a.save-search-link href={ "/saved?q=" (urlencoding::encode(&search.query)) } title="Get notified about new results" { "Save" }
+            }
         }
-        @if search.config.image_search.enabled {
+        @if !tabs.is_empty() {
             div.search-tabs {
                 @if search.tab == SearchTab::All { span.search-tab.selected { "All" } }
-                @else { a.search-tab href={ "?q=" (search.query) } { "All" } }
-                @if search.tab == SearchTab::Images { span.search-tab.selected { "Images" } }
-                @else { a.search-tab href={ "?q=" (search.query) "&tab=images" } { "Images" } }
+                @else { a.search-tab href={ "?q=" (urlencoding::encode(&search.query)) (extra_params) } { "All" } }
+                @for (tab, name) in &tabs {
+                    @if search.tab == *tab { span.search-tab.selected { (name) } }
+                    @else { a.search-tab href={ "?q=" (urlencoding::encode(&search.query)) "&tab=" (tab) (extra_params) } { (name) } }
+                }
+            }
+        }
+        @if let Some(session) = session.filter(|s| s.queries().len() > 1) {
+            div.session-chips {
+                @for query in session.queries() {
+                    @if query == search.query { span.session-chip.selected { (query) } }
+                    @else { a.session-chip href={ "?q=" (urlencoding::encode(query)) "&session=" (session.id()) } { (query) } }
+                }
+            }
+        }
+        @if let Some(language) = &search.language {
+            p.only-engines {
+                "Searching in " (language)
+                " · "
+                a href={ (default_language_url) (tab_param) } { "Use your usual language" }
+            }
+        }
+        @if let [(Operator::Site, site)] = site_operators.as_slice() {
+            p.site-search {
+                "Only searching " (site)
+                " · "
+                a href={ "?q=" (urlencoding::encode(&format!("{site} {}", operators.to_query(&not_site, false)))) "&site=0" (tab_param) } { "Search the whole web" }
+            }
+        } @else if let Some((site, _)) = site_prefix {
+            p.site-search {
+                "Searching the whole web · "
+                a href={ "?q=" (urlencoding::encode(&search.query)) (tab_param) } { "Only search " (site) }
+            }
+        }
+        @if !search.only_engines.is_empty() {
+            p.only-engines {
+                "Only searching "
+                (search.only_engines.iter().map(|e| e.id()).collect::<Vec<_>>().join(", "))
+                " · "
+                a href={ "?q=" (urlencoding::encode(&search.query)) (tab_param) (verbatim_param) } { "Search all engines" }
+            }
+        }
+    };
+
+    let drifting_engines = if search.config.drift.enabled && search.config.drift.show_banner {
+        engines::drift::drifting_engines()
+    } else {
+        Vec::new()
+    };
+    let drift_html = html! {
+        @if !drifting_engines.is_empty() {
+            p.drift-warning {
+                "Results from "
+                (drifting_engines.iter().map(|e| e.id()).collect::<Vec<_>>().join(", "))
+                " might be incomplete, they've been giving much fewer results than usual."
             }
         }
     };
@@ -46,24 +193,37 @@ fn render_beginning_of_html(search: &SearchQuery) -> String {
     html! {
         (DOCTYPE)
         html lang="en";
-        {(head_html(Some(&search.query), &search.config))}
+        {(head_html_without_script(Some(&search.query), &search.config))}
         body;
         div.main-container.{"search-" (search.tab.to_string())};
         main;
         (form_html)
+        (drift_html)
         div.progress-updates;
+        // not deferred, so it can show which engines are still pending while
+        // the results load
+        script src="/script.js" {}
     }
     .into_string()
 }
 
+fn render_share_snapshot_form(id: &str) -> PreEscaped<String> {
+    html! {
+        form.share-snapshot method="post" action="/s" {
+            input type="hidden" name="id" value=(id);
+            input type="submit" value="Share snapshot" title="Get a link to these exact results";
+        }
+    }
+}
+
 fn render_end_of_html() -> String {
     r"</main></div></body></html>".to_string()
 }
 
-fn render_results_for_tab(response: ResponseForTab) -> PreEscaped<String> {
+fn render_results_for_tab(response: ResponseForTab, search: &SearchQuery) -> PreEscaped<String> {
     match response {
-        ResponseForTab::All(r) => all::render_results(r),
-        ResponseForTab::Images(r) => images::render_results(r),
+        ResponseForTab::All(r) => all::render_results(*r, search),
+        ResponseForTab::Images(r) => images::render_results(r, search),
     }
 }
 
@@ -76,7 +236,9 @@ fn render_engine_progress_update(
         EngineProgressUpdate::Requesting => "requesting".to_string(),
         EngineProgressUpdate::Downloading => "downloading".to_string(),
         EngineProgressUpdate::Parsing => "parsing".to_string(),
-        EngineProgressUpdate::Done => html! { span.progress-update-done { "done" } }.into_string(),
+        EngineProgressUpdate::Done(results) => {
+            html! { span.progress-update-done { "done" } ", " (results) " results" }.into_string()
+        }
         EngineProgressUpdate::Error(msg) => {
             html! { span.progress-update-error { (msg) } }.into_string()
         }
@@ -95,6 +257,28 @@ fn render_engine_progress_update(
     .into_string()
 }
 
+/// The json for an engine's progress, for the `/search/events` stream.
+fn engine_progress_json(
+    engine: Engine,
+    progress_update: &EngineProgressUpdate,
+    time_ms: u64,
+) -> serde_json::Value {
+    let (status, results, error) = match progress_update {
+        EngineProgressUpdate::Requesting => ("requesting", None, None),
+        EngineProgressUpdate::Downloading => ("downloading", None, None),
+        EngineProgressUpdate::Parsing => ("parsing", None, None),
+        EngineProgressUpdate::Done(results) => ("done", Some(*results), None),
+        EngineProgressUpdate::Error(msg) => ("error", None, Some(msg)),
+    };
+    serde_json::json!({
+        "engine": engine.id(),
+        "status": status,
+        "time_ms": time_ms,
+        "results": results,
+        "error": error,
+    })
+}
+
 pub fn render_engine_list(engines: &[engines::Engine], config: &Config) -> PreEscaped<String> {
     let mut html = String::new();
     for (i, engine) in engines.iter().enumerate() {
@@ -116,39 +300,70 @@ pub fn render_engine_list(engines: &[engines::Engine], config: &Config) -> PreEs
     }
 }
 
-pub async fn get(
-    Query(params): Query<HashMap<String, String>>,
-    Extension(config): Extension<Config>,
-    headers: HeaderMap,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
-) -> axum::response::Response {
-    let query = params
-        .get("q")
-        .cloned()
-        .unwrap_or_default()
-        .trim()
-        .replace('\n', " ");
-    if query.is_empty() {
-        // redirect to index
-        return (
-            StatusCode::FOUND,
-            [
-                (header::LOCATION, "/"),
-                (header::CONTENT_TYPE, "text/html; charset=utf-8"),
-            ],
-            Body::from("<a href=\"/\">No query provided, click here to go back to index</a>"),
-        )
-            .into_response();
-    }
+/// The most results that can be asked for with `&count=`.
+const MAX_REQUESTED_COUNT: usize = 100;
 
+/// Build the query for a search from the url parameters and headers. `query` is
+/// the search text, after any bangs were handled. It goes through the
+/// preprocessing steps again here, which doesn't change a query that already
+/// went through them.
+pub(super) fn search_query(
+    query: String,
+    params: &HashMap<String, String>,
+    mut config: Config,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+) -> SearchQuery {
     let search_tab = params
         .get("tab")
         .and_then(|t| SearchTab::from_str(t).ok())
         .unwrap_or_default();
 
-    let query = SearchQuery {
+    let verbatim = params.get("verbatim").is_some_and(|v| v == "1");
+    let requested_count = params
+        .get("count")
+        .and_then(|c| c.parse::<usize>().ok())
+        .map_or(config.requested_count, |c| c.clamp(1, MAX_REQUESTED_COUNT));
+    let debug = params.get("debug").is_some_and(|v| v == "1");
+    let preprocessed = engines::preprocess::preprocess(&query, &config.preprocess.steps);
+    let query = preprocessed.query;
+
+    let only_engines = params
+        .get("engines")
+        .map(|e| engines::preprocess::parse_engine_list(e))
+        .unwrap_or_default();
+    if !only_engines.is_empty() {
+        engines::preprocess::restrict_engines(&mut config, &only_engines);
+    }
+    let language = params
+        .get("lang")
+        .filter(|l| engines::preprocess::is_language_tag(l))
+        .cloned();
+    if let Some(language) = &language {
+        config.language = language.clone();
+    }
+    if config.language == "auto" {
+        config.language = engines::preprocess::auto_language(
+            &query,
+            headers
+                .get(header::ACCEPT_LANGUAGE)
+                .and_then(|h| h.to_str().ok()),
+        );
+    }
+
+    SearchQuery {
         query,
         tab: search_tab,
+        verbatim,
+        only_engines,
+        label: params
+            .get("label")
+            .and_then(|l| HostLabel::from_str(l).ok()),
+        language,
+        image_filters: ImageFilters::from_params(|name| params.get(name).cloned()),
+        requested_count,
+        info: preprocessed.info,
+        debug,
         request_headers: headers
             .clone()
             .into_iter()
@@ -167,9 +382,204 @@ pub async fn get(
                 || addr.ip().to_string(),
                 |ip| ip.to_str().unwrap_or_default().to_string(),
             ),
-        config: config.clone().into(),
+        config: config.into(),
+    }
+}
+
+/// Do a search and return the responses, for the json apis.
+pub(super) async fn search_responses(query: SearchQuery) -> eyre::Result<Vec<ResponseForTab>> {
+    // the search is spawned, so it has to be told which request it's for
+    let span = Span::current();
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+    let search_future =
+        tokio::spawn(async move { engines::search(&query, progress_tx).await }.instrument(span));
+    search_future.await??;
+
+    let mut responses = Vec::new();
+    while let Some(progress_update) = progress_rx.recv().await {
+        if let ProgressUpdateData::Response(r) = progress_update.data {
+            responses.push(r);
+        }
+    }
+    Ok(responses)
+}
+
+/// The progress of a search as server-sent events, so the results page can show
+/// which engines are still pending. The updates come from the identical search
+/// that the page is loading, so the engines aren't searched again, and the
+/// stream ends right away if that search is already done. Every engine update
+/// is an `engine` event, and the stream ends with `done` (or `error`).
+pub async fn events(
+    Query(params): Query<HashMap<String, String>>,
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> axum::response::Response {
+    // the query is made the same way as on the results page, so it's the same
+    // search
+    let processed = engines::preprocess::preprocess(
+        params.get("q").map(String::as_str).unwrap_or_default(),
+        &config.preprocess.steps,
+    );
+    let explain_results = config.ui.explain_results;
+    let mut query = search_query(processed.query, &params, config, &headers, addr);
+    if query.query.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Missing q parameter").into_response();
+    }
+    query.debug |= explain_results;
+
+    // if the client goes away, the stream is dropped along with the receiver,
+    // and the search stops sending updates to it
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+    let search = engines::follow_search(&query, progress_tx);
+
+    let s = stream! {
+        type R = Result<Event, Infallible>;
+
+        let Some(search) = search else {
+            yield R::Ok(Event::default().event("done").data(""));
+            return;
+        };
+
+        while let Some(progress_update) = progress_rx.recv().await {
+            if let ProgressUpdateData::Engine { engine, update } = progress_update.data {
+                let json = engine_progress_json(engine, &update, progress_update.time_ms);
+                yield R::Ok(Event::default().event("engine").data(json.to_string()));
+            }
+        }
+
+        yield R::Ok(match search.await {
+            Ok(()) => Event::default().event("done").data(""),
+            Err(e) => Event::default().event("error").data(e.to_string()),
+        });
     };
 
+    Sse::new(s).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// A page of results without the rest of the html, so the results page can add
+/// more results as the user scrolls. The results are the ones from the search
+/// that the first page was from, unless they were forgotten, in which case the
+/// search is done again.
+pub async fn fragment(
+    Query(params): Query<HashMap<String, String>>,
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> axum::response::Response {
+    let query = params.get("q").cloned().unwrap_or_default();
+    let page = params
+        .get("page")
+        .and_then(|p| p.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let explain_results = config.ui.explain_results;
+    let mut search = search_query(query, &params, config, &headers, addr);
+    if search.query.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Missing q parameter").into_response();
+    }
+    search.debug |= explain_results;
+
+    let response = match pages::get(&search) {
+        Some(response) => response,
+        None => {
+            let responses = match search_responses(search.clone()).await {
+                Ok(responses) => responses,
+                Err(err) => {
+                    error!("Error loading page {page} of {}: {err}", search.query);
+                    return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+                }
+            };
+            let Some(ResponseForTab::All(response)) = responses.into_iter().next() else {
+                return (StatusCode::BAD_REQUEST, "Only the all tab has pages").into_response();
+            };
+            pages::remember(&search, &response);
+            (*response).into()
+        }
+    };
+
+    (
+        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+        all::render_page(&response, &search, page).into_string(),
+    )
+        .into_response()
+}
+
+/// Redirect to a random site from Wiby, for the `!random` bang.
+pub(super) async fn random_site() -> axum::response::Response {
+    match engines::search::wiby::surprise().await {
+        Ok(url) => (StatusCode::FOUND, [(header::LOCATION, url)]).into_response(),
+        Err(err) => {
+            error!("Couldn't get a random site from Wiby: {err}");
+            (StatusCode::BAD_GATEWAY, "Couldn't get a random site").into_response()
+        }
+    }
+}
+
+pub async fn get(
+    Query(params): Query<HashMap<String, String>>,
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> axum::response::Response {
+    let processed = engines::preprocess::preprocess(
+        params.get("q").map(String::as_str).unwrap_or_default(),
+        &config.preprocess.steps,
+    );
+    if processed.query.is_empty() && processed.bang.is_none() {
+        // redirect to index
+        return (
+            StatusCode::FOUND,
+            [
+                (header::LOCATION, "/"),
+                (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+            ],
+            Body::from("<a href=\"/\">No query provided, click here to go back to index</a>"),
+        )
+            .into_response();
+    }
+
+    // turn bangs into parameters so they stick around for the next search
+    if let Some(bang) = &processed.bang {
+        let (name, value) = match bang {
+            Bang::Only(engines) => (
+                "engines",
+                engines.iter().map(|e| e.id()).collect::<Vec<_>>().join(","),
+            ),
+            Bang::Tab(tab) => ("tab", tab.to_string()),
+            Bang::Random => return random_site().await,
+        };
+        let mut params = params.into_iter().collect::<Vec<_>>();
+        params.retain(|(k, _)| k != "q" && k != name);
+        params.sort();
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        serializer.append_pair("q", &processed.query);
+        serializer.append_pair(name, &value);
+        serializer.extend_pairs(params);
+        let location = format!("/search?{}", serializer.finish());
+        return (StatusCode::FOUND, [(header::LOCATION, location)]).into_response();
+    }
+    let query = processed.query;
+
+    // a query like "docs.python.org asyncio" only searches that site, unless the
+    // user switched to searching the whole web
+    let search_whole_web = params.get("site").is_some_and(|s| s == "0");
+    if let Some((site, rest)) =
+        engines::preprocess::parse_site_prefix(&query).filter(|_| !search_whole_web)
+    {
+        let mut params = params.into_iter().collect::<Vec<_>>();
+        params.retain(|(k, _)| k != "q");
+        params.sort();
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        serializer.append_pair("q", &format!("site:{site} {rest}"));
+        serializer.extend_pairs(params);
+        let location = format!("/search?{}", serializer.finish());
+        return (StatusCode::FOUND, [(header::LOCATION, location)]).into_response();
+    }
+
+    let query = search_query(query, &params, config, &headers, addr);
+    let config = query.config.clone();
+
     let trying_to_use_api = query
         .request_headers
         .get("accept")
@@ -180,22 +590,29 @@ pub async fn get(
             return (StatusCode::FORBIDDEN, "API access is disabled").into_response();
         }
 
-        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
-        let search_future = tokio::spawn(async move { engines::search(&query, progress_tx).await });
-        if let Err(e) = search_future.await {
-            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
-        }
+        return match search_responses(query).await {
+            Ok(results) => Json(results).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        };
+    }
 
-        let mut results = Vec::new();
-        while let Some(progress_update) = progress_rx.recv().await {
-            if let ProgressUpdateData::Response(r) = progress_update.data {
-                results.push(r);
-            }
-        }
+    let mut query = query;
+    query.debug |= config.ui.explain_results;
 
-        return Json(results).into_response();
-    }
+    let session = if config.history.enabled {
+        if let Err(err) = history::record(&query.query) {
+            error!("Couldn't record search in history: {err}");
+        }
+        let window = config.history.session_minutes as i64 * 60;
+        params
+            .get("session")
+            .and_then(|id| id.parse().ok())
+            .and_then(|id| history::session(id, window).ok().flatten())
+    } else {
+        None
+    };
 
+    let span = Span::current();
     let s = stream! {
         type R = Result<Bytes, eyre::Error>;
 
@@ -205,15 +622,20 @@ pub async fn get(
         // 2) the results
         // 3) the post-search infobox (usually not sent) + the end of the html
 
-        let first_part = render_beginning_of_html(&query);
+        let first_part = render_beginning_of_html(&query, session.as_ref());
         // second part is in the loop
         let mut third_part = String::new();
+        // the results and post-search infobox, in case they're shared
+        let mut snapshot_html = String::new();
 
         yield R::Ok(Bytes::from(first_part));
 
         let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
 
-        let search_future = tokio::spawn(async move { engines::search(&query, progress_tx).await });
+        let search = query.clone();
+        let search_future = tokio::spawn(
+            async move { engines::search(&query, progress_tx).await }.instrument(span),
+        );
 
         while let Some(progress_update) = progress_rx.recv().await {
             match progress_update.data {
@@ -225,16 +647,39 @@ pub async fn get(
                     yield R::Ok(Bytes::from(progress_html));
                 },
                 ProgressUpdateData::Response(results) => {
+                    if let ResponseForTab::All(response) = &results {
+                        pages::remember(&search, response);
+                    }
                     let mut second_part = String::new();
 
                     second_part.push_str("</div>"); // close progress-updates
                     #[allow(clippy::literal_string_with_formatting_args)]
                     second_part.push_str("<style>.progress-updates{display:none}</style>");
-                    second_part.push_str(&render_results_for_tab(results).into_string());
+                    let results_html = render_results_for_tab(results, &search).into_string();
+                    second_part.push_str(&results_html);
+                    snapshot_html.push_str(&results_html);
                     yield Ok(Bytes::from(second_part));
                 },
                 ProgressUpdateData::PostSearchInfobox(infobox) => {
-                    third_part.push_str(&all::render_infobox(&infobox, &config).into_string());
+                    let infobox_html = all::render_infobox(&infobox, &config).into_string();
+                    third_part.push_str(&infobox_html);
+                    snapshot_html.push_str(&infobox_html);
+                }
+                ProgressUpdateData::DeadLinks(indexes) => {
+                    // script.js flags them and moves them down when the page is done loading
+                    yield R::Ok(Bytes::from(all::render_dead_links(&indexes).into_string()));
+                }
+                ProgressUpdateData::Archived(archived) => {
+                    // script.js moves them into their results when the page is done loading
+                    yield R::Ok(Bytes::from(all::render_archived(&archived).into_string()));
+                }
+                ProgressUpdateData::Descriptions(descriptions) => {
+                    // script.js moves them into their results when the page is done loading
+                    yield R::Ok(Bytes::from(all::render_descriptions(&descriptions, &search).into_string()));
+                }
+                ProgressUpdateData::Recipes(recipes) => {
+                    // script.js moves them into their results when the page is done loading
+                    yield R::Ok(Bytes::from(all::render_recipes(&recipes, &config).into_string()));
                 }
             }
         }
@@ -250,6 +695,10 @@ pub async fn get(
             return;
         };
 
+        if config.snapshots.enabled {
+            let id = snapshots::stash(&search.query, &search.tab.to_string(), snapshot_html);
+            third_part.push_str(&render_share_snapshot_form(&id).into_string());
+        }
         third_part.push_str(&render_end_of_html());
 
         yield Ok(Bytes::from(third_part));
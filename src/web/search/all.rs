@@ -3,13 +3,26 @@
 use maud::{html, PreEscaped};
 
 use crate::{
-    config::Config,
-    engines::{self, EngineSearchResult, Infobox, Response},
-    web::search::render_engine_list,
+    config::{Config, RankingAlgorithm},
+    engines::{
+        self, refine::Refinement, ArchivedCopy, EngineSearchResult, Explanation, HostLabel,
+        Infobox, Response, ResultExtras, SearchQuery,
+    },
+    urls,
+    web::{
+        cite, redirect,
+        search::{
+            highlight::{highlight, Terms},
+            pages, render_engine_list,
+        },
+    },
 };
 
-pub fn render_results(response: Response) -> PreEscaped<String> {
+pub fn render_results(response: Response, search: &SearchQuery) -> PreEscaped<String> {
     let mut html = String::new();
+    if let Some(corrected_query) = &response.corrected_query {
+        html.push_str(&render_correction(corrected_query, search).into_string());
+    }
     if let Some(answer) = &response.answer {
         html.push_str(
             &html! {
@@ -21,6 +34,19 @@ pub fn render_results(response: Response) -> PreEscaped<String> {
             .into_string(),
         );
     }
+    if let Some(answer) = &response.secondary_answer {
+        html.push_str(
+            &html! {
+                details.secondary-answer {
+                    summary { "Another answer from " (answer.engine) }
+                    div.answer {
+                        (answer.html)
+                    }
+                }
+            }
+            .into_string(),
+        );
+    }
     if let Some(infobox) = &response.infobox {
         html.push_str(
             &html! {
@@ -33,10 +59,30 @@ pub fn render_results(response: Response) -> PreEscaped<String> {
         );
     }
     if let Some(featured_snippet) = &response.featured_snippet {
-        html.push_str(&render_featured_snippet(featured_snippet, &response.config).into_string());
+        html.push_str(
+            &render_featured_snippet(featured_snippet, search, &response.config).into_string(),
+        );
     }
-    for result in &response.search_results {
-        html.push_str(&render_search_result(result, &response.config).into_string());
+    if !response.skipped_engines.is_empty() {
+        html.push_str(
+            &html! {
+                p.skipped-engines {
+                    @for (i, skipped) in response.skipped_engines.iter().enumerate() {
+                        @if i > 0 { br; }
+                        "Skipped " (skipped.engine) " because it " (skipped.reason) "."
+                    }
+                }
+            }
+            .into_string(),
+        );
+    }
+    if !response.refinements.is_empty() {
+        html.push_str(&render_refinements(&response.refinements).into_string());
+    }
+    html.push_str(&render_host_label_filters(&response, search).into_string());
+    html.push_str(&render_page(&response, search, 1).into_string());
+    if !response.related_searches.is_empty() {
+        html.push_str(&render_related_searches(&response.related_searches, search).into_string());
     }
 
     if html.is_empty() {
@@ -51,38 +97,411 @@ pub fn render_results(response: Response) -> PreEscaped<String> {
     PreEscaped(html)
 }
 
+/// The results on a page, starting at 1, followed by a button to load the next
+/// page if there is one. Everything is on the first page if
+/// `results_per_page` isn't set.
+pub fn render_page(response: &Response, search: &SearchQuery, page: usize) -> PreEscaped<String> {
+    let per_page = response
+        .config
+        .ui
+        .results_per_page
+        .filter(|n| *n > 0)
+        .unwrap_or(usize::MAX);
+    let start = page.saturating_sub(1).saturating_mul(per_page);
+    let has_next = response.search_results.len() > start.saturating_add(per_page);
+
+    let terms = Terms::new(&search.query);
+    let mut html = String::new();
+    for (i, result) in response
+        .search_results
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(per_page)
+    {
+        html.push_str(&render_search_result(i + 1, result, &terms, &response.config).into_string());
+    }
+    if has_next {
+        html.push_str(
+            &html! {
+                div.load-more {
+                    a.load-more-link href=(pages::fragment_url(search, page + 1)) { "Load more results" }
+                }
+            }
+            .into_string(),
+        );
+    }
+    PreEscaped(html)
+}
+
+/// The "did you mean" link. It's not shown for verbatim searches, since those
+/// are asking for the query exactly as it was typed.
+fn render_correction(corrected_query: &str, search: &SearchQuery) -> PreEscaped<String> {
+    if search.verbatim || corrected_query.to_lowercase() == search.query.to_lowercase() {
+        return PreEscaped(String::new());
+    }
+    html! {
+        p.did-you-mean {
+            "Did you mean "
+            a href={ "?q=" (urlencoding::encode(corrected_query)) } { i { (corrected_query) } }
+            "?"
+        }
+    }
+}
+
+fn render_related_searches(
+    related_searches: &[String],
+    search: &SearchQuery,
+) -> PreEscaped<String> {
+    html! {
+        div.related-searches {
+            h2 { "Related searches" }
+            ul {
+                @for related in related_searches.iter().filter(|q| q.to_lowercase() != search.query.to_lowercase()) {
+                    li { a href={ "?q=" (urlencoding::encode(related)) } { (related) } }
+                }
+            }
+        }
+    }
+}
+
+fn render_refinements(refinements: &[Refinement]) -> PreEscaped<String> {
+    html! {
+        div.refinements {
+            span.refinements-label { "Refine by" }
+            @for refinement in refinements {
+                a.refinement href={ "/search?q=" (urlencoding::encode(&refinement.query)) } { (refinement.text) }
+            }
+        }
+    }
+}
+
+/// Links for only showing results from one kind of site, for the labels that
+/// are on the results, and a button that hides everything but discussions
+/// without searching again. Empty if there's nothing to filter by.
+fn render_host_label_filters(response: &Response, search: &SearchQuery) -> PreEscaped<String> {
+    let labels = HostLabel::ALL
+        .iter()
+        .filter(|label| {
+            search.label == Some(**label)
+                || response
+                    .search_results
+                    .iter()
+                    .any(|r| r.result.extras.host_label == Some(**label))
+        })
+        .collect::<Vec<_>>();
+    let has_discussions = labels.iter().any(|l| l.is_discussion());
+    if search.label.is_none() && !has_discussions && labels.len() < 2 {
+        return PreEscaped(String::new());
+    }
+    let query = urlencoding::encode(&search.query);
+    let tab_param = if search.tab == Default::default() {
+        String::new()
+    } else {
+        format!("&tab={}", search.tab)
+    };
+
+    html! {
+        div.refinements.host-label-filters {
+            span.refinements-label { "Only show" }
+            @if has_discussions && search.label.is_none() {
+                button.refinement.discussions-filter type="button" aria-pressed="false" title="Only show forums and Q&A sites" { "Discussions" }
+            }
+            @if search.label.is_none() { span.refinement.selected { "All" } }
+            @else { a.refinement href={ "?q=" (query) (tab_param) } { "All" } }
+            @for label in labels {
+                @if search.label == Some(*label) { span.refinement.selected { (label.name()) } }
+                @else { a.refinement href={ "?q=" (query) (tab_param) "&label=" (label) } { (label.name()) } }
+            }
+        }
+    }
+}
+
+/// `index` starts at 1, and is used for the `#result-N` anchor that keyboard
+/// navigation and the api refer to.
 fn render_search_result(
+    index: usize,
     result: &engines::SearchResult<EngineSearchResult>,
+    terms: &Terms,
     config: &Config,
 ) -> PreEscaped<String> {
     html! {
-        div.search-result {
-            a.search-result-anchor rel="noreferrer" href=(result.result.url) {
+        div.search-result.discussion[result.result.extras.host_label.is_some_and(|l| l.is_discussion())].unreachable[result.result.extras.unreachable] id={ "result-" (index) } data-index=(index) {
+            a.search-result-anchor rel="noreferrer" href=(result_href(&result.result, config)) target=[link_target(config)] {
                 span.search-result-url { (result.result.url) }
-                h3.search-result-title { (result.result.title) }
+                h3.search-result-title { (highlight(&result.result.title, terms)) }
+            }
+            @if result.result.description.is_empty() {
+                p.search-result-description.no-description { "No description available." }
+            } @else if result.result.description_from_page {
+                (render_description_from_page(&result.result.description, terms))
+            } @else {
+                p.search-result-description { (highlight(&result.result.description, terms)) }
+            }
+            @if let Some(note) = urls::get_url_annotation(&result.result.url, &config.urls) {
+                p.search-result-annotation { (note) }
+            }
+            @if !result.result.extras.is_empty() {
+                (render_result_extras(&result.result.extras))
+            }
+            @if let Some(scholar) = &result.result.extras.scholar {
+                div.search-result-cite {
+                    "Cite: "
+                    a href=(cite::href(&result.result, scholar, "bibtex")) rel="nofollow" { "BibTeX" }
+                    " · "
+                    a href=(cite::href(&result.result, scholar, "csl-json")) rel="nofollow" { "CSL-JSON" }
+                }
             }
-            p.search-result-description { (result.result.description) }
             (render_engine_list(&result.engines.iter().copied().collect::<Vec<_>>(), config))
+            @if let Some(host) = url::Url::parse(&result.result.url).ok().and_then(|u| u.host_str().map(str::to_owned)) {
+                form.block-site method="post" action="/settings/block" {
+                    input type="hidden" name="host" value=(host);
+                    button type="submit" title={ "Never show results from " (host) } { "Block site" }
+                }
+            }
+            @if let Some(explanation) = &result.explanation {
+                (render_explanation(result, explanation, config))
+            }
+        }
+    }
+}
+
+/// The "why is this here" popover, with the engines that returned the result
+/// and the rules that changed its score.
+fn render_explanation(
+    result: &engines::SearchResult<EngineSearchResult>,
+    explanation: &Explanation,
+    config: &Config,
+) -> PreEscaped<String> {
+    let ranker = match config.ranking.algorithm {
+        RankingAlgorithm::RankSum => "weight / position".to_string(),
+        RankingAlgorithm::ReciprocalRankFusion => {
+            format!("weight / ({} + position)", config.ranking.rrf_k)
+        }
+    };
+    // urls that were merged into this result, after url replacements or
+    // normalization made them the same
+    let mut merged_urls = explanation
+        .ranks
+        .iter()
+        .map(|rank| rank.url.as_str())
+        .filter(|url| urls::normalize_url(url) != result.result.url)
+        .collect::<Vec<_>>();
+    merged_urls.sort_unstable();
+    merged_urls.dedup();
+
+    html! {
+        details.search-result-explanation {
+            summary title="Why is this here?" { "Why is this here?" }
+            div.search-result-explanation-popover {
+                table {
+                    tr { th { "Engine" } th { "Position" } th { "Weight" } th { "Score" } }
+                    @for rank in &explanation.ranks {
+                        tr {
+                            td { (rank.engine) }
+                            td { (rank.position) }
+                            td { (rank.weight) }
+                            td { (format!("{:.3}", rank.score)) }
+                        }
+                    }
+                }
+                ul {
+                    li { "Each engine adds " code { (ranker) } " to the score, for a total of " (format!("{:.3}", result.score)) "." }
+                    @if explanation.url_weight > 1. {
+                        li { "Boosted ×" (explanation.url_weight) " by this instance's url weights." }
+                    } @else if explanation.url_weight < 1. {
+                        li { "Demoted ×" (explanation.url_weight) " by this instance's url weights." }
+                    }
+                    @for url in merged_urls {
+                        li { "Merged with " span.search-result-explanation-url { (url) } "." }
+                    }
+                    @if explanation.ranks.len() > 1 {
+                        li { "The title and description are from " (explanation.title_from) ", which has the highest weight." }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn render_result_extras(extras: &ResultExtras) -> PreEscaped<String> {
+    html! {
+        div.search-result-extras {
+            @if let Some(label) = extras.host_label {
+                span.search-result-label title="The kind of site this is from" { (label.name()) }
+            }
+            @if let Some(job) = &extras.job {
+                span.search-result-company { (job.company) }
+                @if !job.location.is_empty() {
+                    span.search-result-location { "📍 " (job.location) }
+                }
+                @if let Some(posted) = &job.posted {
+                    span.search-result-posted { "Posted " (posted) }
+                }
+            }
+            @if let Some(site) = &extras.site {
+                span.search-result-site { (site) }
+            }
+            @if let Some(rating) = &extras.rating {
+                span.search-result-rating title={ (rating.value) " out of " (rating.best) } {
+                    "★ " (rating.value) "/" (rating.best)
+                    @if let Some(count) = &rating.count {
+                        " (" (count) ")"
+                    }
+                }
+            }
+            @if let Some(total_time) = &extras.total_time {
+                span.search-result-total-time { "⏱ " (total_time) }
+            }
+            @if let Some(ingredient_count) = extras.ingredient_count {
+                span.search-result-ingredient-count {
+                    (ingredient_count) @if ingredient_count == 1 { " ingredient" } @else { " ingredients" }
+                }
+            }
+            @if let Some(answer_count) = extras.answer_count {
+                span.search-result-answer-count {
+                    (answer_count) @if answer_count == 1 { " answer" } @else { " answers" }
+                }
+            }
+            @if let Some(scholar) = &extras.scholar {
+                @if !scholar.authors.is_empty() {
+                    span.search-result-authors { (scholar.authors.join(", ")) }
+                }
+            }
+            @if let Some(venue) = &extras.venue {
+                span.search-result-venue { (venue) }
+            }
+            @if let Some(cited_by) = extras.cited_by {
+                span.search-result-cited-by { "Cited by " (cited_by) }
+            }
+            @if let Some(open_access) = &extras.open_access {
+                span.search-result-open-access title={ "Open access (" (open_access) ")" } { "Open access" }
+            }
+            @if let Some(pdf_url) = extras.scholar.as_ref().and_then(|s| s.pdf_url.as_ref()) {
+                a.search-result-pdf href=(pdf_url) rel="noreferrer" { "PDF" }
+            }
+            @if let Some(audio_url) = &extras.audio_url {
+                a.search-result-audio href=(audio_url) rel="noreferrer" { "▶ Listen" }
+            }
+            @if extras.unreachable {
+                span.search-result-unreachable title="The page didn't load when we checked it" { "Unreachable" }
+            }
+            @if let Some(archived) = &extras.archived {
+                (render_archived_link(archived))
+            }
         }
     }
 }
 
 fn render_featured_snippet(
     featured_snippet: &engines::FeaturedSnippet,
+    search: &SearchQuery,
     config: &Config,
 ) -> PreEscaped<String> {
+    let terms = Terms::new(&search.query);
     html! {
         div.featured-snippet {
-            p.search-result-description { (featured_snippet.description) }
-            a.search-result-anchor rel="noreferrer" href=(featured_snippet.url) {
+            p.search-result-description { (highlight(&featured_snippet.description, &terms)) }
+            a.search-result-anchor rel="noreferrer" href=(redirect::href(&featured_snippet.url, &featured_snippet.title, config)) target=[link_target(config)] {
                 span.search-result-url { (featured_snippet.url) }
-                h3.search-result-title { (featured_snippet.title) }
+                h3.search-result-title { (highlight(&featured_snippet.title, &terms)) }
             }
             (render_engine_list(&[featured_snippet.engine], config))
         }
     }
 }
 
+/// Where a result links to, which is the recipe card if the user wants to skip
+/// the story before it.
+fn result_href(result: &EngineSearchResult, config: &Config) -> String {
+    let url = match &result.extras.recipe_anchor {
+        Some(anchor) if config.ui.skip_recipe_stories => {
+            let url = result.url.split('#').next().unwrap_or(&result.url);
+            format!("{url}#{anchor}")
+        }
+        _ => result.url.clone(),
+    };
+    redirect::href(&url, &result.title, config)
+}
+
+fn link_target(config: &Config) -> Option<&'static str> {
+    config.ui.open_in_new_tab.then_some("_blank")
+}
+
+/// The results that turned out to be dead after they were sent, for script.js
+/// to flag and move down.
+pub fn render_dead_links(indexes: &[usize]) -> PreEscaped<String> {
+    let indexes = indexes
+        .iter()
+        .map(usize::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    html! {
+        div.dead-links hidden data-results=(indexes) {}
+    }
+}
+
+fn render_description_from_page(description: &str, terms: &Terms) -> PreEscaped<String> {
+    html! {
+        p.search-result-description.description-from-page title="From the page's meta tags" {
+            (highlight(description, terms))
+        }
+    }
+}
+
+/// The descriptions that were fetched from the pages after the results were
+/// sent, for script.js to move into their results.
+pub fn render_descriptions(
+    descriptions: &[(usize, String)],
+    search: &SearchQuery,
+) -> PreEscaped<String> {
+    let terms = Terms::new(&search.query);
+    html! {
+        div.page-descriptions hidden {
+            @for (index, description) in descriptions {
+                div data-result=(index) { (render_description_from_page(description, &terms)) }
+            }
+        }
+    }
+}
+
+/// The results that turned out to be recipes after the results were sent, for
+/// script.js to add their recipe data and link to.
+pub fn render_recipes(
+    recipes: &[(usize, EngineSearchResult)],
+    config: &Config,
+) -> PreEscaped<String> {
+    html! {
+        div.recipes hidden {
+            @for (index, result) in recipes {
+                div data-result=(index) data-href=(result_href(result, config)) {
+                    (render_result_extras(&result.extras))
+                }
+            }
+        }
+    }
+}
+
+fn render_archived_link(archived: &ArchivedCopy) -> PreEscaped<String> {
+    html! {
+        a.search-result-archived href=(archived.url) rel="noreferrer" title="The Wayback Machine's copy of the page" {
+            "Archived " (archived.date)
+        }
+    }
+}
+
+/// The archived copies that were looked up after the results were sent, for
+/// script.js to move into their results.
+pub fn render_archived(archived: &[(usize, ArchivedCopy)]) -> PreEscaped<String> {
+    html! {
+        div.archived-copies hidden {
+            @for (index, copy) in archived {
+                div data-result=(index) { (render_archived_link(copy)) }
+            }
+        }
+    }
+}
+
 pub fn render_infobox(infobox: &Infobox, config: &Config) -> PreEscaped<String> {
     html! {
         div.infobox.postsearch-infobox {
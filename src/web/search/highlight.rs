@@ -0,0 +1,189 @@
+//! Bolding the words from the query in result titles and descriptions, since
+//! the text we scrape loses the engines' own highlighting. Matching ignores case
+//! and accents, so "cafe" highlights "Café".
+
+use icu_normalizer::DecomposingNormalizerBorrowed;
+use maud::PreEscaped;
+
+/// The words from a query that are highlighted, folded with [`fold`].
+pub struct Terms(Vec<String>);
+
+impl Terms {
+    pub fn new(query: &str) -> Self {
+        let mut terms = query
+            .split_whitespace()
+            // excluded words and operators like site:
+            .filter(|word| !word.starts_with('-') && !word.contains(':'))
+            .flat_map(|word| word.split(|c: char| !c.is_alphanumeric()))
+            .map(fold)
+            .filter(|term| !term.is_empty())
+            .collect::<Vec<_>>();
+        // longer terms first, so they win when they overlap with shorter ones
+        terms.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        terms.dedup();
+        Self(terms)
+    }
+}
+
+/// Lowercase the text and remove accents.
+fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        fold_char(c, &mut folded);
+    }
+    folded
+}
+
+fn fold_char(c: char, out: &mut String) {
+    if c.is_ascii() {
+        out.push(c.to_ascii_lowercase());
+        return;
+    }
+    // letters that don't decompose into a base letter and an accent
+    let replacement = match c {
+        'ø' | 'Ø' => "o",
+        'ł' | 'Ł' => "l",
+        'đ' | 'Đ' => "d",
+        'ı' => "i",
+        'ß' => "ss",
+        'æ' | 'Æ' => "ae",
+        'œ' | 'Œ' => "oe",
+        _ => "",
+    };
+    if !replacement.is_empty() {
+        out.push_str(replacement);
+        return;
+    }
+    let mut buf = [0; 4];
+    let decomposed = DecomposingNormalizerBorrowed::new_nfd().normalize(c.encode_utf8(&mut buf));
+    for c in decomposed.chars().filter(|c| !is_combining_mark(*c)) {
+        out.extend(c.to_lowercase());
+    }
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036f}'
+        | '\u{1ab0}'..='\u{1aff}'
+        | '\u{1dc0}'..='\u{1dff}'
+        | '\u{20d0}'..='\u{20ff}'
+        | '\u{fe20}'..='\u{fe2f}'
+    )
+}
+
+/// Scripts that aren't written with spaces between words, so a term can match
+/// in the middle of a run of letters.
+fn is_unspaced(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // hiragana and katakana
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{0e00}'..='\u{0eff}' // thai and lao
+    )
+}
+
+/// The text as html, with the terms in `<b>` tags. Terms have to match at the
+/// start of a word, and short ones have to match a whole word so "c" doesn't
+/// highlight the first letter of every word.
+pub fn highlight(text: &str, terms: &Terms) -> PreEscaped<String> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    // where each character of the text starts in the folded text
+    let mut folded = String::with_capacity(text.len());
+    let mut folded_starts = Vec::with_capacity(chars.len() + 1);
+    for &(_, c) in &chars {
+        folded_starts.push(folded.len());
+        fold_char(c, &mut folded);
+    }
+    folded_starts.push(folded.len());
+
+    let is_word_char = |i: usize| chars.get(i).is_some_and(|(_, c)| c.is_alphanumeric());
+    let mut highlighted = vec![false; chars.len()];
+    for term in &terms.0 {
+        let unspaced = term.chars().next().is_some_and(is_unspaced);
+        let is_short = term.chars().count() < 3;
+        for (start, _) in folded.match_indices(term.as_str()) {
+            let end = start + term.len();
+            // the match has to start and end on characters of the text, which
+            // it might not if a character folded into more than one
+            let (Ok(first), Ok(last)) = (
+                folded_starts.binary_search(&start),
+                folded_starts.binary_search(&end),
+            ) else {
+                continue;
+            };
+            if !unspaced && first > 0 && is_word_char(first - 1) {
+                continue;
+            }
+            if !unspaced && is_short && is_word_char(last) {
+                continue;
+            }
+            if highlighted[first..last].iter().any(|h| *h) {
+                continue;
+            }
+            highlighted[first..last].fill(true);
+        }
+    }
+
+    let mut html = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let bold = highlighted[i];
+        let start = chars[i].0;
+        while i < chars.len() && highlighted[i] == bold {
+            i += 1;
+        }
+        let end = chars.get(i).map_or(text.len(), |(index, _)| *index);
+        let segment = html_escape::encode_text(&text[start..end]);
+        if bold {
+            html.push_str("<b>");
+            html.push_str(&segment);
+            html.push_str("</b>");
+        } else {
+            html.push_str(&segment);
+        }
+    }
+    PreEscaped(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighted(text: &str, query: &str) -> String {
+        highlight(text, &Terms::new(query)).0
+    }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlighted("Rust is a language", "rust LANGUAGE"),
+            "<b>Rust</b> is a <b>language</b>"
+        );
+        assert_eq!(
+            highlighted("Trusty <rust> & rusted", "rust"),
+            "Trusty &lt;<b>rust</b>&gt; &amp; <b>rust</b>ed"
+        );
+        assert_eq!(
+            highlighted("C is not Cobol", "c -cobol site:example.com"),
+            "<b>C</b> is not Cobol"
+        );
+    }
+
+    #[test]
+    fn test_highlight_accents() {
+        assert_eq!(
+            highlighted("Café Ørsted", "cafe orsted"),
+            "<b>Café</b> <b>Ørsted</b>"
+        );
+        assert_eq!(
+            highlighted("creme brulee", "crème brûlée"),
+            "<b>creme</b> <b>brulee</b>"
+        );
+        assert_eq!(highlighted("Straße", "strasse"), "<b>Straße</b>");
+        assert_eq!(
+            highlighted("東京タワーの歴史", "タワー"),
+            "東京<b>タワー</b>の歴史"
+        );
+    }
+}
@@ -2,12 +2,16 @@ use maud::{html, PreEscaped};
 
 use crate::{
     config::Config,
-    engines::{self, EngineImageResult, ImagesResponse},
+    engines::{
+        self, image_filters::ImageFilter, EngineImageResult, ImageFilters, ImagesResponse,
+        SafeSearch, SearchQuery,
+    },
     web::search::render_engine_list,
 };
 
-pub fn render_results(response: ImagesResponse) -> PreEscaped<String> {
+pub fn render_results(response: ImagesResponse, search: &SearchQuery) -> PreEscaped<String> {
     html! {
+        (render_filters(search))
         div.image-results {
             @for image in &response.image_results {
                 (render_image_result(image, &response.config))
@@ -16,6 +20,82 @@ pub fn render_results(response: ImagesResponse) -> PreEscaped<String> {
     }
 }
 
+/// A row of chips for each filter, where the selected one isn't a link.
+fn render_filters(search: &SearchQuery) -> PreEscaped<String> {
+    let filters = search.image_filters;
+    let rows = [
+        (
+            "Size",
+            filter_options(filters, filters.size, |f, v| f.size = v),
+        ),
+        (
+            "Aspect",
+            filter_options(filters, filters.aspect, |f, v| f.aspect = v),
+        ),
+        (
+            "Type",
+            filter_options(filters, filters.kind, |f, v| f.kind = v),
+        ),
+        (
+            "License",
+            filter_options(filters, filters.license, |f, v| f.license = v),
+        ),
+    ];
+
+    html! {
+        div.image-filters {
+            @for (label, options) in rows {
+                div.refinements {
+                    span.refinements-label { (label) }
+                    @for (name, selected, filters) in options {
+                        @if selected { span.refinement.selected { (name) } }
+                        @else { a.refinement href=(filters_url(search, filters)) { (name) } }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// "Any" and every value of a filter, with whether it's selected and the
+/// filters that the chip links to.
+fn filter_options<T: ImageFilter>(
+    filters: ImageFilters,
+    selected: Option<T>,
+    set: impl Fn(&mut ImageFilters, Option<T>),
+) -> Vec<(&'static str, bool, ImageFilters)> {
+    [None]
+        .into_iter()
+        .chain(T::ALL.iter().copied().map(Some))
+        .map(|value| {
+            let mut filters = filters;
+            set(&mut filters, value);
+            let name = value.map_or("Any", T::name);
+            (name, value == selected, filters)
+        })
+        .collect()
+}
+
+fn filters_url(search: &SearchQuery, filters: ImageFilters) -> String {
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    serializer.append_pair("q", &search.query);
+    serializer.append_pair("tab", "images");
+    if search.verbatim {
+        serializer.append_pair("verbatim", "1");
+    }
+    if !search.only_engines.is_empty() {
+        let engines = search.only_engines.iter().map(|e| e.id());
+        serializer.append_pair("engines", &engines.collect::<Vec<_>>().join(","));
+    }
+    if let Some(language) = &search.language {
+        serializer.append_pair("lang", language);
+    }
+    for (name, value) in filters.to_params() {
+        serializer.append_pair(name, &value);
+    }
+    format!("?{}", serializer.finish())
+}
+
 fn render_image_result(
     result: &engines::SearchResult<EngineImageResult>,
     config: &Config,
@@ -29,8 +109,10 @@ fn render_image_result(
     } else {
         original_image_src.to_string()
     };
+    let blur =
+        result.result.nsfw && config.image_search.blur_nsfw && config.safesearch != SafeSearch::Off;
     html! {
-        div.image-result {
+        div.image-result.nsfw[blur] {
             a.image-result-anchor rel="noreferrer" href=(original_image_src) target="_blank" {
                 div.image-result-img-container {
                     img loading="lazy" src=(image_src) width=(result.result.width) height=(result.result.height);
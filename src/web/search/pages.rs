@@ -0,0 +1,72 @@
+//! The merged results of recent searches, so the next page of results can be
+//! loaded without searching again. Searching again would give slightly
+//! different results, which would make results repeat or go missing between
+//! pages.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::engines::{Response, SearchQuery, SearchTab};
+
+/// How long the results are kept. People usually scroll right after searching,
+/// if at all.
+const TTL: Duration = Duration::from_secs(60 * 10);
+const MAX_ENTRIES: usize = 100;
+
+type PagesCache = HashMap<String, (Instant, Arc<Response>)>;
+static CACHE: LazyLock<Mutex<PagesCache>> = LazyLock::new(Default::default);
+
+/// Keep the results for a search, if they don't fit on one page.
+pub fn remember(search: &SearchQuery, response: &Response) {
+    let Some(per_page) = response.config.ui.results_per_page else {
+        return;
+    };
+    if response.search_results.len() <= per_page {
+        return;
+    }
+    let Some(key) = search.cache_key() else {
+        return;
+    };
+    let mut cache = CACHE.lock();
+    cache.retain(|_, (time, _)| time.elapsed() < TTL);
+    if cache.len() < MAX_ENTRIES {
+        cache.insert(key, (Instant::now(), Arc::new(response.clone())));
+    }
+}
+
+/// The results for a search that were remembered, if they're still around.
+pub fn get(search: &SearchQuery) -> Option<Arc<Response>> {
+    let cache = CACHE.lock();
+    let (time, response) = cache.get(&search.cache_key()?)?;
+    (time.elapsed() < TTL).then(|| response.clone())
+}
+
+/// The url of the `/fragment/results` endpoint for a page of a search, which
+/// has the parameters that the search was made with.
+pub fn fragment_url(search: &SearchQuery, page: usize) -> String {
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    serializer.append_pair("q", &search.query);
+    if search.tab != SearchTab::default() {
+        serializer.append_pair("tab", &search.tab.to_string());
+    }
+    if search.verbatim {
+        serializer.append_pair("verbatim", "1");
+    }
+    if !search.only_engines.is_empty() {
+        let engines = search.only_engines.iter().map(|e| e.id());
+        serializer.append_pair("engines", &engines.collect::<Vec<_>>().join(","));
+    }
+    if let Some(label) = search.label {
+        serializer.append_pair("label", &label.to_string());
+    }
+    if let Some(language) = &search.language {
+        serializer.append_pair("lang", language);
+    }
+    serializer.append_pair("page", &page.to_string());
+    format!("/fragment/results?{}", serializer.finish())
+}
@@ -1,17 +1,129 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, OnceLock},
+};
+
 use axum::{
+    extract::State,
     http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Extension, Form,
 };
-use axum_extra::extract::{cookie::Cookie, CookieJar};
+use axum_extra::extract::{
+    cookie::{Cookie, Key},
+    SignedCookieJar,
+};
+use base64::Engine as _;
 use maud::{html, Markup, PreEscaped, DOCTYPE};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    config::{Config, HostAndPath},
+    engines::{preprocess, Engine, SafeSearch},
+    urls,
+    web::{
+        check_same_origin, head_html,
+        themes::{self, THEMES},
+    },
+};
+
+const SETTINGS_COOKIE_NAME: &str = "settings";
+/// Browsers drop cookies that are bigger than about 4KB, so bigger settings
+/// aren't saved. This counts the name, the value, and its signature.
+const MAX_SETTINGS_COOKIE_SIZE: usize = 4000;
+/// The signature that's put before the value, which is a base64 HMAC-SHA256.
+const COOKIE_SIGNATURE_SIZE: usize = 44;
+
+/// The url weight of results from the sites that a user boosted.
+const BOOSTED_SITE_WEIGHT: f64 = 1.5;
+
+/// The languages that are selectable in the settings page. Any BCP 47 tag can
+/// still be set in the config.
+const LANGUAGES: &[(&str, &str)] = &[
+    ("auto", "Auto-detect"),
+    ("en-US", "English (US)"),
+    ("en-GB", "English (UK)"),
+    ("de-DE", "Deutsch"),
+    ("es-ES", "Español"),
+    ("fr-FR", "Français"),
+    ("it-IT", "Italiano"),
+    ("nl-NL", "Nederlands"),
+    ("pl-PL", "Polski"),
+    ("pt-BR", "Português (Brasil)"),
+    ("ru-RU", "Русский"),
+    ("ja-JP", "日本語"),
+    ("ko-KR", "한국어"),
+    ("zh-CN", "中文 (简体)"),
+];
 
-use crate::{config::Config, web::head_html};
+/// Where the generated key is saved when `cookie_secret` isn't set, relative to
+/// where metasearch is run, so settings survive restarts anyway.
+const GENERATED_KEY_PATH: &str = "cookie-secret";
 
-pub async fn get(Extension(config): Extension<Config>) -> impl IntoResponse {
-    let theme_option = |value: &str, name: &str| -> Markup {
-        let selected = config.ui.stylesheet_url == value;
+static COOKIE_KEY: OnceLock<Key> = OnceLock::new();
+
+/// Set up the key used for signing the settings cookie. This must be called
+/// before the server starts.
+pub fn init_cookie_key(config: &Config) {
+    let key = if config.cookie_secret.len() >= 32 {
+        Key::derive_from(config.cookie_secret.as_bytes())
+    } else {
+        if !config.cookie_secret.is_empty() {
+            warn!("cookie_secret must be at least 32 bytes long, using a generated one instead");
+        }
+        generated_cookie_key(Path::new(GENERATED_KEY_PATH))
+    };
+    let _ = COOKIE_KEY.set(key);
+}
+
+/// The key that was generated the last time, or a new one that's saved for
+/// next time.
+fn generated_cookie_key(path: &Path) -> Key {
+    let engine = base64::engine::general_purpose::STANDARD;
+    if let Ok(saved) = fs::read_to_string(path) {
+        match engine.decode(saved.trim()) {
+            Ok(bytes) if bytes.len() == 64 => return Key::from(&bytes),
+            _ => warn!("{path:?} doesn't have a valid key in it, generating a new one"),
+        }
+    }
+
+    let key = Key::generate();
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // only metasearch should be able to read it
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let saved = options
+        .open(path)
+        .and_then(|mut file| file.write_all(engine.encode(key.master()).as_bytes()));
+    if let Err(err) = saved {
+        warn!("Couldn't save the generated cookie key to {path:?}, so settings will be reset when metasearch restarts: {err}");
+    }
+    key
+}
+
+pub(super) fn cookie_key() -> Key {
+    COOKIE_KEY.get_or_init(Key::generate).clone()
+}
+
+pub async fn get(
+    State(server_config): State<Arc<Config>>,
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    // the config has the annotations and site weights merged with the
+    // instance's, so the user's own are read from the cookie
+    let user_settings = Settings::from_headers(&headers).unwrap_or_default();
+    let settings_annotations = user_settings.annotations;
+    let blocked_sites = user_settings.blocked_sites.join("\n");
+    let boosted_sites = user_settings.boosted_sites.join("\n");
+    let safesearch_option = |value: SafeSearch, name: &str| -> Markup {
+        let selected = config.safesearch == value;
         html! {
             option value=(value) selected[selected] {
                 { (name) }
@@ -31,15 +143,15 @@ pub async fn get(Extension(config): Extension<Config>) -> impl IntoResponse {
                         h1 { "Settings" }
                         form.settings-form method="post" {
                             label for="theme" { "Theme" }
-                            select name="stylesheet-url" selected=(config.ui.stylesheet_url) {
-                                { (theme_option("", "Ayu Dark")) }
-                                { (theme_option("/themes/catppuccin-mocha.css", "Catppuccin Mocha")) }
-                                { (theme_option("/themes/catppuccin-macchiato.css", "Catppuccin Macchiato")) }
-                                { (theme_option("/themes/catppuccin-latte.css", "Catppuccin Latte")) }
-                                { (theme_option("/themes/nord-bluish.css", "Nord Bluish")) }
-                                { (theme_option("/themes/discord.css", "Discord")) }
+                            select #theme name="theme" {
+                                @for theme in THEMES {
+                                    option value=(theme.id) selected[theme.id == config.ui.theme] { (theme.name) }
+                                }
                             }
 
+                            label for="stylesheet-url" { "Custom stylesheet URL" }
+                            input #stylesheet-url type="url" name="stylesheet-url" value=(config.ui.stylesheet_url) placeholder="Loaded after the theme";
+
                             br;
 
                             // custom css textarea
@@ -50,8 +162,85 @@ pub async fn get(Extension(config): Extension<Config>) -> impl IntoResponse {
                                 }
                             }
 
+                            h2 { "Search" }
+
+                            label for="safesearch" { "Safe search" }
+                            select #safesearch name="safesearch" {
+                                { (safesearch_option(SafeSearch::Off, "Off")) }
+                                { (safesearch_option(SafeSearch::Moderate, "Moderate")) }
+                                { (safesearch_option(SafeSearch::Strict, "Strict")) }
+                            }
+
+                            label for="language" { "Language" }
+                            select #language name="language" {
+                                @if !LANGUAGES.iter().any(|(tag, _)| *tag == config.language) {
+                                    option value=(config.language) selected { (config.language) }
+                                }
+                                @for (tag, name) in LANGUAGES {
+                                    option value=(tag) selected[*tag == config.language] { (name) }
+                                }
+                            }
+
+                            label for="results-per-page" { "Results per page" }
+                            input #results-per-page type="number" min="1" name="results-per-page" placeholder="All" value=[config.ui.results_per_page];
+
+                            label.settings-checkbox {
+                                input type="checkbox" name="open-in-new-tab" value="1" checked[config.ui.open_in_new_tab];
+                                "Open results in a new tab"
+                            }
+
+                            label.settings-checkbox {
+                                input type="checkbox" name="skip-recipe-stories" value="1" checked[config.ui.skip_recipe_stories];
+                                "Skip the story on recipe pages"
+                            }
+
+                            label.settings-checkbox {
+                                input type="checkbox" name="redirect-links" value="1" checked[config.ui.redirect_links];
+                                "Open results through a redirect that hides the referrer"
+                            }
+
+                            details.settings-annotations {
+                                summary { "Annotations" }
+                                p {
+                                    "Notes that are shown under results from some sites, one per line, like "
+                                    code { "example.com ## Copies answers from Stack Overflow" }
+                                    ". Start a host with a dot to only match its subdomains."
+                                }
+                                textarea #annotations name="annotations" {
+                                    (settings_annotations)
+                                }
+                            }
+
+                            details.settings-sites open[!blocked_sites.is_empty() || !boosted_sites.is_empty()] {
+                                summary { "Blocked and boosted sites" }
+                                p { "One site per line. Their subdomains are included too." }
+                                label for="blocked-sites" { "Never show results from" }
+                                textarea #blocked-sites name="blocked-sites" {
+                                    (blocked_sites)
+                                }
+                                label for="boosted-sites" { "Rank results higher from" }
+                                textarea #boosted-sites name="boosted-sites" {
+                                    (boosted_sites)
+                                }
+                            }
+
+                            h2 { "Engines" }
+                            div.settings-engines {
+                                @for engine in Engine::all().iter().filter(|e| !is_hidden(&server_config, **e)) {
+                                    label.settings-checkbox {
+                                        input type="checkbox" name="engine" value=(engine.id()) checked[config.engines.get(*engine).enabled];
+                                        (engine.id())
+                                    }
+                                }
+                            }
+
                             input #save-settings-button type="submit" value="Save";
                         }
+                        @if server_config.visits.enabled {
+                            form method="post" action="/visits/clear" {
+                                input type="submit" value="Forget visited pages";
+                            }
+                        }
                     }
                 }
             }
@@ -62,31 +251,323 @@ pub async fn get(Extension(config): Extension<Config>) -> impl IntoResponse {
     ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html)
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case", default)]
 pub struct Settings {
+    pub theme: Option<String>,
     pub stylesheet_url: String,
     pub stylesheet_str: String,
+    pub safesearch: Option<SafeSearch>,
+    pub language: Option<String>,
+    pub results_per_page: Option<usize>,
+    pub open_in_new_tab: bool,
+    pub skip_recipe_stories: bool,
+    pub redirect_links: bool,
+    /// Notes for results from some sites, in the format from
+    /// [`crate::urls::parse_annotations`].
+    pub annotations: String,
+    /// Hosts that results are hidden from, along with their subdomains.
+    pub blocked_sites: Vec<String>,
+    /// Hosts that results are ranked higher from, along with their subdomains.
+    pub boosted_sites: Vec<String>,
+    /// Engine ids mapped to whether they're enabled. Only engines where the
+    /// user's choice differs from the server config are stored, so newly added
+    /// engines still get the instance's default.
+    pub engines: BTreeMap<String, bool>,
+}
+
+impl Settings {
+    /// Read the settings from the signed cookie, if it's present and valid.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let jar = SignedCookieJar::from_headers(headers, cookie_key());
+        let cookie = jar.get(SETTINGS_COOKIE_NAME)?;
+        serde_json::from_str(cookie.value()).ok()
+    }
+
+    /// The settings that don't change anything about the server config, for
+    /// users that didn't save any settings yet.
+    fn from_config(config: &Config) -> Self {
+        Settings {
+            stylesheet_url: config.ui.stylesheet_url.clone(),
+            stylesheet_str: config.ui.stylesheet_str.clone(),
+            open_in_new_tab: config.ui.open_in_new_tab,
+            skip_recipe_stories: config.ui.skip_recipe_stories,
+            redirect_links: config.ui.redirect_links,
+            ..Default::default()
+        }
+    }
+
+    /// Merge the user's settings into the server config.
+    pub fn apply(self, config: &mut Config) {
+        if let Some(theme) = self.theme.filter(|t| themes::get(t).is_some()) {
+            config.ui.theme = theme;
+            config.ui.stylesheet_url = self.stylesheet_url;
+        } else if let Some(theme) = themes::from_stylesheet_url(&self.stylesheet_url) {
+            // from before there were themes
+            config.ui.theme = theme.id.to_string();
+            config.ui.stylesheet_url = String::new();
+        } else {
+            config.ui.stylesheet_url = self.stylesheet_url;
+        }
+        config.ui.stylesheet_str = self.stylesheet_str;
+        if let Some(safesearch) = self.safesearch {
+            config.safesearch = safesearch;
+        }
+        // the language is sent to engines in headers and cookies, so it can't be
+        // anything other than a language tag
+        if let Some(language) = self.language.filter(|l| preprocess::is_language_tag(l)) {
+            config.language = language;
+        }
+        config.ui.results_per_page = self.results_per_page.or(config.ui.results_per_page);
+        config.ui.open_in_new_tab = self.open_in_new_tab;
+        config.ui.skip_recipe_stories = self.skip_recipe_stories;
+        config.ui.redirect_links = self.redirect_links;
+        // the user's own annotations take priority over the instance's
+        config
+            .urls
+            .annotations
+            .splice(0..0, urls::parse_annotations(&self.annotations));
+        // and their own sites take priority over the instance's url weights,
+        // with blocking winning over boosting
+        let user_weights = site_weights(&self.blocked_sites, 0.)
+            .chain(site_weights(&self.boosted_sites, BOOSTED_SITE_WEIGHT))
+            .collect::<Vec<_>>();
+        config.urls.weight.splice(0..0, user_weights);
+
+        if !self.engines.is_empty() {
+            let mut engines = config.engines.as_ref().clone();
+            for (engine_id, enabled) in self.engines {
+                let Ok(engine) = Engine::from_str(&engine_id) else {
+                    continue;
+                };
+                engines.set_enabled(engine, enabled);
+            }
+            config.engines = engines.into();
+        }
+    }
+
+    fn from_form(form: Vec<(String, String)>, config: &Config) -> Self {
+        let mut settings = Settings::default();
+        let mut checked_engines = Vec::new();
+        for (key, value) in form {
+            match key.as_str() {
+                "theme" => settings.theme = Some(value),
+                "stylesheet-url" => settings.stylesheet_url = value,
+                "stylesheet-str" => settings.stylesheet_str = value,
+                "safesearch" => settings.safesearch = SafeSearch::from_str(&value).ok(),
+                "language" if preprocess::is_language_tag(&value) => {
+                    settings.language = Some(value)
+                }
+                "results-per-page" => settings.results_per_page = value.parse().ok(),
+                "open-in-new-tab" => settings.open_in_new_tab = value == "1",
+                "skip-recipe-stories" => settings.skip_recipe_stories = value == "1",
+                "redirect-links" => settings.redirect_links = value == "1",
+                "annotations" => settings.annotations = value,
+                "blocked-sites" => {
+                    settings.blocked_sites = value.lines().filter_map(parse_site).collect()
+                }
+                "boosted-sites" => {
+                    settings.boosted_sites = value.lines().filter_map(parse_site).collect()
+                }
+                "engine" => {
+                    if let Ok(engine) = Engine::from_str(&value) {
+                        checked_engines.push(engine);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for &engine in Engine::all() {
+            if is_hidden(config, engine) {
+                continue;
+            }
+            let enabled = checked_engines.contains(&engine);
+            if enabled != config.engines.get(engine).enabled {
+                settings.engines.insert(engine.id().to_string(), enabled);
+            }
+        }
+
+        settings
+    }
+}
+
+/// The url weights for the sites and their subdomains.
+fn site_weights(sites: &[String], weight: f64) -> impl Iterator<Item = (HostAndPath, f64)> + '_ {
+    sites.iter().flat_map(move |site| {
+        [
+            (HostAndPath::new(site), weight),
+            (HostAndPath::new(&format!(".{site}")), weight),
+        ]
+    })
+}
+
+/// The host from a site that a user typed, which might be a whole url.
+fn parse_site(site: &str) -> Option<String> {
+    let site = site.trim();
+    let site = site.split_once("://").map_or(site, |(_, rest)| rest);
+    let host = site
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches("www.")
+        .to_lowercase();
+    (!host.is_empty()).then_some(host)
+}
+
+/// Opt-in engines that aren't enabled in the config can't be enabled by users,
+/// so they're not shown.
+fn is_hidden(config: &Config, engine: Engine) -> bool {
+    let engine_config = config.engines.get(engine);
+    engine_config.opt_in && !engine_config.enabled
 }
 
 pub async fn post(
+    State(server_config): State<Arc<Config>>,
     headers: HeaderMap,
-    mut jar: CookieJar,
-    Form(settings): Form<Settings>,
+    Form(form): Form<Vec<(String, String)>>,
 ) -> Response {
-    let Some(origin) = headers.get("origin").and_then(|h| h.to_str().ok()) else {
-        return (StatusCode::BAD_REQUEST, "Missing or invalid Origin header").into_response();
+    if let Err(err) = check_same_origin(&headers) {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+
+    // engine overrides are relative to the server's config, not the one that
+    // already has the user's old settings applied
+    let settings = Settings::from_form(form, &server_config);
+    let jar = match save(&headers, &settings) {
+        Ok(jar) => jar,
+        Err(err) => return (StatusCode::PAYLOAD_TOO_LARGE, err).into_response(),
     };
-    let Some(host) = headers.get("host").and_then(|h| h.to_str().ok()) else {
-        return (StatusCode::BAD_REQUEST, "Missing or invalid Host header").into_response();
+
+    (StatusCode::FOUND, [(header::LOCATION, "/settings")], jar).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct BlockSiteForm {
+    host: String,
+}
+
+/// Add a site to the user's blocked sites, from the button next to results,
+/// and go back to the page the button was on.
+pub async fn block_site(
+    State(server_config): State<Arc<Config>>,
+    headers: HeaderMap,
+    Form(form): Form<BlockSiteForm>,
+) -> Response {
+    if let Err(err) = check_same_origin(&headers) {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+    let Some(host) = parse_site(&form.host) else {
+        return (StatusCode::BAD_REQUEST, "Missing host").into_response();
     };
-    if origin != format!("http://{host}") && origin != format!("https://{host}") {
-        return (StatusCode::BAD_REQUEST, "Origin does not match Host").into_response();
+
+    let mut settings =
+        Settings::from_headers(&headers).unwrap_or_else(|| Settings::from_config(&server_config));
+    if !settings.blocked_sites.contains(&host) {
+        settings.blocked_sites.push(host);
     }
+    let jar = match save(&headers, &settings) {
+        Ok(jar) => jar,
+        Err(err) => return (StatusCode::PAYLOAD_TOO_LARGE, err).into_response(),
+    };
 
-    let mut settings_cookie = Cookie::new("settings", serde_json::to_string(&settings).unwrap());
+    // the origin was already checked, so only the path of the referer is used
+    let location = headers
+        .get(header::REFERER)
+        .and_then(|r| url::Url::parse(r.to_str().ok()?).ok())
+        .map(|r| match r.query() {
+            Some(query) => format!("{}?{query}", r.path()),
+            None => r.path().to_string(),
+        })
+        .unwrap_or_else(|| "/".to_string());
+    (StatusCode::FOUND, [(header::LOCATION, location)], jar).into_response()
+}
+
+/// The cookie jar with the settings cookie replaced, or an error for the user
+/// if the settings are too big for a cookie.
+fn save(headers: &HeaderMap, settings: &Settings) -> Result<SignedCookieJar, &'static str> {
+    let mut settings_cookie = Cookie::new(
+        SETTINGS_COOKIE_NAME,
+        serde_json::to_string(settings).unwrap(),
+    );
+    if cookie_size(&settings_cookie) > MAX_SETTINGS_COOKIE_SIZE {
+        return Err("Your settings are too big to save. Try blocking or boosting fewer sites, or removing some annotations.");
+    }
     settings_cookie.make_permanent();
-    jar = jar.add(settings_cookie);
+    Ok(SignedCookieJar::from_headers(headers, cookie_key()).add(settings_cookie))
+}
 
-    (StatusCode::FOUND, [(header::LOCATION, "/settings")], jar).into_response()
+/// How big the cookie's name and value will be once it's signed and encoded.
+fn cookie_size(cookie: &Cookie) -> usize {
+    cookie.encoded().to_string().len() + COOKIE_SIGNATURE_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_site_weights() {
+        let settings = Settings {
+            blocked_sites: vec!["spam.com".to_string()],
+            boosted_sites: ["https://www.Docs.rs/serde", "spam.com"]
+                .into_iter()
+                .filter_map(parse_site)
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(settings.boosted_sites, ["docs.rs", "spam.com"]);
+
+        let mut config = Config::default();
+        settings.apply(&mut config);
+        assert_eq!(urls::get_url_weight("https://spam.com/a", &config.urls), 0.);
+        assert_eq!(
+            urls::get_url_weight("https://blog.spam.com/", &config.urls),
+            0.
+        );
+        assert_eq!(
+            urls::get_url_weight("https://docs.rs/serde", &config.urls),
+            BOOSTED_SITE_WEIGHT
+        );
+        assert_eq!(
+            urls::get_url_weight("https://example.com/", &config.urls),
+            1.
+        );
+    }
+
+    #[test]
+    fn test_generated_cookie_key() {
+        let path =
+            std::env::temp_dir().join(format!("metasearch-cookie-secret-{}", std::process::id()));
+        let key = generated_cookie_key(&path);
+        assert_eq!(generated_cookie_key(&path).master(), key.master());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_settings_too_big() {
+        let headers = HeaderMap::new();
+        let settings = Settings {
+            blocked_sites: vec!["spam.com".to_string()],
+            ..Default::default()
+        };
+        assert!(save(&headers, &settings).is_ok());
+
+        let settings = Settings {
+            blocked_sites: (0..500).map(|i| format!("spam{i}.com")).collect(),
+            ..Default::default()
+        };
+        assert!(save(&headers, &settings).is_err());
+    }
+
+    #[test]
+    fn test_invalid_language() {
+        let settings = Settings {
+            language: Some("en\r\nCookie: x".to_string()),
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        settings.apply(&mut config);
+        assert_eq!(config.language, Config::default().language);
+    }
 }
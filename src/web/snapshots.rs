@@ -0,0 +1,110 @@
+//! Sharing snapshots of result pages, and viewing them at /s/<id>.
+
+use axum::{
+    extract::Path,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Form,
+};
+use maud::{html, PreEscaped, DOCTYPE};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::{config::Config, snapshots, web::head_html};
+
+use super::check_same_origin;
+
+#[derive(Deserialize)]
+pub struct ShareForm {
+    id: String,
+}
+
+fn disabled() -> Response {
+    (StatusCode::NOT_FOUND, "Snapshots are disabled").into_response()
+}
+
+fn internal_error(err: eyre::Report) -> Response {
+    error!("Snapshots error: {err}");
+    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+}
+
+pub async fn share(
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+    Form(form): Form<ShareForm>,
+) -> Response {
+    if !config.snapshots.enabled {
+        return disabled();
+    }
+    if let Err(err) = check_same_origin(&headers) {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+    match snapshots::share(&form.id, config.snapshots.expiry_days) {
+        Ok(true) => (
+            StatusCode::FOUND,
+            [(header::LOCATION, format!("/s/{}", form.id))],
+        )
+            .into_response(),
+        Ok(false) => (
+            StatusCode::GONE,
+            "These results are too old to share, search again to share them",
+        )
+            .into_response(),
+        Err(err) => internal_error(err),
+    }
+}
+
+/// A read-only page with the results exactly like they were when they were
+/// shared. It doesn't need auth, so it can be shared with anyone.
+pub async fn get(Path(id): Path<String>, Extension(config): Extension<Config>) -> Response {
+    if !config.snapshots.enabled {
+        return disabled();
+    }
+    let (snapshot, expires_at) = match snapshots::get(&id) {
+        Ok(Some(snapshot)) => snapshot,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                "This snapshot doesn't exist or expired",
+            )
+                .into_response()
+        }
+        Err(err) => return internal_error(err),
+    };
+
+    let format_time = |timestamp: i64| {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%d %H:%M UTC")
+            .to_string()
+    };
+    let tab_param = if snapshot.tab == "all" {
+        String::new()
+    } else {
+        format!("&tab={}", snapshot.tab)
+    };
+
+    let html = html! {
+        (DOCTYPE)
+        html lang="en" {
+            {(head_html(Some(&snapshot.query), &config))}
+            body {
+                div.main-container.{"search-" (snapshot.tab)} {
+                    main {
+                        p.snapshot-banner {
+                            "A snapshot of the results for "
+                            b { (snapshot.query) }
+                            " from " (format_time(snapshot.created_at))
+                            ", until " (format_time(expires_at)) ". "
+                            a href={ "/search?q=" (urlencoding::encode(&snapshot.query)) (tab_param) } { "Search again" }
+                        }
+                        (PreEscaped(snapshot.html))
+                    }
+                }
+            }
+        }
+    }
+    .into_string();
+
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response()
+}
@@ -0,0 +1,94 @@
+//! The built-in themes. They're applied in the `<head>` of every page, so
+//! there's no flash of the wrong theme while a script figures out which one to
+//! use.
+
+pub struct Theme {
+    /// What's in `ui.theme` and the settings cookie.
+    pub id: &'static str,
+    /// What's shown in the settings page.
+    pub name: &'static str,
+    /// Stylesheets that are loaded after style.css, with the media query they
+    /// apply to if they don't always.
+    pub stylesheets: &'static [(&'static str, Option<&'static str>)],
+    /// For the `color-scheme` meta tag, so scrollbars and form controls match
+    /// the theme.
+    pub color_scheme: &'static str,
+}
+
+/// The theme that's used if the config or settings have one that doesn't
+/// exist.
+pub const DEFAULT_THEME: &str = "dark";
+
+pub const THEMES: &[Theme] = &[
+    Theme {
+        id: "dark",
+        name: "Ayu Dark",
+        stylesheets: &[],
+        color_scheme: "dark",
+    },
+    Theme {
+        id: "light",
+        name: "Light",
+        stylesheets: &[("/themes/light.css", None)],
+        color_scheme: "light",
+    },
+    Theme {
+        id: "auto",
+        name: "Auto (light or dark, like your device)",
+        stylesheets: &[("/themes/light.css", Some("(prefers-color-scheme: light)"))],
+        color_scheme: "light dark",
+    },
+    Theme {
+        id: "high-contrast",
+        name: "High contrast",
+        stylesheets: &[("/themes/high-contrast.css", None)],
+        color_scheme: "dark",
+    },
+    Theme {
+        id: "catppuccin-mocha",
+        name: "Catppuccin Mocha",
+        stylesheets: &[("/themes/catppuccin-mocha.css", None)],
+        color_scheme: "dark",
+    },
+    Theme {
+        id: "catppuccin-macchiato",
+        name: "Catppuccin Macchiato",
+        stylesheets: &[("/themes/catppuccin-macchiato.css", None)],
+        color_scheme: "dark",
+    },
+    Theme {
+        id: "catppuccin-latte",
+        name: "Catppuccin Latte",
+        stylesheets: &[("/themes/catppuccin-latte.css", None)],
+        color_scheme: "light",
+    },
+    Theme {
+        id: "nord-bluish",
+        name: "Nord Bluish",
+        stylesheets: &[("/themes/nord-bluish.css", None)],
+        color_scheme: "dark",
+    },
+    Theme {
+        id: "discord",
+        name: "Discord",
+        stylesheets: &[("/themes/discord.css", None)],
+        color_scheme: "dark",
+    },
+];
+
+pub fn get(id: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|t| t.id == id)
+}
+
+/// The theme for `ui.theme`, or the default one if it doesn't exist.
+pub fn get_or_default(id: &str) -> &'static Theme {
+    get(id).unwrap_or_else(|| get(DEFAULT_THEME).unwrap())
+}
+
+/// Themes used to be picked by setting the stylesheet url to theirs, so the
+/// theme for those urls is still recognized.
+pub fn from_stylesheet_url(url: &str) -> Option<&'static Theme> {
+    THEMES
+        .iter()
+        .find(|t| matches!(t.stylesheets, [(stylesheet, None)] if *stylesheet == url))
+}
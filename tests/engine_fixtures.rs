@@ -0,0 +1,160 @@
+//! Checks that the search engine parsers still work on saved responses, since
+//! markup changes would otherwise break them silently.
+//!
+//! To save fresh responses from the live engines, run:
+//! `METASEARCH_RECORD=1 cargo test --test engine_fixtures record -- --ignored`
+//!
+//! Once an engine's fixture is committed, add it to [`FIXTURES`]. Every engine
+//! there needs a fixture, or [`parse_fixtures`] fails.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use metasearch::{
+    config::Config,
+    engines::{
+        search, Engine, EngineResponse, RequestResponse, SearchQuery, SearchTab,
+        DEFAULT_REQUESTED_COUNT,
+    },
+};
+
+type ParseFn = fn(&str) -> eyre::Result<EngineResponse>;
+
+/// The engines that can be recorded, their parsers, and the minimum number of
+/// results we expect them to find.
+const ENGINES: &[(&str, ParseFn, usize)] = &[
+    ("4get", search::fourget::parse_response, 5),
+    ("bing", search::bing::parse_response, 5),
+    ("brave", search::brave::parse_response, 5),
+    ("codeberg", search::codeberg::parse_response, 3),
+    ("google", search::google::parse_response, 5),
+    ("google_scholar", search::google_scholar::parse_response, 5),
+    ("kagi", search::kagi::parse_response, 5),
+    ("librey", search::librey::parse_response, 5),
+    ("marginalia", search::marginalia::parse_response, 3),
+    ("mwmbl", search::mwmbl::parse_response, 1),
+    ("openalex", search::openalex::parse_response, 5),
+    ("petal", search::petal::parse_response, 5),
+    ("remotive", search::remotive::parse_response, 1),
+    ("rightdao", search::rightdao::parse_response, 3),
+    ("searxng", search::searxng::parse_response, 5),
+    ("sogou", search::sogou::parse_response, 5),
+    ("stract", search::stract::parse_response, 3),
+    ("wiby", search::wiby::parse_response, 3),
+    ("yep", search::yep::parse_response, 3),
+];
+
+/// The engines in [`ENGINES`] that have a committed fixture, which are the
+/// ones [`parse_fixtures`] checks. None have been recorded yet.
+const FIXTURES: &[&str] = &[];
+
+/// The query that fixtures are recorded with. It's short so every engine
+/// (including Marginalia) will answer it.
+const QUERY: &str = "rust programming";
+
+fn fixture_path(engine_id: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(format!("{engine_id}.txt"))
+}
+
+#[test]
+fn parse_fixtures() {
+    let mut missing = Vec::new();
+    for &engine_id in FIXTURES {
+        let &(_, parse, min_results) = ENGINES
+            .iter()
+            .find(|(id, _, _)| *id == engine_id)
+            .unwrap_or_else(|| panic!("{engine_id} is in FIXTURES but not ENGINES"));
+        let path = fixture_path(engine_id);
+        let Ok(body) = fs::read_to_string(&path) else {
+            missing.push(engine_id);
+            continue;
+        };
+
+        let response =
+            parse(&body).unwrap_or_else(|err| panic!("{engine_id} failed to parse: {err}"));
+        assert!(
+            response.search_results.len() >= min_results,
+            "{engine_id} only found {} results, expected at least {min_results}",
+            response.search_results.len()
+        );
+        for result in &response.search_results {
+            assert!(!result.url.is_empty(), "{engine_id} result has no url");
+            assert!(
+                !result.title.is_empty(),
+                "{engine_id} result {} has no title",
+                result.url
+            );
+        }
+    }
+    assert!(
+        missing.is_empty(),
+        "no fixtures for {}, record them with METASEARCH_RECORD=1",
+        missing.join(", ")
+    );
+}
+
+#[tokio::test]
+#[ignore = "makes real requests, run with METASEARCH_RECORD=1 to update fixtures"]
+async fn record() {
+    if std::env::var("METASEARCH_RECORD").is_err() {
+        eprintln!("METASEARCH_RECORD isn't set, not recording");
+        return;
+    }
+
+    // engines that need an api key, like Kagi, get it from this config
+    let config = match std::env::var("METASEARCH_CONFIG") {
+        Ok(path) => Config::read_or_create(Path::new(&path)).unwrap(),
+        Err(_) => Config::default(),
+    };
+    let query = SearchQuery {
+        query: QUERY.to_string(),
+        tab: SearchTab::All,
+        verbatim: false,
+        only_engines: vec![],
+        label: None,
+        language: None,
+        image_filters: Default::default(),
+        requested_count: DEFAULT_REQUESTED_COUNT,
+        info: Default::default(),
+        debug: false,
+        request_headers: Default::default(),
+        ip: "127.0.0.1".to_string(),
+        config: Arc::new(config),
+    };
+
+    fs::create_dir_all(fixture_path("").parent().unwrap()).unwrap();
+    for &(engine_id, _, _) in ENGINES {
+        let engine = Engine::from_str(engine_id).unwrap();
+        let request = match engine.request(&query).await {
+            Ok(RequestResponse::Http(request)) => *request,
+            // engines with several endpoints are recorded from the first one
+            Ok(RequestResponse::Failover(mut requests)) if !requests.is_empty() => {
+                requests.remove(0).request
+            }
+            Ok(_) => {
+                eprintln!("{engine_id} didn't make a request, skipping");
+                continue;
+            }
+            Err(err) => {
+                eprintln!("{engine_id} request error: {err}");
+                continue;
+            }
+        };
+        let body = match request.send().await {
+            Ok(res) => res.text().await.unwrap_or_default(),
+            Err(err) => {
+                eprintln!("{engine_id} request error: {err}");
+                continue;
+            }
+        };
+        fs::write(fixture_path(engine_id), body).unwrap();
+        eprintln!("recorded {engine_id}");
+    }
+}
@@ -0,0 +1,498 @@
+//! Runs the whole app against fake engines, to check that a search goes all the
+//! way through: the requests to the engines, parsing, ranking, and rendering.
+//!
+//! The fake engines are LibreY and 4get, since their instances can be set with
+//! `endpoints` in the config.
+
+use std::{
+    net::SocketAddr,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use metasearch::{
+    config::{BasicAuthConfig, Config, IpRange, PartialEnginesConfig},
+    engines::Engine,
+    web,
+};
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// A config with only the given engines enabled, using these endpoints.
+fn config(engines: &[(&str, &[String])]) -> Config {
+    let mut config = Config::default();
+    config.api = true;
+    // so a generated one isn't saved to the working directory
+    config.cookie_secret = "a cookie secret that's only for the tests".to_string();
+
+    let mut engines_config = config.engines.as_ref().clone();
+    for &engine in Engine::all() {
+        engines_config.set_enabled(engine, false);
+    }
+    let mut partial = String::new();
+    for (id, endpoints) in engines {
+        partial.push_str(&format!(
+            "\"{id}\" = {{ enabled = true, weight = 1.0, endpoints = {endpoints:?} }}\n"
+        ));
+    }
+    engines_config.overlay(toml::from_str::<PartialEnginesConfig>(&partial).unwrap());
+    config.engines = Arc::new(engines_config);
+    config
+}
+
+/// Serve the app on a random port, and return its url.
+async fn serve(config: Config) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = web::app(Arc::new(config));
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    format!("http://{addr}")
+}
+
+async fn get(url: &str) -> (u16, String) {
+    let client = wreq::ClientBuilder::new().build().unwrap();
+    let res = client.get(url).send().await.unwrap();
+    let status = res.status().as_u16();
+    (status, res.text().await.unwrap())
+}
+
+async fn status(req: wreq::RequestBuilder) -> u16 {
+    req.send().await.unwrap().status().as_u16()
+}
+
+async fn api_search(server: &str, query: &str) -> Value {
+    let (status, body) = get(&format!(
+        "{server}/api/search?q={}",
+        urlencoding::encode(query)
+    ))
+    .await;
+    assert_eq!(status, 200, "{body}");
+    serde_json::from_str(&body).unwrap()
+}
+
+fn result_urls(response: &Value) -> Vec<&str> {
+    response["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["url"].as_str().unwrap())
+        .collect()
+}
+
+fn librey_results(urls: &[&str]) -> ResponseTemplate {
+    let results = urls
+        .iter()
+        .map(|url| json!({ "url": url, "title": format!("Title of {url}"), "description": "" }))
+        .collect::<Vec<_>>();
+    ResponseTemplate::new(200).set_body_json(results)
+}
+
+fn fourget_results(urls: &[&str]) -> ResponseTemplate {
+    let results = urls
+        .iter()
+        .map(|url| json!({ "url": url, "title": format!("Title of {url}"), "description": null }))
+        .collect::<Vec<_>>();
+    ResponseTemplate::new(200).set_body_json(json!({ "status": "ok", "web": results }))
+}
+
+#[tokio::test]
+async fn search_merges_and_ranks_results() {
+    let engine = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api.php"))
+        .respond_with(librey_results(&[
+            "https://a.example/page",
+            "https://b.example/page",
+        ]))
+        .mount(&engine)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/web"))
+        .respond_with(fourget_results(&[
+            "https://b.example/page",
+            "https://c.example/page",
+        ]))
+        .mount(&engine)
+        .await;
+    let server = serve(config(&[
+        ("librey", &[engine.uri()]),
+        ("4get", &[engine.uri()]),
+    ]))
+    .await;
+
+    let response = api_search(&server, "merging").await;
+    // b is from both engines, so it's first
+    assert_eq!(
+        result_urls(&response),
+        [
+            "https://b.example/page",
+            "https://a.example/page",
+            "https://c.example/page"
+        ]
+    );
+
+    let (status, html) = get(&format!("{server}/search?q=merging")).await;
+    assert_eq!(status, 200);
+    assert!(html.contains("Title of https://b.example/page"), "{html}");
+    assert!(html.contains(r#"id="result-3""#), "{html}");
+}
+
+#[tokio::test]
+async fn blocked_endpoint_fails_over() {
+    let blocked = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&blocked)
+        .await;
+    let working = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api.php"))
+        .respond_with(librey_results(&["https://a.example/page"]))
+        .mount(&working)
+        .await;
+    let server = serve(config(&[
+        ("librey", &[blocked.uri(), working.uri()]),
+        // every endpoint of 4get is blocked, which shouldn't break the search
+        ("4get", &[blocked.uri()]),
+    ]))
+    .await;
+
+    let response = api_search(&server, "blocked").await;
+    assert_eq!(result_urls(&response), ["https://a.example/page"]);
+}
+
+#[tokio::test]
+async fn results_page_shows_engine_progress() {
+    let engine = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api.php"))
+        .respond_with(librey_results(&[
+            "https://a.example/page",
+            "https://b.example/page",
+        ]))
+        .mount(&engine)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/web"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&engine)
+        .await;
+    let server = serve(config(&[
+        ("librey", &[engine.uri()]),
+        ("4get", &[engine.uri()]),
+    ]))
+    .await;
+
+    let (status, html) = get(&format!("{server}/search?q=progress")).await;
+    assert_eq!(status, 200);
+    assert!(
+        html.contains(r#"librey <span class="progress-update-done">done</span>, 2 results"#),
+        "{html}"
+    );
+    assert!(
+        html.contains(r#"4get <span class="progress-update-error">"#),
+        "{html}"
+    );
+}
+
+#[tokio::test]
+async fn progress_events_follow_the_running_search() {
+    let engine = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api.php"))
+        .respond_with(
+            librey_results(&["https://a.example/page"]).set_delay(Duration::from_millis(500)),
+        )
+        // the events don't search again
+        .expect(1)
+        .mount(&engine)
+        .await;
+    let server = serve(config(&[("librey", &[engine.uri()])])).await;
+
+    let (status, events) = get(&format!("{server}/search/events?q=events")).await;
+    assert_eq!(status, 200);
+    // nothing is running yet, so it's done right away
+    assert!(!events.contains("event: engine"), "{events}");
+    assert!(events.contains("event: done"), "{events}");
+
+    let ((status, _), (_, events)) =
+        tokio::join!(get(&format!("{server}/search?q=events")), async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            get(&format!("{server}/search/events?q=events")).await
+        });
+    assert_eq!(status, 200);
+    assert!(events.contains("event: engine"), "{events}");
+    assert!(events.contains(r#""status":"done""#), "{events}");
+    assert!(events.contains("event: done"), "{events}");
+}
+
+#[tokio::test]
+async fn slow_engine_times_out() {
+    let engine = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api.php"))
+        .respond_with(librey_results(&["https://a.example/page"]))
+        .mount(&engine)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/web"))
+        .respond_with(
+            fourget_results(&["https://b.example/page"]).set_delay(Duration::from_secs(30)),
+        )
+        .mount(&engine)
+        .await;
+    let server = serve(config(&[
+        ("librey", &[engine.uri()]),
+        ("4get", &[engine.uri()]),
+    ]))
+    .await;
+
+    let start = Instant::now();
+    let response = api_search(&server, "timeout").await;
+    assert!(start.elapsed() < Duration::from_secs(20));
+    assert_eq!(result_urls(&response), ["https://a.example/page"]);
+}
+
+#[tokio::test]
+async fn next_page_comes_from_cache() {
+    let engine = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api.php"))
+        .respond_with(librey_results(&[
+            "https://a.example/page",
+            "https://b.example/page",
+            "https://c.example/page",
+            "https://d.example/page",
+        ]))
+        .expect(1)
+        .mount(&engine)
+        .await;
+    let mut config = config(&[("librey", &[engine.uri()])]);
+    config.ui.results_per_page = Some(2);
+    let server = serve(config).await;
+
+    let (_, html) = get(&format!("{server}/search?q=pages")).await;
+    assert!(html.contains("Title of https://b.example/page"), "{html}");
+    assert!(!html.contains("Title of https://c.example/page"), "{html}");
+
+    let (status, fragment) = get(&format!("{server}/fragment/results?q=pages&page=2")).await;
+    assert_eq!(status, 200);
+    assert!(
+        fragment.contains("Title of https://c.example/page"),
+        "{fragment}"
+    );
+    assert!(fragment.contains(r#"id="result-3""#), "{fragment}");
+    assert!(!fragment.contains("load-more"), "{fragment}");
+
+    // the second page didn't search again
+    engine.verify().await;
+}
+
+#[tokio::test]
+async fn links_encode_the_query() {
+    let engine = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api.php"))
+        .respond_with(librey_results(&["https://a.example/page"]))
+        .mount(&engine)
+        .await;
+    let server = serve(config(&[
+        ("librey", &[engine.uri()]),
+        ("4get", &[engine.uri()]),
+    ]))
+    .await;
+
+    let (status, html) = get(&format!(
+        "{server}/search?q={}&engines=librey",
+        urlencoding::encode("fish & chips")
+    ))
+    .await;
+    assert_eq!(status, 200);
+    assert!(
+        html.contains(r#"href="?q=fish%20%26%20chips">Search all engines"#),
+        "{html}"
+    );
+    assert!(!html.contains("?q=fish &amp; chips"), "{html}");
+}
+
+#[cfg(feature = "admin")]
+#[tokio::test]
+async fn admin_endpoints_need_the_admin_token() {
+    let mut config = config(&[]);
+    config.drift.enabled = true;
+    // general auth doesn't let anyone into them, and they don't need it
+    config.auth.token = Some("a token for searching".to_string());
+    config.auth.admin_token = Some("a token for admins".to_string());
+    let server = serve(config).await;
+
+    let client = wreq::ClientBuilder::new().build().unwrap();
+    let metrics = format!("{server}/metrics");
+    assert_eq!(status(client.get(&metrics)).await, 403);
+    assert_eq!(
+        status(client.get(&metrics).bearer_auth("a token for searching")).await,
+        403
+    );
+    assert_eq!(
+        status(client.get(&metrics).header("x-admin-token", "wrong")).await,
+        403
+    );
+    assert_eq!(
+        status(
+            client
+                .get(&metrics)
+                .header("x-admin-token", "a token for admins")
+        )
+        .await,
+        200
+    );
+}
+
+#[tokio::test]
+async fn basic_auth_is_required() {
+    let mut config = config(&[]);
+    config.auth.basic = Some(BasicAuthConfig {
+        username: "family".to_string(),
+        password: "hunter2".to_string(),
+    });
+    let server = serve(config).await;
+
+    let client = wreq::ClientBuilder::new().build().unwrap();
+    assert_eq!(status(client.get(&server)).await, 401);
+    assert_eq!(
+        status(client.get(&server).basic_auth("family", Some("hunter3"))).await,
+        401
+    );
+    assert_eq!(
+        status(client.get(&server).basic_auth("family", Some("hunter2"))).await,
+        200
+    );
+}
+
+#[tokio::test]
+async fn token_login_sets_a_signed_cookie() {
+    let mut config = config(&[]);
+    config.auth.token = Some("correct horse".to_string());
+    let server = serve(config).await;
+
+    let client = wreq::ClientBuilder::new().build().unwrap();
+    let res = client.get(&server).send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 302);
+    assert_eq!(res.headers()["location"], "/login");
+    assert_eq!(
+        status(client.get(&server).bearer_auth("correct horse")).await,
+        200
+    );
+    assert_eq!(
+        status(client.get(&server).bearer_auth("correct")).await,
+        302
+    );
+
+    let login = format!("{server}/login");
+    assert_eq!(
+        status(client.post(&login).form(&[("token", "wrong")])).await,
+        401
+    );
+    let res = client
+        .post(&login)
+        .form(&[("token", "correct horse")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 302);
+    let cookie = res.headers()["set-cookie"].to_str().unwrap();
+    let cookie = cookie.split(';').next().unwrap().to_string();
+    // the token itself isn't in the cookie
+    assert!(!cookie.contains("correct"), "{cookie}");
+    assert_eq!(
+        status(client.get(&server).header("cookie", &cookie)).await,
+        200
+    );
+    assert_eq!(
+        status(
+            client
+                .get(&server)
+                .header("cookie", "auth-token=correct%20horse")
+        )
+        .await,
+        302
+    );
+}
+
+#[tokio::test]
+async fn allowed_ips_skip_auth() {
+    let client = wreq::ClientBuilder::new().build().unwrap();
+    let server_allowing = |range: &str| {
+        let mut config = config(&[]);
+        config.auth.token = Some("correct horse".to_string());
+        config.auth.allowed_ips = vec![IpRange::from_str(range).unwrap()];
+        serve(config)
+    };
+
+    let server = server_allowing("127.0.0.0/8").await;
+    assert_eq!(status(client.get(&server)).await, 200);
+
+    let server = server_allowing("10.0.0.0/8").await;
+    assert_eq!(status(client.get(&server)).await, 302);
+    // the header is ignored unless we're told to trust it
+    assert_eq!(
+        status(client.get(&server).header("x-forwarded-for", "10.0.0.1")).await,
+        302
+    );
+}
+
+#[tokio::test]
+async fn identical_searches_make_one_request() {
+    let engine = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api.php"))
+        .respond_with(
+            librey_results(&["https://a.example/page"]).set_delay(Duration::from_millis(300)),
+        )
+        .expect(1)
+        .mount(&engine)
+        .await;
+    let server = serve(config(&[("librey", &[engine.uri()])])).await;
+
+    let url = format!("{server}/search?q=together");
+    let ((status_a, html_a), (status_b, html_b)) = tokio::join!(get(&url), get(&url));
+    assert_eq!((status_a, status_b), (200, 200));
+    for html in [html_a, html_b] {
+        assert!(html.contains("Title of https://a.example/page"), "{html}");
+    }
+    engine.verify().await;
+}
+
+#[tokio::test]
+async fn repeated_search_comes_from_cache() {
+    let engine = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api.php"))
+        .respond_with(librey_results(&[
+            "https://a.example/page",
+            "https://b.example/page",
+        ]))
+        .expect(1)
+        .mount(&engine)
+        .await;
+    let server = serve(config(&[("librey", &[engine.uri()])])).await;
+
+    let first = api_search(&server, "again").await;
+    let second = api_search(&server, "again").await;
+    assert_eq!(result_urls(&first), result_urls(&second));
+    assert_eq!(
+        result_urls(&second),
+        ["https://a.example/page", "https://b.example/page"]
+    );
+    engine.verify().await;
+}